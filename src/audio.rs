@@ -0,0 +1,112 @@
+#[cfg(feature = "cpal")]
+pub mod cpal;
+#[cfg(feature = "rodio")]
+pub mod envelope;
+#[cfg(any(feature = "rodio", feature = "cpal"))]
+pub mod gate;
+#[cfg(test)]
+pub mod mock;
+#[cfg(feature = "rodio")]
+pub mod rodio;
+
+use serde::Deserialize;
+use std::path::PathBuf;
+
+pub const MIN_FREQUENCY: f32 = 100.0;
+pub const MAX_FREQUENCY: f32 = 2000.0;
+// How much the hotkeys move the pitch and the volume.
+pub const FREQUENCY_STEP: f32 = 50.0;
+pub const VOLUME_STEP: i8 = 10;
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AudioOptions {
+    pub beep_freq: f32,
+    // Percentage of full scale.
+    pub volume: u8,
+    pub beep_sample: Option<PathBuf>,
+}
+
+impl Default for AudioOptions {
+    fn default() -> Self {
+        Self {
+            beep_freq: 700.0,
+            volume: 100,
+            beep_sample: None,
+        }
+    }
+}
+
+// What the frontends drive the buzzer through. Settings are kept even when
+// nothing is played, so that their hotkeys keep working.
+pub trait Audio {
+    // Hands over the sound timer, which the audio callback counts down on
+    // its own, so the beep lasts as long as the timer whenever it is heard.
+    fn sound(&self, timer: u8);
+    // Silences the buzzer at once, such as when emulation is paused.
+    fn pause(&self);
+    #[cfg(feature = "egui")]
+    fn frequency(&self) -> f32;
+    // Returns the new frequency, which stays within the bounds.
+    fn adjust_frequency(&mut self, delta: f32) -> f32;
+    fn volume(&self) -> u8;
+    fn set_volume(&mut self, volume: u8);
+
+    // Returns the new volume, for the hotkeys to show.
+    fn adjust_volume(&mut self, delta: i8) -> u8 {
+        self.set_volume(self.volume().saturating_add_signed(delta));
+        self.volume()
+    }
+}
+
+// Stands in for the buzzer when there is no audio device to play it on.
+pub struct NullAudio {
+    frequency: f32,
+    volume: u8,
+}
+
+impl NullAudio {
+    pub fn new(frequency: f32, volume: u8) -> Self {
+        Self {
+            frequency: frequency.clamp(MIN_FREQUENCY, MAX_FREQUENCY),
+            volume: volume.min(100),
+        }
+    }
+}
+
+impl Audio for NullAudio {
+    fn sound(&self, _timer: u8) {}
+
+    fn pause(&self) {}
+
+    #[cfg(feature = "egui")]
+    fn frequency(&self) -> f32 {
+        self.frequency
+    }
+
+    fn adjust_frequency(&mut self, delta: f32) -> f32 {
+        self.frequency = (self.frequency + delta).clamp(MIN_FREQUENCY, MAX_FREQUENCY);
+        self.frequency
+    }
+
+    fn volume(&self) -> u8 {
+        self.volume
+    }
+
+    fn set_volume(&mut self, volume: u8) {
+        self.volume = volume.min(100);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::audio::Audio;
+    use crate::audio::NullAudio;
+    #[test]
+    fn null_audio_keeps_the_settings() {
+        let mut audio = NullAudio::new(700.0, 50);
+        audio.sound(3);
+        assert_eq!(audio.adjust_frequency(-50.0), 650.0);
+        assert_eq!(audio.adjust_volume(-10), 40);
+    }
+}
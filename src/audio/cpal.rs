@@ -0,0 +1,185 @@
+use crate::audio::gate::Countdown;
+use crate::audio::gate::Gate;
+use crate::audio::gate::RAMP;
+use crate::audio::Audio;
+use crate::audio::MAX_FREQUENCY;
+use crate::audio::MIN_FREQUENCY;
+use cpal::traits::DeviceTrait;
+use cpal::traits::HostTrait;
+use cpal::traits::StreamTrait;
+use cpal::Device;
+use cpal::FromSample;
+use cpal::SampleFormat;
+use cpal::SizedSample;
+use cpal::Stream;
+use cpal::StreamConfig;
+use std::f32::consts::TAU;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use tracing::warn;
+
+// Settings read by the audio callback, with the floats stored as their bits.
+struct Controls {
+    gate: Gate,
+    frequency: AtomicU32,
+    volume: AtomicU32,
+}
+
+// Talks to the output device directly through cpal, for systems where rodio
+// misbehaves. The tone is synthesized in the audio callback and faded in and
+// out there, like the envelope does for rodio.
+pub struct CpalAudio {
+    controls: Arc<Controls>,
+    frequency: f32,
+    volume: u8,
+    // Playback stops when the stream is dropped.
+    _stream: Stream,
+}
+
+impl CpalAudio {
+    pub fn open(frequency: f32, volume: u8) -> Result<Self, String> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or("no output device found")?;
+        let supported = device.default_output_config().map_err(|e| e.to_string())?;
+        let controls = Arc::new(Controls {
+            gate: Gate::default(),
+            frequency: AtomicU32::new(0),
+            volume: AtomicU32::new(0),
+        });
+        let config = supported.config();
+        let stream = match supported.sample_format() {
+            SampleFormat::F32 => build::<f32>(&device, &config, controls.clone()),
+            SampleFormat::I16 => build::<i16>(&device, &config, controls.clone()),
+            SampleFormat::U16 => build::<u16>(&device, &config, controls.clone()),
+            format => Err(format!("unsupported sample format {:?}", format)),
+        }?;
+        stream.play().map_err(|e| e.to_string())?;
+        let mut audio = Self {
+            controls,
+            frequency: 0.0,
+            volume: 0,
+            _stream: stream,
+        };
+        audio.adjust_frequency(frequency);
+        audio.set_volume(volume);
+        Ok(audio)
+    }
+}
+
+impl Audio for CpalAudio {
+    fn sound(&self, timer: u8) {
+        self.controls.gate.sound(timer);
+    }
+
+    fn pause(&self) {
+        self.controls.gate.stop();
+    }
+
+    #[cfg(feature = "egui")]
+    fn frequency(&self) -> f32 {
+        self.frequency
+    }
+
+    fn adjust_frequency(&mut self, delta: f32) -> f32 {
+        self.frequency = (self.frequency + delta).clamp(MIN_FREQUENCY, MAX_FREQUENCY);
+        self.controls
+            .frequency
+            .store(self.frequency.to_bits(), Ordering::Relaxed);
+        self.frequency
+    }
+
+    fn volume(&self) -> u8 {
+        self.volume
+    }
+
+    fn set_volume(&mut self, volume: u8) {
+        self.volume = volume.min(100);
+        let scale = self.volume as f32 / 100.0;
+        self.controls
+            .volume
+            .store(scale.to_bits(), Ordering::Relaxed);
+    }
+}
+
+fn build<T>(
+    device: &Device,
+    config: &StreamConfig,
+    controls: Arc<Controls>,
+) -> Result<Stream, String>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let channels = config.channels as usize;
+    let mut tone = Tone::new(config.sample_rate.0 as f32);
+    device
+        .build_output_stream(
+            config,
+            move |data: &mut [T], _| {
+                for frame in data.chunks_mut(channels) {
+                    frame.fill(T::from_sample(tone.next(&controls)));
+                }
+            },
+            |error| warn!("audio stream error, {}", error),
+            None,
+        )
+        .map_err(|e| e.to_string())
+}
+
+// A sine wave whose gain ramps towards the state of the gate.
+struct Tone {
+    sample_rate: f32,
+    countdown: Countdown,
+    phase: f32,
+    gain: f32,
+    step: f32,
+}
+
+impl Tone {
+    fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            countdown: Countdown::new(sample_rate),
+            phase: 0.0,
+            gain: 0.0,
+            step: 1.0 / (sample_rate * RAMP.as_secs_f32()).max(1.0),
+        }
+    }
+
+    fn next(&mut self, controls: &Controls) -> f32 {
+        self.gain = if self.countdown.next(&controls.gate) {
+            (self.gain + self.step).min(1.0)
+        } else {
+            (self.gain - self.step).max(0.0)
+        };
+        let frequency = f32::from_bits(controls.frequency.load(Ordering::Relaxed));
+        let volume = f32::from_bits(controls.volume.load(Ordering::Relaxed));
+        self.phase = (self.phase + frequency / self.sample_rate).fract();
+        (self.phase * TAU).sin() * self.gain * volume
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::audio::cpal::Controls;
+    use crate::audio::cpal::Tone;
+    use crate::audio::gate::Gate;
+    use std::sync::atomic::AtomicU32;
+    #[test]
+    fn tone_is_silent_until_the_gate_opens() {
+        let controls = Controls {
+            gate: Gate::default(),
+            frequency: AtomicU32::new(250f32.to_bits()),
+            volume: AtomicU32::new(1f32.to_bits()),
+        };
+        let mut tone = Tone::new(1000.0);
+        assert_eq!(tone.next(&controls), 0.0);
+        controls.gate.sound(1);
+        // Every fourth sample of a 250 Hz wave is a peak, at full gain once
+        // the 5 sample ramp is over.
+        let wave: Vec<f32> = (0..9).map(|_| tone.next(&controls)).collect();
+        let peak = wave[5..].iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        assert!((peak - 1.0).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,83 @@
+use crate::audio::gate::Countdown;
+use crate::audio::gate::Gate;
+use crate::audio::gate::RAMP;
+use rodio::Source;
+use std::sync::Arc;
+use std::time::Duration;
+
+// Fades the wrapped source in while the sound timer runs and out once it
+// stops, one sample at a time.
+pub struct Envelope<S> {
+    source: S,
+    gate: Arc<Gate>,
+    countdown: Countdown,
+    gain: f32,
+    step: f32,
+}
+
+impl<S: Source<Item = f32>> Envelope<S> {
+    pub fn new(source: S, gate: Arc<Gate>) -> Self {
+        let rate = source.sample_rate() as f32 * source.channels() as f32;
+        let samples = rate * RAMP.as_secs_f32();
+        Self {
+            source,
+            gate,
+            countdown: Countdown::new(rate),
+            gain: 0.0,
+            step: 1.0 / samples.max(1.0),
+        }
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for Envelope<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        self.gain = if self.countdown.next(&self.gate) {
+            (self.gain + self.step).min(1.0)
+        } else {
+            (self.gain - self.step).max(0.0)
+        };
+        self.source.next().map(|sample| sample * self.gain)
+    }
+}
+
+impl<S: Source<Item = f32>> Source for Envelope<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.source.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.source.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.source.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.source.total_duration()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::audio::envelope::Envelope;
+    use crate::audio::gate::Gate;
+    use rodio::buffer::SamplesBuffer;
+    use std::sync::Arc;
+    #[test]
+    fn ramps_instead_of_cutting() {
+        let gate = Arc::new(Gate::default());
+        // A 5 ms ramp at 1200 Hz takes 6 samples, a tick 20.
+        let source = SamplesBuffer::new(1, 1200, vec![1.0; 40]);
+        let mut envelope = Envelope::new(source, gate.clone());
+        gate.sound(1);
+        let first = envelope.next().unwrap();
+        assert!(first > 0.0 && first < 0.5);
+        assert_eq!(envelope.nth(18), Some(1.0));
+        let released = envelope.next().unwrap();
+        assert!(released > 0.5 && released < 1.0);
+        assert_eq!(envelope.nth(5), Some(0.0));
+    }
+}
@@ -0,0 +1,113 @@
+use crate::frontend::runner::TIMER_SPEED;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::AtomicU8;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+// Long enough to avoid the click of a wave cut off mid-cycle, short enough
+// for the fade not to be heard as one.
+pub const RAMP: Duration = Duration::from_millis(5);
+
+// The sound timer as last reported by the emulator, for the audio callback
+// to count down itself. A beep is then heard for as long as the timer says
+// even when it starts and ends between two callbacks.
+pub struct Gate {
+    timer: AtomicU8,
+    stopped: AtomicBool,
+    // Playback speed of the source, stored as its bits, which changes how
+    // many samples a tick takes.
+    speed: AtomicU32,
+}
+
+impl Default for Gate {
+    fn default() -> Self {
+        Self {
+            timer: AtomicU8::new(0),
+            stopped: AtomicBool::new(false),
+            speed: AtomicU32::new(1f32.to_bits()),
+        }
+    }
+}
+
+impl Gate {
+    pub fn sound(&self, timer: u8) {
+        if timer > 0 {
+            self.stopped.store(false, Ordering::Relaxed);
+            self.timer.fetch_max(timer, Ordering::Relaxed);
+        }
+    }
+
+    // Cuts the beep short instead of letting the timer run out.
+    pub fn stop(&self) {
+        self.timer.store(0, Ordering::Relaxed);
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+
+    #[cfg(feature = "rodio")]
+    pub fn set_speed(&self, speed: f32) {
+        self.speed.store(speed.to_bits(), Ordering::Relaxed);
+    }
+}
+
+// Counts the ticks picked up from the gate down one sample at a time.
+pub struct Countdown {
+    per_tick: f32,
+    remaining: u32,
+}
+
+impl Countdown {
+    pub fn new(samples_per_second: f32) -> Self {
+        Self {
+            per_tick: samples_per_second / TIMER_SPEED as f32,
+            remaining: 0,
+        }
+    }
+
+    // Whether the next sample is to be heard.
+    pub fn next(&mut self, gate: &Gate) -> bool {
+        if gate.stopped.load(Ordering::Relaxed) {
+            self.remaining = 0;
+        }
+        let timer = gate.timer.swap(0, Ordering::Relaxed);
+        if timer > 0 {
+            let speed = f32::from_bits(gate.speed.load(Ordering::Relaxed));
+            let samples = (timer as f32 * self.per_tick * speed) as u32;
+            self.remaining = self.remaining.max(samples);
+        }
+        if self.remaining == 0 {
+            return false;
+        }
+        self.remaining -= 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::audio::gate::Countdown;
+    use crate::audio::gate::Gate;
+    #[test]
+    fn countdown_plays_every_tick_reported() {
+        let gate = Gate::default();
+        let mut countdown = Countdown::new(600.0);
+        // A one tick beep already over by the time the callback runs.
+        gate.sound(1);
+        assert_eq!((0..12).filter(|_| countdown.next(&gate)).count(), 10);
+        gate.sound(3);
+        gate.stop();
+        assert!(!countdown.next(&gate));
+    }
+    #[cfg(feature = "rodio")]
+    #[test]
+    fn countdown_follows_the_speed() {
+        let gate = Gate::default();
+        let mut countdown = Countdown::new(600.0);
+        gate.sound(1);
+        assert_eq!((0..12).filter(|_| countdown.next(&gate)).count(), 10);
+        gate.set_speed(2.0);
+        gate.sound(2);
+        gate.sound(1);
+        assert_eq!((0..50).filter(|_| countdown.next(&gate)).count(), 40);
+    }
+}
@@ -0,0 +1,93 @@
+use crate::audio::Audio;
+use crate::audio::MAX_FREQUENCY;
+use crate::audio::MIN_FREQUENCY;
+use std::cell::Cell;
+use std::cell::RefCell;
+
+// Records when the buzzer starts and stops, counting every sound timer
+// handed over or pause as one update, so that tests can check beep timing without an audio device.
+#[derive(Default)]
+pub struct MockAudio {
+    updates: Cell<u32>,
+    playing: Cell<bool>,
+    pub changes: RefCell<Vec<(u32, bool)>>,
+    frequency: f32,
+    volume: u8,
+}
+
+impl MockAudio {
+    fn update(&self, playing: bool) {
+        self.updates.set(self.updates.get() + 1);
+        if playing != self.playing.replace(playing) {
+            self.changes
+                .borrow_mut()
+                .push((self.updates.get(), playing));
+        }
+    }
+}
+
+impl Audio for MockAudio {
+    fn sound(&self, timer: u8) {
+        self.update(timer > 0);
+    }
+
+    fn pause(&self) {
+        self.update(false);
+    }
+
+    #[cfg(feature = "egui")]
+    fn frequency(&self) -> f32 {
+        self.frequency
+    }
+
+    fn adjust_frequency(&mut self, delta: f32) -> f32 {
+        self.frequency = (self.frequency + delta).clamp(MIN_FREQUENCY, MAX_FREQUENCY);
+        self.frequency
+    }
+
+    fn volume(&self) -> u8 {
+        self.volume
+    }
+
+    fn set_volume(&mut self, volume: u8) {
+        self.volume = volume.min(100);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::audio::mock::MockAudio;
+    use crate::audio::Audio;
+    use crate::chip8::Chip8;
+    use crate::frontend::runner::Runner;
+    use crate::frontend::runner::TIMER_SPEED;
+    use std::time::Duration;
+    #[test]
+    fn beep_lasts_as_long_as_the_sound_timer() {
+        let mut chip8 = Chip8::new();
+        // LD V0, 03; LD ST, V0; JP 204
+        chip8.load(&[0x60, 0x03, 0xF0, 0x18, 0x12, 0x04]).unwrap();
+        let mut runner = Runner::new(600);
+        let audio = MockAudio::default();
+        let frame = Duration::from_secs(1) / TIMER_SPEED;
+        for _ in 0..6 {
+            runner.advance(&mut chip8, frame);
+            audio.sound(chip8.take_sound());
+        }
+        assert_eq!(*audio.changes.borrow(), [(1, true), (4, false)]);
+    }
+    #[test]
+    fn one_tick_beep_is_not_missed() {
+        let mut chip8 = Chip8::new();
+        // LD V0, 01; LD ST, V0; JP 204
+        chip8.load(&[0x60, 0x01, 0xF0, 0x18, 0x12, 0x04]).unwrap();
+        let mut runner = Runner::new(600);
+        let audio = MockAudio::default();
+        let frame = Duration::from_secs(1) / TIMER_SPEED;
+        for _ in 0..3 {
+            runner.advance(&mut chip8, frame);
+            audio.sound(chip8.take_sound());
+        }
+        assert_eq!(*audio.changes.borrow(), [(1, true), (2, false)]);
+    }
+}
@@ -0,0 +1,178 @@
+use crate::audio::envelope::Envelope;
+use crate::audio::gate::Gate;
+use crate::audio::Audio;
+use crate::audio::MAX_FREQUENCY;
+use crate::audio::MIN_FREQUENCY;
+use rodio::decoder::DecoderError;
+use rodio::Decoder;
+use rodio::OutputStream;
+use rodio::Sink;
+use rodio::Source;
+use std::fs::File;
+use std::io::BufReader;
+use std::io::Read;
+use std::io::Seek;
+use std::path::Path;
+use std::sync::Arc;
+
+// The tone played while the sound timer is running. It is appended to the
+// sink once and keeps playing, faded in and out by an envelope rather than
+// pausing the sink, which would cut the wave short and pop. The pitch is
+// changed through the playback speed rather than by replacing the tone.
+pub struct Beeper {
+    sink: Sink,
+    gate: Arc<Gate>,
+    base: f32,
+    frequency: f32,
+    volume: u8,
+    // The device the sink plays on, closed when the beeper is dropped.
+    stream: Option<OutputStream>,
+}
+
+impl Beeper {
+    pub fn new(sink: Sink, frequency: f32, volume: u8) -> Self {
+        let frequency = frequency.clamp(MIN_FREQUENCY, MAX_FREQUENCY);
+        let tone = rodio::source::SineWave::new(frequency);
+        Self::start(sink, tone, frequency, volume)
+    }
+
+    // Loops a sound file in place of the generated tone. The frequency is
+    // only the reference its pitch is moved from.
+    pub fn from_file(sink: Sink, path: &Path, frequency: f32, volume: u8) -> Result<Self, String> {
+        let file =
+            File::open(path).map_err(|e| format!("unable to open {}: {}", path.display(), e))?;
+        let sample =
+            looped(BufReader::new(file)).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let frequency = frequency.clamp(MIN_FREQUENCY, MAX_FREQUENCY);
+        Ok(Self::start(sink, sample, frequency, volume))
+    }
+
+    fn start<S>(sink: Sink, source: S, frequency: f32, volume: u8) -> Self
+    where
+        S: Source<Item = f32> + Send + 'static,
+    {
+        let gate = Arc::new(Gate::default());
+        sink.append(Envelope::new(source, gate.clone()));
+        let mut beeper = Self {
+            sink,
+            gate,
+            base: frequency,
+            frequency,
+            volume: 0,
+            stream: None,
+        };
+        beeper.set_volume(volume);
+        beeper
+    }
+
+    pub fn playing_on(mut self, stream: OutputStream) -> Self {
+        self.stream = Some(stream);
+        self
+    }
+}
+
+impl Audio for Beeper {
+    fn sound(&self, timer: u8) {
+        self.gate.sound(timer);
+    }
+
+    fn pause(&self) {
+        self.gate.stop();
+    }
+
+    #[cfg(feature = "egui")]
+    fn frequency(&self) -> f32 {
+        self.frequency
+    }
+
+    fn adjust_frequency(&mut self, delta: f32) -> f32 {
+        self.frequency = (self.frequency + delta).clamp(MIN_FREQUENCY, MAX_FREQUENCY);
+        self.sink.set_speed(self.frequency / self.base);
+        self.gate.set_speed(self.frequency / self.base);
+        self.frequency
+    }
+
+    fn volume(&self) -> u8 {
+        self.volume
+    }
+
+    fn set_volume(&mut self, volume: u8) {
+        self.volume = volume.min(100);
+        self.sink.set_volume(self.volume as f32 / 100.0);
+    }
+}
+
+// Decodes a WAV (or any other format rodio knows) to be played over and over.
+fn looped<R>(reader: R) -> Result<impl Source<Item = f32>, DecoderError>
+where
+    R: Read + Seek + Send + Sync + 'static,
+{
+    Ok(Decoder::new(reader)?
+        .convert_samples()
+        .buffered()
+        .repeat_infinite())
+}
+
+// Opens the default output device, which plays for as long as the stream
+// is kept.
+pub fn open_sink() -> Result<(OutputStream, Sink), String> {
+    let (stream, handle) = OutputStream::try_default().map_err(|e| e.to_string())?;
+    let sink = Sink::try_new(&handle).map_err(|e| e.to_string())?;
+    Ok((stream, sink))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::audio::rodio::looped;
+    use crate::audio::rodio::Beeper;
+    use crate::audio::Audio;
+    use crate::audio::MAX_FREQUENCY;
+    use rodio::Sink;
+    use std::io::Cursor;
+    #[test]
+    fn frequency_stays_within_bounds() {
+        let (sink, _output) = Sink::new_idle();
+        let mut beeper = Beeper::new(sink, 700.0, 100);
+        assert_eq!(beeper.adjust_frequency(50.0), 750.0);
+        assert_eq!(beeper.adjust_frequency(5000.0), MAX_FREQUENCY);
+        let (sink, _output) = Sink::new_idle();
+        assert_eq!(Beeper::new(sink, 10.0, 100).adjust_frequency(0.0), 100.0);
+    }
+    #[test]
+    fn volume_is_a_percentage() {
+        let (sink, _output) = Sink::new_idle();
+        let mut beeper = Beeper::new(sink, 700.0, 95);
+        assert_eq!(beeper.adjust_volume(10), 100);
+        assert_eq!(beeper.adjust_volume(-30), 70);
+        beeper.set_volume(0);
+        assert_eq!(beeper.adjust_volume(-10), 0);
+    }
+    #[test]
+    fn sample_loops_forever() {
+        // A mono 16 bit PCM WAV with three samples.
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&42u32.to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&8000u32.to_le_bytes());
+        wav.extend_from_slice(&16000u32.to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&6u32.to_le_bytes());
+        for sample in [0i16, i16::MIN, 0] {
+            wav.extend_from_slice(&sample.to_le_bytes());
+        }
+        let samples: Vec<f32> = looped(Cursor::new(wav)).unwrap().take(7).collect();
+        assert_eq!(samples, [0.0, -1.0, 0.0, 0.0, -1.0, 0.0, 0.0]);
+    }
+    #[test]
+    fn missing_sample_is_an_error() {
+        let (sink, _output) = Sink::new_idle();
+        let path = std::path::Path::new("missing.wav");
+        assert!(Beeper::from_file(sink, path, 700.0, 100).is_err());
+    }
+}
@@ -1,14 +1,45 @@
+pub mod asm;
+pub mod disasm;
 pub mod display;
+pub mod hash;
+mod keypad;
+pub mod octo;
+pub mod platform;
+pub mod profile;
+pub mod quirks;
 mod sprites;
+pub mod timing;
 
 use display::Display;
+use keypad::Latch;
+use profile::Profile;
+use quirks::Quirks;
+use rand::rngs::StdRng;
 use rand::Rng;
+use rand::SeedableRng;
 use sprites::FONT_SPRITES;
 use sprites::FONT_SPRITES_MEM_ADDR;
 use sprites::FONT_SPRITE_LEN;
+use std::sync::Arc;
+use std::sync::Mutex;
+use timing::vip_cycles;
+use tracing::debug;
 
-const RAM_SIZE: usize = 4096;
+pub const RAM_SIZE: usize = 4096;
+const PROGRAM_START: usize = 0x200;
+// The largest ROM memory has room for after the interpreter.
+pub const MAX_ROM_SIZE: usize = RAM_SIZE - PROGRAM_START;
 
+// A soft reset runs the program again with memory as it was left, as the
+// reset switch of the COSMAC VIP did, while a hard one starts over from the
+// ROM as loaded, as if the machine had been turned off and on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Reset {
+    Soft,
+    Hard,
+}
+
+#[derive(Clone)]
 pub struct Chip8 {
     pub display: Display,
     pub ram: [u8; RAM_SIZE],
@@ -18,13 +49,42 @@ pub struct Chip8 {
     reg_v: [u8; 16],
     delay_timer: u8,
     sound_timer: u8,
+    // Highest sound timer loaded since the audio last asked for it.
+    sound_loaded: u8,
     keyboard: [bool; 16],
+    latch: Latch,
     paused: bool,
     store_keypress_in_reg: u8,
+    cycles: u64,
+    // The machine cycles the instructions executed would have taken on the
+    // COSMAC VIP.
+    vip_cycles: u64,
+    // Sprites drawn so far, and those that turned pixels off.
+    draws: u64,
+    collisions: u64,
+    quirks: Quirks,
+    // Set by a draw with the display wait quirk until the next frame.
+    waiting_display: bool,
+    // The ROM as loaded, for a hard reset.
+    program: Vec<u8>,
+    // Where the random numbers of Cxnn come from, which a hard reset sets
+    // back to the start along with memory.
+    seed: u64,
+    rng: StdRng,
+    // Where the instructions executed are counted, when they are, shared
+    // with whoever reads it, and with the copies made of the machine.
+    profile: Option<Arc<Mutex<Profile>>>,
+}
+
+impl Default for Chip8 {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Chip8 {
     pub fn new() -> Self {
+        let seed = rand::random();
         let mut emu = Self {
             display: Display::new(),
             ram: [0; RAM_SIZE],
@@ -34,9 +94,21 @@ impl Chip8 {
             reg_v: [0; 16],
             delay_timer: 0,
             sound_timer: 0,
+            sound_loaded: 0,
             keyboard: [false; 16],
+            latch: Latch::default(),
             paused: false,
             store_keypress_in_reg: 0,
+            cycles: 0,
+            vip_cycles: 0,
+            draws: 0,
+            collisions: 0,
+            quirks: Quirks::default(),
+            waiting_display: false,
+            program: Vec::new(),
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            profile: None,
         };
         emu.load_sprites();
         emu
@@ -55,6 +127,11 @@ impl Chip8 {
         self.store_keypress_in_reg = reg;
     }
 
+    // Whether Fx0A holds up the program until a key is pressed.
+    pub fn waiting_for_key(&self) -> bool {
+        self.paused
+    }
+
     pub fn beep(&mut self) -> bool {
         self.sound_timer > 0
     }
@@ -64,22 +141,127 @@ impl Chip8 {
             self.reg_v[self.store_keypress_in_reg as usize] = key;
             self.paused = false;
         }
-        self.keyboard[key as usize] = state;
+        if state {
+            self.latch.press(key as usize);
+            self.keyboard[key as usize] = true;
+        } else if self.latch.release(key as usize) {
+            self.keyboard[key as usize] = false;
+        }
+    }
+
+    // Keeps taps pressed for at least this many frames, or until the ROM
+    // checks them, with 0 turning latching off.
+    pub fn set_latch(&mut self, frames: u8) {
+        self.latch = Latch::new(frames);
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        debug!(?quirks, "quirks set");
+        self.quirks = quirks;
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    // Draws the random numbers from the seed given, the same for the same
+    // seed, instead of the one picked at random.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
     }
 
-    pub fn load(&mut self, data: &[u8]) {
-        let start: usize = 0x200;
+    // Counts the instructions executed from now on into the profile.
+    pub fn set_profile(&mut self, profile: Arc<Mutex<Profile>>) {
+        self.profile = Some(profile);
+    }
+
+    pub fn profile(&self) -> Option<&Arc<Mutex<Profile>>> {
+        self.profile.as_ref()
+    }
+
+    // Copies the program into memory and runs it from the start, unless it
+    // does not fit there.
+    pub fn load(&mut self, data: &[u8]) -> Result<(), String> {
+        if data.len() > MAX_ROM_SIZE {
+            return Err(format!(
+                "ROM too large: {} bytes, at most {}",
+                data.len(),
+                MAX_ROM_SIZE
+            ));
+        }
+        let start = PROGRAM_START;
         let end = start + data.len();
         self.ram[start..end].copy_from_slice(data);
-        self.pc = 0x200;
+        self.pc = PROGRAM_START as u16;
+        self.program = data.to_vec();
+        debug!(bytes = data.len(), "ROM loaded");
+        Ok(())
+    }
+
+    // Clears the registers, the stack, the timers and the display and runs
+    // the program from the start, keeping the quirks, the latch and the keys
+    // held down. The instructions executed keep counting.
+    pub fn reset(&mut self, reset: Reset) {
+        if reset == Reset::Hard {
+            self.ram = [0; RAM_SIZE];
+            self.load_sprites();
+            let end = PROGRAM_START + self.program.len();
+            self.ram[PROGRAM_START..end].copy_from_slice(&self.program);
+            self.rng = StdRng::seed_from_u64(self.seed);
+        }
+        self.display.clear();
+        self.pc = PROGRAM_START as u16;
+        self.stack.clear();
+        self.reg_i = 0;
+        self.reg_v = [0; 16];
+        self.delay_timer = 0;
+        self.sound_timer = 0;
+        self.sound_loaded = 0;
+        self.paused = false;
+        self.waiting_display = false;
+        debug!(?reset, "reset");
     }
 
     pub fn fetch_execute(&mut self) {
-        if self.paused {
+        if self.paused || self.waiting_display {
             return;
         };
+        let address = self.pc;
         let opcode = self.fetch();
+        if let Some(profile) = &self.profile {
+            let routine = self.routine();
+            profile.lock().unwrap().count(address, opcode, routine);
+        }
         self.execute(opcode);
+        self.cycles += 1;
+        self.vip_cycles += vip_cycles(opcode) as u64;
+    }
+
+    // Where the subroutine running starts, read from the call before the
+    // address it returns to, or else the start of the program.
+    fn routine(&self) -> u16 {
+        let Some(&back) = self.stack.last() else {
+            return PROGRAM_START as u16;
+        };
+        let call = back.wrapping_sub(2) as usize;
+        match self.ram.get(call..call + 2) {
+            Some(&[high, low]) if high >> 4 == 0x2 => u16::from_be_bytes([high & 0x0F, low]),
+            _ => PROGRAM_START as u16,
+        }
+    }
+
+    // Whether the program stopped itself in a jump to the same instruction,
+    // the way CHIP-8 programs end.
+    pub fn halted(&self) -> bool {
+        let pc = self.pc as usize;
+        pc + 1 < RAM_SIZE
+            && self.ram[pc] >> 4 == 0x1
+            && u16::from_be_bytes([self.ram[pc] & 0x0F, self.ram[pc + 1]]) == self.pc
     }
 
     pub fn dec_timers(&mut self) {
@@ -89,6 +271,14 @@ impl Chip8 {
         if self.sound_timer > 0 {
             self.sound_timer -= 1;
         }
+        self.latch.tick(&mut self.keyboard);
+        self.waiting_display = false;
+    }
+
+    fn vf_reset(&mut self) {
+        if self.quirks.vf_reset {
+            self.reg_v[0xF] = 0;
+        }
     }
 
     fn fetch(&mut self) -> u16 {
@@ -167,14 +357,17 @@ impl Chip8 {
             // 8xy1 - Set Vx = Vx OR Vy
             (0x8, x, y, 0x1) => {
                 self.reg_v[x as usize] |= self.reg_v[y as usize];
+                self.vf_reset();
             }
             // 8xy2 - Vy - Set Vx = Vx AND Vy
             (0x8, x, y, 0x2) => {
                 self.reg_v[x as usize] &= self.reg_v[y as usize];
+                self.vf_reset();
             }
             // 8xy3 - Set Vx = Vx XOR Vy
             (0x8, x, y, 0x3) => {
                 self.reg_v[x as usize] ^= self.reg_v[y as usize];
+                self.vf_reset();
             }
             // 8xy4 - Set Vx = Vx + Vy, set VF = carry
             (0x8, x, y, 0x4) => {
@@ -190,11 +383,11 @@ impl Chip8 {
                 self.reg_v[x as usize] = result;
                 self.reg_v[0xF] = if borrow { 0x0 } else { 0x1 };
             }
-            // 8xy6 - Set Vx = Vx SHR 1
-            (0x8, x, _, 0x6) => {
-                let lsb = self.reg_v[x as usize] & 0b00000001;
-                self.reg_v[x as usize] >>= 1;
-                self.reg_v[0xF] = lsb;
+            // 8xy6 - Set Vx = Vx SHR 1, or Vy SHR 1 without the shifting quirk
+            (0x8, x, y, 0x6) => {
+                let value = self.reg_v[if self.quirks.shifting { x } else { y } as usize];
+                self.reg_v[x as usize] = value >> 1;
+                self.reg_v[0xF] = value & 0b00000001;
             }
             // 8xy7 - Set Vx = Vy - Vx, set VF = NOT borrow
             (0x8, x, y, 0x7) => {
@@ -203,11 +396,11 @@ impl Chip8 {
                 self.reg_v[x as usize] = result;
                 self.reg_v[0xF] = if borrow { 0x0 } else { 0x1 };
             }
-            // 8xyE - Set Vx = Vx SHL 1
-            (0x8, x, _, 0xE) => {
-                let msb = (self.reg_v[x as usize] & 0b10000000) >> 7;
-                self.reg_v[x as usize] <<= 1;
-                self.reg_v[0xF] = msb;
+            // 8xyE - Set Vx = Vx SHL 1, or Vy SHL 1 without the shifting quirk
+            (0x8, x, y, 0xE) => {
+                let value = self.reg_v[if self.quirks.shifting { x } else { y } as usize];
+                self.reg_v[x as usize] = value << 1;
+                self.reg_v[0xF] = (value & 0b10000000) >> 7;
             }
             // 9xy0 - Skip next instruction if Vx != Vy
             (0x9, x, y, 0x0) => {
@@ -222,15 +415,16 @@ impl Chip8 {
                 let nnn = opcode & 0x0FFF;
                 self.reg_i = nnn;
             }
-            // Bnnn - Jump to location nnn + V0
-            (0xB, _, _, _) => {
+            // Bnnn - Jump to location nnn + V0, or xnn + Vx with the jumping quirk
+            (0xB, x, _, _) => {
                 let nnn = opcode & 0x0FFF;
-                self.reg_i = nnn + self.reg_v[0x0] as u16;
+                let offset = self.reg_v[if self.quirks.jumping { x } else { 0 } as usize];
+                self.pc = (nnn + offset as u16) & 0x0FFF;
             }
             // Cxnn - Set Vx = random byte AND nn
             (0xC, x, _, _) => {
                 let nn = opcode & 0x00FF;
-                self.reg_v[x as usize] = (rand::thread_rng().gen_range(0..256) & nn) as u8;
+                self.reg_v[x as usize] = self.rng.gen::<u8>() & nn as u8;
             }
             // Dxyn - Display n-byte sprite starting at memory location I at (Vx, Vy), set VF = collision
             (0xD, x, y, n) => {
@@ -239,8 +433,11 @@ impl Chip8 {
                 let addr_start = self.reg_i as usize;
                 let addr_end = addr_start + n as usize;
                 let sprite: Vec<u8> = self.ram[addr_start..addr_end].to_vec();
-                let unset = self.display.draw(sprite, vx, vy);
-                self.reg_v[0xF] = if unset { 1 } else { 0 }
+                let unset = self.display.draw(sprite, vx, vy, self.quirks.clipping);
+                self.reg_v[0xF] = if unset { 1 } else { 0 };
+                self.waiting_display = self.quirks.display_wait;
+                self.draws += 1;
+                self.collisions += u64::from(unset);
             }
             // Ex9E - Display n-byte sprite starting at memory location I at (Vx, Vy), set VF = collision
             (0xE, x, 0x9, 0xE) => {
@@ -248,6 +445,9 @@ impl Chip8 {
                 if self.keyboard[vx] {
                     self.pc += 2;
                 }
+                if self.latch.observe(vx) {
+                    self.keyboard[vx] = false;
+                }
             }
             // ExA1 - Skip next instruction if key with the value of Vx is not pressed
             (0xE, x, 0xA, 0x1) => {
@@ -255,6 +455,9 @@ impl Chip8 {
                 if !self.keyboard[vx] {
                     self.pc += 2;
                 }
+                if self.latch.observe(vx) {
+                    self.keyboard[vx] = false;
+                }
             }
             // Fx07 - Set Vx = delay timer value
             (0xF, x, 0x0, 0x7) => {
@@ -271,6 +474,7 @@ impl Chip8 {
             // Fx18 - Set sound timer = Vx
             (0xF, x, 0x1, 0x8) => {
                 self.sound_timer = self.reg_v[x as usize];
+                self.sound_loaded = self.sound_loaded.max(self.sound_timer);
             }
             // Fx1E - The values of I and Vx are added, and the results are stored in I
             (0xF, x, 0x1, 0xE) => {
@@ -297,6 +501,9 @@ impl Chip8 {
                     let to_i = (self.reg_i + i) as usize;
                     self.ram[to_i] = self.reg_v[i as usize];
                 }
+                if self.quirks.memory {
+                    self.reg_i += x + 1;
+                }
             }
             // Fx65 - Read registers V0 through Vx from memory starting at location I
             (0xF, x, 0x6, 0x5) => {
@@ -304,6 +511,9 @@ impl Chip8 {
                     let from_i = (self.reg_i + i) as usize;
                     self.reg_v[i as usize] = self.ram[from_i];
                 }
+                if self.quirks.memory {
+                    self.reg_i += x + 1;
+                }
             }
             (_, _, _, _) => {
                 panic!("unimplemented {:#06x}", opcode);
@@ -312,19 +522,174 @@ impl Chip8 {
     }
 }
 
+// Read-only views of the interpreter state, for frontends that show it.
+impl Chip8 {
+    pub fn reg_v(&self) -> &[u8; 16] {
+        &self.reg_v
+    }
+
+    pub fn reg_i(&self) -> u16 {
+        self.reg_i
+    }
+
+    pub fn stack(&self) -> &[u16] {
+        &self.stack
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    // The sound timer for the audio to play, or what it was loaded with if
+    // that is more, so that a beep over before the frame ends is still heard.
+    pub fn take_sound(&mut self) -> u8 {
+        self.sound_timer.max(std::mem::take(&mut self.sound_loaded))
+    }
+
+    // Number of instructions executed so far.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    // Machine cycles the instructions executed so far would have taken on
+    // the COSMAC VIP, which timing::vip_time turns into time.
+    pub fn vip_cycles(&self) -> u64 {
+        self.vip_cycles
+    }
+
+    // Number of sprites drawn so far, as the instructions keep counting.
+    pub fn draws(&self) -> u64 {
+        self.draws
+    }
+
+    // Number of sprites drawn so far that turned pixels off.
+    pub fn collisions(&self) -> u64 {
+        self.collisions
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::chip8::display::RES_WIDTH;
+    use crate::chip8::platform::Platform;
+    use crate::chip8::profile::Counts;
+    use crate::chip8::profile::Profile;
+    use crate::chip8::quirks::Quirks;
     use crate::chip8::Chip8;
+    use crate::chip8::Reset;
     use crate::chip8::FONT_SPRITES_MEM_ADDR;
+    use std::sync::Arc;
+    use std::sync::Mutex;
     #[test]
     fn loaded_data_is_in_memory() {
         let mut emu = Chip8::new();
         let data: [u8; 4] = [0xA, 0x1, 0xF, 0x12];
-        emu.load(&data);
+        emu.load(&data).unwrap();
         assert_eq!(emu.ram[0x200..0x204], data);
     }
     #[test]
+    fn data_too_large_for_memory_is_not_loaded() {
+        let mut emu = Chip8::new();
+        assert!(emu.load(&[0x12; 3584]).is_ok());
+        assert_eq!(
+            emu.load(&[0x34; 3585]),
+            Err(String::from("ROM too large: 3585 bytes, at most 3584"))
+        );
+        assert_eq!(emu.ram[0x200], 0x12);
+    }
+    #[test]
+    fn reset_keeps_memory_unless_hard() {
+        let mut emu = Chip8::new();
+        // Stores V0 over the first instruction, then waits for a key.
+        emu.load(&[0x60, 0x12, 0xF0, 0x55, 0xF1, 0x0A]).unwrap();
+        emu.reg_i = 0x200;
+        for _ in 0..3 {
+            emu.fetch_execute();
+        }
+        emu.display.draw(vec![0x80], 0, 0, true);
+        emu.reset(Reset::Soft);
+        assert_eq!(emu.pc, 0x200);
+        assert_eq!(emu.reg_v[0], 0);
+        assert_eq!(emu.reg_i, 0);
+        assert!(!emu.paused);
+        assert!(!emu.display.as_buffer()[0]);
+        assert_eq!(emu.ram[0x200], 0x12);
+        assert_eq!(emu.cycles(), 3);
+        emu.reset(Reset::Hard);
+        assert_eq!(emu.ram[0x200..0x206], [0x60, 0x12, 0xF0, 0x55, 0xF1, 0x0A]);
+        assert_eq!(emu.ram[FONT_SPRITES_MEM_ADDR], 0xF0);
+    }
+    #[test]
+    fn same_seed_draws_the_same_numbers() {
+        let draw = |emu: &mut Chip8| {
+            (0..8)
+                .map(|_| {
+                    emu.reset(Reset::Soft);
+                    emu.fetch_execute();
+                    emu.reg_v[0]
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut emu = Chip8::new();
+        emu.set_seed(42);
+        emu.load(&[0xC0, 0xFF]).unwrap();
+        let first = draw(&mut emu);
+        let mut other = Chip8::new();
+        other.set_seed(emu.seed());
+        other.load(&[0xC0, 0xFF]).unwrap();
+        assert_eq!(draw(&mut other), first);
+        emu.reset(Reset::Hard);
+        assert_eq!(draw(&mut emu), first);
+    }
+    #[test]
+    fn fetch_execute_counts_cycles_but_not_while_waiting_for_a_key() {
+        let mut emu = Chip8::new();
+        emu.load(&[0x60, 0x01, 0xF0, 0x0A]).unwrap();
+        emu.fetch_execute();
+        emu.fetch_execute();
+        emu.fetch_execute();
+        assert_eq!(emu.cycles(), 2);
+        assert!(emu.waiting_for_key());
+    }
+    #[test]
+    fn profile_counts_instructions_by_address_family_and_routine() {
+        let mut emu = Chip8::new();
+        // Calls a subroutine at 206 adding to V1, then adds to V0 and stops.
+        emu.load(&[0x22, 0x06, 0x70, 0x01, 0x12, 0x04, 0x71, 0x01, 0x00, 0xEE])
+            .unwrap();
+        let profile = Arc::new(Mutex::new(Profile::new()));
+        emu.set_profile(profile.clone());
+        for _ in 0..6 {
+            emu.fetch_execute();
+        }
+        let profile = profile.lock().unwrap();
+        let counts = |executed, vip_cycles| Counts {
+            executed,
+            vip_cycles,
+        };
+        assert_eq!(profile.total(), counts(6, 112));
+        assert_eq!(emu.vip_cycles(), 112);
+        assert_eq!(
+            profile.routines(),
+            [(0x200, counts(4, 79)), (0x206, counts(2, 33))]
+        );
+        let families: Vec<(&str, u64)> = profile
+            .families()
+            .into_iter()
+            .map(|(name, counts)| (name, counts.executed))
+            .collect();
+        assert_eq!(
+            families,
+            [("1nnn", 2), ("7xkk", 2), ("00EE", 1), ("2nnn", 1)]
+        );
+        assert_eq!(profile.addresses()[0], (0x204, 0x1204, counts(2, 46)));
+        assert!(profile.summary(1).contains("204      1204    JP 204"));
+    }
+    #[test]
     fn opcode_00e0_clear_display() {
         let mut emu = Chip8::new();
         emu.execute(0x00E0);
@@ -493,6 +858,41 @@ mod tests {
         assert_eq!(emu.reg_v[0xF], 0x00);
     }
     #[test]
+    fn opcode_8xy6_shift_vy_without_the_shifting_quirk() {
+        let mut emu = Chip8::new();
+        emu.set_quirks(Quirks::of(Platform::Chip8));
+        emu.reg_v[0x3] = 0x4A;
+        emu.reg_v[0x9] = 0x0B;
+        emu.execute(0x8396);
+        assert_eq!(emu.reg_v[0x3], 0x05);
+        assert_eq!(emu.reg_v[0xF], 0x01);
+        emu.execute(0x839E);
+        assert_eq!(emu.reg_v[0x3], 0x16);
+        assert_eq!(emu.reg_v[0xF], 0x00);
+    }
+    #[test]
+    fn opcode_8xy1_resets_vf_with_the_quirk() {
+        let mut emu = Chip8::new();
+        emu.reg_v[0xF] = 0x7;
+        emu.execute(0x8011);
+        assert_eq!(emu.reg_v[0xF], 0x7);
+        emu.set_quirks(Quirks::of(Platform::Chip8));
+        emu.execute(0x8012);
+        assert_eq!(emu.reg_v[0xF], 0x0);
+    }
+    #[test]
+    fn opcode_dxyn_waits_for_the_display_with_the_quirk() {
+        let mut emu = Chip8::new();
+        emu.set_quirks(Quirks::of(Platform::Chip8));
+        emu.load(&[0xD0, 0x01, 0x60, 0x05]).unwrap();
+        emu.fetch_execute();
+        emu.fetch_execute();
+        assert_eq!(emu.reg_v[0x0], 0x00);
+        emu.dec_timers();
+        emu.fetch_execute();
+        assert_eq!(emu.reg_v[0x0], 0x05);
+    }
+    #[test]
     fn opcode_8xy7_set_vx_as_vy_minus_vx_without_borrow() {
         let mut emu = Chip8::new();
         emu.reg_v[0x3] = 0x4A;
@@ -543,11 +943,20 @@ mod tests {
         assert_eq!(emu.reg_i, 0xE12);
     }
     #[test]
-    fn opcode_bnnn_set_i_to_nn_plus_v0() {
+    fn opcode_bnnn_jump_to_nnn_plus_v0() {
         let mut emu = Chip8::new();
         emu.reg_v[0x0] = 0x3;
         emu.execute(0xBE12);
-        assert_eq!(emu.reg_i, 0xE15);
+        assert_eq!(emu.pc, 0xE15);
+    }
+    #[test]
+    fn opcode_bxnn_jump_to_xnn_plus_vx() {
+        let mut emu = Chip8::new();
+        emu.set_quirks(Quirks::of(Platform::Schip));
+        emu.reg_v[0x0] = 0x3;
+        emu.reg_v[0xE] = 0x5;
+        emu.execute(0xBE12);
+        assert_eq!(emu.pc, 0xE17);
     }
     #[test]
     fn opcode_ex9e_skip_next_if_key_vx_is_pressed() {
@@ -585,10 +994,24 @@ mod tests {
         emu.execute(0xE7A1);
         assert_eq!(emu.pc, 0x208);
     }
+    #[test]
+    fn latched_tap_is_seen_by_ex9e() {
+        let mut emu = Chip8::new();
+        emu.set_latch(4);
+        emu.reg_v[0x7] = 0x4;
+        emu.key_pressed(0x4, true);
+        emu.key_pressed(0x4, false);
+        emu.pc = 0x206;
+        emu.execute(0xE79E);
+        assert_eq!(emu.pc, 0x208);
+        emu.execute(0xE79E);
+        assert_eq!(emu.pc, 0x208);
+    }
     // TODO: understand how to seed RNG to test CXNN
     // fn opcode_cxnn_set_vx_to_rand_and_nn() {
     // }
     #[test]
+    #[allow(clippy::identity_op)]
     fn opcode_dxyn_draw_sprite() {
         let mut emu = Chip8::new();
         emu.load_sprites();
@@ -618,6 +1041,26 @@ mod tests {
         );
     }
     #[test]
+    fn opcode_dxyn_marks_display_dirty() {
+        let mut emu = Chip8::new();
+        emu.display.take_dirty();
+        emu.execute(0xD010);
+        assert!(!emu.display.take_dirty());
+        emu.execute(0xA000 + FONT_SPRITES_MEM_ADDR as u16);
+        emu.execute(0xD015);
+        assert!(emu.display.take_dirty());
+        assert!(!emu.display.take_dirty());
+    }
+    #[test]
+    fn opcode_dxyn_counts_draws_and_collisions() {
+        let mut emu = Chip8::new();
+        emu.execute(0xA000 + FONT_SPRITES_MEM_ADDR as u16);
+        emu.execute(0xD015);
+        emu.execute(0xD015);
+        emu.execute(0xD015);
+        assert_eq!((emu.draws(), emu.collisions()), (3, 1));
+    }
+    #[test]
     fn opcode_fx1e_add_vx_to_i() {
         let mut emu = Chip8::new();
         emu.reg_i = 0x342;
@@ -647,6 +1090,15 @@ mod tests {
         assert_eq!(emu.sound_timer, 0x55);
     }
     #[test]
+    fn short_beep_is_kept_for_the_audio() {
+        let mut emu = Chip8::new();
+        emu.reg_v[0x0] = 0x01;
+        emu.execute(0xF018);
+        emu.dec_timers();
+        assert_eq!(emu.take_sound(), 1);
+        assert_eq!(emu.take_sound(), 0);
+    }
+    #[test]
     fn opcode_fx29_set_sprite_addr() {
         let mut emu = Chip8::new();
         emu.execute(0xF329);
@@ -678,6 +1130,16 @@ mod tests {
         assert_eq!(emu.ram[0x22C], 0x33);
     }
     #[test]
+    fn opcode_fx55_moves_i_with_the_memory_quirk() {
+        let mut emu = Chip8::new();
+        emu.set_quirks(Quirks::of(Platform::XoChip));
+        emu.reg_i = 0x22A;
+        emu.execute(0xF255);
+        assert_eq!(emu.reg_i, 0x22D);
+        emu.execute(0xF065);
+        assert_eq!(emu.reg_i, 0x22E);
+    }
+    #[test]
     fn opcode_fx65_reg_load_from_v0_to_vx() {
         let mut emu = Chip8::new();
         emu.ram[0x22A] = 0x11;
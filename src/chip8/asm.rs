@@ -0,0 +1,254 @@
+use std::collections::BTreeMap;
+
+const START: u16 = 0x200;
+const END: u16 = 0x1000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operand<'a> {
+    V(u16),
+    I,
+    // [I], the memory I points to.
+    Memory,
+    Dt,
+    St,
+    K,
+    F,
+    B,
+    // A number or a label.
+    Value(&'a str),
+}
+
+impl<'a> Operand<'a> {
+    fn parse(text: &'a str) -> Self {
+        let upper = text.to_ascii_uppercase();
+        match upper.as_str() {
+            "I" => Operand::I,
+            "[I]" => Operand::Memory,
+            "DT" => Operand::Dt,
+            "ST" => Operand::St,
+            "K" => Operand::K,
+            "F" => Operand::F,
+            "B" => Operand::B,
+            _ => match upper.strip_prefix('V') {
+                Some(digit) if digit.len() == 1 => u16::from_str_radix(digit, 16)
+                    .map(Operand::V)
+                    .unwrap_or(Operand::Value(text)),
+                _ => Operand::Value(text),
+            },
+        }
+    }
+}
+
+struct Statement<'a> {
+    line: usize,
+    mnemonic: String,
+    operands: Vec<Operand<'a>>,
+}
+
+impl Statement<'_> {
+    fn size(&self) -> u16 {
+        match self.mnemonic.as_str() {
+            "DB" => self.operands.len() as u16,
+            _ => 2,
+        }
+    }
+}
+
+// Turns Cowgod's assembly syntax, as the disassembler writes it, into a ROM
+// loaded at 200. Numbers are hexadecimal, with or without 0x in front, and
+// jumps can go to labels, written as a name and a colon at the start of a
+// line. DB and DW put bytes and words in as they are, and ; starts a comment.
+pub fn assemble(source: &str) -> Result<Vec<u8>, String> {
+    let mut labels = BTreeMap::new();
+    let mut statements = Vec::new();
+    let mut address = START;
+    for (index, line) in source.lines().enumerate() {
+        let error = |message: String| format!("line {}: {}", index + 1, message);
+        let mut line = line.split(';').next().unwrap().trim();
+        if let Some((label, rest)) = line.split_once(':') {
+            let label = label.trim();
+            if !is_label(label) {
+                return Err(error(format!("\"{}\" is not a valid label", label)));
+            }
+            if labels.insert(label, address).is_some() {
+                return Err(error(format!(
+                    "label \"{}\" is defined more than once",
+                    label
+                )));
+            }
+            line = rest.trim();
+        }
+        if line.is_empty() {
+            continue;
+        }
+        let (mnemonic, operands) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let statement = Statement {
+            line: index + 1,
+            mnemonic: mnemonic.to_ascii_uppercase(),
+            operands: operands
+                .split(',')
+                .map(str::trim)
+                .filter(|operand| !operand.is_empty())
+                .map(Operand::parse)
+                .collect(),
+        };
+        address += statement.size();
+        if address > END {
+            return Err(error(String::from("the program does not fit in memory")));
+        }
+        statements.push(statement);
+    }
+    let mut rom = Vec::new();
+    for statement in &statements {
+        let bytes = encode(statement, &labels)
+            .map_err(|message| format!("line {}: {}", statement.line, message))?;
+        rom.extend(bytes);
+    }
+    Ok(rom)
+}
+
+fn is_label(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// The number an operand stands for, a label or a hex number up to max.
+fn value(operand: &Operand, labels: &BTreeMap<&str, u16>, max: u16) -> Result<u16, String> {
+    let text = match operand {
+        Operand::Value(text) => *text,
+        // Also the hex digits F and B.
+        Operand::F => "F",
+        Operand::B => "B",
+        _ => return Err(String::from("expected a number or a label")),
+    };
+    let number = match labels.get(text) {
+        Some(address) => *address,
+        None => {
+            let digits = text
+                .strip_prefix("0x")
+                .or_else(|| text.strip_prefix("0X"))
+                .unwrap_or(text);
+            u16::from_str_radix(digits, 16)
+                .map_err(|_| format!("\"{}\" is neither a hex number nor a label", text))?
+        }
+    };
+    if number > max {
+        return Err(format!("{} is out of range, at most {:X}", text, max));
+    }
+    Ok(number)
+}
+
+fn encode(statement: &Statement, labels: &BTreeMap<&str, u16>) -> Result<Vec<u8>, String> {
+    use Operand::*;
+    let value = |operand, max| value(operand, labels, max);
+    let xy = |x: &u16, y: &u16| (x << 8) | (y << 4);
+    let word = match (statement.mnemonic.as_str(), statement.operands.as_slice()) {
+        ("CLS", []) => 0x00E0,
+        ("RET", []) => 0x00EE,
+        ("SYS", [a]) => value(a, 0xFFF)?,
+        ("JP", [V(0), a]) => 0xB000 | value(a, 0xFFF)?,
+        ("JP", [a]) => 0x1000 | value(a, 0xFFF)?,
+        ("CALL", [a]) => 0x2000 | value(a, 0xFFF)?,
+        ("SE", [V(x), V(y)]) => 0x5000 | xy(x, y),
+        ("SE", [V(x), b]) => 0x3000 | (x << 8) | value(b, 0xFF)?,
+        ("SNE", [V(x), V(y)]) => 0x9000 | xy(x, y),
+        ("SNE", [V(x), b]) => 0x4000 | (x << 8) | value(b, 0xFF)?,
+        ("LD", [V(x), V(y)]) => 0x8000 | xy(x, y),
+        ("LD", [V(x), Dt]) => 0xF007 | (x << 8),
+        ("LD", [V(x), K]) => 0xF00A | (x << 8),
+        ("LD", [V(x), Memory]) => 0xF065 | (x << 8),
+        ("LD", [V(x), b]) => 0x6000 | (x << 8) | value(b, 0xFF)?,
+        ("LD", [I, a]) => 0xA000 | value(a, 0xFFF)?,
+        ("LD", [Dt, V(x)]) => 0xF015 | (x << 8),
+        ("LD", [St, V(x)]) => 0xF018 | (x << 8),
+        ("LD", [F, V(x)]) => 0xF029 | (x << 8),
+        ("LD", [B, V(x)]) => 0xF033 | (x << 8),
+        ("LD", [Memory, V(x)]) => 0xF055 | (x << 8),
+        ("ADD", [V(x), V(y)]) => 0x8004 | xy(x, y),
+        ("ADD", [V(x), b]) => 0x7000 | (x << 8) | value(b, 0xFF)?,
+        ("ADD", [I, V(x)]) => 0xF01E | (x << 8),
+        ("OR", [V(x), V(y)]) => 0x8001 | xy(x, y),
+        ("AND", [V(x), V(y)]) => 0x8002 | xy(x, y),
+        ("XOR", [V(x), V(y)]) => 0x8003 | xy(x, y),
+        ("SUB", [V(x), V(y)]) => 0x8005 | xy(x, y),
+        ("SHR", [V(x), V(y)]) => 0x8006 | xy(x, y),
+        ("SHR", [V(x)]) => 0x8006 | (x << 8),
+        ("SUBN", [V(x), V(y)]) => 0x8007 | xy(x, y),
+        ("SHL", [V(x), V(y)]) => 0x800E | xy(x, y),
+        ("SHL", [V(x)]) => 0x800E | (x << 8),
+        ("RND", [V(x), b]) => 0xC000 | (x << 8) | value(b, 0xFF)?,
+        ("DRW", [V(x), V(y), n]) => 0xD000 | xy(x, y) | value(n, 0xF)?,
+        ("SKP", [V(x)]) => 0xE09E | (x << 8),
+        ("SKNP", [V(x)]) => 0xE0A1 | (x << 8),
+        ("DW", [w]) => value(w, 0xFFFF)?,
+        ("DB", bytes) if !bytes.is_empty() => {
+            return bytes
+                .iter()
+                .map(|byte| value(byte, 0xFF).map(|byte| byte as u8))
+                .collect();
+        }
+        (mnemonic, operands) => {
+            return Err(format!(
+                "{} does not take {} operand(s) like these",
+                mnemonic,
+                operands.len()
+            ))
+        }
+    };
+    Ok(word.to_be_bytes().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::asm::assemble;
+    use crate::chip8::disasm::disassemble;
+    #[test]
+    fn assemble_resolves_labels() {
+        let source = "
+            ; draws a digit and waits
+            start:  LD V0, 0x0A
+                    LD F, V0
+                    CALL draw
+            loop:   JP loop
+            draw:   DRW V1, V2, 5
+                    RET
+            data:   DB 1, FF
+        ";
+        assert_eq!(
+            assemble(source).unwrap(),
+            vec![
+                0x60, 0x0A, 0xF0, 0x29, 0x22, 0x08, 0x12, 0x06, 0xD1, 0x25, 0x00, 0xEE, 0x01, 0xFF
+            ]
+        );
+    }
+    #[test]
+    fn assemble_reverses_disassemble() {
+        for opcode in 0..=0xFFFF {
+            let text = disassemble(opcode);
+            assert_eq!(assemble(&text).unwrap(), opcode.to_be_bytes(), "{}", text);
+        }
+    }
+    #[test]
+    fn assemble_errors_name_the_line() {
+        let error = |source| assemble(source).unwrap_err();
+        assert_eq!(
+            error("CLS\nJP nowhere"),
+            "line 2: \"nowhere\" is neither a hex number nor a label"
+        );
+        assert_eq!(
+            error("LD V0, 100"),
+            "line 1: 100 is out of range, at most FF"
+        );
+        assert_eq!(
+            error("a: CLS\na: RET"),
+            "line 2: label \"a\" is defined more than once"
+        );
+        assert_eq!(
+            error("CLS V0"),
+            "line 1: CLS does not take 1 operand(s) like these"
+        );
+    }
+}
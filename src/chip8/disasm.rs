@@ -0,0 +1,125 @@
+// Turns an opcode into Cowgod's assembly syntax, with addresses and constants
+// in hexadecimal. Anything that is not a known instruction is shown as a data
+// word, since ROMs mix sprites in with their code.
+pub fn disassemble(opcode: u16) -> String {
+    let x = (opcode & 0x0F00) >> 8;
+    let y = (opcode & 0x00F0) >> 4;
+    let n = opcode & 0x000F;
+    let nn = opcode & 0x00FF;
+    let nnn = opcode & 0x0FFF;
+    match (opcode >> 12, x, y, n) {
+        (0x0, 0x0, 0xE, 0x0) => String::from("CLS"),
+        (0x0, 0x0, 0xE, 0xE) => String::from("RET"),
+        (0x0, _, _, _) => format!("SYS {:03X}", nnn),
+        (0x1, _, _, _) => format!("JP {:03X}", nnn),
+        (0x2, _, _, _) => format!("CALL {:03X}", nnn),
+        (0x3, _, _, _) => format!("SE V{:X}, {:02X}", x, nn),
+        (0x4, _, _, _) => format!("SNE V{:X}, {:02X}", x, nn),
+        (0x5, _, _, 0x0) => format!("SE V{:X}, V{:X}", x, y),
+        (0x6, _, _, _) => format!("LD V{:X}, {:02X}", x, nn),
+        (0x7, _, _, _) => format!("ADD V{:X}, {:02X}", x, nn),
+        (0x8, _, _, 0x0) => format!("LD V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x1) => format!("OR V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x2) => format!("AND V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x3) => format!("XOR V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x4) => format!("ADD V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x5) => format!("SUB V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x6) => format!("SHR V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x7) => format!("SUBN V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0xE) => format!("SHL V{:X}, V{:X}", x, y),
+        (0x9, _, _, 0x0) => format!("SNE V{:X}, V{:X}", x, y),
+        (0xA, _, _, _) => format!("LD I, {:03X}", nnn),
+        (0xB, _, _, _) => format!("JP V0, {:03X}", nnn),
+        (0xC, _, _, _) => format!("RND V{:X}, {:02X}", x, nn),
+        (0xD, _, _, _) => format!("DRW V{:X}, V{:X}, {:X}", x, y, n),
+        (0xE, _, 0x9, 0xE) => format!("SKP V{:X}", x),
+        (0xE, _, 0xA, 0x1) => format!("SKNP V{:X}", x),
+        (0xF, _, 0x0, 0x7) => format!("LD V{:X}, DT", x),
+        (0xF, _, 0x0, 0xA) => format!("LD V{:X}, K", x),
+        (0xF, _, 0x1, 0x5) => format!("LD DT, V{:X}", x),
+        (0xF, _, 0x1, 0x8) => format!("LD ST, V{:X}", x),
+        (0xF, _, 0x1, 0xE) => format!("ADD I, V{:X}", x),
+        (0xF, _, 0x2, 0x9) => format!("LD F, V{:X}", x),
+        (0xF, _, 0x3, 0x3) => format!("LD B, V{:X}", x),
+        (0xF, _, 0x5, 0x5) => format!("LD [I], V{:X}", x),
+        (0xF, _, 0x6, 0x5) => format!("LD V{:X}, [I]", x),
+        _ => format!("DW {:04X}", opcode),
+    }
+}
+
+// The kind of instruction an opcode is, named as Cowgod's reference names it,
+// with the digits that vary in lower case, as 8xy4 for every ADD of two
+// registers.
+pub fn family(opcode: u16) -> &'static str {
+    match (
+        opcode >> 12,
+        (opcode & 0x0F00) >> 8,
+        (opcode & 0x00F0) >> 4,
+        opcode & 0x000F,
+    ) {
+        (0x0, 0x0, 0xE, 0x0) => "00E0",
+        (0x0, 0x0, 0xE, 0xE) => "00EE",
+        (0x0, _, _, _) => "0nnn",
+        (0x1, _, _, _) => "1nnn",
+        (0x2, _, _, _) => "2nnn",
+        (0x3, _, _, _) => "3xkk",
+        (0x4, _, _, _) => "4xkk",
+        (0x5, _, _, 0x0) => "5xy0",
+        (0x6, _, _, _) => "6xkk",
+        (0x7, _, _, _) => "7xkk",
+        (0x8, _, _, 0x0) => "8xy0",
+        (0x8, _, _, 0x1) => "8xy1",
+        (0x8, _, _, 0x2) => "8xy2",
+        (0x8, _, _, 0x3) => "8xy3",
+        (0x8, _, _, 0x4) => "8xy4",
+        (0x8, _, _, 0x5) => "8xy5",
+        (0x8, _, _, 0x6) => "8xy6",
+        (0x8, _, _, 0x7) => "8xy7",
+        (0x8, _, _, 0xE) => "8xyE",
+        (0x9, _, _, 0x0) => "9xy0",
+        (0xA, _, _, _) => "Annn",
+        (0xB, _, _, _) => "Bnnn",
+        (0xC, _, _, _) => "Cxkk",
+        (0xD, _, _, _) => "Dxyn",
+        (0xE, _, 0x9, 0xE) => "Ex9E",
+        (0xE, _, 0xA, 0x1) => "ExA1",
+        (0xF, _, 0x0, 0x7) => "Fx07",
+        (0xF, _, 0x0, 0xA) => "Fx0A",
+        (0xF, _, 0x1, 0x5) => "Fx15",
+        (0xF, _, 0x1, 0x8) => "Fx18",
+        (0xF, _, 0x1, 0xE) => "Fx1E",
+        (0xF, _, 0x2, 0x9) => "Fx29",
+        (0xF, _, 0x3, 0x3) => "Fx33",
+        (0xF, _, 0x5, 0x5) => "Fx55",
+        (0xF, _, 0x6, 0x5) => "Fx65",
+        _ => "data",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::disasm::disassemble;
+    use crate::chip8::disasm::family;
+    #[test]
+    fn disassemble_uses_cowgod_syntax() {
+        assert_eq!(disassemble(0x00E0), "CLS");
+        assert_eq!(disassemble(0x1234), "JP 234");
+        assert_eq!(disassemble(0x6A0F), "LD VA, 0F");
+        assert_eq!(disassemble(0x8124), "ADD V1, V2");
+        assert_eq!(disassemble(0xD015), "DRW V0, V1, 5");
+        assert_eq!(disassemble(0xF355), "LD [I], V3");
+    }
+    #[test]
+    fn disassemble_shows_unknown_opcodes_as_data() {
+        assert_eq!(disassemble(0x5121), "DW 5121");
+        assert_eq!(disassemble(0xFFFF), "DW FFFF");
+    }
+    #[test]
+    fn family_keeps_the_digits_that_name_the_instruction() {
+        assert_eq!(family(0x00EE), "00EE");
+        assert_eq!(family(0x8124), "8xy4");
+        assert_eq!(family(0xD015), "Dxyn");
+        assert_eq!(family(0xF355), "Fx55");
+        assert_eq!(family(0x5121), "data");
+    }
+}
@@ -3,44 +3,86 @@ pub const RES_HEIGHT: usize = 32;
 
 pub type DisplayBuffer = [bool; RES_WIDTH * RES_HEIGHT];
 
+#[derive(Clone)]
 pub struct Display {
     buffer: DisplayBuffer,
+    dirty: bool,
+}
+
+impl Default for Display {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Display {
     pub fn new() -> Self {
         Self {
             buffer: [false; RES_WIDTH * RES_HEIGHT],
+            dirty: true,
         }
     }
 
     pub fn clear(&mut self) {
         self.buffer = [false; RES_WIDTH * RES_HEIGHT];
+        self.dirty = true;
+    }
+
+    // Reports whether the buffer changed since the last call.
+    pub fn take_dirty(&mut self) -> bool {
+        std::mem::replace(&mut self.dirty, false)
     }
 
     pub fn as_buffer(&mut self) -> DisplayBuffer {
         self.buffer
     }
 
-    pub fn draw(&mut self, sprite: Vec<u8>, x: u8, y: u8) -> bool {
+    // Draws from the coordinates wrapped around the display, with the parts
+    // of the sprite past the edges either cut or wrapped around as well.
+    pub fn draw(&mut self, sprite: Vec<u8>, x: u8, y: u8, clip: bool) -> bool {
         let x_wrapped = x as usize % RES_WIDTH;
         let y_wrapped = y as usize % RES_HEIGHT;
         let mut unset = false;
         for (row, byte) in sprite.iter().enumerate() {
+            let y = y_wrapped + row;
+            if clip && y >= RES_HEIGHT {
+                break;
+            }
             for col in 0..8 {
+                let x = x_wrapped + col;
+                if clip && x >= RES_WIDTH {
+                    break;
+                }
                 let pixel_value = byte & (0b1000_0000 >> col);
-                let pixel_idx = (y_wrapped + row) * RES_WIDTH + x_wrapped + col;
-                if pixel_idx >= RES_WIDTH * RES_HEIGHT {
-                    continue;
-                };
+                let pixel_idx = (y % RES_HEIGHT) * RES_WIDTH + x % RES_WIDTH;
                 if pixel_value > 0 {
                     if self.buffer[pixel_idx] {
                         unset = true;
                     }
                     self.buffer[pixel_idx] = !self.buffer[pixel_idx];
+                    self.dirty = true;
                 }
             }
         }
         unset
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::display::Display;
+    use crate::chip8::display::RES_WIDTH;
+    #[test]
+    fn draw_clips_or_wraps_at_the_edges() {
+        let mut display = Display::new();
+        assert!(!display.draw(vec![0xFF, 0xFF], 60, 31, true));
+        let lit = display.as_buffer().iter().filter(|lit| **lit).count();
+        assert_eq!(lit, 4);
+        let mut display = Display::new();
+        display.draw(vec![0xFF, 0xFF], 124, 31, false);
+        let buffer = display.as_buffer();
+        assert_eq!(buffer.iter().filter(|lit| **lit).count(), 16);
+        assert!(buffer[0] && buffer[3] && !buffer[4]);
+        assert!(buffer[31 * RES_WIDTH + 63]);
+    }
+}
@@ -0,0 +1,58 @@
+use crate::chip8::Chip8;
+use sha1::Digest;
+use sha1::Sha1;
+
+// Tells ROMs apart by their content rather than by their file names, which
+// differ from one collection to another.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RomHash {
+    pub sha1: [u8; 20],
+    pub crc32: u32,
+}
+
+impl RomHash {
+    pub fn of(rom: &[u8]) -> Self {
+        Self {
+            sha1: Sha1::digest(rom).into(),
+            crc32: crc32fast::hash(rom),
+        }
+    }
+
+    pub fn sha1(&self) -> String {
+        self.sha1
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    pub fn crc32(&self) -> String {
+        format!("{:08x}", self.crc32)
+    }
+}
+
+// Everything a frame can change, to tell whether two machines still agree.
+pub fn state(chip8: &mut Chip8) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(&chip8.ram);
+    hasher.update(&chip8.pc.to_be_bytes());
+    hasher.update(chip8.reg_v());
+    hasher.update(&chip8.reg_i().to_be_bytes());
+    for address in chip8.stack() {
+        hasher.update(&address.to_be_bytes());
+    }
+    hasher.update(&[chip8.delay_timer(), chip8.sound_timer()]);
+    let pixels: Vec<u8> = chip8.display.as_buffer().map(u8::from).to_vec();
+    hasher.update(&pixels);
+    hasher.finalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::hash::RomHash;
+    #[test]
+    fn of_matches_the_usual_tools() {
+        let hash = RomHash::of(b"abc");
+        assert_eq!(hash.sha1(), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(hash.crc32(), "352441c2");
+    }
+}
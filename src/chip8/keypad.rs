@@ -0,0 +1,88 @@
+// Keeps a key pressed after it is let go of until it has been down for a
+// minimum number of frames or the ROM has checked it, so that taps shorter
+// than the time between two checks with EX9E are not missed.
+#[derive(Clone, Default)]
+pub struct Latch {
+    frames: u8,
+    // Frames each key has been down for.
+    held: [u8; 16],
+    observed: [bool; 16],
+    releasing: [bool; 16],
+}
+
+impl Latch {
+    pub fn new(frames: u8) -> Self {
+        Self {
+            frames,
+            ..Self::default()
+        }
+    }
+
+    pub fn press(&mut self, key: usize) {
+        self.held[key] = 0;
+        self.observed[key] = false;
+        self.releasing[key] = false;
+    }
+
+    // Returns whether the key goes up at once, or is kept down for now.
+    pub fn release(&mut self, key: usize) -> bool {
+        let now = self.observed[key] || self.held[key] >= self.frames;
+        self.releasing[key] = !now;
+        now
+    }
+
+    // Returns whether the key, having been seen by the ROM, goes up now.
+    pub fn observe(&mut self, key: usize) -> bool {
+        self.observed[key] = true;
+        std::mem::take(&mut self.releasing[key])
+    }
+
+    // Counts a frame, letting go of the keys held long enough.
+    pub fn tick(&mut self, keyboard: &mut [bool; 16]) {
+        for (key, pressed) in keyboard.iter_mut().enumerate() {
+            if !*pressed {
+                continue;
+            }
+            self.held[key] = self.held[key].saturating_add(1);
+            if self.releasing[key] && self.held[key] >= self.frames {
+                self.releasing[key] = false;
+                *pressed = false;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::keypad::Latch;
+    #[test]
+    fn tap_is_held_for_the_minimum() {
+        let mut latch = Latch::new(3);
+        let mut keyboard = [false; 16];
+        keyboard[5] = true;
+        latch.press(5);
+        latch.tick(&mut keyboard);
+        assert!(!latch.release(5));
+        latch.tick(&mut keyboard);
+        assert!(keyboard[5]);
+        latch.tick(&mut keyboard);
+        assert!(!keyboard[5]);
+    }
+    #[test]
+    fn tap_seen_by_the_rom_goes_up() {
+        let mut latch = Latch::new(3);
+        latch.press(5);
+        assert!(!latch.release(5));
+        assert!(latch.observe(5));
+        assert!(!latch.observe(5));
+        latch.press(5);
+        latch.observe(5);
+        assert!(latch.release(5));
+    }
+    #[test]
+    fn without_latching_keys_go_up_at_once() {
+        let mut latch = Latch::default();
+        latch.press(5);
+        assert!(latch.release(5));
+    }
+}
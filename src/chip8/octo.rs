@@ -0,0 +1,800 @@
+use std::collections::BTreeMap;
+use std::collections::VecDeque;
+
+const START: u16 = 0x200;
+const END: u16 = 0x1000;
+
+// Statements of SCHIP and XO-CHIP, which the interpreter does not run.
+const UNSUPPORTED: [&str; 15] = [
+    "hires",
+    "lores",
+    "scroll-down",
+    "scroll-up",
+    "scroll-left",
+    "scroll-right",
+    "exit",
+    "saveflags",
+    "loadflags",
+    "plane",
+    "audio",
+    "pitch",
+    "bighex",
+    "long",
+    ":stringmode",
+];
+
+#[derive(Clone, Copy)]
+struct Token<'a> {
+    text: &'a str,
+    line: usize,
+}
+
+struct Macro<'a> {
+    params: Vec<&'a str>,
+    body: Vec<Token<'a>>,
+}
+
+// How an address found later goes into the program.
+#[derive(Clone, Copy)]
+enum Fixup {
+    // The lower 12 bits of an instruction.
+    Address,
+    // The low nibble of the byte after the instruction, as in :unpack.
+    High,
+    // The byte after the instruction.
+    Low,
+}
+
+struct Patch<'a> {
+    at: u16,
+    name: &'a str,
+    line: usize,
+    fixup: Fixup,
+}
+
+enum Block {
+    // The jump over the statements to patch, on begin and then on else.
+    Branch(u16),
+    // The first statement of the loop and the jumps out of it on while.
+    Loop(u16, Vec<u16>),
+}
+
+// Turns a program written for Octo, the CHIP-8 assembler by John Earnest,
+// into a ROM. Execution starts at the label main, jumped to from 200. Only
+// the statements of CHIP-8 itself are known, along with :const, :alias,
+// :calc, :macro, :unpack, :next, :org and :byte, and errors name the line.
+pub fn assemble(source: &str) -> Result<Vec<u8>, String> {
+    let mut assembler = Assembler::new(source);
+    while let Some(token) = assembler.tokens.pop_front() {
+        assembler.line = token.line;
+        assembler
+            .statement(token)
+            .map_err(|message| format!("line {}: {}", assembler.line, message))?;
+    }
+    assembler.finish()
+}
+
+struct Assembler<'a> {
+    tokens: VecDeque<Token<'a>>,
+    memory: Vec<u8>,
+    address: u16,
+    end: u16,
+    line: usize,
+    labels: BTreeMap<&'a str, u16>,
+    constants: BTreeMap<&'a str, i64>,
+    aliases: BTreeMap<&'a str, u16>,
+    macros: BTreeMap<&'a str, Macro<'a>>,
+    patches: Vec<Patch<'a>>,
+    blocks: Vec<(Block, usize)>,
+}
+
+impl<'a> Assembler<'a> {
+    fn new(source: &'a str) -> Self {
+        let tokens = source
+            .lines()
+            .enumerate()
+            .flat_map(|(index, line)| {
+                line.split('#')
+                    .next()
+                    .unwrap()
+                    .split_whitespace()
+                    .map(move |text| Token {
+                        text,
+                        line: index + 1,
+                    })
+            })
+            .collect();
+        let mut assembler = Self {
+            tokens,
+            memory: vec![0; (END - START) as usize],
+            address: START,
+            end: START,
+            line: 0,
+            labels: BTreeMap::new(),
+            constants: BTreeMap::new(),
+            aliases: BTreeMap::new(),
+            macros: BTreeMap::new(),
+            patches: Vec::new(),
+            blocks: Vec::new(),
+        };
+        // The jump to main, patched at the end.
+        assembler.memory[0] = 0x10;
+        assembler.address += 2;
+        assembler.end = assembler.address;
+        assembler
+    }
+
+    fn finish(mut self) -> Result<Vec<u8>, String> {
+        if let Some((block, line)) = self.blocks.pop() {
+            let open = match block {
+                Block::Branch(_) => "begin",
+                Block::Loop(..) => "loop",
+            };
+            return Err(format!("line {}: {} is never closed", line, open));
+        }
+        let main = *self
+            .labels
+            .get("main")
+            .ok_or_else(|| String::from("the program has no main label"))?;
+        self.patch(START, main, Fixup::Address);
+        for patch in std::mem::take(&mut self.patches) {
+            let address = *self
+                .labels
+                .get(patch.name)
+                .ok_or_else(|| format!("line {}: \"{}\" is not defined", patch.line, patch.name))?;
+            self.patch(patch.at, address, patch.fixup);
+        }
+        self.memory.truncate((self.end - START) as usize);
+        Ok(self.memory)
+    }
+
+    fn patch(&mut self, at: u16, address: u16, fixup: Fixup) {
+        let index = (at - START) as usize;
+        match fixup {
+            Fixup::Address => {
+                self.memory[index] = (self.memory[index] & 0xF0) | (address >> 8) as u8;
+                self.memory[index + 1] = address as u8;
+            }
+            Fixup::High => {
+                self.memory[index + 1] = (self.memory[index + 1] & 0xF0) | (address >> 8) as u8
+            }
+            Fixup::Low => self.memory[index + 1] = address as u8,
+        }
+    }
+
+    fn byte(&mut self, byte: u8) -> Result<(), String> {
+        if self.address >= END {
+            return Err(String::from("the program does not fit in memory"));
+        }
+        self.memory[(self.address - START) as usize] = byte;
+        self.address += 1;
+        self.end = self.end.max(self.address);
+        Ok(())
+    }
+
+    fn emit(&mut self, word: u16) -> Result<(), String> {
+        self.byte((word >> 8) as u8)?;
+        self.byte(word as u8)
+    }
+
+    fn next(&mut self) -> Result<&'a str, String> {
+        let token = self
+            .tokens
+            .pop_front()
+            .ok_or_else(|| String::from("the program ends in the middle of a statement"))?;
+        self.line = token.line;
+        Ok(token.text)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        match self.next()? {
+            text if text == expected => Ok(()),
+            text => Err(format!("expected {} but found \"{}\"", expected, text)),
+        }
+    }
+
+    fn name(&mut self) -> Result<&'a str, String> {
+        let name = self.next()?;
+        if !is_name(name) || self.register(name).is_some() {
+            return Err(format!("\"{}\" is not a valid name", name));
+        }
+        Ok(name)
+    }
+
+    fn register(&self, text: &str) -> Option<u16> {
+        if let Some(register) = self.aliases.get(text) {
+            return Some(*register);
+        }
+        let digit = text.strip_prefix('v').or_else(|| text.strip_prefix('V'))?;
+        match digit.len() {
+            1 => u16::from_str_radix(digit, 16).ok(),
+            _ => None,
+        }
+    }
+
+    fn vx(&mut self) -> Result<u16, String> {
+        let text = self.next()?;
+        self.register(text)
+            .ok_or_else(|| format!("expected a register but found \"{}\"", text))
+    }
+
+    // A number or a constant, which have to be known by now.
+    fn known(&self, text: &str) -> Option<i64> {
+        number(text).or_else(|| self.constants.get(text).copied())
+    }
+
+    // A number, a constant, a label defined already or an expression in
+    // braces, between min and max.
+    fn value(&mut self, text: &'a str, min: i64, max: i64) -> Result<i64, String> {
+        let value = if text == "{" {
+            self.calc()?
+        } else {
+            self.known(text)
+                .or_else(|| self.labels.get(text).map(|address| *address as i64))
+                .ok_or_else(|| format!("\"{}\" is not a number or a constant", text))?
+        };
+        if value < min || value > max {
+            return Err(format!("{} is out of range", text));
+        }
+        Ok(value)
+    }
+
+    // A byte, where negative numbers stand for their two's complement.
+    fn byte_value(&mut self, text: &'a str) -> Result<u16, String> {
+        self.value(text, -128, 255)
+            .map(|value| (value & 0xFF) as u16)
+    }
+
+    fn immediate(&mut self) -> Result<u16, String> {
+        let text = self.next()?;
+        self.byte_value(text)
+    }
+
+    // An address, where a label can be used before it is defined.
+    fn address(&mut self, opcode: u16) -> Result<(), String> {
+        let text = self.next()?;
+        self.address_of(text, opcode, Fixup::Address)
+    }
+
+    fn address_of(&mut self, text: &'a str, word: u16, fixup: Fixup) -> Result<(), String> {
+        let at = self.address;
+        match self
+            .known(text)
+            .or_else(|| self.labels.get(text).map(|a| *a as i64))
+        {
+            Some(address) if (0..=0xFFF).contains(&address) => {
+                self.emit(word)?;
+                self.patch(at, address as u16, fixup);
+                Ok(())
+            }
+            Some(_) => Err(format!("{} is out of range", text)),
+            None if is_name(text) => {
+                self.emit(word)?;
+                self.patches.push(Patch {
+                    at,
+                    name: text,
+                    line: self.line,
+                    fixup,
+                });
+                Ok(())
+            }
+            None => Err(format!("\"{}\" is not an address", text)),
+        }
+    }
+
+    fn statement(&mut self, token: Token<'a>) -> Result<(), String> {
+        let text = token.text;
+        if UNSUPPORTED.contains(&text) {
+            return Err(format!(
+                "{} is an SCHIP or XO-CHIP statement, which is not supported",
+                text
+            ));
+        }
+        if let Some(x) = self.register(text) {
+            return self.assignment(x);
+        }
+        match text {
+            ":" => {
+                let name = self.name()?;
+                if self.labels.insert(name, self.address).is_some() {
+                    return Err(format!("\"{}\" is defined more than once", name));
+                }
+            }
+            ":const" => {
+                let name = self.name()?;
+                let text = self.next()?;
+                let value = self.value(text, i64::MIN, i64::MAX)?;
+                self.constants.insert(name, value);
+            }
+            ":calc" => {
+                let name = self.name()?;
+                self.expect("{")?;
+                let value = self.calc()?;
+                self.constants.insert(name, value);
+            }
+            ":alias" => {
+                let name = self.name()?;
+                let register = self.vx()?;
+                self.aliases.insert(name, register);
+            }
+            ":macro" => self.define_macro()?,
+            ":unpack" => {
+                let nibble = self.next()?;
+                let nibble = self.value(nibble, 0, 0xF)? as u16;
+                let label = self.next()?;
+                self.address_of(label, 0x6000 | (nibble << 4), Fixup::High)?;
+                self.address_of(label, 0x6100, Fixup::Low)?;
+            }
+            ":next" => {
+                let name = self.name()?;
+                self.labels.insert(name, self.address + 1);
+            }
+            ":org" => {
+                let text = self.next()?;
+                self.address = self.value(text, START as i64, END as i64 - 1)? as u16;
+            }
+            ":byte" => {
+                let byte = self.immediate()?;
+                self.byte(byte as u8)?;
+            }
+            ":call" => self.address(0x2000)?,
+            ":breakpoint" => {
+                self.next()?;
+            }
+            ":monitor" => {
+                self.next()?;
+                self.next()?;
+            }
+            "clear" => self.emit(0x00E0)?,
+            "return" | ";" => self.emit(0x00EE)?,
+            "bcd" => {
+                let x = self.vx()?;
+                self.emit(0xF033 | (x << 8))?;
+            }
+            "save" => {
+                let x = self.vx()?;
+                self.emit(0xF055 | (x << 8))?;
+            }
+            "load" => {
+                let x = self.vx()?;
+                self.emit(0xF065 | (x << 8))?;
+            }
+            "sprite" => {
+                let x = self.vx()?;
+                let y = self.vx()?;
+                let text = self.next()?;
+                let n = self.value(text, 0, 0xF)? as u16;
+                self.emit(0xD000 | (x << 8) | (y << 4) | n)?;
+            }
+            "jump" => self.address(0x1000)?,
+            "jump0" => self.address(0xB000)?,
+            "native" => self.address(0x0000)?,
+            "delay" | "buzzer" => {
+                self.expect(":=")?;
+                let x = self.vx()?;
+                self.emit(if text == "delay" { 0xF015 } else { 0xF018 } | (x << 8))?;
+            }
+            "i" => self.index()?,
+            "if" => self.conditional()?,
+            "else" => {
+                let Some((Block::Branch(jump), line)) = self.blocks.pop() else {
+                    return Err(String::from("else without begin"));
+                };
+                let at = self.address;
+                self.emit(0x1000)?;
+                self.patch(jump, self.address, Fixup::Address);
+                self.blocks.push((Block::Branch(at), line));
+            }
+            "end" => {
+                let Some((Block::Branch(jump), _)) = self.blocks.pop() else {
+                    return Err(String::from("end without begin"));
+                };
+                self.patch(jump, self.address, Fixup::Address);
+            }
+            "loop" => self
+                .blocks
+                .push((Block::Loop(self.address, Vec::new()), self.line)),
+            "while" => {
+                if !matches!(self.blocks.last(), Some((Block::Loop(..), _))) {
+                    return Err(String::from("while outside of a loop"));
+                }
+                let jump = self.test(true)?;
+                if let Some((Block::Loop(_, exits), _)) = self.blocks.last_mut() {
+                    exits.push(jump);
+                }
+            }
+            "again" => {
+                let Some((Block::Loop(start, exits), _)) = self.blocks.pop() else {
+                    return Err(String::from("again without loop"));
+                };
+                self.emit(0x1000 | start)?;
+                for jump in exits {
+                    self.patch(jump, self.address, Fixup::Address);
+                }
+            }
+            _ if self.macros.contains_key(text) => self.expand(text)?,
+            _ if number(text).is_some() || self.constants.contains_key(text) => {
+                let byte = self.byte_value(text)?;
+                self.byte(byte as u8)?;
+            }
+            _ if is_name(text) => self.address_of(text, 0x2000, Fixup::Address)?,
+            _ => return Err(format!("\"{}\" is not a statement", text)),
+        }
+        Ok(())
+    }
+
+    fn assignment(&mut self, x: u16) -> Result<(), String> {
+        let operator = self.next()?;
+        let text = self.next()?;
+        let source = self.register(text);
+        let opcode = match (operator, source, text) {
+            (":=", Some(y), _) => 0x8000 | (y << 4),
+            (":=", None, "key") => 0xF00A,
+            (":=", None, "delay") => 0xF007,
+            (":=", None, "random") => 0xC000 | self.immediate()?,
+            (":=", None, _) => 0x6000 | self.byte_value(text)?,
+            ("+=", Some(y), _) => 0x8004 | (y << 4),
+            ("+=", None, _) => 0x7000 | self.byte_value(text)?,
+            ("-=", Some(y), _) => 0x8005 | (y << 4),
+            // Adding the two's complement instead.
+            ("-=", None, _) => 0x7000 | (self.byte_value(text)?.wrapping_neg() & 0xFF),
+            ("=-", Some(y), _) => 0x8007 | (y << 4),
+            ("|=", Some(y), _) => 0x8001 | (y << 4),
+            ("&=", Some(y), _) => 0x8002 | (y << 4),
+            ("^=", Some(y), _) => 0x8003 | (y << 4),
+            (">>=", Some(y), _) => 0x8006 | (y << 4),
+            ("<<=", Some(y), _) => 0x800E | (y << 4),
+            _ => return Err(format!("cannot assign with {} {}", operator, text)),
+        };
+        self.emit(opcode | (x << 8))
+    }
+
+    fn index(&mut self) -> Result<(), String> {
+        let operator = self.next()?;
+        let text = self.next()?;
+        match (operator, text) {
+            (":=", "hex") => {
+                let x = self.vx()?;
+                self.emit(0xF029 | (x << 8))
+            }
+            (":=", _) if UNSUPPORTED.contains(&text) => Err(format!(
+                "i := {} is an SCHIP or XO-CHIP statement, which is not supported",
+                text
+            )),
+            (":=", "{") => {
+                let address = self.value(text, 0, 0xFFF)? as u16;
+                self.emit(0xA000 | address)
+            }
+            (":=", _) => self.address_of(text, 0xA000, Fixup::Address),
+            ("+=", _) => match self.register(text) {
+                Some(x) => self.emit(0xF01E | (x << 8)),
+                None => Err(format!("expected a register but found \"{}\"", text)),
+            },
+            _ => Err(format!("cannot assign to i with {}", operator)),
+        }
+    }
+
+    fn conditional(&mut self) -> Result<(), String> {
+        let line = self.line;
+        let (setup, when_holds, otherwise) = self.condition()?;
+        for word in setup {
+            self.emit(word)?;
+        }
+        match self.next()? {
+            // Skips the statement when the condition does not hold.
+            "then" => self.emit(otherwise),
+            // Skips the jump past the block when it does.
+            "begin" => {
+                self.emit(when_holds)?;
+                let jump = self.address;
+                self.emit(0x1000)?;
+                self.blocks.push((Block::Branch(jump), line));
+                Ok(())
+            }
+            text => Err(format!("expected then or begin but found \"{}\"", text)),
+        }
+    }
+
+    // Emits a condition that skips the next instruction when it holds, or
+    // else when it does not, followed by a jump to patch, returning where
+    // the jump is.
+    fn test(&mut self, holds: bool) -> Result<u16, String> {
+        let (setup, when_holds, otherwise) = self.condition()?;
+        for word in setup {
+            self.emit(word)?;
+        }
+        self.emit(if holds { when_holds } else { otherwise })?;
+        let jump = self.address;
+        self.emit(0x1000)?;
+        Ok(jump)
+    }
+
+    // The instructions a condition needs first, then the one that skips the
+    // next instruction when it holds and the one that skips it otherwise.
+    fn condition(&mut self) -> Result<(Vec<u16>, u16, u16), String> {
+        let x = self.vx()?;
+        let relation = self.next()?;
+        let (equal, operand) = match relation {
+            "key" => return Ok((Vec::new(), 0xE09E | (x << 8), 0xE0A1 | (x << 8))),
+            "-key" => return Ok((Vec::new(), 0xE0A1 | (x << 8), 0xE09E | (x << 8))),
+            "==" => (true, self.next()?),
+            "!=" => (false, self.next()?),
+            "<" | ">" | "<=" | ">=" => {
+                let operand = self.next()?;
+                return self.comparison(x, relation, operand);
+            }
+            _ => return Err(format!("\"{}\" is not a comparison", relation)),
+        };
+        let (skip_equal, skip_differ) = match self.register(operand) {
+            Some(y) => (0x5000 | (x << 8) | (y << 4), 0x9000 | (x << 8) | (y << 4)),
+            None => {
+                let nn = self.byte_value(operand)?;
+                (0x3000 | (x << 8) | nn, 0x4000 | (x << 8) | nn)
+            }
+        };
+        Ok(if equal {
+            (Vec::new(), skip_equal, skip_differ)
+        } else {
+            (Vec::new(), skip_differ, skip_equal)
+        })
+    }
+
+    // Orders through the borrow flag of a subtraction into vf, which is lost
+    // as in Octo.
+    fn comparison(
+        &mut self,
+        x: u16,
+        relation: &str,
+        operand: &'a str,
+    ) -> Result<(Vec<u16>, u16, u16), String> {
+        // Whether vf ends up 1 when it holds, and whether x is subtracted
+        // from the operand rather than the operand from x.
+        let (set, reversed) = match relation {
+            "<" => (false, false),
+            ">" => (false, true),
+            "<=" => (true, true),
+            _ => (true, false),
+        };
+        let setup = match (self.register(operand), reversed) {
+            (Some(y), false) => vec![0x8F00 | (x << 4), 0x8F05 | (y << 4)],
+            (Some(y), true) => vec![0x8F00 | (y << 4), 0x8F05 | (x << 4)],
+            (None, false) => vec![0x6F00 | self.byte_value(operand)?, 0x8F07 | (x << 4)],
+            (None, true) => vec![0x6F00 | self.byte_value(operand)?, 0x8F05 | (x << 4)],
+        };
+        let (skip_set, skip_clear) = (0x3F01, 0x4F01);
+        Ok(if set {
+            (setup, skip_set, skip_clear)
+        } else {
+            (setup, skip_clear, skip_set)
+        })
+    }
+
+    fn define_macro(&mut self) -> Result<(), String> {
+        let name = self.name()?;
+        let mut params = Vec::new();
+        loop {
+            match self.next()? {
+                "{" => break,
+                param => params.push(param),
+            }
+        }
+        let mut body = Vec::new();
+        let mut depth = 1;
+        loop {
+            let token = self
+                .tokens
+                .pop_front()
+                .ok_or_else(|| format!("the macro {} is never closed", name))?;
+            match token.text {
+                "{" => depth += 1,
+                "}" if depth == 1 => break,
+                "}" => depth -= 1,
+                _ => {}
+            }
+            body.push(token);
+        }
+        self.macros.insert(name, Macro { params, body });
+        Ok(())
+    }
+
+    fn expand(&mut self, name: &str) -> Result<(), String> {
+        let count = self.macros[name].params.len();
+        let mut args = Vec::new();
+        for _ in 0..count {
+            let text = self.next()?;
+            args.push(text);
+        }
+        let expansion: Vec<Token> = self.macros[name]
+            .body
+            .iter()
+            .map(|token| {
+                match self.macros[name]
+                    .params
+                    .iter()
+                    .position(|p| *p == token.text)
+                {
+                    Some(index) => Token {
+                        text: args[index],
+                        line: token.line,
+                    },
+                    None => *token,
+                }
+            })
+            .collect();
+        for token in expansion.into_iter().rev() {
+            self.tokens.push_front(token);
+        }
+        Ok(())
+    }
+
+    // An expression up to the closing brace, worked out right to left as
+    // Octo does, with parentheses to group.
+    fn calc(&mut self) -> Result<i64, String> {
+        let mut tokens = Vec::new();
+        loop {
+            match self.next()? {
+                "}" => break,
+                text => tokens.push(text),
+            }
+        }
+        let (value, rest) = self.expression(&tokens)?;
+        match rest.first() {
+            None => Ok(value.floor() as i64),
+            Some(text) => Err(format!("unexpected \"{}\" in the expression", text)),
+        }
+    }
+
+    fn expression<'t>(&self, tokens: &'t [&'a str]) -> Result<(f64, &'t [&'a str]), String> {
+        let (a, rest) = self.term(tokens)?;
+        let (operator, after) = match rest.split_first() {
+            None | Some((&")", _)) => return Ok((a, rest)),
+            Some(split) => split,
+        };
+        let (b, rest) = self.expression(after)?;
+        let value = match *operator {
+            "+" => a + b,
+            "-" => a - b,
+            "*" => a * b,
+            "/" => a / b,
+            "%" => a % b,
+            "&" => (a as i64 & b as i64) as f64,
+            "|" => (a as i64 | b as i64) as f64,
+            "^" => (a as i64 ^ b as i64) as f64,
+            "<<" => ((a as i64) << b as i64) as f64,
+            ">>" => ((a as i64) >> b as i64) as f64,
+            "min" => a.min(b),
+            "max" => a.max(b),
+            "<" => (a < b) as i64 as f64,
+            ">" => (a > b) as i64 as f64,
+            "<=" => (a <= b) as i64 as f64,
+            ">=" => (a >= b) as i64 as f64,
+            "==" => (a == b) as i64 as f64,
+            "!=" => (a != b) as i64 as f64,
+            _ => return Err(format!("\"{}\" is not an operator", operator)),
+        };
+        Ok((value, rest))
+    }
+
+    fn term<'t>(&self, tokens: &'t [&'a str]) -> Result<(f64, &'t [&'a str]), String> {
+        let (first, rest) = tokens
+            .split_first()
+            .ok_or_else(|| String::from("the expression ends too soon"))?;
+        match *first {
+            "(" => {
+                let (value, rest) = self.expression(rest)?;
+                match rest.split_first() {
+                    Some((&")", rest)) => Ok((value, rest)),
+                    _ => Err(String::from("a parenthesis is never closed")),
+                }
+            }
+            "-" => self.term(rest).map(|(value, rest)| (-value, rest)),
+            "~" => self
+                .term(rest)
+                .map(|(value, rest)| (!(value as i64) as f64, rest)),
+            "!" => self
+                .term(rest)
+                .map(|(value, rest)| ((value == 0.0) as i64 as f64, rest)),
+            "floor" => self.term(rest).map(|(value, rest)| (value.floor(), rest)),
+            "HERE" => Ok((self.address as f64, rest)),
+            text => self
+                .known(text)
+                .or_else(|| self.labels.get(text).map(|address| *address as i64))
+                .map(|value| (value as f64, rest))
+                .ok_or_else(|| format!("\"{}\" is not a number or a constant", text)),
+        }
+    }
+}
+
+fn is_name(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+// Decimal, hexadecimal after 0x or binary after 0b, possibly negative.
+fn number(text: &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = digits.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2).ok()?
+    } else if digits.chars().all(|c| c.is_ascii_digit()) && !digits.is_empty() {
+        digits.parse().ok()?
+    } else {
+        return None;
+    };
+    Some(if negative { -value } else { value })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::octo::assemble;
+    #[test]
+    fn assemble_jumps_to_main_and_resolves_labels() {
+        let source = "
+            # draws a row of digits
+            : main
+                v0 := 5
+                i := digit
+                loop
+                    sprite v0 v1 5
+                    v0 += 1
+                    while v0 != 10
+                again
+                if v1 == 3 then v2 := 4
+                jump main
+            : digit
+                0xF0 0x90 0xF0 0x90 0x90
+        ";
+        assert_eq!(
+            assemble(source).unwrap(),
+            [
+                0x12, 0x02, 0x60, 0x05, 0xA2, 0x16, 0xD0, 0x15, 0x70, 0x01, 0x40, 0x0A, 0x12, 0x10,
+                0x12, 0x06, 0x41, 0x03, 0x62, 0x04, 0x12, 0x02, 0xF0, 0x90, 0xF0, 0x90, 0x90
+            ]
+        );
+    }
+    #[test]
+    fn assemble_expands_the_directives() {
+        let source = "
+            :const speed 3
+            :alias x v4
+            :calc double { speed * 2 }
+            :macro bump reg { reg += speed }
+            : main
+                if x < 7 begin
+                    bump x
+                else
+                    x := double
+                end
+                :unpack 0xA main
+        ";
+        assert_eq!(
+            assemble(source).unwrap(),
+            [
+                0x12, 0x02, 0x6F, 0x07, 0x8F, 0x47, 0x4F, 0x01, 0x12, 0x0E, 0x74, 0x03, 0x12, 0x10,
+                0x64, 0x06, 0x60, 0xA2, 0x61, 0x02
+            ]
+        );
+    }
+    #[test]
+    fn assemble_errors_name_the_line() {
+        let error = |source| assemble(source).unwrap_err();
+        assert_eq!(
+            error(": main\n  clear\n  jump nowhere"),
+            "line 3: \"nowhere\" is not defined"
+        );
+        assert_eq!(error(": main\nv0 := 300"), "line 2: 300 is out of range");
+        assert_eq!(
+            error(": main\n\nhires"),
+            "line 3: hires is an SCHIP or XO-CHIP statement, which is not supported"
+        );
+        assert_eq!(error(": main\nloop\nclear"), "line 2: loop is never closed");
+        assert_eq!(error("clear"), "the program has no main label");
+    }
+}
@@ -0,0 +1,102 @@
+use crate::chip8::MAX_ROM_SIZE;
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::fmt;
+use std::iter;
+
+// The CHIP-8 variants ROMs are written for, each a superset of the one
+// before it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Platform {
+    Chip8,
+    Chip48,
+    Schip,
+    #[value(name = "xochip")]
+    XoChip,
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Platform::Chip8 => "CHIP-8",
+            Platform::Chip48 => "CHIP-48",
+            Platform::Schip => "SCHIP",
+            Platform::XoChip => "XO-CHIP",
+        })
+    }
+}
+
+// Guesses the platform from the instructions the ROM can reach from its
+// entry point, following jumps, calls and skips, so that sprites and other
+// data in between do not pass for instructions of a later variant.
+pub fn detect(rom: &[u8]) -> Platform {
+    let mut platform = if rom.len() > MAX_ROM_SIZE {
+        Platform::XoChip
+    } else {
+        Platform::Chip8
+    };
+    let mut visited = vec![false; rom.len()];
+    let mut pending = vec![0];
+    while let Some(offset) = pending.pop() {
+        if offset + 1 >= rom.len() || std::mem::replace(&mut visited[offset], true) {
+            continue;
+        }
+        let opcode = u16::from_be_bytes([rom[offset], rom[offset + 1]]);
+        // Jumps below the program, into the interpreter, lead nowhere to follow.
+        let target = ((opcode & 0x0FFF) as usize).checked_sub(0x200);
+        let next = offset + 2;
+        let (x, nn) = ((opcode >> 8) & 0xF, opcode & 0xFF);
+        let (seen, paths) = match (opcode >> 12, x, nn) {
+            (0x0, 0x0, 0xE0) => (Platform::Chip8, vec![next]),
+            (0x0, 0x0, 0xEE) => (Platform::Chip8, vec![]),
+            // Scrolling, low and high resolution.
+            (0x0, 0x0, 0xC0..=0xCF | 0xFB | 0xFC | 0xFE | 0xFF) => (Platform::Schip, vec![next]),
+            // Exit.
+            (0x0, 0x0, 0xFD) => (Platform::Schip, vec![]),
+            (0x0, 0x0, 0xD0..=0xDF) => (Platform::XoChip, vec![next]),
+            (0x0, _, _) => (Platform::Chip8, vec![]),
+            (0x1, _, _) => (Platform::Chip8, target.into_iter().collect()),
+            (0x2, _, _) => (Platform::Chip8, iter::once(next).chain(target).collect()),
+            (0x5, _, _) if opcode & 0xF == 0x2 || opcode & 0xF == 0x3 => {
+                (Platform::XoChip, vec![next])
+            }
+            (0x3 | 0x4 | 0x5 | 0x9 | 0xE, _, _) => (Platform::Chip8, vec![next, next + 2]),
+            (0xB, _, _) => (Platform::Chip8, vec![]),
+            (0xD, _, _) if opcode & 0xF == 0 => (Platform::Schip, vec![next]),
+            // Loading I with the 16 bit address in the next word.
+            (0xF, 0x0, 0x00) => (Platform::XoChip, vec![next + 2]),
+            (0xF, _, 0x01 | 0x02 | 0x3A) => (Platform::XoChip, vec![next]),
+            (0xF, _, 0x30 | 0x75 | 0x85) => (Platform::Schip, vec![next]),
+            _ => (Platform::Chip8, vec![next]),
+        };
+        platform = platform.max(seen);
+        pending.extend(paths);
+    }
+    platform
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::asm::assemble;
+    use crate::chip8::platform::detect;
+    use crate::chip8::platform::Platform;
+    #[test]
+    fn detect_follows_the_code() {
+        let chip8 = assemble("CLS\nloop: JP loop").unwrap();
+        assert_eq!(detect(&chip8), Platform::Chip8);
+        // High resolution, but only as sprite data after the loop.
+        let data = assemble("loop: JP loop\nDW 00FF").unwrap();
+        assert_eq!(detect(&data), Platform::Chip8);
+        let schip = assemble("SE V0, 1\nDW 00FF\nloop: JP loop").unwrap();
+        assert_eq!(detect(&schip), Platform::Schip);
+        let xo = assemble("DW F000\nDW 0300\nDW F002\nloop: JP loop").unwrap();
+        assert_eq!(detect(&xo), Platform::XoChip);
+        assert_eq!(detect(&[0x12; 4000]), Platform::XoChip);
+    }
+    #[test]
+    fn detect_ignores_jumps_below_the_program() {
+        assert_eq!(detect(&[0x11, 0xFF]), Platform::Chip8);
+        assert_eq!(detect(&[0x21, 0xFE, 0x00, 0xE0]), Platform::Chip8);
+    }
+}
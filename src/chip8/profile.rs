@@ -0,0 +1,135 @@
+use crate::chip8::disasm::disassemble;
+use crate::chip8::disasm::family;
+use crate::chip8::timing::vip_cycles;
+use crate::chip8::timing::vip_time;
+use crate::chip8::RAM_SIZE;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+// Instructions run, and the machine cycles they would have taken on the
+// COSMAC VIP.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Counts {
+    pub executed: u64,
+    pub vip_cycles: u64,
+}
+
+impl Counts {
+    fn add(&mut self, cycles: u32) {
+        self.executed += 1;
+        self.vip_cycles += cycles as u64;
+    }
+}
+
+// How many instructions ran at each address, of each kind and within each
+// subroutine, so that ROM authors can see which routines the time goes to
+// and emulator developers which instructions are worth making faster.
+#[derive(Clone)]
+pub struct Profile {
+    total: Counts,
+    // Instructions run at each address, with the opcode last run there.
+    addresses: Vec<(Counts, u16)>,
+    // Instructions run within the subroutine starting at each address,
+    // those of the subroutines it calls left out.
+    routines: Vec<Counts>,
+    families: BTreeMap<&'static str, Counts>,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// The counts from the most run down, the first of equal ones first.
+fn ranked<T: Copy + Ord>(counts: impl Iterator<Item = (T, Counts)>) -> Vec<(T, Counts)> {
+    let mut counts: Vec<(T, Counts)> = counts.filter(|(_, counts)| counts.executed > 0).collect();
+    counts.sort_by(|a, b| b.1.executed.cmp(&a.1.executed).then(a.0.cmp(&b.0)));
+    counts
+}
+
+impl Profile {
+    pub fn new() -> Self {
+        Self {
+            total: Counts::default(),
+            addresses: vec![(Counts::default(), 0); RAM_SIZE],
+            routines: vec![Counts::default(); RAM_SIZE],
+            families: BTreeMap::new(),
+        }
+    }
+
+    // Counts the opcode run at the address, within the subroutine starting
+    // at routine.
+    pub fn count(&mut self, address: u16, opcode: u16, routine: u16) {
+        let cycles = vip_cycles(opcode);
+        self.total.add(cycles);
+        let entry = &mut self.addresses[address as usize % RAM_SIZE];
+        entry.0.add(cycles);
+        entry.1 = opcode;
+        self.routines[routine as usize % RAM_SIZE].add(cycles);
+        self.families.entry(family(opcode)).or_default().add(cycles);
+    }
+
+    pub fn total(&self) -> Counts {
+        self.total
+    }
+
+    // The kinds of instruction run, as 8xy4, the most run first.
+    pub fn families(&self) -> Vec<(&'static str, Counts)> {
+        ranked(self.families.iter().map(|(name, counts)| (*name, *counts)))
+    }
+
+    // The subroutines run, by the address they start at, with what runs
+    // outside of any counted at the start of the program.
+    pub fn routines(&self) -> Vec<(u16, Counts)> {
+        ranked((0..).zip(self.routines.iter().copied()))
+    }
+
+    // The addresses run, with the opcode last run there, the most run first.
+    pub fn addresses(&self) -> Vec<(u16, u16, Counts)> {
+        ranked((0..).zip(self.addresses.iter().map(|(counts, _)| *counts)))
+            .into_iter()
+            .map(|(address, counts)| (address, self.addresses[address as usize].1, counts))
+            .collect()
+    }
+
+    // A table of the kinds of instruction run, and of the subroutines and
+    // addresses most run, up to top of each, with the share of the
+    // instructions each ran and how long they would have taken on the
+    // COSMAC VIP.
+    pub fn summary(&self, top: usize) -> String {
+        let columns = |counts: Counts| {
+            format!(
+                "{:>12} {:>6.1}% {:>12.1}",
+                counts.executed,
+                counts.executed as f64 * 100.0 / self.total.executed.max(1) as f64,
+                vip_time(counts.vip_cycles).as_secs_f64() * 1000.0
+            )
+        };
+        let mut text = format!(
+            "{} instructions executed, {:.3} seconds on the COSMAC VIP\n",
+            self.total.executed,
+            vip_time(self.total.vip_cycles).as_secs_f64()
+        );
+        text.push_str("\nInstruction      Executed   Share     VIP (ms)\n");
+        for (name, counts) in self.families() {
+            let _ = writeln!(text, "{:<12} {}", name, columns(counts));
+        }
+        text.push_str("\nSubroutine       Executed   Share     VIP (ms)\n");
+        for (address, counts) in self.routines().into_iter().take(top) {
+            let _ = writeln!(text, "{:03X}          {}", address, columns(counts));
+        }
+        text.push_str("\nAddress  Opcode  Instruction          Executed   Share     VIP (ms)\n");
+        for (address, opcode, counts) in self.addresses().into_iter().take(top) {
+            let _ = writeln!(
+                text,
+                "{:03X}      {:04X}    {:<16} {}",
+                address,
+                opcode,
+                disassemble(opcode),
+                columns(counts)
+            );
+        }
+        text
+    }
+}
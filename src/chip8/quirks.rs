@@ -0,0 +1,168 @@
+use crate::chip8::platform::Platform;
+use serde::Deserialize;
+
+// Named in the configuration file as --quirk names them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Quirk {
+    VfReset,
+    Memory,
+    DisplayWait,
+    Clipping,
+    Shifting,
+    Jumping,
+}
+
+// In the order --quirk lists them.
+pub const NAMES: [(&str, Quirk); 6] = [
+    ("vf-reset", Quirk::VfReset),
+    ("memory", Quirk::Memory),
+    ("display-wait", Quirk::DisplayWait),
+    ("clipping", Quirk::Clipping),
+    ("shifting", Quirk::Shifting),
+    ("jumping", Quirk::Jumping),
+];
+
+// The ways CHIP-8 interpreters differ in running the same instructions,
+// which ROMs written for one of them come to rely on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quirks {
+    // 8xy1, 8xy2 and 8xy3 reset VF.
+    pub vf_reset: bool,
+    // Fx55 and Fx65 leave I past the last register.
+    pub memory: bool,
+    // Dxyn waits for the next frame, as drawing waited for the display
+    // interrupt on the COSMAC VIP.
+    pub display_wait: bool,
+    // Sprites are cut at the edges of the display instead of wrapping
+    // around to the other side.
+    pub clipping: bool,
+    // 8xy6 and 8xyE shift Vx in place instead of storing Vy shifted in Vx.
+    pub shifting: bool,
+    // Bxnn jumps to xnn plus Vx instead of Bnnn to nnn plus V0.
+    pub jumping: bool,
+}
+
+// What the interpreter has always done when no platform is given.
+impl Default for Quirks {
+    fn default() -> Self {
+        Self {
+            vf_reset: false,
+            memory: false,
+            display_wait: false,
+            clipping: true,
+            shifting: true,
+            jumping: false,
+        }
+    }
+}
+
+impl Quirks {
+    pub fn of(platform: Platform) -> Self {
+        let (vf_reset, memory, display_wait, clipping, shifting, jumping) = match platform {
+            Platform::Chip8 => (true, true, true, true, false, false),
+            Platform::Chip48 => (false, true, false, true, true, true),
+            Platform::Schip => (false, false, false, true, true, true),
+            Platform::XoChip => (false, true, false, false, false, false),
+        };
+        Self {
+            vf_reset,
+            memory,
+            display_wait,
+            clipping,
+            shifting,
+            jumping,
+        }
+    }
+
+    // Those of the platform, if any, with the given ones turned on or off.
+    pub fn resolve(platform: Option<Platform>, overrides: &[(Quirk, bool)]) -> Self {
+        platform
+            .map(Self::of)
+            .unwrap_or_default()
+            .overridden(overrides)
+    }
+
+    fn flag(&mut self, quirk: Quirk) -> &mut bool {
+        match quirk {
+            Quirk::VfReset => &mut self.vf_reset,
+            Quirk::Memory => &mut self.memory,
+            Quirk::DisplayWait => &mut self.display_wait,
+            Quirk::Clipping => &mut self.clipping,
+            Quirk::Shifting => &mut self.shifting,
+            Quirk::Jumping => &mut self.jumping,
+        }
+    }
+
+    // These with the given ones turned on or off.
+    pub fn overridden(self, overrides: &[(Quirk, bool)]) -> Self {
+        let mut quirks = self;
+        for (quirk, on) in overrides {
+            *quirks.flag(*quirk) = *on;
+        }
+        quirks
+    }
+
+    // Every quirk by the name --quirk knows it by, with whether it is on.
+    pub fn listed(mut self) -> [(&'static str, bool); 6] {
+        NAMES.map(|(name, quirk)| (name, *self.flag(quirk)))
+    }
+}
+
+// Reads a quirk turned on or off, as in shifting=off.
+pub fn parse_override(text: &str) -> Result<(Quirk, bool), String> {
+    let (name, state) = text
+        .split_once('=')
+        .ok_or_else(|| String::from("expected NAME=on or NAME=off"))?;
+    let quirk = NAMES
+        .iter()
+        .find(|(known, _)| *known == name)
+        .map(|(_, quirk)| *quirk)
+        .ok_or_else(|| {
+            let names: Vec<&str> = NAMES.iter().map(|(name, _)| *name).collect();
+            format!("unknown quirk '{}' (available: {})", name, names.join(", "))
+        })?;
+    match state {
+        "on" => Ok((quirk, true)),
+        "off" => Ok((quirk, false)),
+        _ => Err(format!("'{}' is neither on nor off", state)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::platform::Platform;
+    use crate::chip8::quirks::parse_override;
+    use crate::chip8::quirks::Quirk;
+    use crate::chip8::quirks::Quirks;
+    #[test]
+    fn resolve_layers_overrides_over_the_platform() {
+        assert_eq!(Quirks::resolve(None, &[]), Quirks::default());
+        let quirks = Quirks::resolve(
+            Some(Platform::Chip8),
+            &[(Quirk::DisplayWait, false), (Quirk::Shifting, true)],
+        );
+        assert!(quirks.vf_reset && quirks.memory && quirks.shifting);
+        assert!(!quirks.display_wait && !quirks.jumping);
+        assert_eq!(
+            Quirks::resolve(Some(Platform::Schip), &[(Quirk::Memory, true)]),
+            Quirks {
+                memory: true,
+                ..Quirks::of(Platform::Chip48)
+            }
+        );
+        assert_eq!(Quirks::default().listed()[3], ("clipping", true));
+    }
+    #[test]
+    fn parse_override_names_the_quirks() {
+        assert_eq!(parse_override("vf-reset=on"), Ok((Quirk::VfReset, true)));
+        assert_eq!(parse_override("clipping=off"), Ok((Quirk::Clipping, false)));
+        assert_eq!(
+            parse_override("wrap=on").unwrap_err(),
+            "unknown quirk 'wrap' (available: vf-reset, memory, display-wait, clipping, shifting, \
+             jumping)"
+        );
+        assert!(parse_override("memory").is_err());
+        assert!(parse_override("memory=yes").is_err());
+    }
+}
@@ -0,0 +1,87 @@
+use crate::chip8::disasm::family;
+use std::time::Duration;
+
+// A machine cycle of the COSMAC VIP, 8 pulses of its 1.7609 MHz clock.
+pub const VIP_CYCLE: Duration = Duration::from_nanos(4543);
+
+// An instruction of each kind, to list what each costs.
+const KINDS: [u16; 35] = [
+    0x00E0, 0x00EE, 0x0000, 0x1000, 0x2000, 0x3000, 0x4000, 0x5000, 0x6000, 0x7000, 0x8000, 0x8001,
+    0x8002, 0x8003, 0x8004, 0x8005, 0x8006, 0x8007, 0x800E, 0x9000, 0xA000, 0xB000, 0xC000, 0xD000,
+    0xE09E, 0xE0A1, 0xF007, 0xF00A, 0xF015, 0xF018, 0xF01E, 0xF029, 0xF033, 0xF055, 0xF065,
+];
+
+// Machine cycles the interpreter of the COSMAC VIP takes, on average, to
+// carry out an instruction, whatever the quirks or the speed the machine
+// runs at, for tools to tell how long a program would have taken on the
+// original hardware rather than how many instructions it ran. A draw waits
+// for the display interrupt, as it did there, and a skip taken costs no
+// more here. Machine code called with 0nnn, and data, cost nothing, and
+// Fx0A counts without the wait for a key.
+pub fn vip_cycles(opcode: u16) -> u32 {
+    let digits = (
+        opcode >> 12,
+        (opcode & 0x0F00) >> 8,
+        (opcode & 0x00F0) >> 4,
+        opcode & 0x000F,
+    );
+    match digits {
+        (0x0, 0x0, 0xE, 0x0) => 24,
+        (0x0, 0x0, 0xE, 0xE) => 23,
+        (0x0, _, _, _) => 0,
+        (0x1, _, _, _) | (0x2, _, _, _) | (0xB, _, _, _) => 23,
+        (0x3, _, _, _) | (0x4, _, _, _) | (0xA, _, _, _) => 12,
+        (0x5, _, _, 0x0) | (0x9, _, _, 0x0) => 16,
+        (0x6, _, _, _) => 6,
+        (0x7, _, _, _) => 10,
+        (0x8, _, _, 0x0..=0x7 | 0xE) => 44,
+        (0xC, _, _, _) => 36,
+        (0xD, _, _, _) => 5004,
+        (0xE, _, 0x9, 0xE) | (0xE, _, 0xA, 0x1) => 16,
+        (0xF, _, 0x0, 0x7) | (0xF, _, 0x0, 0xA) | (0xF, _, 0x1, 0x5) | (0xF, _, 0x1, 0x8) => 10,
+        (0xF, _, 0x1, 0xE) => 19,
+        (0xF, _, 0x2, 0x9) => 20,
+        (0xF, _, 0x3, 0x3) => 204,
+        (0xF, _, 0x5, 0x5) | (0xF, _, 0x6, 0x5) => 133,
+        _ => 0,
+    }
+}
+
+// The machine cycles of each kind of instruction, named as disasm::family
+// names them, in the order of Cowgod's reference.
+pub fn vip_table() -> Vec<(&'static str, u32)> {
+    KINDS
+        .iter()
+        .map(|opcode| (family(*opcode), vip_cycles(*opcode)))
+        .collect()
+}
+
+// How long the machine cycles took on the COSMAC VIP.
+pub fn vip_time(cycles: u64) -> Duration {
+    Duration::from_nanos(cycles.saturating_mul(VIP_CYCLE.as_nanos() as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::timing::vip_cycles;
+    use crate::chip8::timing::vip_table;
+    use crate::chip8::timing::vip_time;
+    use std::time::Duration;
+    #[test]
+    fn vip_cycles_depend_on_the_kind_only() {
+        assert_eq!(vip_cycles(0x6A0F), 6);
+        assert_eq!(vip_cycles(0x6000), 6);
+        assert_eq!(vip_cycles(0x8124), 44);
+        assert_eq!(vip_cycles(0xD015), 5004);
+        assert_eq!(vip_cycles(0x5121), 0);
+    }
+    #[test]
+    fn vip_table_lists_every_kind_once() {
+        let table = vip_table();
+        assert_eq!(table.len(), 35);
+        assert_eq!(table[0], ("00E0", 24));
+        assert_eq!(table[34], ("Fx65", 133));
+        assert!(table.iter().all(|(name, _)| *name != "data"));
+        assert_eq!(vip_time(1000), Duration::from_micros(4543));
+    }
+}
@@ -0,0 +1,419 @@
+pub mod portable;
+pub mod sidecar;
+
+use crate::audio::AudioOptions;
+use crate::chip8::platform::Platform;
+use crate::chip8::quirks::Quirk;
+use crate::frontend::backdrop::BackgroundOptions;
+use crate::frontend::bezel::BezelOptions;
+use crate::frontend::browser::BrowserOptions;
+use crate::frontend::buttons::Buttons;
+use crate::frontend::crt::CrtOptions;
+use crate::frontend::keymap::Keymap;
+use crate::frontend::keymap::KEYPAD;
+use crate::frontend::macros::Macros;
+use crate::frontend::midi::MidiOptions;
+use crate::frontend::monitor::WindowOptions;
+use crate::frontend::palette::Tone;
+use crate::frontend::stick::StickOptions;
+#[cfg(any(feature = "sdl", feature = "egui"))]
+use crate::frontend::watch::Watch;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+#[cfg(any(feature = "sdl", feature = "egui"))]
+use std::time::Instant;
+#[cfg(any(feature = "sdl", feature = "egui"))]
+use tracing::info;
+
+// Every setting with its default, commented, except for the bindings, which
+// are spelled out in full so that they can be edited in place.
+const TEMPLATE: &str = include_str!("config/template.toml");
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub emulation: EmulationOptions,
+    pub crt: CrtOptions,
+    pub color: Tone,
+    pub bezel: BezelOptions,
+    pub background: BackgroundOptions,
+    pub window: WindowOptions,
+    pub audio: AudioOptions,
+    pub keys: Keymap,
+    pub buttons: Buttons,
+    pub stick: StickOptions,
+    pub macros: Macros,
+    pub midi: MidiOptions,
+    pub browser: BrowserOptions,
+    pub discord: DiscordOptions,
+    // Named sets of settings laid out as the rest of the file, which
+    // --profile puts in place of those of the file.
+    pub profiles: BTreeMap<String, toml::Table>,
+}
+
+// The tables of bindings a profile replaces as a whole, since a layout of
+// keys added to another one would press the same values twice.
+const BINDINGS: [&str; 4] = ["keys", "buttons", "stick", "macros"];
+
+// What the emulator starts with when neither the command line nor the ROM's
+// own settings say.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EmulationOptions {
+    pub speed: Option<u16>,
+    pub palette: Option<String>,
+    pub platform: Option<Platform>,
+    // Quirks turned on or off over those of the platform, as --quirk does.
+    pub quirks: BTreeMap<Quirk, bool>,
+}
+
+// Showing the ROM played on Discord, which takes a build with the discord
+// feature, though any build reads the table.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiscordOptions {
+    // Whether to show the ROM being played on the user's Discord profile.
+    pub presence: bool,
+    // The application registered on Discord's developer portal, whose name
+    // Discord shows above the ROM.
+    pub client_id: Option<String>,
+}
+
+// The settings a configuration file edited while the emulator runs changes,
+// which take effect at once. Everything else waits for the next start.
+#[cfg(any(feature = "sdl", feature = "egui"))]
+#[derive(Debug, Default, PartialEq)]
+pub struct Changes {
+    pub speed: Option<u16>,
+    pub palette: Option<String>,
+    pub tone: Option<Tone>,
+    pub keys: Option<Keymap>,
+}
+
+#[cfg(any(feature = "sdl", feature = "egui"))]
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    // Names what changed, as in "speed 1500, keys".
+    pub fn describe(&self) -> String {
+        let mut changes = Vec::new();
+        if let Some(speed) = self.speed {
+            changes.push(format!("speed {}", speed));
+        }
+        if let Some(palette) = &self.palette {
+            changes.push(format!("palette {}", palette));
+        }
+        if let Some(tone) = self.tone {
+            changes.push(format!(
+                "brightness {}, gamma {}",
+                tone.brightness, tone.gamma
+            ));
+        }
+        if self.keys.is_some() {
+            changes.push(String::from("keys"));
+        }
+        changes.join(", ")
+    }
+}
+
+// The configuration file, read again whenever it is written to so that
+// speed, palette, colors and keys can be tweaked without restarting.
+#[cfg(any(feature = "sdl", feature = "egui"))]
+pub struct LiveConfig {
+    watch: Watch,
+    profile: Option<String>,
+    config: Config,
+}
+
+#[cfg(any(feature = "sdl", feature = "egui"))]
+impl LiveConfig {
+    // Watches the file given, or else the one in the standard place, which
+    // may only be created later. What it has now is read again, as the
+    // settings in effect are those of the command line and the ROM as well.
+    pub fn new(path: Option<&Path>, profile: Option<&str>) -> Option<Self> {
+        let config = Config::find(path, profile).unwrap_or_default();
+        let path = path.map(Path::to_path_buf).or_else(Config::default_path)?;
+        Some(Self {
+            watch: Watch::new(&path),
+            profile: profile.map(String::from),
+            config,
+        })
+    }
+
+    // What the file changed since it was last read, if it was written to, or
+    // why it cannot be read, leaving the settings as they were.
+    pub fn poll(&mut self, now: Instant) -> Result<Option<Changes>, String> {
+        if !self.watch.changed(now) {
+            return Ok(None);
+        }
+        let config = Config::load(self.watch.path(), self.profile.as_deref())?;
+        let changes = self.config.changes(&config);
+        self.config = config;
+        if !changes.is_empty() {
+            info!(
+                "{} reloaded: {}",
+                self.watch.path().display(),
+                changes.describe()
+            );
+        }
+        Ok(Some(changes).filter(|changes| !changes.is_empty()))
+    }
+}
+
+impl Config {
+    pub fn default_path() -> Option<PathBuf> {
+        portable::config_dir().map(|dir| dir.join("config.toml"))
+    }
+
+    // The settings of the file, with those of the profile, if any, in their
+    // place.
+    pub fn load(path: &Path, profile: Option<&str>) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("unable to read {}: {}", path.display(), e))?;
+        let config: Self =
+            toml::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let Some(name) = profile else {
+            return Ok(config);
+        };
+        let overlay = config.profiles.get(name).ok_or_else(|| {
+            let names: Vec<&str> = config.profiles.keys().map(String::as_str).collect();
+            format!(
+                "{}: no profile named '{}' (available: {})",
+                path.display(),
+                name,
+                if names.is_empty() {
+                    String::from("none")
+                } else {
+                    names.join(", ")
+                }
+            )
+        })?;
+        // Having been read as a configuration already, the file is a table.
+        let mut table: toml::Table = toml::from_str(&content).unwrap();
+        for (section, settings) in overlay {
+            match (table.get_mut(section), settings) {
+                (Some(toml::Value::Table(table)), toml::Value::Table(settings))
+                    if !BINDINGS.contains(&section.as_str()) =>
+                {
+                    merge(table, settings)
+                }
+                _ => {
+                    table.insert(section.clone(), settings.clone());
+                }
+            }
+        }
+        toml::Value::Table(table)
+            .try_into()
+            .map_err(|e| format!("{}: profile {}: {}", path.display(), name, e))
+    }
+
+    // An explicitly requested file must exist, while the default location is
+    // only read when present, unless a profile has to be read from it.
+    pub fn find(path: Option<&Path>, profile: Option<&str>) -> Result<Self, String> {
+        match path {
+            Some(path) => Self::load(path, profile),
+            None => match Self::default_path() {
+                Some(path) if path.exists() => Self::load(&path, profile),
+                _ if profile.is_some() => Err(String::from(
+                    "there is no configuration file to read profiles from",
+                )),
+                _ => Ok(Self::default()),
+            },
+        }
+    }
+
+    // The settings that differ in the new configuration, each only when the
+    // file changed it, so that the command line and the ROM's own settings
+    // stay in effect for the rest.
+    #[cfg(any(feature = "sdl", feature = "egui"))]
+    pub fn changes(&self, new: &Config) -> Changes {
+        let emulation = &new.emulation;
+        Changes {
+            speed: emulation
+                .speed
+                .filter(|_| emulation.speed != self.emulation.speed),
+            palette: emulation
+                .palette
+                .clone()
+                .filter(|_| emulation.palette != self.emulation.palette),
+            tone: Some(new.color).filter(|tone| *tone != self.color),
+            keys: Some(&new.keys).filter(|keys| **keys != self.keys).cloned(),
+        }
+    }
+
+    // Writes the commented template, leaving alone a file already there.
+    pub fn generate(path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("unable to create {}: {}", dir.display(), e))?;
+        }
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(TEMPLATE.as_bytes()))
+            .map_err(|e| format!("unable to write {}: {}", path.display(), e))
+    }
+
+    // What presses each keypad value, row by row as on the keypad, followed
+    // by the macros.
+    pub fn bindings(&self) -> String {
+        let mut lines = vec![format!("{:<8}{:<16}{}", "Keypad", "Keyboard", "Controller")];
+        for value in KEYPAD.iter().flatten() {
+            let mut controls: Vec<String> = self
+                .buttons
+                .buttons(*value)
+                .into_iter()
+                .map(String::from)
+                .collect();
+            controls.extend(
+                self.stick
+                    .directions(*value)
+                    .into_iter()
+                    .map(|direction| format!("stick {}", direction)),
+            );
+            lines.push(format!(
+                "{:<8}{:<16}{}",
+                format!("{:X}", value),
+                self.keys.keys(*value).join(", "),
+                controls.join(", ")
+            ));
+        }
+        let macros = self.macros.describe();
+        if !macros.is_empty() {
+            lines.push(String::new());
+            lines.push(format!("{:<8}{}", "Macro", "Presses"));
+            for (name, binding) in macros {
+                lines.push(format!("{:<8}{}", name, binding));
+            }
+        }
+        lines
+            .iter()
+            .map(|line| line.trim_end())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+// Puts each setting of the profile in place of the one of the file, going
+// into tables such as [emulation.quirks], so that a profile only has to
+// hold what it changes.
+fn merge(table: &mut toml::Table, settings: &toml::Table) {
+    for (key, value) in settings {
+        match (table.get_mut(key), value) {
+            (Some(toml::Value::Table(table)), toml::Value::Table(settings)) => {
+                merge(table, settings)
+            }
+            _ => {
+                table.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::platform::Platform;
+    use crate::chip8::quirks::Quirk;
+    #[cfg(any(feature = "sdl", feature = "egui"))]
+    use crate::config::Changes;
+    use crate::config::Config;
+    use crate::config::TEMPLATE;
+    use crate::frontend::buttons::Buttons;
+    use crate::frontend::keymap::Keymap;
+    use crate::frontend::stick::StickOptions;
+    use std::fs;
+    #[test]
+    fn template_spells_out_the_defaults() {
+        let config: Config = toml::from_str(TEMPLATE).unwrap();
+        assert_eq!(config.keys, Keymap::default());
+        assert_eq!(config.buttons, Buttons::default());
+        assert_eq!(config.stick, StickOptions::default());
+        // Every commented setting has to be valid once uncommented.
+        let uncommented: Vec<&str> = TEMPLATE
+            .lines()
+            .map(|line| match line.strip_prefix("# ") {
+                Some(setting) if setting.starts_with('[') || setting.contains(" = ") => setting,
+                _ => line,
+            })
+            .collect();
+        toml::from_str::<Config>(&uncommented.join("\n")).unwrap();
+    }
+    #[test]
+    fn bindings_list_every_control() {
+        let config = Config {
+            macros: toml::from_str("T = { autofire = 5 }").unwrap(),
+            ..Default::default()
+        };
+        let bindings = config.bindings();
+        let lines: Vec<&str> = bindings.lines().collect();
+        assert_eq!(lines[0], "Keypad  Keyboard        Controller");
+        assert_eq!(lines[1], "1       1");
+        assert_eq!(lines[6], "5       W               DPadUp, stick up");
+        assert_eq!(lines[19], "T       autofire 5, 10 times a second");
+    }
+    #[cfg(any(feature = "sdl", feature = "egui"))]
+    #[test]
+    fn changes_only_name_what_the_file_changed() {
+        let old: Config =
+            toml::from_str("[emulation]\nspeed = 1000\n[color]\ngamma = 1.2").unwrap();
+        assert!(old.changes(&old.clone()).is_empty());
+        let new: Config = toml::from_str(
+            "[emulation]\nspeed = 1500\npalette = \"amber\"\n[color]\ngamma = 1.2\n[keys]\nUp = 5",
+        )
+        .unwrap();
+        let changes = old.changes(&new);
+        assert_eq!(
+            changes,
+            Changes {
+                speed: Some(1500),
+                palette: Some(String::from("amber")),
+                tone: None,
+                keys: Some(new.keys.clone()),
+            }
+        );
+        assert_eq!(changes.describe(), "speed 1500, palette amber, keys");
+        // Dropping a setting leaves the one in effect alone.
+        assert!(new.changes(&Config::default()).speed.is_none());
+    }
+    #[test]
+    fn load_puts_the_profile_over_the_file() {
+        let path = std::env::temp_dir().join(format!("chip8-profiles-{}.toml", std::process::id()));
+        fs::write(
+            &path,
+            "[emulation]\nspeed = 700\npalette = \"amber\"\n[keys]\nW = 5\nS = 8\n\
+             [profiles.couch.emulation]\nspeed = 1000\nplatform = \"schip\"\n\
+             quirks = { clipping = false, vf-reset = true }\n\
+             [profiles.couch.keys]\nUp = 5\n\
+             [profiles.couch.window]\nborderless = true",
+        )
+        .unwrap();
+        let plain = Config::load(&path, None);
+        let couch = Config::load(&path, Some("couch"));
+        let missing = Config::load(&path, Some("tas"));
+        fs::remove_file(&path).unwrap();
+        let plain = plain.unwrap();
+        assert_eq!(plain.emulation.speed, Some(700));
+        assert!(!plain.window.borderless);
+        let couch = couch.unwrap();
+        assert_eq!(couch.emulation.speed, Some(1000));
+        assert_eq!(couch.emulation.palette.as_deref(), Some("amber"));
+        assert_eq!(couch.emulation.platform, Some(Platform::Schip));
+        assert_eq!(
+            couch.emulation.quirks.into_iter().collect::<Vec<_>>(),
+            [(Quirk::VfReset, true), (Quirk::Clipping, false)]
+        );
+        assert_eq!(couch.keys, toml::from_str("Up = 5").unwrap());
+        assert!(couch.window.borderless);
+        assert!(missing
+            .unwrap_err()
+            .ends_with("no profile named 'tas' (available: couch)"));
+    }
+}
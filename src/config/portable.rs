@@ -0,0 +1,66 @@
+use std::env;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+// The file that makes the emulator portable when it is next to the
+// executable, so that nothing has to be given on the command line.
+const MARKER: &str = "portable.txt";
+
+// The directory beside the executable that holds the configuration, the
+// per-ROM settings and the recent ROMs when portable, as when running from a
+// USB stick, instead of the user's directories.
+static DIR: OnceLock<PathBuf> = OnceLock::new();
+
+fn beside(exe: &Path) -> PathBuf {
+    exe.with_file_name("chip8-emu-rust-data")
+}
+
+// Goes portable when asked to or when the marker is next to the executable,
+// which has to be found for either.
+pub fn init(portable: bool) -> Result<(), String> {
+    let exe = match env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) if portable => return Err(format!("unable to find the executable: {}", e)),
+        Err(_) => return Ok(()),
+    };
+    if portable || exe.with_file_name(MARKER).exists() {
+        DIR.get_or_init(|| beside(&exe));
+    }
+    Ok(())
+}
+
+pub fn dir() -> Option<&'static Path> {
+    DIR.get().map(PathBuf::as_path)
+}
+
+// Where the configuration goes, the portable directory or the emulator's
+// one in the user configuration directory.
+pub fn config_dir() -> Option<PathBuf> {
+    match dir() {
+        Some(dir) => Some(dir.to_path_buf()),
+        None => dirs::config_dir().map(|dir| dir.join("chip8-emu-rust")),
+    }
+}
+
+// Where what the emulator records goes, the portable directory or the
+// emulator's one in the user data directory.
+pub fn data_dir() -> Option<PathBuf> {
+    match dir() {
+        Some(dir) => Some(dir.to_path_buf()),
+        None => dirs::data_dir().map(|dir| dir.join("chip8-emu-rust")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::portable::beside;
+    use std::path::Path;
+    #[test]
+    fn beside_keeps_the_directory_of_the_executable() {
+        assert_eq!(
+            beside(Path::new("/media/usb/chip8-emu-rust")),
+            Path::new("/media/usb/chip8-emu-rust-data")
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use crate::chip8::hash::RomHash;
+use crate::chip8::platform::Platform;
+use crate::chip8::quirks::Quirk;
+use crate::config::Config;
+use crate::frontend::keymap::Keymap;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+// Settings for a single ROM, read from a file next to it named after the ROM
+// with .toml appended, such as pong.ch8.toml, or else from one named after
+// its SHA-1 in the roms directory beside the configuration file, which keeps
+// applying however the ROM is renamed. Whatever it leaves out stays as the
+// configuration file and the command line have it.
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Sidecar {
+    pub speed: Option<u16>,
+    pub palette: Option<String>,
+    pub platform: Option<Platform>,
+    // Quirks turned on or off over those of the platform, as --quirk does.
+    pub quirks: BTreeMap<Quirk, bool>,
+    // A keymap file, relative to the directory of the sidecar file, or else
+    // the bindings themselves in a [keys] table.
+    pub keymap: Option<PathBuf>,
+    pub keys: Option<Keymap>,
+}
+
+impl Sidecar {
+    pub fn path(rom: &Path) -> PathBuf {
+        let mut name = OsString::from(rom.as_os_str());
+        name.push(".toml");
+        PathBuf::from(name)
+    }
+
+    pub fn hashed_path(hash: &RomHash) -> Option<PathBuf> {
+        let config = Config::default_path()?;
+        Some(
+            config
+                .with_file_name("roms")
+                .join(format!("{}.toml", hash.sha1())),
+        )
+    }
+
+    pub fn locate(rom: &Path, hash: &RomHash) -> Option<PathBuf> {
+        let mut candidates = vec![Self::path(rom)];
+        candidates.extend(Self::hashed_path(hash));
+        candidates.into_iter().find(|path| path.exists())
+    }
+
+    // The settings for the ROM, none at all when it has no sidecar file.
+    pub fn find(rom: &Path, hash: &RomHash) -> Result<Self, String> {
+        match Self::locate(rom, hash) {
+            Some(path) => Self::load(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    // A keymap file is read in place of the [keys] table.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("unable to read {}: {}", path.display(), e))?;
+        let mut sidecar: Self =
+            toml::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))?;
+        if let Some(keymap) = sidecar.keymap.take() {
+            if sidecar.keys.is_some() {
+                return Err(format!(
+                    "{}: keymap and [keys] cannot be used together",
+                    path.display()
+                ));
+            }
+            let dir = path.parent().unwrap_or(Path::new(""));
+            sidecar.keys = Some(Keymap::load(&dir.join(keymap))?);
+        }
+        Ok(sidecar)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::hash::RomHash;
+    use crate::chip8::platform::Platform;
+    use crate::chip8::quirks::Quirk;
+    use crate::chip8::quirks::Quirks;
+    use crate::config::sidecar::Sidecar;
+    use crate::frontend::keymap::Keymap;
+    use crate::Cli;
+    use clap::Parser;
+    use std::fs;
+    use std::path::Path;
+    #[test]
+    fn path_appends_toml() {
+        assert_eq!(
+            Sidecar::path(Path::new("roms/pong.ch8")),
+            Path::new("roms/pong.ch8.toml")
+        );
+        assert_eq!(Sidecar::path(Path::new("PONG")), Path::new("PONG.toml"));
+    }
+    #[test]
+    fn find_reads_the_keymap_next_to_the_rom() {
+        let dir = std::env::temp_dir().join(format!("chip8-sidecar-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let rom = dir.join("pong.ch8");
+        let hash = RomHash::of(&[0x12, 0x00]);
+        assert_eq!(Sidecar::locate(&rom, &hash), None);
+        fs::write(dir.join("paddles.toml"), "Up = 1\nDown = 4").unwrap();
+        fs::write(
+            dir.join("pong.ch8.toml"),
+            "speed = 500\npalette = \"deuteranopia\"\nkeymap = \"paddles.toml\"",
+        )
+        .unwrap();
+        assert_eq!(Sidecar::locate(&rom, &hash), Some(Sidecar::path(&rom)));
+        let sidecar = Sidecar::find(&rom, &hash);
+        fs::write(
+            dir.join("pong.ch8.toml"),
+            "keymap = \"paddles.toml\"\n[keys]\nW = 1",
+        )
+        .unwrap();
+        let conflict = Sidecar::find(&rom, &hash);
+        fs::remove_dir_all(&dir).unwrap();
+        let sidecar = sidecar.unwrap();
+        assert_eq!(sidecar.speed, Some(500));
+        assert_eq!(sidecar.palette.as_deref(), Some("deuteranopia"));
+        let keys: Keymap = toml::from_str("Up = 1\nDown = 4").unwrap();
+        assert_eq!(sidecar.keys, Some(keys));
+        assert!(conflict
+            .unwrap_err()
+            .ends_with("keymap and [keys] cannot be used together"));
+    }
+    #[test]
+    fn quirks_go_between_those_of_the_configuration_and_the_command_line() {
+        let sidecar: Sidecar =
+            toml::from_str("platform = \"schip\"\nquirks = { clipping = false, shifting = false }")
+                .unwrap();
+        let cli = Cli::try_parse_from(["chip8-emu-rust", "--quirk", "shifting=on", "PONG"]);
+        let mut args = cli.unwrap().run;
+        args.config_quirks = vec![(Quirk::Clipping, true), (Quirk::Memory, true)];
+        assert_eq!(
+            args.quirks(sidecar.platform, &sidecar.quirks, None),
+            Quirks::of(Platform::Schip).overridden(&[
+                (Quirk::Memory, true),
+                (Quirk::Clipping, false),
+                (Quirk::Shifting, true)
+            ])
+        );
+        assert!(toml::from_str::<Sidecar>("quirks = { sprites = true }").is_err());
+    }
+}
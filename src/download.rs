@@ -0,0 +1,95 @@
+#[cfg(feature = "download")]
+use crate::chip8::MAX_ROM_SIZE;
+#[cfg(feature = "download")]
+use crate::frontend::cartridge::is_source;
+#[cfg(feature = "download")]
+use std::io::Read;
+#[cfg(feature = "download")]
+use std::path::Path;
+
+// Octo cartridges and source take many more bytes than the ROM they turn
+// into.
+#[cfg(feature = "download")]
+const OCTO_LIMIT: usize = 0x100000;
+
+pub fn is_url(rom: &str) -> bool {
+    rom.starts_with("http://") || rom.starts_with("https://")
+}
+
+#[cfg(feature = "download")]
+pub fn download(url: &str) -> Result<Vec<u8>, String> {
+    // ureq's errors name the URL already.
+    let response = ureq::get(url)
+        .call()
+        .map_err(|e| format!("unable to download {}", e))?;
+    let length = response
+        .header("Content-Length")
+        .and_then(|length| length.parse::<usize>().ok());
+    let limit = limit(url);
+    if length.is_some_and(|length| length > limit) {
+        return Err(too_big(url, limit));
+    }
+    let mut rom = Vec::new();
+    response
+        .into_reader()
+        .take(limit as u64 + 1)
+        .read_to_end(&mut rom)
+        .map_err(|e| format!("unable to download {}: {}", url, e))?;
+    if rom.len() > limit {
+        return Err(too_big(url, limit));
+    }
+    Ok(rom)
+}
+
+#[cfg(not(feature = "download"))]
+pub fn download(url: &str) -> Result<Vec<u8>, String> {
+    Err(format!(
+        "unable to download {}, built without the download feature",
+        url
+    ))
+}
+
+// The most a download takes: what fits in memory, so that it stops short
+// of anything that could not be a ROM, or more for Octo files, told apart
+// by the extension as the data is not there yet.
+#[cfg(feature = "download")]
+fn limit(url: &str) -> usize {
+    let path = Path::new(url.split(['?', '#']).next().unwrap_or(url));
+    let cartridge = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("gif"));
+    if cartridge || is_source(path) {
+        OCTO_LIMIT
+    } else {
+        MAX_ROM_SIZE
+    }
+}
+
+#[cfg(feature = "download")]
+fn too_big(url: &str, limit: usize) -> String {
+    format!("{} is larger than the {} bytes it can take", url, limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::download::is_url;
+    #[cfg(feature = "download")]
+    use crate::download::limit;
+    #[test]
+    fn is_url_takes_only_http() {
+        assert!(is_url(
+            "https://johnearnest.github.io/chip8Archive/roms/br8kout.ch8"
+        ));
+        assert!(is_url("http://example.com/PONG"));
+        assert!(!is_url("roms/PONG"));
+        assert!(!is_url("ftp://example.com/PONG"));
+        assert!(!is_url("https.ch8"));
+    }
+    #[cfg(feature = "download")]
+    #[test]
+    fn limit_leaves_room_for_octo_files() {
+        assert_eq!(limit("https://example.com/br8kout.ch8"), 3584);
+        assert_eq!(limit("https://example.com/game.GIF?raw=true"), 0x100000);
+        assert_eq!(limit("https://example.com/game.8o"), 0x100000);
+    }
+}
@@ -0,0 +1,84 @@
+use std::any::Any;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+// Why the emulator stopped short, each kind with its own exit status so that
+// scripts can tell them apart. Command line mistakes exit with 2, as clap
+// has them.
+#[derive(Debug, PartialEq)]
+pub enum Failure {
+    // A tool found a problem with the ROM, or could not do its job.
+    Tool(String),
+    // A file, or a setting, given on the command line or in a configuration
+    // file cannot be used.
+    Input(String),
+    // There is nowhere to show the display.
+    Video(String),
+    // The ROM stopped itself before --max-cycles or --timeout was reached.
+    Halted(String),
+    // The ROM made the interpreter fail, as with an instruction it does not
+    // know.
+    Crashed(String),
+}
+
+impl Failure {
+    // A file that cannot be read, telling a missing one from the rest.
+    pub fn file(kind: &str, path: &Path, error: io::Error) -> Self {
+        Self::Input(match error.kind() {
+            io::ErrorKind::NotFound => format!("{} file not found: {}", kind, path.display()),
+            _ => format!("unable to read {} file {}: {}", kind, path.display(), error),
+        })
+    }
+
+    // The interpreter failing, with what made it panic.
+    pub fn crashed(cause: Box<dyn Any + Send>) -> Self {
+        let message = cause
+            .downcast_ref::<String>()
+            .cloned()
+            .or_else(|| cause.downcast_ref::<&str>().map(|text| text.to_string()))
+            .unwrap_or_default();
+        Self::Crashed(format!("the interpreter failed: {}", message))
+    }
+
+    pub fn code(&self) -> i32 {
+        match self {
+            Self::Tool(_) => 1,
+            Self::Input(_) => 3,
+            Self::Video(_) => 4,
+            Self::Halted(_) => 5,
+            Self::Crashed(_) => 6,
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Tool(message)
+            | Self::Input(message)
+            | Self::Video(message)
+            | Self::Halted(message)
+            | Self::Crashed(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::failure::Failure;
+    use std::io;
+    use std::path::Path;
+    #[test]
+    fn file_tells_missing_files_apart() {
+        let missing = io::Error::from(io::ErrorKind::NotFound);
+        let failure = Failure::file("ROM", Path::new("roms/PONG3"), missing);
+        assert_eq!(failure.to_string(), "ROM file not found: roms/PONG3");
+        assert_eq!(failure.code(), 3);
+        let denied = io::Error::from(io::ErrorKind::PermissionDenied);
+        assert_eq!(
+            Failure::file("ROM", Path::new("PONG"), denied).to_string(),
+            "unable to read ROM file PONG: permission denied"
+        );
+    }
+}
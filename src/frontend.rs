@@ -0,0 +1,78 @@
+#[cfg(feature = "sdl")]
+pub mod achievements;
+pub mod backdrop;
+pub mod bezel;
+pub mod browser;
+pub mod buttons;
+#[cfg(feature = "sdl")]
+pub mod canvas;
+pub mod cartridge;
+#[cfg(feature = "sdl")]
+pub mod chat;
+#[cfg(feature = "sdl")]
+pub mod compare;
+pub mod control;
+#[cfg(feature = "sdl")]
+pub mod controller;
+pub mod crt;
+#[cfg(feature = "sdl")]
+pub mod debugger;
+#[cfg(all(feature = "dialog", any(feature = "sdl", feature = "egui")))]
+pub mod dialog;
+pub mod dump;
+#[cfg(feature = "egui")]
+pub mod egui;
+pub mod focus;
+pub mod framebuffer;
+pub mod glyphs;
+pub mod graphics;
+#[cfg(feature = "sdl")]
+pub mod input;
+pub mod keymap;
+#[cfg(feature = "sdl")]
+pub mod keypad;
+pub mod layout;
+pub mod limits;
+pub mod macros;
+pub mod metrics;
+pub mod midi;
+#[cfg(feature = "minifb")]
+pub mod minifb;
+pub mod monitor;
+#[cfg(feature = "sdl")]
+pub mod netplay;
+pub mod notifications;
+pub mod pacing;
+pub mod palette;
+pub mod phosphor;
+pub mod playlist;
+pub mod pokes;
+#[cfg(all(feature = "discord", feature = "sdl"))]
+pub mod presence;
+pub mod recent;
+#[cfg(feature = "sdl")]
+pub mod renderer;
+#[cfg(feature = "sdl")]
+pub mod replay;
+pub mod rpc;
+#[cfg(feature = "sdl")]
+pub mod rumble;
+pub mod runner;
+pub mod script;
+#[cfg(feature = "sdl")]
+pub mod sdl;
+pub mod shape;
+pub mod speed;
+pub mod splash;
+pub mod stats;
+pub mod stick;
+pub mod terminal;
+#[cfg(any(feature = "sdl", feature = "minifb"))]
+pub mod text;
+pub mod title;
+#[cfg(any(feature = "sdl", feature = "egui"))]
+pub mod watch;
+pub mod web;
+pub mod websocket;
+#[cfg(feature = "wgpu")]
+pub mod wgpu;
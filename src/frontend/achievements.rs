@@ -0,0 +1,339 @@
+use crate::chip8::hash::RomHash;
+use crate::chip8::Chip8;
+use crate::config::portable;
+use crate::config::Config;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use tracing::info;
+use tracing::warn;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operand {
+    Ram(u16),
+    V(u8),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+// A byte of memory or a register compared with a number, as in
+// RAM[0x3A0] >= 10.
+#[derive(Debug, PartialEq)]
+struct Condition {
+    operand: Operand,
+    comparison: Comparison,
+    value: u8,
+}
+
+fn number(text: &str) -> Option<u32> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+impl Condition {
+    fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        // The longer operators first, so that >= is not read as >.
+        let operators = [
+            ("==", Comparison::Equal),
+            ("!=", Comparison::NotEqual),
+            ("<=", Comparison::LessOrEqual),
+            (">=", Comparison::GreaterOrEqual),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+        ];
+        let (left, comparison, right) = operators
+            .iter()
+            .find_map(|(operator, comparison)| {
+                let (left, right) = text.split_once(operator)?;
+                Some((left.trim(), *comparison, right.trim()))
+            })
+            .ok_or_else(|| format!("\"{}\" compares nothing, as in RAM[0x3A0] >= 10", text))?;
+        let index = |prefix: &str| {
+            let inside = left
+                .get(..prefix.len())
+                .filter(|start| start.eq_ignore_ascii_case(prefix))
+                .and_then(|_| left[prefix.len()..].strip_suffix(']'))?;
+            number(inside.trim())
+        };
+        let operand = match (index("RAM["), index("V[")) {
+            (Some(address), _) if address < 0x1000 => Operand::Ram(address as u16),
+            (_, Some(register)) if register < 16 => Operand::V(register as u8),
+            _ => {
+                return Err(format!(
+                    "\"{}\" is not RAM[0x000] to RAM[0xFFF] or V[0] to V[15]",
+                    left
+                ))
+            }
+        };
+        let value = number(right)
+            .and_then(|value| u8::try_from(value).ok())
+            .ok_or_else(|| format!("\"{}\" is not a byte, 0 to 255", right))?;
+        Ok(Self {
+            operand,
+            comparison,
+            value,
+        })
+    }
+
+    fn holds(&self, chip8: &Chip8) -> bool {
+        let byte = match self.operand {
+            Operand::Ram(address) => chip8.ram[address as usize],
+            Operand::V(register) => chip8.reg_v()[register as usize],
+        };
+        match self.comparison {
+            Comparison::Equal => byte == self.value,
+            Comparison::NotEqual => byte != self.value,
+            Comparison::Less => byte < self.value,
+            Comparison::LessOrEqual => byte <= self.value,
+            Comparison::Greater => byte > self.value,
+            Comparison::GreaterOrEqual => byte >= self.value,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Definition {
+    title: String,
+    // Conditions that all have to hold together, joined by &&.
+    when: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Definitions {
+    #[serde(rename = "achievement")]
+    achievements: Vec<Definition>,
+}
+
+struct Achievement {
+    title: String,
+    conditions: Vec<Condition>,
+}
+
+// When each achievement of each ROM was unlocked, by the SHA-1 of the ROM
+// and the title of the achievement, in seconds since the Unix epoch.
+type Unlocked = BTreeMap<String, BTreeMap<String, u64>>;
+
+// The achievements of a ROM, unlocked as soon as what they ask for shows in
+// its memory or registers, read from a file named after the ROM with
+// .achievements.toml appended, or else after its SHA-1 in the achievements
+// directory beside the configuration file, as sidecar files are.
+pub struct Achievements {
+    sha1: String,
+    achievements: Vec<Achievement>,
+    // Titles unlocked already, in this session or an earlier one.
+    unlocked: BTreeMap<String, u64>,
+}
+
+impl Achievements {
+    fn path(rom: &Path) -> PathBuf {
+        let mut name = OsString::from(rom.as_os_str());
+        name.push(".achievements.toml");
+        PathBuf::from(name)
+    }
+
+    fn hashed_path(hash: &RomHash) -> Option<PathBuf> {
+        let config = Config::default_path()?;
+        Some(
+            config
+                .with_file_name("achievements")
+                .join(format!("{}.toml", hash.sha1())),
+        )
+    }
+
+    fn unlocked_path() -> Option<PathBuf> {
+        portable::data_dir().map(|dir| dir.join("achievements.toml"))
+    }
+
+    // The achievements of the ROM, if it has any.
+    pub fn find(rom: &Path) -> Result<Option<Self>, String> {
+        let Ok(data) = fs::read(rom) else {
+            return Ok(None);
+        };
+        let hash = RomHash::of(&data);
+        let mut candidates = vec![Self::path(rom)];
+        candidates.extend(Self::hashed_path(&hash));
+        let Some(path) = candidates.into_iter().find(|path| path.exists()) else {
+            return Ok(None);
+        };
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("unable to read {}: {}", path.display(), e))?;
+        let mut achievements =
+            Self::parse(&content, hash.sha1()).map_err(|e| format!("{}: {}", path.display(), e))?;
+        achievements.unlocked = Self::load_unlocked()
+            .remove(&achievements.sha1)
+            .unwrap_or_default();
+        info!(
+            "{} of {} achievements unlocked",
+            achievements.unlocked.len(),
+            achievements.achievements.len()
+        );
+        Ok(Some(achievements))
+    }
+
+    fn parse(content: &str, sha1: String) -> Result<Self, String> {
+        let definitions: Definitions = toml::from_str(content).map_err(|e| e.to_string())?;
+        let achievements = definitions
+            .achievements
+            .into_iter()
+            .map(|definition| {
+                let conditions = definition
+                    .when
+                    .split("&&")
+                    .map(Condition::parse)
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| format!("achievement \"{}\": {}", definition.title, e))?;
+                Ok(Achievement {
+                    title: definition.title,
+                    conditions,
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self {
+            sha1,
+            achievements,
+            unlocked: BTreeMap::new(),
+        })
+    }
+
+    // Nothing has been unlocked yet when the file is missing or unreadable.
+    fn load_unlocked() -> Unlocked {
+        let Some(path) = Self::unlocked_path().filter(|path| path.exists()) else {
+            return Unlocked::new();
+        };
+        fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| toml::from_str(&content).map_err(|e| e.to_string()))
+            .unwrap_or_else(|e| {
+                warn!("unlocked achievements ignored, {}: {}", path.display(), e);
+                Unlocked::new()
+            })
+    }
+
+    // Adds what was unlocked to the file, keeping the other ROMs' as they
+    // are.
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = Self::unlocked_path() else {
+            return Ok(());
+        };
+        let error = |e: std::io::Error| format!("unable to write {}: {}", path.display(), e);
+        let mut unlocked = Self::load_unlocked();
+        unlocked.insert(self.sha1.clone(), self.unlocked.clone());
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(error)?;
+        }
+        fs::write(&path, toml::to_string(&unlocked).unwrap()).map_err(error)
+    }
+
+    // The titles of the achievements unlocked by the state the machine is
+    // in, which are kept as unlocked from then on.
+    fn unlock(&mut self, chip8: &Chip8, now: u64) -> Vec<String> {
+        let mut titles = Vec::new();
+        for achievement in &self.achievements {
+            if self.unlocked.contains_key(&achievement.title)
+                || !achievement
+                    .conditions
+                    .iter()
+                    .all(|condition| condition.holds(chip8))
+            {
+                continue;
+            }
+            self.unlocked.insert(achievement.title.clone(), now);
+            titles.push(achievement.title.clone());
+        }
+        titles
+    }
+
+    // Unlocks what the machine now shows, saving it at once, and returns the
+    // titles to announce.
+    pub fn check(&mut self, chip8: &Chip8) -> Vec<String> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let titles = self.unlock(chip8, now);
+        if !titles.is_empty() {
+            if let Err(e) = self.save() {
+                warn!("{}", e);
+            }
+        }
+        titles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::Chip8;
+    use crate::frontend::achievements::Achievements;
+    use crate::frontend::achievements::Comparison;
+    use crate::frontend::achievements::Condition;
+    use crate::frontend::achievements::Operand;
+    #[test]
+    fn condition_reads_memory_and_registers() {
+        assert_eq!(
+            Condition::parse(" RAM[0x3A0] >= 10 "),
+            Ok(Condition {
+                operand: Operand::Ram(0x3A0),
+                comparison: Comparison::GreaterOrEqual,
+                value: 10
+            })
+        );
+        assert_eq!(
+            Condition::parse("v[15] != 0x01"),
+            Ok(Condition {
+                operand: Operand::V(15),
+                comparison: Comparison::NotEqual,
+                value: 1
+            })
+        );
+        assert!(Condition::parse("RAM[0x1000] == 1")
+            .unwrap_err()
+            .contains("RAM[0xFFF]"));
+        assert!(Condition::parse("RAM[0x300] == 256")
+            .unwrap_err()
+            .contains("not a byte"));
+        assert!(Condition::parse("RAM[0x300]")
+            .unwrap_err()
+            .contains("compares nothing"));
+    }
+    #[test]
+    fn achievements_unlock_once() {
+        let mut achievements = Achievements::parse(
+            "[[achievement]]\ntitle = \"Score 10\"\nwhen = \"RAM[0x3A0] >= 10\"\n\
+             [[achievement]]\ntitle = \"Perfect\"\nwhen = \"RAM[0x3A0] >= 10 && V[0] == 0\"",
+            String::from("sha1"),
+        )
+        .unwrap();
+        let mut chip8 = Chip8::new();
+        chip8.load(&[0x60, 0x01]).unwrap();
+        chip8.fetch_execute();
+        assert!(achievements.unlock(&chip8, 1).is_empty());
+        chip8.ram[0x3A0] = 12;
+        assert_eq!(achievements.unlock(&chip8, 2), ["Score 10"]);
+        assert!(achievements.unlock(&chip8, 3).is_empty());
+        assert_eq!(achievements.unlocked.get("Score 10"), Some(&2));
+        let error = Achievements::parse(
+            "[[achievement]]\ntitle = \"Broken\"\nwhen = \"I == 1\"",
+            String::new(),
+        )
+        .err()
+        .unwrap();
+        assert!(error.starts_with("achievement \"Broken\": \"I\" is not RAM"));
+    }
+}
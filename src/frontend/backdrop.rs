@@ -0,0 +1,109 @@
+#[cfg(feature = "sdl")]
+use crate::frontend::bezel::decode;
+use crate::frontend::palette::Rgb;
+use serde::Deserialize;
+#[cfg(feature = "sdl")]
+use std::fs::File;
+#[cfg(feature = "sdl")]
+use std::path::Path;
+use std::path::PathBuf;
+
+// Rows in a gradient, enough for the steps not to show.
+#[cfg(feature = "sdl")]
+const GRADIENT_STEPS: u32 = 256;
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackgroundOptions {
+    pub image: Option<PathBuf>,
+    // Top and bottom colors of a vertical gradient.
+    pub gradient: Option<[Rgb; 2]>,
+}
+
+// An image shown through the unlit pixels of the display, stretched over it,
+// with the lit pixels drawn on top.
+#[cfg(feature = "sdl")]
+pub struct Backdrop {
+    pub width: u32,
+    pub height: u32,
+    // RGBA, row by row.
+    pub pixels: Vec<u8>,
+}
+
+#[cfg(feature = "sdl")]
+impl Backdrop {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let file =
+            File::open(path).map_err(|e| format!("unable to open {}: {}", path.display(), e))?;
+        let (width, height, pixels) =
+            decode(file).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    pub fn gradient(top: Rgb, bottom: Rgb) -> Self {
+        let pixels = (0..GRADIENT_STEPS)
+            .flat_map(|row| {
+                let color = top.blend(bottom, row as f32 / (GRADIENT_STEPS - 1) as f32);
+                [color.0, color.1, color.2, 255]
+            })
+            .collect();
+        Self {
+            width: 1,
+            height: GRADIENT_STEPS,
+            pixels,
+        }
+    }
+
+    // An image takes precedence over a gradient, and either of them given on
+    // the command line over the configuration file.
+    pub fn select(
+        image: Option<&Path>,
+        gradient: Option<[Rgb; 2]>,
+        options: &BackgroundOptions,
+    ) -> Result<Option<Self>, String> {
+        let (image, gradient) = match (image, gradient) {
+            (None, None) => (options.image.as_deref(), options.gradient),
+            given => given,
+        };
+        match (image, gradient) {
+            (Some(path), _) => Self::load(path).map(Some),
+            (None, Some([top, bottom])) => Ok(Some(Self::gradient(top, bottom))),
+            (None, None) => Ok(None),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "sdl"))]
+mod tests {
+    use crate::frontend::backdrop::Backdrop;
+    use crate::frontend::backdrop::BackgroundOptions;
+    use crate::frontend::palette::Rgb;
+    #[test]
+    fn gradient_runs_from_top_to_bottom() {
+        let backdrop = Backdrop::gradient(Rgb(0, 0, 0), Rgb(255, 0, 100));
+        assert_eq!((backdrop.width, backdrop.height), (1, 256));
+        assert_eq!(&backdrop.pixels[..4], &[0, 0, 0, 255]);
+        assert_eq!(
+            &backdrop.pixels[backdrop.pixels.len() - 4..],
+            &[255, 0, 100, 255]
+        );
+    }
+    #[test]
+    fn command_line_overrides_the_config() {
+        let options = BackgroundOptions {
+            image: None,
+            gradient: Some([Rgb(1, 1, 1), Rgb(2, 2, 2)]),
+        };
+        let backdrop = Backdrop::select(None, Some([Rgb(9, 9, 9), Rgb(9, 9, 9)]), &options)
+            .unwrap()
+            .unwrap();
+        assert_eq!(&backdrop.pixels[..4], &[9, 9, 9, 255]);
+        assert!(Backdrop::select(None, None, &BackgroundOptions::default())
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,238 @@
+#[cfg(feature = "sdl")]
+use crate::frontend::layout::fit;
+#[cfg(feature = "sdl")]
+use crate::frontend::layout::place;
+#[cfg(feature = "sdl")]
+use crate::frontend::layout::PixelAspect;
+#[cfg(feature = "sdl")]
+use crate::frontend::layout::Scaling;
+#[cfg(feature = "sdl")]
+use crate::frontend::layout::Viewport;
+use serde::Deserialize;
+#[cfg(feature = "sdl")]
+use std::fs::File;
+#[cfg(feature = "sdl")]
+use std::io::Read;
+#[cfg(feature = "sdl")]
+use std::path::Path;
+use std::path::PathBuf;
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BezelOptions {
+    pub image: Option<PathBuf>,
+    // Area of the image the display goes into, as x, y, width and height in
+    // image pixels. When missing, the transparent part of the image is used.
+    pub viewport: Option<[u32; 4]>,
+}
+
+// A frame drawn around the display, such as a picture of an old monitor or
+// of a handheld with the screen cut out.
+#[cfg(feature = "sdl")]
+pub struct Bezel {
+    pub width: u32,
+    pub height: u32,
+    // RGBA, row by row.
+    pub pixels: Vec<u8>,
+    screen: Viewport,
+}
+
+#[cfg(feature = "sdl")]
+impl Bezel {
+    pub fn load(path: &Path, viewport: Option<[u32; 4]>) -> Result<Self, String> {
+        let file =
+            File::open(path).map_err(|e| format!("unable to open {}: {}", path.display(), e))?;
+        let (width, height, pixels) =
+            decode(file).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let screen = match viewport {
+            Some([x, y, w, h]) if w > 0 && h > 0 && x + w <= width && y + h <= height => Viewport {
+                x: x as i32,
+                y: y as i32,
+                width: w,
+                height: h,
+            },
+            Some(_) => {
+                return Err(format!(
+                    "{}: the viewport does not fit in the {}x{} image",
+                    path.display(),
+                    width,
+                    height
+                ))
+            }
+            None => transparent_area(width, height, &pixels).ok_or_else(|| {
+                format!(
+                    "{}: no transparent area for the display, set a viewport",
+                    path.display()
+                )
+            })?,
+        };
+        Ok(Self {
+            width,
+            height,
+            pixels,
+            screen,
+        })
+    }
+
+    // Fits the whole image into the output and places the display inside its
+    // screen area, returning where each of them goes.
+    pub fn layout(
+        &self,
+        output: (u32, u32),
+        scaling: Scaling,
+        aspect: PixelAspect,
+    ) -> (Viewport, Viewport) {
+        let area = fit(output, (self.width, self.height));
+        let scale_x = |x: i64| (x * area.width as i64 / self.width as i64) as i32;
+        let scale_y = |y: i64| (y * area.height as i64 / self.height as i64) as i32;
+        let left = area.x + scale_x(self.screen.x as i64);
+        let top = area.y + scale_y(self.screen.y as i64);
+        let right = area.x + scale_x((self.screen.x as u32 + self.screen.width) as i64);
+        let bottom = area.y + scale_y((self.screen.y as u32 + self.screen.height) as i64);
+        let display = place(
+            ((right - left) as u32, (bottom - top) as u32),
+            aspect.display(),
+            scaling,
+        );
+        let display = Viewport {
+            x: left + display.x,
+            y: top + display.y,
+            ..display
+        };
+        (area, display)
+    }
+}
+
+// Where the bezel, if any, and the display go in the output.
+#[cfg(feature = "sdl")]
+pub fn arrange(
+    bezel: Option<&Bezel>,
+    output: (u32, u32),
+    scaling: Scaling,
+    aspect: PixelAspect,
+) -> (Option<Viewport>, Viewport) {
+    match bezel {
+        Some(bezel) => {
+            let (area, display) = bezel.layout(output, scaling, aspect);
+            (Some(area), display)
+        }
+        None => (None, place(output, aspect.display(), scaling)),
+    }
+}
+
+// Decodes a PNG of any color type into 8 bit RGBA.
+#[cfg(feature = "sdl")]
+pub fn decode(reader: impl Read) -> Result<(u32, u32, Vec<u8>), png::DecodingError> {
+    let mut decoder = png::Decoder::new(reader);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+    buffer.truncate(info.buffer_size());
+    let pixels = match info.color_type {
+        png::ColorType::Rgba => buffer,
+        png::ColorType::Rgb => buffer
+            .chunks(3)
+            .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => buffer
+            .chunks(2)
+            .flat_map(|ga| [ga[0], ga[0], ga[0], ga[1]])
+            .collect(),
+        _ => buffer.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+    };
+    Ok((info.width, info.height, pixels))
+}
+
+// The smallest rectangle holding every fully transparent pixel.
+#[cfg(feature = "sdl")]
+fn transparent_area(width: u32, height: u32, pixels: &[u8]) -> Option<Viewport> {
+    let mut bounds: Option<(u32, u32, u32, u32)> = None;
+    for y in 0..height {
+        for x in 0..width {
+            if pixels[((y * width + x) * 4 + 3) as usize] != 0 {
+                continue;
+            }
+            let (left, top, right, bottom) = bounds.unwrap_or((x, y, x, y));
+            bounds = Some((left.min(x), top.min(y), right.max(x), bottom.max(y)));
+        }
+    }
+    bounds.map(|(left, top, right, bottom)| Viewport {
+        x: left as i32,
+        y: top as i32,
+        width: right - left + 1,
+        height: bottom - top + 1,
+    })
+}
+
+#[cfg(all(test, feature = "sdl"))]
+mod tests {
+    use crate::frontend::bezel::decode;
+    use crate::frontend::bezel::transparent_area;
+    use crate::frontend::bezel::Bezel;
+    use crate::frontend::layout::PixelAspect;
+    use crate::frontend::layout::Scaling;
+    use crate::frontend::layout::Viewport;
+    #[test]
+    fn decode_expands_to_rgba() {
+        let mut encoded = Vec::new();
+        let mut encoder = png::Encoder::new(&mut encoded, 2, 1);
+        encoder.set_color(png::ColorType::Rgb);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[1, 2, 3, 4, 5, 6]).unwrap();
+        writer.finish().unwrap();
+        let (width, height, pixels) = decode(encoded.as_slice()).unwrap();
+        assert_eq!((width, height), (2, 1));
+        assert_eq!(pixels, [1, 2, 3, 255, 4, 5, 6, 255]);
+    }
+    #[test]
+    fn transparent_area_bounds_the_hole() {
+        let mut pixels = vec![255; 4 * 4 * 4];
+        for (x, y) in [(1, 1), (2, 2)] {
+            pixels[(y * 4 + x) * 4 + 3] = 0;
+        }
+        assert_eq!(
+            transparent_area(4, 4, &pixels),
+            Some(Viewport {
+                x: 1,
+                y: 1,
+                width: 2,
+                height: 2
+            })
+        );
+        assert_eq!(transparent_area(4, 4, &[255; 4 * 4 * 4]), None);
+    }
+    #[test]
+    fn layout_puts_the_display_in_the_screen_area() {
+        let bezel = Bezel {
+            width: 100,
+            height: 50,
+            pixels: Vec::new(),
+            screen: Viewport {
+                x: 10,
+                y: 5,
+                width: 80,
+                height: 40,
+            },
+        };
+        let (area, display) = bezel.layout((200, 200), Scaling::Fit, PixelAspect::default());
+        assert_eq!(
+            area,
+            Viewport {
+                x: 0,
+                y: 50,
+                width: 200,
+                height: 100
+            }
+        );
+        assert_eq!(
+            display,
+            Viewport {
+                x: 20,
+                y: 60,
+                width: 160,
+                height: 80
+            }
+        );
+    }
+}
@@ -0,0 +1,252 @@
+#[cfg(feature = "sdl")]
+use crate::chip8::platform::detect;
+#[cfg(feature = "sdl")]
+use crate::chip8::platform::Platform;
+#[cfg(feature = "sdl")]
+use crate::frontend::cartridge::Cartridge;
+#[cfg(feature = "sdl")]
+use crate::frontend::recent::ago;
+#[cfg(feature = "sdl")]
+use crate::frontend::recent::now;
+#[cfg(feature = "sdl")]
+use crate::frontend::recent::Played;
+#[cfg(feature = "sdl")]
+use crate::frontend::recent::Recent;
+use serde::Deserialize;
+#[cfg(feature = "sdl")]
+use std::fs;
+#[cfg(feature = "sdl")]
+use std::path::Path;
+use std::path::PathBuf;
+
+// Files bigger than this are not ROMs of any CHIP-8 variant.
+#[cfg(feature = "sdl")]
+const MAX_SIZE: u64 = 0x10000;
+
+// Octo cartridges are GIF images, and Octo source is assembled on loading.
+#[cfg(any(feature = "sdl", all(feature = "dialog", feature = "egui")))]
+pub const EXTENSIONS: [&str; 7] = ["ch8", "c8", "sc8", "xo8", "ch10", "gif", "8o"];
+
+#[cfg(feature = "sdl")]
+const TITLE_WIDTH: usize = 30;
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BrowserOptions {
+    // Where the ROMs to pick from are when the emulator starts without one,
+    // the current directory if missing.
+    pub dir: Option<PathBuf>,
+}
+
+#[cfg(feature = "sdl")]
+pub struct Entry {
+    pub path: PathBuf,
+    pub title: String,
+    pub size: u64,
+    pub platform: Platform,
+    // When it was played, and with which settings, for the recent ROMs.
+    pub played: Option<Played>,
+}
+
+#[cfg(feature = "sdl")]
+impl Entry {
+    fn read(path: PathBuf, title: String, played: Option<Played>) -> Option<Self> {
+        let data = fs::read(&path).ok()?;
+        let size = data.len() as u64;
+        // Octo cartridges and source are told apart by what they assemble
+        // into, when they do.
+        let rom = Cartridge::open(&path, data.clone()).map_or(data, |(rom, _)| rom);
+        Some(Self {
+            path,
+            title,
+            size,
+            platform: detect(&rom),
+            played,
+        })
+    }
+}
+
+// The ROMs in a directory, or those played last, to start one of them from
+// within the window.
+#[cfg(feature = "sdl")]
+pub struct Browser {
+    header: String,
+    entries: Vec<Entry>,
+    selected: usize,
+}
+
+#[cfg(feature = "sdl")]
+impl Browser {
+    // Lists the files with a ROM extension, or none at all as many ROMs come
+    // without one, sorted by title.
+    pub fn scan(dir: &Path) -> Result<Self, String> {
+        let error = |e: std::io::Error| format!("unable to read {}: {}", dir.display(), e);
+        let mut entries = Vec::new();
+        for item in fs::read_dir(dir).map_err(error)? {
+            let path = item.map_err(error)?.path();
+            let Some(title) = path.file_stem().map(|stem| stem.to_string_lossy()) else {
+                continue;
+            };
+            let rom_extension = path.extension().is_none_or(|extension| {
+                EXTENSIONS
+                    .iter()
+                    .any(|rom| extension.eq_ignore_ascii_case(rom))
+            });
+            let size = fs::metadata(&path).map(|metadata| metadata.len());
+            if title.starts_with('.') || !rom_extension || !path.is_file() {
+                continue;
+            }
+            let Ok(1..=MAX_SIZE) = size else {
+                continue;
+            };
+            let title = title.into_owned();
+            let Some(entry) = Entry::read(path, title, None) else {
+                continue;
+            };
+            entries.push(entry);
+        }
+        entries.sort_by_key(|entry| entry.title.to_lowercase());
+        Ok(Self {
+            header: format!("ROMs in {}", dir.display()),
+            entries,
+            selected: 0,
+        })
+    }
+
+    // The ROMs played last that are still there, the latest first.
+    pub fn recent(recent: &Recent) -> Self {
+        let entries = recent
+            .roms
+            .iter()
+            .filter_map(|played| {
+                let title = played.path.file_name()?.to_string_lossy().into_owned();
+                Entry::read(played.path.clone(), title, Some(played.clone()))
+            })
+            .collect();
+        Self {
+            header: String::from("Recently played"),
+            entries,
+            selected: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Moves the selection up or down by as many entries, stopping at either
+    // end of the list.
+    pub fn step(&mut self, delta: isize) {
+        let last = self.entries.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+    }
+
+    pub fn selected(&self) -> Option<&Entry> {
+        self.entries.get(self.selected)
+    }
+
+    // What to show in the given number of rows: the directory, a page of
+    // entries, telling whether each is the selected one, and the keys.
+    pub fn lines(&self, rows: usize) -> Vec<(String, bool)> {
+        let mut lines = vec![(self.header.clone(), false)];
+        let now = now();
+        let page = rows.saturating_sub(2).max(1);
+        let top = self.selected / page * page;
+        if self.entries.is_empty() {
+            lines.push((String::from("No ROMs here"), false));
+        }
+        for (index, entry) in self.entries.iter().enumerate().skip(top).take(page) {
+            let selected = index == self.selected;
+            let title: String = entry.title.chars().take(TITLE_WIDTH).collect();
+            lines.push((
+                format!(
+                    "{} {:<width$} {:>6} B  {}",
+                    if selected { '>' } else { ' ' },
+                    title,
+                    entry.size,
+                    match &entry.played {
+                        Some(played) => ago(played.time, now),
+                        None => entry.platform.to_string(),
+                    },
+                    width = TITLE_WIDTH
+                ),
+                selected,
+            ));
+        }
+        lines.push((
+            String::from("Up/Down: choose  Enter: play  Tab: recent/all  Esc: quit"),
+            false,
+        ));
+        lines
+    }
+}
+
+#[cfg(all(test, feature = "sdl"))]
+mod tests {
+    use crate::chip8::platform::Platform;
+    use crate::frontend::browser::Browser;
+    use crate::frontend::recent::now;
+    use crate::frontend::recent::Played;
+    use crate::frontend::recent::Recent;
+    use std::fs;
+    #[test]
+    fn scan_lists_roms_by_title() {
+        let dir = std::env::temp_dir().join(format!("chip8-browser-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("pong.ch8"), [0x12, 0x00]).unwrap();
+        fs::write(dir.join("INVADERS"), [0x00, 0xFF, 0x12, 0x02]).unwrap();
+        fs::write(dir.join("notes.txt"), "not a ROM").unwrap();
+        fs::write(dir.join("empty.ch8"), []).unwrap();
+        let mut browser = Browser::scan(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        let titles: Vec<_> = browser.entries.iter().map(|e| e.title.as_str()).collect();
+        assert_eq!(titles, ["INVADERS", "pong"]);
+        assert_eq!(browser.entries[0].platform, Platform::Schip);
+        browser.step(5);
+        assert_eq!(browser.selected().unwrap().title, "pong");
+        browser.step(-5);
+        assert_eq!(browser.selected().unwrap().title, "INVADERS");
+    }
+    #[test]
+    fn recent_skips_missing_roms() {
+        let dir = std::env::temp_dir().join(format!("chip8-recent-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("pong.ch8"), [0x12, 0x00]).unwrap();
+        let played = |name: &str| Played {
+            path: dir.join(name),
+            time: now() - 120,
+            speed: 500,
+            palette: String::from("classic"),
+        };
+        let recent = Recent {
+            roms: vec![played("gone.ch8"), played("pong.ch8")],
+        };
+        let browser = Browser::recent(&recent);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(browser.entries.len(), 1);
+        let lines = browser.lines(5);
+        assert_eq!(lines[0].0, "Recently played");
+        assert_eq!(
+            lines[1].0,
+            format!("> {:<30}      2 B  2 minutes ago", "pong.ch8")
+        );
+    }
+    #[test]
+    fn lines_page_through_the_entries() {
+        let dir = std::env::temp_dir().join(format!("chip8-pages-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for index in 0..5 {
+            fs::write(dir.join(format!("game{}.ch8", index)), [0x12, 0x00]).unwrap();
+        }
+        let mut browser = Browser::scan(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        browser.step(4);
+        // Three entries a page, and the second page has the last two.
+        let lines = browser.lines(5);
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[1].0, format!("  {:<30}      2 B  CHIP-8", "game3"));
+        assert!(!lines[1].1);
+        assert!(lines[2].1);
+        assert!(lines[3].0.starts_with("Up/Down"));
+    }
+}
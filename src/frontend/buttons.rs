@@ -0,0 +1,126 @@
+use crate::frontend::keymap::Value;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+// The buttons of a game controller, named as SDL names them.
+const BUTTONS: [&str; 21] = [
+    "A",
+    "B",
+    "X",
+    "Y",
+    "Back",
+    "Guide",
+    "Start",
+    "LeftStick",
+    "RightStick",
+    "LeftShoulder",
+    "RightShoulder",
+    "DPadUp",
+    "DPadDown",
+    "DPadLeft",
+    "DPadRight",
+    "Misc1",
+    "Paddle1",
+    "Paddle2",
+    "Paddle3",
+    "Paddle4",
+    "Touchpad",
+];
+
+// The D-pad on the keys most ROMs steer with (the WASD of the QWERTY layout)
+// and the face buttons on the two next to them.
+const DEFAULT: [(&str, u8); 6] = [
+    ("DPadUp", 0x5),
+    ("DPadDown", 0x8),
+    ("DPadLeft", 0x7),
+    ("DPadRight", 0x9),
+    ("A", 0x6),
+    ("B", 0x4),
+];
+
+// Which controller buttons press which keypad values, on top of the keys
+// bound in the keymap.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "BTreeMap<String, Value>")]
+pub struct Buttons {
+    bindings: BTreeMap<String, u8>,
+}
+
+impl Default for Buttons {
+    fn default() -> Self {
+        Self {
+            bindings: DEFAULT
+                .iter()
+                .map(|(name, value)| (name.to_string(), *value))
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<BTreeMap<String, Value>> for Buttons {
+    type Error = String;
+
+    fn try_from(table: BTreeMap<String, Value>) -> Result<Self, String> {
+        let mut bindings = BTreeMap::new();
+        for (name, value) in table {
+            let button = canonical(&name).ok_or_else(|| {
+                format!(
+                    "unknown button \"{}\", buttons are {}",
+                    name,
+                    BUTTONS.join(", ")
+                )
+            })?;
+            let value = value.keypad().ok_or_else(|| {
+                format!("button \"{}\" must be bound to a keypad value 0 to F", name)
+            })?;
+            if bindings.insert(button, value).is_some() {
+                return Err(format!("button \"{}\" is bound more than once", name));
+            }
+        }
+        Ok(Self { bindings })
+    }
+}
+
+impl Buttons {
+    // The keypad value the named button presses, if it is bound.
+    #[cfg(feature = "sdl")]
+    pub fn key(&self, name: &str) -> Option<u8> {
+        self.bindings.get(&canonical(name)?).copied()
+    }
+
+    // The buttons that press the keypad value.
+    pub fn buttons(&self, value: u8) -> Vec<&str> {
+        self.bindings
+            .iter()
+            .filter(|(_, bound)| **bound == value)
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+}
+
+fn canonical(name: &str) -> Option<String> {
+    BUTTONS
+        .iter()
+        .find(|button| button.eq_ignore_ascii_case(name))
+        .map(|button| button.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frontend::buttons::Buttons;
+    #[cfg(feature = "sdl")]
+    #[test]
+    fn default_steers_with_the_dpad() {
+        let buttons = Buttons::default();
+        assert_eq!(buttons.key("DPadUp"), Some(0x5));
+        assert_eq!(buttons.key("a"), Some(0x6));
+        assert_eq!(buttons.key("Start"), None);
+    }
+    #[test]
+    fn invalid_buttons_are_explained() {
+        let error = |content| toml::from_str::<Buttons>(content).unwrap_err().to_string();
+        assert!(error("Z = 1").contains("unknown button \"Z\", buttons are A, B, X"));
+        assert!(error("start = 1\nStart = 2").contains("button \"start\" is bound more than once"));
+        assert!(error("Y = 16").contains("keypad value 0 to F"));
+    }
+}
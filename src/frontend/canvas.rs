@@ -0,0 +1,327 @@
+use crate::chip8::display::RES_HEIGHT;
+use crate::chip8::display::RES_WIDTH;
+use crate::frontend::backdrop::Backdrop;
+use crate::frontend::bezel::arrange;
+use crate::frontend::bezel::Bezel;
+use crate::frontend::crt::CrtOptions;
+use crate::frontend::framebuffer::rgb24;
+use crate::frontend::framebuffer::rgba32;
+use crate::frontend::layout::boundaries;
+use crate::frontend::layout::Filter;
+use crate::frontend::layout::PixelAspect;
+use crate::frontend::layout::Scaling;
+use crate::frontend::layout::Viewport;
+use crate::frontend::palette::Palette;
+use crate::frontend::palette::Rgb;
+use crate::frontend::renderer::Renderer;
+use crate::frontend::shape::PixelShape;
+use crate::frontend::text::Overlay;
+use crate::frontend::text::OVERLAY_SCALE;
+use sdl2::pixels::Color;
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::rect::Rect;
+use sdl2::render::BlendMode;
+use sdl2::render::Texture;
+use sdl2::render::TextureCreator;
+use sdl2::render::WindowCanvas;
+use sdl2::video::Window;
+use sdl2::video::WindowContext;
+
+// Resolution of the sprite used to draw shaped pixels.
+const DOT_SIZE: usize = 32;
+
+fn sdl_color(rgb: Rgb) -> Color {
+    Color::RGB(rgb.0, rgb.1, rgb.2)
+}
+
+fn sdl_rect(viewport: Viewport) -> Rect {
+    Rect::new(viewport.x, viewport.y, viewport.width, viewport.height)
+}
+
+pub struct CanvasRenderer<'a> {
+    canvas: WindowCanvas,
+    scaling: Scaling,
+    aspect: PixelAspect,
+    texture_creator: &'a TextureCreator<WindowContext>,
+    texture: Texture<'a>,
+    glow: Texture<'a>,
+    osd: Texture<'a>,
+    bezel: Option<(Bezel, Texture<'a>)>,
+    // With a backdrop the display texture holds the intensity of every pixel
+    // as alpha, so that unlit pixels let the backdrop through.
+    backdrop: Option<Texture<'a>>,
+    // Created the first time a shape other than square is drawn.
+    dot: Option<(PixelShape, Texture<'a>)>,
+}
+
+impl<'a> CanvasRenderer<'a> {
+    pub fn new(
+        canvas: WindowCanvas,
+        texture_creator: &'a TextureCreator<WindowContext>,
+        scaling: Scaling,
+        filter: Filter,
+        bezel: Option<Bezel>,
+        backdrop: Option<Backdrop>,
+        aspect: PixelAspect,
+    ) -> Self {
+        // SDL picks the filter of each texture when creating it.
+        sdl2::hint::set(
+            "SDL_RENDER_SCALE_QUALITY",
+            match filter {
+                Filter::Nearest => "nearest",
+                Filter::Linear => "linear",
+            },
+        );
+        let format = match backdrop {
+            Some(_) => PixelFormatEnum::RGBA32,
+            None => PixelFormatEnum::RGB24,
+        };
+        let mut texture = texture_creator
+            .create_texture_streaming(format, RES_WIDTH as u32, RES_HEIGHT as u32)
+            .unwrap();
+        texture.set_blend_mode(BlendMode::Blend);
+        let mut glow = texture_creator
+            .create_texture_streaming(PixelFormatEnum::RGB24, RES_WIDTH as u32, RES_HEIGHT as u32)
+            .unwrap();
+        glow.set_blend_mode(BlendMode::Add);
+        let mut osd = texture_creator
+            .create_texture_streaming(
+                PixelFormatEnum::RGBA32,
+                (RES_WIDTH * OVERLAY_SCALE) as u32,
+                (RES_HEIGHT * OVERLAY_SCALE) as u32,
+            )
+            .unwrap();
+        osd.set_blend_mode(BlendMode::Blend);
+        let bezel = bezel.map(|bezel| {
+            let mut texture = texture_creator
+                .create_texture_static(PixelFormatEnum::RGBA32, bezel.width, bezel.height)
+                .unwrap();
+            texture
+                .update(None, &bezel.pixels, bezel.width as usize * 4)
+                .unwrap();
+            texture.set_blend_mode(BlendMode::Blend);
+            (bezel, texture)
+        });
+        let backdrop = backdrop.map(|backdrop| {
+            let mut texture = texture_creator
+                .create_texture_static(PixelFormatEnum::RGBA32, backdrop.width, backdrop.height)
+                .unwrap();
+            texture
+                .update(None, &backdrop.pixels, backdrop.width as usize * 4)
+                .unwrap();
+            texture.set_blend_mode(BlendMode::Blend);
+            texture
+        });
+        CanvasRenderer {
+            canvas,
+            scaling,
+            aspect,
+            texture_creator,
+            texture,
+            glow,
+            osd,
+            bezel,
+            backdrop,
+            dot: None,
+        }
+    }
+
+    // Draws every lit pixel as a copy of the shape sprite tinted with its
+    // color, following the curvature by moving and shrinking each dot.
+    fn draw_dots(
+        &mut self,
+        levels: &[f32],
+        palette: &Palette,
+        crt: &CrtOptions,
+        viewport: Viewport,
+    ) {
+        if self.dot.as_ref().map(|(shape, _)| *shape) != Some(crt.shape) {
+            let mut dot = self
+                .texture_creator
+                .create_texture_static(PixelFormatEnum::RGBA32, DOT_SIZE as u32, DOT_SIZE as u32)
+                .unwrap();
+            dot.update(None, &crt.shape.mask(DOT_SIZE), DOT_SIZE * 4)
+                .unwrap();
+            dot.set_blend_mode(BlendMode::Blend);
+            self.dot = Some((crt.shape, dot));
+        }
+        let translucent = self.backdrop.is_some();
+        let (_, dot) = self.dot.as_mut().unwrap();
+        let cell_width = viewport.width as f32 / RES_WIDTH as f32;
+        let cell_height = viewport.height as f32 / RES_HEIGHT as f32;
+        for (index, level) in levels.iter().enumerate() {
+            if *level == 0.0 {
+                continue;
+            }
+            let (column, row) = (index % RES_WIDTH, index / RES_WIDTH);
+            let (x, y, factor) = crt.warp(
+                (column as f32 + 0.5) / RES_WIDTH as f32 * 2.0 - 1.0,
+                (row as f32 + 0.5) / RES_HEIGHT as f32 * 2.0 - 1.0,
+            );
+            let (width, height) = (cell_width * factor, cell_height * factor);
+            let center_x = viewport.x as f32 + (x + 1.0) / 2.0 * viewport.width as f32;
+            let center_y = viewport.y as f32 + (y + 1.0) / 2.0 * viewport.height as f32;
+            let (color, alpha) = if translucent {
+                (palette.foreground(), (*level * 255.0).round() as u8)
+            } else {
+                (palette.background.blend(palette.foreground(), *level), 255)
+            };
+            dot.set_color_mod(color.0, color.1, color.2);
+            dot.set_alpha_mod(alpha);
+            let dest = Rect::new(
+                (center_x - width / 2.0).round() as i32,
+                (center_y - height / 2.0).round() as i32,
+                width.ceil() as u32,
+                height.ceil() as u32,
+            );
+            self.canvas.copy(dot, None, dest).unwrap();
+        }
+    }
+}
+
+impl Renderer for CanvasRenderer<'_> {
+    fn window_mut(&mut self) -> &mut Window {
+        self.canvas.window_mut()
+    }
+
+    fn viewport(&self) -> Viewport {
+        let (_, viewport) = arrange(
+            self.bezel.as_ref().map(|(bezel, _)| bezel),
+            self.canvas.output_size().unwrap(),
+            self.scaling,
+            self.aspect,
+        );
+        viewport
+    }
+
+    fn render(&mut self, levels: &[f32], palette: &Palette, crt: &CrtOptions, overlay: &Overlay) {
+        let (area, viewport) = arrange(
+            self.bezel.as_ref().map(|(bezel, _)| bezel),
+            self.canvas.output_size().unwrap(),
+            self.scaling,
+            self.aspect,
+        );
+        let pitch = RES_WIDTH * 3;
+        match self.backdrop {
+            Some(_) => {
+                let pixels = rgba32(levels, palette.foreground());
+                self.texture.update(None, &pixels, RES_WIDTH * 4)
+            }
+            None => {
+                let pixels = rgb24(levels, palette.background, palette.foreground());
+                self.texture.update(None, &pixels, pitch)
+            }
+        }
+        .unwrap();
+
+        self.canvas.set_draw_color(Color::RGB(0, 0, 0));
+        self.canvas.clear();
+        self.canvas.set_draw_color(sdl_color(palette.background));
+        self.canvas.fill_rect(sdl_rect(viewport)).unwrap();
+        self.canvas.set_blend_mode(BlendMode::Blend);
+        if let Some(backdrop) = &self.backdrop {
+            self.canvas
+                .copy(backdrop, None, sdl_rect(viewport))
+                .unwrap();
+        }
+        if crt.shape == PixelShape::Square {
+            copy_warped(&mut self.canvas, &self.texture, crt, viewport, 0);
+        } else {
+            self.draw_dots(levels, palette, crt, viewport);
+        }
+
+        let canvas = &mut self.canvas;
+
+        if crt.bloom > 0.0 {
+            let pixels = rgb24(levels, Rgb(0, 0, 0), palette.foreground());
+            self.glow.update(None, &pixels, pitch).unwrap();
+            self.glow.set_alpha_mod((crt.bloom * 96.0) as u8);
+            let spread = (viewport.height / RES_HEIGHT as u32 / 2) as i32;
+            copy_warped(canvas, &self.glow, crt, viewport, spread);
+        }
+
+        if crt.grid > 0.0 {
+            let background = palette.background;
+            let mut lines: Vec<Rect> = boundaries(viewport.x, viewport.width, RES_WIDTH)
+                .map(|x| Rect::new(x, viewport.y, 1, viewport.height))
+                .collect();
+            lines.extend(
+                boundaries(viewport.y, viewport.height, RES_HEIGHT)
+                    .map(|y| Rect::new(viewport.x, y, viewport.width, 1)),
+            );
+            canvas.set_draw_color(Color::RGBA(
+                background.0,
+                background.1,
+                background.2,
+                (crt.grid * 255.0) as u8,
+            ));
+            canvas.fill_rects(&lines).unwrap();
+        }
+
+        if crt.scanlines > 0.0 {
+            let lines: Vec<Rect> = (1..viewport.height as i32)
+                .step_by(2)
+                .map(|y| Rect::new(viewport.x, viewport.y + y, viewport.width, 1))
+                .collect();
+            canvas.set_draw_color(Color::RGBA(0, 0, 0, (crt.scanlines * 255.0) as u8));
+            canvas.fill_rects(&lines).unwrap();
+        }
+
+        if let (Some(area), Some((_, texture))) = (area, &self.bezel) {
+            canvas.copy(texture, None, sdl_rect(area)).unwrap();
+        }
+
+        if !overlay.is_empty() {
+            self.osd
+                .update(None, &overlay.pixels, overlay.width * 4)
+                .unwrap();
+            canvas.copy(&self.osd, None, sdl_rect(viewport)).unwrap();
+        }
+
+        canvas.present();
+    }
+}
+
+// Copies the texture onto the canvas, row by row when the screen is curved so
+// that every row gets its own width and position. A non-zero spread grows the
+// destination in every direction, which is how the glow pass blurs the image.
+fn copy_warped(
+    canvas: &mut WindowCanvas,
+    texture: &Texture,
+    crt: &CrtOptions,
+    viewport: Viewport,
+    spread: i32,
+) {
+    let grow = |rect: Rect| {
+        Rect::new(
+            rect.x() - spread,
+            rect.y() - spread,
+            rect.width() + spread as u32 * 2,
+            rect.height() + spread as u32 * 2,
+        )
+    };
+    if crt.curvature == 0.0 {
+        canvas
+            .copy(texture, None, grow(sdl_rect(viewport)))
+            .unwrap();
+        return;
+    }
+    let width = viewport.width as f32;
+    let height = viewport.height as f32;
+    let row_height = height / RES_HEIGHT as f32;
+    for row in 0..RES_HEIGHT {
+        let (_, y, factor) = crt.warp(0.0, (row as f32 + 0.5) / RES_HEIGHT as f32 * 2.0 - 1.0);
+        let size_x = width * factor;
+        let size_y = row_height * factor;
+        let center_x = viewport.x as f32 + width / 2.0;
+        let center_y = viewport.y as f32 + (y + 1.0) / 2.0 * height;
+        let dest = Rect::new(
+            (center_x - size_x / 2.0).round() as i32,
+            (center_y - size_y / 2.0).round() as i32,
+            size_x.ceil() as u32,
+            size_y.ceil() as u32,
+        );
+        let src = Rect::new(0, row as i32, RES_WIDTH as u32, 1);
+        canvas.copy(texture, src, grow(dest)).unwrap();
+    }
+}
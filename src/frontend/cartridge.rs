@@ -0,0 +1,222 @@
+use crate::chip8::octo;
+use crate::chip8::quirks::Quirks;
+use crate::frontend::palette::Palette;
+use crate::frontend::palette::Rgb;
+use serde::Deserialize;
+use std::path::Path;
+
+// Octo's colors, for those a cartridge leaves out.
+const BACKGROUND: &str = "#996600";
+const FILL: &str = "#FFCC00";
+const FILL2: &str = "#FF6600";
+const BLEND: &str = "#662200";
+
+// The options Octo saves along with a program, as it names them. The quirks
+// are those of Octo, which all default to off.
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Options {
+    // Instructions per frame.
+    pub tickrate: Option<u32>,
+    pub background_color: Option<String>,
+    pub fill_color: Option<String>,
+    pub fill_color2: Option<String>,
+    pub blend_color: Option<String>,
+    pub logic_quirks: bool,
+    // I is left alone by Fx55 and Fx65.
+    pub load_store_quirks: bool,
+    pub v_blank_quirks: bool,
+    pub clip_quirks: bool,
+    pub shift_quirks: bool,
+    pub jump_quirks: bool,
+}
+
+#[derive(Deserialize)]
+struct Payload {
+    program: String,
+    #[serde(default)]
+    options: Options,
+}
+
+// An Octo cartridge, a GIF image with the source of a program and its options
+// hidden in the lowest two bits of the color index of every pixel, across
+// the frames in order, four to a byte with the highest bits first. The bytes
+// start with their count, in four bytes big endian, and hold the program and
+// its options as JSON.
+pub struct Cartridge {
+    pub rom: Vec<u8>,
+    pub options: Options,
+}
+
+impl Cartridge {
+    pub fn is(data: &[u8]) -> bool {
+        data.starts_with(b"GIF8")
+    }
+
+    // The ROM in a file, assembled first if the file is Octo source, with the
+    // options of the cartridge it came in, if it is one.
+    pub fn open(path: &Path, data: Vec<u8>) -> Result<(Vec<u8>, Option<Options>), String> {
+        if is_source(path) {
+            let source = String::from_utf8(data)
+                .map_err(|_| String::from("the Octo source is not UTF-8 text"))?;
+            return Ok((octo::assemble(&source)?, None));
+        }
+        if !Self::is(&data) {
+            return Ok((data, None));
+        }
+        let cartridge = Self::decode(&data)?;
+        Ok((cartridge.rom, Some(cartridge.options)))
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, String> {
+        let error = |e: gif::DecodingError| format!("unable to read the cartridge: {}", e);
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = options.read_info(data).map_err(error)?;
+        let mut pairs = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().map_err(error)? {
+            pairs.extend(frame.buffer.iter().map(|index| index & 0b11));
+        }
+        let bytes: Vec<u8> = pairs
+            .chunks_exact(4)
+            .map(|pairs| pairs.iter().fold(0, |byte, pair| (byte << 2) | pair))
+            .collect();
+        let payload = bytes
+            .get(4..)
+            .and_then(|rest| {
+                let length = u32::from_be_bytes(bytes[..4].try_into().unwrap()) as usize;
+                rest.get(..length)
+            })
+            .ok_or_else(|| String::from("the cartridge holds no program"))?;
+        let payload: Payload = serde_json::from_slice(payload)
+            .map_err(|e| format!("unable to read the cartridge's program: {}", e))?;
+        Ok(Self {
+            rom: octo::assemble(&payload.program)?,
+            options: payload.options,
+        })
+    }
+}
+
+impl Options {
+    // Octo runs a number of instructions every frame.
+    pub fn speed(&self) -> Option<u16> {
+        self.tickrate
+            .map(|tickrate| tickrate.saturating_mul(60).min(u16::MAX as u32) as u16)
+    }
+
+    pub fn palette(&self) -> Result<Palette, String> {
+        let color = |color: &Option<String>, default: &str| {
+            Rgb::try_from(color.clone().unwrap_or_else(|| String::from(default)))
+        };
+        Ok(Palette {
+            name: String::from("cartridge"),
+            background: color(&self.background_color, BACKGROUND)?,
+            planes: [
+                color(&self.fill_color, FILL)?,
+                color(&self.fill_color2, FILL2)?,
+                color(&self.blend_color, BLEND)?,
+            ],
+        })
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        Quirks {
+            vf_reset: self.logic_quirks,
+            memory: !self.load_store_quirks,
+            display_wait: self.v_blank_quirks,
+            clipping: self.clip_quirks,
+            shifting: self.shift_quirks,
+            jumping: self.jump_quirks,
+        }
+    }
+}
+
+// Octo source files end in .8o.
+pub fn is_source(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("8o"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::quirks::Quirks;
+    use crate::frontend::cartridge::Cartridge;
+    use crate::frontend::palette::Rgb;
+    use std::borrow::Cow;
+    use std::path::Path;
+    // Hides the payload in a cartridge the way Octo does, over a label of a
+    // single color.
+    fn cartridge(payload: &str) -> Vec<u8> {
+        let mut bytes = (payload.len() as u32).to_be_bytes().to_vec();
+        bytes.extend(payload.as_bytes());
+        let mut pixels: Vec<u8> = bytes
+            .iter()
+            .flat_map(|byte| [6, 4, 2, 0].map(|shift| 0b100 | ((byte >> shift) & 0b11)))
+            .collect();
+        let (width, height) = (32, pixels.len().div_ceil(32) as u16);
+        pixels.resize(width as usize * height as usize, 0b100);
+        let palette: Vec<u8> = (0..8).flat_map(|index| [index * 32; 3]).collect();
+        let mut gif = Vec::new();
+        let mut encoder = gif::Encoder::new(&mut gif, width, height, &palette).unwrap();
+        let frame = gif::Frame {
+            width,
+            height,
+            buffer: Cow::Owned(pixels),
+            ..gif::Frame::default()
+        };
+        encoder.write_frame(&frame).unwrap();
+        drop(encoder);
+        gif
+    }
+    #[test]
+    fn decode_assembles_the_program_with_its_options() {
+        let gif = cartridge(
+            r##"{"program": ": main\n  clear\n  jump main",
+                "options": {"tickrate": 15, "fillColor": "#FFFFFF", "shiftQuirks": true,
+                            "loadStoreQuirks": true, "maxSize": 3584}}"##,
+        );
+        assert!(Cartridge::is(&gif));
+        let cartridge = Cartridge::decode(&gif).unwrap();
+        assert_eq!(cartridge.rom, [0x12, 0x02, 0x00, 0xE0, 0x12, 0x02]);
+        assert_eq!(cartridge.options.speed(), Some(900));
+        let palette = cartridge.options.palette().unwrap();
+        assert_eq!(palette.planes[0], Rgb(255, 255, 255));
+        assert_eq!(palette.background, Rgb(0x99, 0x66, 0x00));
+        assert_eq!(
+            cartridge.options.quirks(),
+            Quirks {
+                vf_reset: false,
+                memory: false,
+                display_wait: false,
+                clipping: false,
+                shifting: true,
+                jumping: false,
+            }
+        );
+    }
+    #[test]
+    fn decode_reports_the_line_of_assembly_errors() {
+        let gif = cartridge(r#"{"program": ": main\n  v0 := nowhere"}"#);
+        assert_eq!(
+            Cartridge::decode(&gif).err().unwrap(),
+            "line 2: \"nowhere\" is not a number or a constant"
+        );
+        assert!(!Cartridge::is(b"\x00\xE0"));
+    }
+    #[test]
+    fn open_assembles_source_files() {
+        let source = b": main\n  clear\n  jump main".to_vec();
+        let (rom, options) = Cartridge::open(Path::new("game.8o"), source).unwrap();
+        assert_eq!(rom, [0x12, 0x02, 0x00, 0xE0, 0x12, 0x02]);
+        assert_eq!(options, None);
+        assert_eq!(
+            Cartridge::open(Path::new("game.8o"), b": main\n  jump".to_vec()).unwrap_err(),
+            "line 2: the program ends in the middle of a statement"
+        );
+        let rom = vec![0x00, 0xE0];
+        assert_eq!(
+            Cartridge::open(Path::new("PONG"), rom.clone()).unwrap(),
+            (rom, None)
+        );
+    }
+}
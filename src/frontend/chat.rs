@@ -0,0 +1,198 @@
+use crate::frontend::keymap::canonical;
+use crate::frontend::keymap::Keymap;
+use crate::Args;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::net::TcpStream;
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+use tracing::info;
+use tracing::warn;
+
+pub const TWITCH: &str = "irc.chat.twitch.tv:6667";
+
+// A line of chat.
+#[derive(Debug, PartialEq)]
+struct Said {
+    user: String,
+    text: String,
+}
+
+// The message of an IRC PRIVMSG line, with or without the tags Twitch puts
+// in front of it.
+fn said(line: &str) -> Option<Said> {
+    let line = match line.strip_prefix('@') {
+        Some(tagged) => tagged.split_once(' ')?.1,
+        None => line,
+    };
+    let (prefix, rest) = line.strip_prefix(':')?.split_once(' ')?;
+    let (_, text) = rest.strip_prefix("PRIVMSG ")?.split_once(" :")?;
+    Some(Said {
+        user: String::from(prefix.split('!').next()?),
+        text: String::from(text.trim()),
+    })
+}
+
+// Reads the channel until the server goes. Twitch lets anyone read a
+// channel under a justinfan nickname, without a password.
+fn listen(address: &str, channel: &str, said_to: Sender<Said>) -> io::Result<()> {
+    let stream = TcpStream::connect(address)?;
+    let mut out = stream.try_clone()?;
+    let nick = format!("justinfan{}", rand::random::<u32>() % 100_000);
+    write!(
+        out,
+        "NICK {}\r\nUSER {} 0 * :chip8-emu-rust\r\n",
+        nick, nick
+    )?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if let Some(server) = line.strip_prefix("PING ") {
+            write!(out, "PONG {}\r\n", server)?;
+        } else if line.split(' ').nth(1) == Some("001") {
+            // Servers take the channel once they welcome the client.
+            write!(out, "JOIN {}\r\n", channel)?;
+            info!("reading the chat of {} on {}", channel, address);
+        } else if let Some(said) = said(&line) {
+            if said_to.send(said).is_err() {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+// The votes of a window, each viewer voting once at most, so that nobody
+// can flood the keypad by repeating a command.
+#[derive(Default)]
+struct Tally {
+    // The votes for each key name, with the key it presses.
+    votes: BTreeMap<String, (u8, usize)>,
+    voters: BTreeSet<String>,
+}
+
+impl Tally {
+    fn vote(&mut self, user: &str, name: String, key: u8) -> bool {
+        if !self.voters.insert(user.to_lowercase()) {
+            return false;
+        }
+        self.votes.entry(name).or_insert((key, 0)).1 += 1;
+        true
+    }
+
+    // The command with the most votes, the first by name on a tie, with the
+    // key it presses and the votes for it, starting the next window.
+    fn close(&mut self) -> Option<Verdict> {
+        let mut winner: Option<Verdict> = None;
+        for (name, &(key, votes)) in &self.votes {
+            if winner.as_ref().is_none_or(|winner| votes > winner.votes) {
+                winner = Some(Verdict {
+                    name: name.clone(),
+                    key,
+                    votes,
+                });
+            }
+        }
+        *self = Self::default();
+        winner
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Verdict {
+    pub name: String,
+    pub key: u8,
+    pub votes: usize,
+}
+
+// The keypad played by the viewers of a stream, who send key names as chat
+// messages, as they are bound in the keymap. Their votes are counted over a
+// window, and the key most voted for is held down for the next one.
+pub struct Chat {
+    said: Receiver<Said>,
+    window: Duration,
+    closes: Instant,
+    tally: Tally,
+}
+
+impl Chat {
+    pub fn from_args(args: &Args) -> Option<Self> {
+        let channel = format!("#{}", args.chat.as_ref()?.trim_start_matches('#'));
+        let channel = channel.to_lowercase();
+        let address = args.chat_server.clone();
+        let (said_to, said) = mpsc::channel();
+        thread::spawn(move || match listen(&address, &channel, said_to) {
+            Ok(()) => warn!("the chat server closed the connection"),
+            Err(e) => warn!("chat disabled, {}: {}", address, e),
+        });
+        Some(Self {
+            said,
+            window: args.chat_window,
+            closes: Instant::now() + args.chat_window,
+            tally: Tally::default(),
+        })
+    }
+
+    // Counts the votes in so far and, once the window closes, what the
+    // viewers chose: a key to hold until the next window closes, or nothing.
+    pub fn update(&mut self, now: Instant, keys: &Keymap) -> Option<Option<Verdict>> {
+        for said in self.said.try_iter() {
+            let Some(name) = canonical(&said.text) else {
+                continue;
+            };
+            if let Some(key) = keys.key(&name) {
+                self.tally.vote(&said.user, name, key);
+            }
+        }
+        if now < self.closes {
+            return None;
+        }
+        self.closes = (self.closes + self.window).max(now);
+        Some(self.tally.close())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frontend::chat::said;
+    use crate::frontend::chat::Said;
+    use crate::frontend::chat::Tally;
+    use crate::frontend::chat::Verdict;
+    #[test]
+    fn said_reads_privmsg_lines() {
+        assert_eq!(
+            said("@badges=;color= :alice!alice@alice.tmi.twitch.tv PRIVMSG #chip8 :up "),
+            Some(Said {
+                user: String::from("alice"),
+                text: String::from("up")
+            })
+        );
+        assert_eq!(said(":tmi.twitch.tv 001 justinfan1 :Welcome"), None);
+        assert_eq!(said("PING :tmi.twitch.tv"), None);
+    }
+    #[test]
+    fn tally_counts_a_vote_a_viewer() {
+        let mut tally = Tally::default();
+        assert!(tally.vote("alice", String::from("Up"), 0x5));
+        assert!(!tally.vote("Alice", String::from("Down"), 0x8));
+        tally.vote("bob", String::from("Down"), 0x8);
+        tally.vote("carol", String::from("Down"), 0x8);
+        assert_eq!(
+            tally.close(),
+            Some(Verdict {
+                name: String::from("Down"),
+                key: 0x8,
+                votes: 2
+            })
+        );
+        assert_eq!(tally.close(), None);
+        assert!(tally.vote("alice", String::from("Up"), 0x5));
+    }
+}
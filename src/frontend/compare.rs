@@ -0,0 +1,377 @@
+use crate::audio::Audio;
+use crate::chip8::display::RES_HEIGHT;
+use crate::chip8::display::RES_WIDTH;
+use crate::chip8::hash::state;
+use crate::chip8::Chip8;
+use crate::config::Config;
+use crate::failure::Failure;
+use crate::frontend::pacing::Pacer;
+use crate::frontend::palette::Palette;
+use crate::frontend::palette::Palettes;
+use crate::frontend::palette::Rgb;
+use crate::frontend::runner::frame_instructions;
+use crate::frontend::runner::TIMER_SPEED;
+use crate::frontend::text::text_width;
+use crate::frontend::text::Overlay;
+use crate::frontend::text::LINE_HEIGHT;
+use crate::frontend::text::OVERLAY_SCALE;
+use crate::frontend::title::rom_name;
+use crate::frontend::title::title;
+use crate::Args;
+use sdl2::event::Event;
+use sdl2::event::WindowEvent;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::PixelFormatEnum;
+use std::time::Duration;
+use std::time::Instant;
+use tracing::info;
+
+// Room around and between the two displays, in pixels of the overlay.
+const MARGIN: usize = 2;
+const GAP: usize = 8;
+// The displays sit between a line of quirks above and the status below.
+const TOP: usize = LINE_HEIGHT + 2 * MARGIN;
+const DISPLAY_WIDTH: usize = RES_WIDTH * OVERLAY_SCALE;
+const DISPLAY_HEIGHT: usize = RES_HEIGHT * OVERLAY_SCALE;
+const WIDTH: usize = 2 * MARGIN + 2 * DISPLAY_WIDTH + GAP;
+const HEIGHT: usize = 2 * TOP + DISPLAY_HEIGHT;
+// Pixels the two displays differ on, and quirks only one machine has on.
+const DIFFERENT: Rgb = Rgb(255, 48, 48);
+// Milliseconds the loop waits for an event at most while paused.
+const IDLE_WAIT: u32 = 100;
+
+// Where two machines first went different ways: the frame, and the pixels
+// of the display they disagreed on, none when only their memory or
+// registers did.
+#[derive(Debug, PartialEq)]
+pub struct Divergence {
+    pub frame: u64,
+    pub pixels: Vec<usize>,
+}
+
+// Two machines run frame by frame with the same keys, seed and speed,
+// usually the same ROM under different quirks, to find the first frame
+// where they part ways.
+pub struct Comparison {
+    pub left: Chip8,
+    pub right: Chip8,
+    ips: u32,
+    frame: u64,
+    divergence: Option<Divergence>,
+}
+
+impl Comparison {
+    pub fn new(left: Chip8, right: Chip8, ips: u32) -> Self {
+        Self {
+            left,
+            right,
+            ips,
+            frame: 0,
+            divergence: None,
+        }
+    }
+
+    pub fn press(&mut self, key: u8, down: bool) {
+        self.left.key_pressed(key, down);
+        self.right.key_pressed(key, down);
+    }
+
+    // The frames run so far.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub fn divergence(&self) -> Option<&Divergence> {
+        self.divergence.as_ref()
+    }
+
+    // The pixels the two displays differ on now.
+    pub fn differences(&mut self) -> Vec<usize> {
+        let (left, right) = (
+            self.left.display.as_buffer(),
+            self.right.display.as_buffer(),
+        );
+        (0..left.len())
+            .filter(|pixel| left[*pixel] != right[*pixel])
+            .collect()
+    }
+
+    // Runs a frame of both machines, and tells whether it is the one where
+    // they first went different ways.
+    pub fn step(&mut self) -> bool {
+        for chip8 in [&mut self.left, &mut self.right] {
+            for _ in 0..frame_instructions(self.frame, self.ips) {
+                chip8.fetch_execute();
+            }
+            chip8.dec_timers();
+        }
+        let frame = self.frame;
+        self.frame += 1;
+        if self.divergence.is_some() {
+            return false;
+        }
+        let pixels = self.differences();
+        if pixels.is_empty() && state(&mut self.left) == state(&mut self.right) {
+            return false;
+        }
+        self.divergence = Some(Divergence { frame, pixels });
+        true
+    }
+}
+
+// The two displays side by side, each under the quirks its machine runs
+// with, those the other does not share marked, and the pixels they differ
+// on marked once they have gone different ways.
+fn draw(overlay: &mut Overlay, comparison: &mut Comparison, palette: &Palette, paused: bool) {
+    overlay.fill(0, 0, WIDTH, HEIGHT, Rgb(0, 0, 0), 255);
+    let different = comparison.divergence().is_some();
+    let pixels = comparison.differences();
+    let left = comparison.left.quirks().listed();
+    let right = comparison.right.quirks().listed();
+    for (index, (chip8, quirks)) in [(&mut comparison.left, left), (&mut comparison.right, right)]
+        .into_iter()
+        .enumerate()
+    {
+        let x = MARGIN + index * (DISPLAY_WIDTH + GAP);
+        let mut label = x;
+        for ((name, on), ((_, on_left), (_, on_right))) in
+            quirks.iter().zip(left.iter().zip(&right))
+        {
+            if *on {
+                let color = if on_left != on_right {
+                    DIFFERENT
+                } else {
+                    Rgb(255, 255, 255)
+                };
+                overlay.draw_text(label, MARGIN, name, color);
+                label += text_width(name) + 6;
+            }
+        }
+        overlay.fill(
+            x,
+            TOP,
+            DISPLAY_WIDTH,
+            DISPLAY_HEIGHT,
+            palette.background,
+            255,
+        );
+        for (pixel, lit) in chip8.display.as_buffer().iter().enumerate() {
+            let color = if different && pixels.contains(&pixel) {
+                DIFFERENT
+            } else if *lit {
+                palette.foreground()
+            } else {
+                continue;
+            };
+            overlay.fill(
+                x + pixel % RES_WIDTH * OVERLAY_SCALE,
+                TOP + pixel / RES_WIDTH * OVERLAY_SCALE,
+                OVERLAY_SCALE,
+                OVERLAY_SCALE,
+                color,
+                255,
+            );
+        }
+    }
+    let mut status = format!("Frame {}", comparison.frame());
+    if let Some(divergence) = comparison.divergence() {
+        status.push_str(&format!(
+            ", apart since frame {} in {}",
+            divergence.frame,
+            if divergence.pixels.is_empty() {
+                "memory or registers"
+            } else {
+                "the display"
+            }
+        ));
+    }
+    if paused {
+        status.push_str(", paused");
+    }
+    let color = if different {
+        DIFFERENT
+    } else {
+        Rgb(255, 255, 255)
+    };
+    overlay.draw_text(MARGIN, HEIGHT - TOP + MARGIN, &status, color);
+}
+
+// A window running the two machines of the comparison side by side with the
+// keys of the keyboard, 60 frames a second, pausing at the frame they go
+// different ways in. Space pauses and resumes, N runs a single frame while
+// paused, and the buzzer sounds for the machine on the left.
+pub fn run(
+    args: &Args,
+    config: &Config,
+    palettes: Palettes,
+    mut comparison: Comparison,
+    audio: &mut dyn Audio,
+) -> Result<(), Failure> {
+    let video_failure = |e: String| Failure::Video(format!("SDL video unavailable: {}", e));
+    let sdl_context = sdl2::init().map_err(video_failure)?;
+    let video_subsystem = sdl_context.video().map_err(video_failure)?;
+    // As wide as two displays at the scale asked for.
+    let zoom = (args.scale as usize / OVERLAY_SCALE / 2).max(1);
+    let rom = args.rom().map(rom_name).unwrap_or_default();
+    let mut paused = args.pause_on_start;
+    let window = video_subsystem
+        .window(
+            &title(&rom, args.ips, paused),
+            (WIDTH * zoom) as u32,
+            (HEIGHT * zoom) as u32,
+        )
+        .position_centered()
+        .allow_highdpi()
+        .build()
+        .map_err(|e| video_failure(e.to_string()))?;
+    let mut canvas = window
+        .into_canvas()
+        .build()
+        .map_err(|e| video_failure(e.to_string()))?;
+    let texture_creator = canvas.texture_creator();
+    let mut texture = texture_creator
+        .create_texture_streaming(PixelFormatEnum::RGBA32, WIDTH as u32, HEIGHT as u32)
+        .map_err(|e| video_failure(e.to_string()))?;
+    let mut overlay = Overlay::new(WIDTH, HEIGHT);
+    let mut event_pump = sdl_context.event_pump().map_err(video_failure)?;
+    let mut pacer = Pacer::new();
+    let mut next_frame = Instant::now();
+    let mut redraw = true;
+    let mut woken = None;
+
+    'running: loop {
+        let mut step = false;
+        for event in woken.take().into_iter().chain(event_pump.poll_iter()) {
+            match event {
+                Event::Quit { .. }
+                | Event::Window {
+                    win_event: WindowEvent::Close,
+                    ..
+                }
+                | Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } => break 'running,
+                Event::KeyDown {
+                    keycode: Some(Keycode::Space),
+                    ..
+                } => {
+                    paused = !paused;
+                    redraw = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::N),
+                    ..
+                } if paused => step = true,
+                Event::KeyDown {
+                    keycode: Some(key),
+                    repeat: false,
+                    ..
+                } => {
+                    if let Some(x) = config.keys.key(&key.name()) {
+                        comparison.press(x, true);
+                    }
+                }
+                Event::KeyUp {
+                    keycode: Some(key), ..
+                } => {
+                    if let Some(x) = config.keys.key(&key.name()) {
+                        comparison.press(x, false);
+                    }
+                }
+                Event::Window {
+                    win_event: WindowEvent::Exposed,
+                    ..
+                } => redraw = true,
+                _ => {}
+            }
+        }
+
+        let due = !paused && Instant::now() >= next_frame;
+        if due || step {
+            next_frame = (next_frame + Duration::from_secs(1) / TIMER_SPEED).max(Instant::now());
+            if comparison.step() {
+                let divergence = comparison.divergence().unwrap();
+                info!(
+                    "the machines went different ways at frame {}, PC {:03X} and {:03X}",
+                    divergence.frame, comparison.left.pc, comparison.right.pc
+                );
+                paused = true;
+            }
+            redraw = true;
+        }
+        if paused {
+            audio.pause();
+        } else {
+            audio.sound(comparison.left.take_sound());
+        }
+
+        if redraw {
+            canvas
+                .window_mut()
+                .set_title(&title(&rom, args.ips, paused))
+                .unwrap();
+            draw(&mut overlay, &mut comparison, palettes.current(), paused);
+            texture
+                .update(None, &overlay.pixels, WIDTH * 4)
+                .map_err(|e| video_failure(e.to_string()))?;
+            canvas.clear();
+            canvas.copy(&texture, None, None).map_err(video_failure)?;
+            canvas.present();
+            redraw = false;
+        }
+        if paused {
+            woken = event_pump.wait_event_timeout(IDLE_WAIT);
+        } else {
+            pacer.wait_until(next_frame);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::quirks::Quirks;
+    use crate::chip8::Chip8;
+    use crate::frontend::compare::Comparison;
+    // Shifts V0, which holds 1, one way or the other, then draws the first
+    // bytes of the ROM at V0 and waits in a loop.
+    const ROM: [u8; 12] = [
+        0x60, 0x01, 0x61, 0x02, 0x80, 0x16, 0xA2, 0x00, 0xD0, 0x05, 0x12, 0x0A,
+    ];
+    fn machines(rom: &[u8]) -> (Chip8, Chip8) {
+        let mut left = Chip8::new();
+        left.load(rom).unwrap();
+        let mut right = left.clone();
+        right.set_quirks(Quirks {
+            shifting: false,
+            ..Quirks::default()
+        });
+        (left, right)
+    }
+    #[test]
+    fn step_finds_the_first_frame_apart() {
+        let (left, right) = machines(&ROM);
+        let mut same = Comparison::new(left.clone(), left.clone(), 600);
+        for _ in 0..3 {
+            assert!(!same.step());
+        }
+        assert_eq!(same.divergence(), None);
+        let mut comparison = Comparison::new(left, right, 600);
+        assert!(comparison.step());
+        assert!(!comparison.step());
+        assert_eq!(comparison.frame(), 2);
+        let pixels = comparison.differences();
+        let divergence = comparison.divergence().unwrap();
+        assert_eq!(divergence.frame, 0);
+        assert!(!pixels.is_empty());
+        assert_eq!(divergence.pixels, pixels);
+    }
+    #[test]
+    fn step_finds_machines_apart_in_memory_alone() {
+        let (left, right) = machines(&[0x60, 0x01, 0x61, 0x02, 0x80, 0x16, 0x12, 0x06]);
+        let mut comparison = Comparison::new(left, right, 600);
+        assert!(comparison.step());
+        assert!(comparison.divergence().unwrap().pixels.is_empty());
+    }
+}
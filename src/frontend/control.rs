@@ -0,0 +1,252 @@
+use crate::chip8::Chip8;
+use crate::chip8::Reset;
+use crate::chip8::MAX_ROM_SIZE;
+use crate::failure::Failure;
+use crate::frontend::dump::dump;
+use crate::frontend::dump::Format;
+use crate::frontend::runner::TIMER_SPEED;
+use serde::Deserialize;
+use serde::Serialize;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+
+// The most instructions one step runs, a second's worth at 1000 a frame, so
+// that a request cannot hold up the emulation and its viewers for long.
+pub const MAX_STEP: u32 = 1000 * TIMER_SPEED;
+
+// What test harnesses and other tools can have the web frontend do, each
+// done between two frames.
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Load(Vec<u8>),
+    Pause(bool),
+    Step(u32),
+    Key { key: u8, down: bool },
+    State,
+    Display,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Response {
+    pub status: &'static str,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    fn json(value: &impl Serialize) -> Self {
+        Self {
+            status: "200 OK",
+            content_type: "application/json",
+            body: serde_json::to_vec(value).unwrap(),
+        }
+    }
+
+    pub fn error(status: &'static str, message: &str) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: format!("{}\n", message).into_bytes(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct KeyBody {
+    key: u8,
+    down: bool,
+}
+
+// The machine as the API and the debug protocol show it.
+#[derive(Serialize)]
+pub struct State<'a> {
+    pc: u16,
+    i: u16,
+    v: &'a [u8; 16],
+    stack: &'a [u16],
+    delay: u8,
+    sound: u8,
+    cycles: u64,
+    vip_cycles: u64,
+    paused: bool,
+}
+
+impl<'a> State<'a> {
+    pub fn of(chip8: &'a Chip8, paused: bool) -> Self {
+        Self {
+            pc: chip8.pc,
+            i: chip8.reg_i(),
+            v: chip8.reg_v(),
+            stack: chip8.stack(),
+            delay: chip8.delay_timer(),
+            sound: chip8.sound_timer(),
+            cycles: chip8.cycles(),
+            vip_cycles: chip8.vip_cycles(),
+            paused,
+        }
+    }
+}
+
+// The command a request asks for, or the answer to give when it asks for
+// none. Requests that are not for the API are None.
+pub fn route(method: &str, path: &str, body: &[u8]) -> Option<Result<Command, Response>> {
+    let (path, query) = path.split_once('?').unwrap_or((path, ""));
+    let bad = |message: String| Some(Err(Response::error("400 Bad Request", &message)));
+    let command = match (method, path) {
+        ("POST", "/load") => {
+            if body.is_empty() || body.len() > MAX_ROM_SIZE {
+                return bad(format!(
+                    "the ROM is {} bytes, it has to be 1 to {}",
+                    body.len(),
+                    MAX_ROM_SIZE
+                ));
+            }
+            Command::Load(body.to_vec())
+        }
+        ("POST", "/pause") => Command::Pause(true),
+        ("POST", "/resume") => Command::Pause(false),
+        ("POST", "/step") => {
+            let count = query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("count="))
+                .unwrap_or("1");
+            match count.parse() {
+                Ok(count) if count <= MAX_STEP => Command::Step(count),
+                Ok(_) => return bad(format!("a step runs at most {} instructions", MAX_STEP)),
+                Err(_) => return bad(format!("{} is not a number of instructions", count)),
+            }
+        }
+        ("POST", "/key") => match serde_json::from_slice::<KeyBody>(body) {
+            Ok(KeyBody {
+                key: key @ 0..=0xF,
+                down,
+            }) => Command::Key { key, down },
+            Ok(KeyBody { key, .. }) => return bad(format!("there is no key {}", key)),
+            Err(e) => return bad(format!("expected {{\"key\": 5, \"down\": true}}: {}", e)),
+        },
+        ("GET", "/state") => Command::State,
+        ("GET", "/display.png") => Command::Display,
+        (_, "/load" | "/pause" | "/resume" | "/step" | "/key" | "/state" | "/display.png") => {
+            return Some(Err(Response::error(
+                "405 Method Not Allowed",
+                "wrong method",
+            )))
+        }
+        _ => return None,
+    };
+    Some(Ok(command))
+}
+
+// Does what the command asks for, answering with the state of the machine
+// where there is nothing else to tell. A ROM that makes the interpreter fail
+// while stepping is paused, and the server keeps going.
+pub fn apply(command: Command, chip8: &mut Chip8, paused: &mut bool) -> Response {
+    match command {
+        Command::Load(rom) => {
+            if let Err(error) = chip8.load(&rom) {
+                return Response::error("400 Bad Request", &error);
+            }
+            chip8.reset(Reset::Hard);
+        }
+        Command::Pause(pause) => *paused = pause,
+        Command::Step(count) => {
+            let stepped = panic::catch_unwind(AssertUnwindSafe(|| {
+                for _ in 0..count {
+                    chip8.fetch_execute();
+                }
+            }));
+            if let Err(cause) = stepped {
+                *paused = true;
+                return Response::error(
+                    "500 Internal Server Error",
+                    &Failure::crashed(cause).to_string(),
+                );
+            }
+        }
+        Command::Key { key, down } => chip8.key_pressed(key, down),
+        Command::State => {}
+        Command::Display => {
+            return Response {
+                status: "200 OK",
+                content_type: "image/png",
+                body: dump(&chip8.display.as_buffer(), Format::Png),
+            }
+        }
+    }
+    Response::json(&State::of(chip8, *paused))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::Chip8;
+    use crate::frontend::control::apply;
+    use crate::frontend::control::route;
+    use crate::frontend::control::Command;
+    use crate::frontend::control::Response;
+    #[test]
+    fn route_reads_the_commands() {
+        assert_eq!(
+            route("POST", "/step?count=3", b""),
+            Some(Ok(Command::Step(3)))
+        );
+        assert_eq!(
+            route("POST", "/key", br#"{"key": 10, "down": true}"#),
+            Some(Ok(Command::Key {
+                key: 10,
+                down: true
+            }))
+        );
+        assert_eq!(route("GET", "/state", b""), Some(Ok(Command::State)));
+        assert_eq!(route("GET", "/", b""), None);
+        let status =
+            |result: Option<Result<Command, Response>>| result.unwrap().unwrap_err().status;
+        assert_eq!(status(route("POST", "/load", b"")), "400 Bad Request");
+        assert_eq!(
+            status(route("POST", "/key", br#"{"key": 16, "down": true}"#)),
+            "400 Bad Request"
+        );
+        assert_eq!(
+            status(route("GET", "/pause", b"")),
+            "405 Method Not Allowed"
+        );
+        assert_eq!(
+            status(route("POST", "/step?count=60001", b"")),
+            "400 Bad Request"
+        );
+    }
+    #[test]
+    fn apply_loads_and_steps_the_rom() {
+        let mut chip8 = Chip8::new();
+        chip8.load(&[0x60, 0x01, 0x61, 0x02, 0x12, 0x00]).unwrap();
+        chip8.fetch_execute();
+        let mut paused = false;
+        apply(
+            Command::Load(vec![0x6A, 0x07, 0x12, 0x02]),
+            &mut chip8,
+            &mut paused,
+        );
+        assert_eq!(chip8.reg_v()[0], 0);
+        apply(Command::Pause(true), &mut chip8, &mut paused);
+        let state = apply(Command::Step(1), &mut chip8, &mut paused);
+        let state: serde_json::Value = serde_json::from_slice(&state.body).unwrap();
+        assert_eq!(state["pc"], 0x202);
+        assert_eq!(state["v"][10], 7);
+        assert_eq!(state["paused"], true);
+        // Nothing of the first ROM is left past the second.
+        assert_eq!(chip8.ram[0x204], 0);
+    }
+    #[test]
+    fn apply_answers_an_interpreter_failure() {
+        let mut chip8 = Chip8::new();
+        chip8.load(&[0x60, 0x01, 0xE0, 0x00]).unwrap();
+        let mut paused = false;
+        let response = apply(Command::Step(2), &mut chip8, &mut paused);
+        assert_eq!(response.status, "500 Internal Server Error");
+        assert_eq!(
+            response.body,
+            b"the interpreter failed: unimplemented 0xe000\n"
+        );
+        assert!(paused);
+        assert_eq!(chip8.reg_v()[0], 1);
+    }
+}
@@ -0,0 +1,56 @@
+use sdl2::controller::GameController;
+use sdl2::GameControllerSubsystem;
+use sdl2::Sdl;
+
+// The game controller whose buttons press keypad keys alongside the
+// keyboard: the first connected one SDL knows the layout of, or the first
+// plugged in later while there is none.
+pub struct Controller {
+    active: Option<GameController>,
+    subsystem: GameControllerSubsystem,
+}
+
+impl Controller {
+    pub fn open(sdl_context: &Sdl) -> Result<Self, String> {
+        let mut controller = Self {
+            active: None,
+            subsystem: sdl_context.game_controller()?,
+        };
+        controller.active = controller.first();
+        Ok(controller)
+    }
+
+    fn first(&self) -> Option<GameController> {
+        (0..self.subsystem.num_joysticks().ok()?)
+            .filter(|index| self.subsystem.is_game_controller(*index))
+            .find_map(|index| self.subsystem.open(index).ok())
+    }
+
+    pub fn name(&self) -> Option<String> {
+        self.active.as_ref().map(|controller| controller.name())
+    }
+
+    // Takes up the controller plugged in at the given index if there is none
+    // in use, returning whether it did.
+    pub fn added(&mut self, index: u32) -> bool {
+        if self.active.is_some() {
+            return false;
+        }
+        self.active = self.subsystem.open(index).ok();
+        self.active.is_some()
+    }
+
+    // Returns whether the controller unplugged was the one in use, moving
+    // on to another connected one if there is any.
+    pub fn removed(&mut self, id: u32) -> bool {
+        let active = self
+            .active
+            .as_ref()
+            .map(|controller| controller.instance_id());
+        if active != Some(id) {
+            return false;
+        }
+        self.active = self.first();
+        true
+    }
+}
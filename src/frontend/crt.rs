@@ -0,0 +1,53 @@
+use crate::frontend::shape::PixelShape;
+use serde::Deserialize;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CrtOptions {
+    pub scanlines: f32,
+    pub curvature: f32,
+    pub bloom: f32,
+    pub grid: f32,
+    pub shape: PixelShape,
+}
+
+#[cfg(feature = "sdl")]
+impl CrtOptions {
+    pub fn clamped(self) -> Self {
+        Self {
+            scanlines: self.scanlines.clamp(0.0, 1.0),
+            curvature: self.curvature.clamp(0.0, 1.0),
+            bloom: self.bloom.clamp(0.0, 1.0),
+            grid: self.grid.clamp(0.0, 1.0),
+            shape: self.shape,
+        }
+    }
+
+    // Maps a point in normalized screen coordinates (-1.0..=1.0 on both axes)
+    // through a barrel distortion, returning the new point and the local
+    // scaling factor to apply to whatever is drawn there.
+    pub fn warp(&self, x: f32, y: f32) -> (f32, f32, f32) {
+        let factor = 1.0 - self.curvature * 0.25 * (x * x + y * y);
+        (x * factor, y * factor, factor)
+    }
+}
+
+#[cfg(all(test, feature = "sdl"))]
+mod tests {
+    use crate::frontend::crt::CrtOptions;
+    #[test]
+    fn warp_without_curvature_is_identity() {
+        let crt = CrtOptions::default();
+        assert_eq!(crt.warp(0.5, -1.0), (0.5, -1.0, 1.0));
+    }
+    #[test]
+    fn warp_pulls_corners_towards_center() {
+        let crt = CrtOptions {
+            curvature: 0.4,
+            ..Default::default()
+        };
+        assert_eq!(crt.warp(0.0, 0.0), (0.0, 0.0, 1.0));
+        let (x, y, scale) = crt.warp(1.0, 1.0);
+        assert!(x < 1.0 && y < 1.0 && scale < 1.0);
+    }
+}
@@ -0,0 +1,187 @@
+use crate::chip8::disasm::disassemble;
+use crate::chip8::Chip8;
+use crate::frontend::palette::Rgb;
+use crate::frontend::text::Overlay;
+use crate::frontend::text::LINE_HEIGHT;
+use sdl2::pixels::Color;
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::render::TextureCreator;
+use sdl2::render::WindowCanvas;
+use sdl2::video::WindowContext;
+use sdl2::VideoSubsystem;
+use std::time::Duration;
+use std::time::Instant;
+
+const WIDTH: usize = 168;
+const HEIGHT: usize = 144;
+const SCALE: u32 = 4;
+const MARGIN: usize = 2;
+// Left edge of the disassembly, to the right of the registers.
+const CODE_COLUMN: usize = 56;
+const CODE_LINES: usize = 13;
+const MEMORY_LINES: usize = 4;
+const REFRESH: Duration = Duration::from_millis(33);
+
+// A second window with the registers, the code around the program counter
+// and the memory I points to, so that none of it covers the display.
+pub struct DebugWindow {
+    canvas: WindowCanvas,
+    texture_creator: TextureCreator<WindowContext>,
+    overlay: Overlay,
+    drawn: Option<Instant>,
+}
+
+impl DebugWindow {
+    pub fn open(video_subsystem: &VideoSubsystem) -> Self {
+        let window = video_subsystem
+            .window(
+                "Chip8 debugger",
+                WIDTH as u32 * SCALE,
+                HEIGHT as u32 * SCALE,
+            )
+            .build()
+            .unwrap();
+        let canvas = window.into_canvas().build().unwrap();
+        let texture_creator = canvas.texture_creator();
+        Self {
+            canvas,
+            texture_creator,
+            overlay: Overlay::new(WIDTH, HEIGHT),
+            drawn: None,
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.canvas.window().id()
+    }
+
+    // Redraws the views, at most a few dozen times a second since the state
+    // changes with every instruction.
+    pub fn update(&mut self, chip8: &Chip8, now: Instant) {
+        if self
+            .drawn
+            .is_some_and(|drawn| now.saturating_duration_since(drawn) < REFRESH)
+        {
+            return;
+        }
+        self.drawn = Some(now);
+
+        self.overlay.clear();
+        let white = Rgb(255, 255, 255);
+        for (line, text) in registers(chip8).iter().enumerate() {
+            self.overlay
+                .draw_text(MARGIN, MARGIN + line * LINE_HEIGHT, text, white);
+        }
+        for (line, text) in code(chip8).iter().enumerate() {
+            self.overlay
+                .draw_text(CODE_COLUMN, MARGIN + line * LINE_HEIGHT, text, white);
+        }
+        let top = MARGIN + (CODE_LINES + 1) * LINE_HEIGHT;
+        for (line, text) in memory(chip8).iter().enumerate() {
+            self.overlay
+                .draw_text(MARGIN, top + line * LINE_HEIGHT, text, white);
+        }
+
+        let mut texture = self
+            .texture_creator
+            .create_texture_static(PixelFormatEnum::RGBA32, WIDTH as u32, HEIGHT as u32)
+            .unwrap();
+        texture
+            .update(None, &self.overlay.pixels, WIDTH * 4)
+            .unwrap();
+        self.canvas.set_draw_color(Color::RGB(0, 0, 0));
+        self.canvas.clear();
+        self.canvas.copy(&texture, None, None).unwrap();
+        self.canvas.present();
+    }
+}
+
+fn registers(chip8: &Chip8) -> Vec<String> {
+    let v = chip8.reg_v();
+    let mut lines: Vec<String> = (0..8)
+        .map(|r| format!("V{:X} {:02X}  V{:X} {:02X}", r, v[r], r + 8, v[r + 8]))
+        .collect();
+    lines.push(String::new());
+    lines.push(format!("PC {:04X}", chip8.pc));
+    lines.push(format!("I  {:04X}", chip8.reg_i()));
+    lines.push(format!(
+        "DT {:02X}  ST {:02X}",
+        chip8.delay_timer(),
+        chip8.sound_timer()
+    ));
+    lines.push(format!("SP {:X}", chip8.stack().len()));
+    lines.extend(
+        chip8
+            .stack()
+            .iter()
+            .rev()
+            .map(|addr| format!("   {:04X}", addr)),
+    );
+    lines
+}
+
+// The instructions around the program counter, which is marked with an arrow.
+fn code(chip8: &Chip8) -> Vec<String> {
+    let pc = chip8.pc as usize;
+    let start = pc.saturating_sub(CODE_LINES / 2 * 2);
+    (0..CODE_LINES)
+        .map(|line| start + line * 2)
+        .take_while(|addr| addr + 1 < chip8.ram.len())
+        .map(|addr| {
+            let opcode = (chip8.ram[addr] as u16) << 8 | chip8.ram[addr + 1] as u16;
+            let marker = if addr == pc { '>' } else { ' ' };
+            format!(
+                "{} {:03X} {:04X} {}",
+                marker,
+                addr,
+                opcode,
+                disassemble(opcode)
+            )
+        })
+        .collect()
+}
+
+// A few rows of memory starting at I, which is where sprites and the data
+// loaded and stored by Fx55 and Fx65 live.
+fn memory(chip8: &Chip8) -> Vec<String> {
+    let start = chip8.reg_i() as usize & !7;
+    (0..MEMORY_LINES)
+        .map(|row| start + row * 8)
+        .filter(|addr| addr + 8 <= chip8.ram.len())
+        .map(|addr| {
+            let bytes: Vec<String> = chip8.ram[addr..addr + 8]
+                .iter()
+                .map(|byte| format!("{:02X}", byte))
+                .collect();
+            format!("{:03X}: {}", addr, bytes.join(" "))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::Chip8;
+    use crate::frontend::debugger::code;
+    use crate::frontend::debugger::memory;
+    use crate::frontend::debugger::registers;
+    #[test]
+    fn code_marks_the_program_counter() {
+        let mut chip8 = Chip8::new();
+        chip8.load(&[0x00, 0xE0, 0x12, 0x00]).unwrap();
+        let lines = code(&chip8);
+        let current = lines.iter().find(|line| line.starts_with('>')).unwrap();
+        assert_eq!(current, "> 200 00E0 CLS");
+        assert!(lines.contains(&String::from("  202 1200 JP 200")));
+    }
+    #[test]
+    fn registers_and_memory_are_in_hexadecimal() {
+        let mut chip8 = Chip8::new();
+        chip8.load(&[0x6A, 0x2F, 0xA2, 0x00]).unwrap();
+        chip8.fetch_execute();
+        chip8.fetch_execute();
+        let registers = registers(&chip8);
+        assert_eq!(registers[2], "V2 00  VA 2F");
+        assert!(registers.contains(&String::from("I  0200")));
+        assert_eq!(memory(&chip8)[0], "200: 6A 2F A2 00 00 00 00 00");
+    }
+}
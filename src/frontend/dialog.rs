@@ -0,0 +1,11 @@
+use crate::frontend::browser::EXTENSIONS;
+use std::path::PathBuf;
+
+// Asks for a ROM with the system's file picker, None if it was cancelled.
+pub fn pick_rom() -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .set_title("Open ROM")
+        .add_filter("CHIP-8 ROMs", &EXTENSIONS)
+        .add_filter("All files", &["*"])
+        .pick_file()
+}
@@ -0,0 +1,130 @@
+use crate::chip8::display::DisplayBuffer;
+use crate::chip8::display::RES_HEIGHT;
+use crate::chip8::display::RES_WIDTH;
+use clap::ValueEnum;
+use std::path::Path;
+
+// The ways the display can be written out for other tools: text for
+// reading and diffing, Netpbm and PNG images, and the bare bits, eight
+// pixels a byte from the left as CHIP-8 draws them.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Format {
+    Text,
+    Pbm,
+    Pgm,
+    Png,
+    Raw,
+}
+
+impl Format {
+    // The format a file name asks for by its extension, if any.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        match extension.as_str() {
+            "txt" => Some(Format::Text),
+            "pbm" => Some(Format::Pbm),
+            "pgm" => Some(Format::Pgm),
+            "png" => Some(Format::Png),
+            "raw" | "bin" => Some(Format::Raw),
+            _ => None,
+        }
+    }
+}
+
+// One line a row, # for lit pixels and . for unlit ones.
+pub fn text(buffer: &DisplayBuffer) -> String {
+    buffer
+        .chunks(RES_WIDTH)
+        .map(|row| row.iter().map(|lit| if *lit { '#' } else { '.' }).collect())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn bits(buffer: &DisplayBuffer) -> Vec<u8> {
+    buffer
+        .chunks(8)
+        .map(|pixels| {
+            pixels
+                .iter()
+                .fold(0, |byte, lit| byte << 1 | u8::from(*lit))
+        })
+        .collect()
+}
+
+// Lit pixels are white in the grayscale images, as on the screen, while
+// PBM has them black, as ink on paper.
+fn grays(buffer: &DisplayBuffer) -> Vec<u8> {
+    buffer
+        .iter()
+        .map(|lit| if *lit { 255 } else { 0 })
+        .collect()
+}
+
+pub fn dump(buffer: &DisplayBuffer, format: Format) -> Vec<u8> {
+    let header = |magic| format!("{}\n{} {}\n", magic, RES_WIDTH, RES_HEIGHT).into_bytes();
+    match format {
+        Format::Text => format!("{}\n", text(buffer)).into_bytes(),
+        Format::Pbm => [header("P4"), bits(buffer)].concat(),
+        Format::Pgm => [header("P5"), b"255\n".to_vec(), grays(buffer)].concat(),
+        Format::Png => {
+            let mut encoded = Vec::new();
+            let mut encoder = png::Encoder::new(&mut encoded, RES_WIDTH as u32, RES_HEIGHT as u32);
+            encoder.set_color(png::ColorType::Grayscale);
+            encoder.set_depth(png::BitDepth::One);
+            // Writing into memory cannot fail.
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&bits(buffer)).unwrap();
+            writer.finish().unwrap();
+            encoded
+        }
+        Format::Raw => bits(buffer),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::display::Display;
+    #[cfg(feature = "sdl")]
+    use crate::frontend::bezel::decode;
+    use crate::frontend::dump::dump;
+    use crate::frontend::dump::Format;
+    use std::path::Path;
+    fn display() -> Display {
+        let mut display = Display::new();
+        display.draw(vec![0xA0], 0, 0, true);
+        display
+    }
+    #[test]
+    fn from_path_goes_by_extension() {
+        assert_eq!(Format::from_path(Path::new("shot.PNG")), Some(Format::Png));
+        assert_eq!(Format::from_path(Path::new("frame.bin")), Some(Format::Raw));
+        assert_eq!(Format::from_path(Path::new("screen")), None);
+    }
+    #[test]
+    fn dump_packs_eight_pixels_a_byte() {
+        let buffer = display().as_buffer();
+        let raw = dump(&buffer, Format::Raw);
+        assert_eq!(raw.len(), 256);
+        assert_eq!(raw[..2], [0xA0, 0x00]);
+        let pbm = dump(&buffer, Format::Pbm);
+        assert_eq!(pbm[..9], *b"P4\n64 32\n");
+        assert_eq!(pbm[9..], raw);
+        let pgm = dump(&buffer, Format::Pgm);
+        assert_eq!(pgm.len(), 13 + 64 * 32);
+        assert_eq!(pgm[13..16], [255, 0, 255]);
+        let text = dump(&buffer, Format::Text);
+        assert!(text.starts_with(b"#.#....."));
+        assert_eq!(text.len(), 65 * 32);
+    }
+    #[cfg(feature = "sdl")]
+    #[test]
+    fn dump_png_decodes_back() {
+        let buffer = display().as_buffer();
+        let (width, height, pixels) = decode(dump(&buffer, Format::Png).as_slice()).unwrap();
+        assert_eq!((width, height), (64, 32));
+        assert_eq!(
+            pixels[..12],
+            [255, 255, 255, 255, 0, 0, 0, 255, 255, 255, 255, 255]
+        );
+    }
+}
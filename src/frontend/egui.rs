@@ -0,0 +1,946 @@
+use crate::audio::Audio;
+use crate::audio::MAX_FREQUENCY;
+use crate::audio::MIN_FREQUENCY;
+use crate::chip8::display::RES_HEIGHT;
+use crate::chip8::display::RES_WIDTH;
+use crate::chip8::hash::RomHash;
+use crate::chip8::platform::Platform;
+use crate::chip8::quirks::Quirk;
+use crate::chip8::quirks::Quirks;
+use crate::chip8::Chip8;
+use crate::chip8::Reset;
+use crate::config::sidecar::Sidecar;
+use crate::config::Config;
+use crate::config::LiveConfig;
+use crate::failure::Failure;
+use crate::frontend::cartridge::Cartridge;
+#[cfg(feature = "dialog")]
+use crate::frontend::dialog::pick_rom;
+use crate::frontend::focus::Focus;
+use crate::frontend::framebuffer::levels;
+use crate::frontend::framebuffer::rgb24;
+use crate::frontend::keymap::Keymap;
+use crate::frontend::keymap::KEYPAD;
+use crate::frontend::layout::place;
+use crate::frontend::layout::Filter;
+use crate::frontend::layout::PixelAspect;
+use crate::frontend::layout::Scaling;
+use crate::frontend::limits::Limits;
+use crate::frontend::macros::Player;
+use crate::frontend::notifications::Notifications;
+use crate::frontend::palette::Palettes;
+use crate::frontend::phosphor::Phosphor;
+use crate::frontend::pokes::Pokes;
+use crate::frontend::recent::ago;
+use crate::frontend::recent::now;
+use crate::frontend::recent::Played;
+use crate::frontend::recent::Recent;
+use crate::frontend::runner::step_speed;
+use crate::frontend::runner::Hold;
+use crate::frontend::runner::Runner;
+use crate::frontend::script::Script;
+use crate::frontend::speed::describe;
+use crate::frontend::stats::Stats;
+use crate::frontend::title::rom_name;
+use crate::frontend::title::title;
+use crate::frontend::watch::Watch;
+use crate::Args;
+use clap::ValueEnum;
+use eframe::egui;
+use std::cell::RefCell;
+use std::path::Path;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::Instant;
+use tracing::info;
+use tracing::warn;
+
+const MENU_HEIGHT: f32 = 24.0;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Dock {
+    Hidden,
+    Left,
+    Right,
+    Bottom,
+}
+
+struct Emulator {
+    chip8: Chip8,
+    rom_name: String,
+    // Last title given to the window, which is only updated on change.
+    title: String,
+    saved: Option<Chip8>,
+    palettes: Palettes,
+    speed: u32,
+    runner: Runner,
+    phosphor: Option<Phosphor>,
+    last_frame: Instant,
+    paused: bool,
+    pause_on_start: bool,
+    focus: Focus,
+    focused: bool,
+    fast_forward: bool,
+    slow_motion: bool,
+    hold: Hold,
+    audio: Box<dyn Audio>,
+    keymap: Keymap,
+    // Where rebound keys are saved, as a file and the table in it.
+    keys_file: Option<(PathBuf, Option<&'static str>)>,
+    // The keypad key waiting for a key to be pressed to bind it to.
+    rebinding: Option<u8>,
+    key_latch: u8,
+    // That of --seed, for every ROM opened.
+    seed: Option<u64>,
+    quirks: Quirks,
+    // Those of --platform, the configuration file and --quirk, to work out
+    // the quirks of each ROM opened along with its sidecar file.
+    platform: Option<Platform>,
+    config_quirks: Vec<(Quirk, bool)>,
+    quirk_overrides: Vec<(Quirk, bool)>,
+    macros: Player,
+    script: Option<Script>,
+    // Those of --poke, until another ROM is opened.
+    pokes: Option<Pokes>,
+    limits: Option<Limits>,
+    // How a run given limits ended, for once the window is closed.
+    end: Rc<RefCell<Option<Failure>>>,
+    texture: Option<egui::TextureHandle>,
+    scaling: Scaling,
+    aspect: PixelAspect,
+    filter: Filter,
+    notifications: Notifications,
+    stats: Stats,
+    show_stats: bool,
+    visual_beep: bool,
+    registers: Dock,
+    keypad: bool,
+    // The keypad key held down with the pointer.
+    clicked: Option<u8>,
+    settings_open: bool,
+    // Path typed into the Open ROM window while it is shown.
+    open_path: Option<String>,
+    open_error: Option<String>,
+    // With --watch, the file of the ROM running, to run it again on change.
+    watching: bool,
+    watch: Option<Watch>,
+    live_config: Option<LiveConfig>,
+}
+
+impl Emulator {
+    // Runs the ROM on a new machine with the settings in effect, or keeps
+    // the one running if the ROM does not fit in memory.
+    fn start(&mut self, rom: &[u8]) -> Result<(), String> {
+        let mut chip8 = Chip8::new();
+        match self.seed {
+            Some(seed) => chip8.set_seed(seed),
+            None => info!("random seed {}", chip8.seed()),
+        }
+        chip8.set_latch(self.key_latch);
+        chip8.set_quirks(self.quirks);
+        chip8.load(rom)?;
+        if let Some(pokes) = &self.pokes {
+            pokes.apply(&mut chip8);
+        }
+        self.chip8 = chip8;
+        if let Some(phosphor) = self.phosphor.as_mut() {
+            phosphor.clear();
+        }
+        self.paused |= self.pause_on_start;
+        Ok(())
+    }
+
+    // A soft reset from the menu or F2, or a hard one with Shift, which also
+    // forgets the saved state and starts the macros and the input script
+    // over.
+    fn restart(&mut self, reset: Reset) {
+        if reset == Reset::Hard {
+            self.saved = None;
+            self.macros.stop(&mut self.chip8);
+            if let Some(script) = self.script.as_mut() {
+                script.rewind(&mut self.chip8);
+            }
+        }
+        self.chip8.reset(reset);
+        if let Some(phosphor) = self.phosphor.as_mut() {
+            phosphor.clear();
+        }
+        self.paused |= self.pause_on_start;
+        self.notifications.push(match reset {
+            Reset::Soft => "Reset",
+            Reset::Hard => "Hard reset",
+        });
+    }
+
+    fn invert(&mut self) {
+        let inverted = self.palettes.invert();
+        self.notifications.push(if inverted {
+            "Colors inverted"
+        } else {
+            "Colors restored"
+        });
+    }
+
+    // Starts the ROM, with the settings of its sidecar file, if any.
+    fn open(&mut self, path: &str) -> Result<(), String> {
+        let data = std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+        let sidecar = Sidecar::find(Path::new(path), &RomHash::of(&data))?;
+        let (rom, cartridge) =
+            Cartridge::open(Path::new(path), data).map_err(|e| format!("{}: {}", path, e))?;
+        self.pokes = None;
+        // The sidecar file's quirks go between those of the configuration
+        // file and those of --quirk.
+        let overrides: Vec<(Quirk, bool)> = self
+            .config_quirks
+            .iter()
+            .copied()
+            .chain(sidecar.quirks.iter().map(|(quirk, on)| (*quirk, *on)))
+            .chain(self.quirk_overrides.iter().copied())
+            .collect();
+        self.quirks = match (self.platform.or(sidecar.platform), &cartridge) {
+            (None, Some(options)) => options.quirks().overridden(&overrides),
+            (platform, _) => Quirks::resolve(platform, &overrides),
+        };
+        self.start(&rom).map_err(|e| format!("{}: {}", path, e))?;
+        self.rom_name = rom_name(path);
+        self.saved = None;
+        let cartridge_speed = cartridge.as_ref().and_then(|options| options.speed());
+        if let Some(speed) = sidecar.speed.or(cartridge_speed) {
+            self.set_speed(speed as u32);
+        }
+        if let Some(palette) = sidecar.palette {
+            if let Err(error) = self.palettes.select(&palette) {
+                self.notifications.push(error);
+            }
+        }
+        if let Some(keys) = sidecar.keys {
+            self.keymap = keys;
+        }
+        Recent::record(Path::new(path), self.speed, &self.palettes.current().name);
+        if self.watching {
+            self.watch = Some(Watch::new(Path::new(path)));
+        }
+        self.notifications.push(format!("Opened {}", path));
+        Ok(())
+    }
+
+    // Takes up edits to the configuration file as they are saved.
+    fn reload_config(&mut self) {
+        let Some(live) = self.live_config.as_mut() else {
+            return;
+        };
+        let changes = match live.poll(Instant::now()) {
+            Ok(Some(changes)) => changes,
+            Ok(None) => return,
+            Err(error) => {
+                warn!("{}", error);
+                self.notifications.push(error);
+                return;
+            }
+        };
+        if let Some(speed) = changes.speed {
+            self.set_speed(speed as u32);
+        }
+        if let Some(palette) = &changes.palette {
+            if let Err(error) = self.palettes.select(palette) {
+                self.notifications.push(error);
+            }
+        }
+        if let Some(tone) = changes.tone {
+            self.palettes.set_tone(tone);
+        }
+        if let Some(keys) = changes.keys.clone() {
+            self.keymap = keys;
+        }
+        self.notifications
+            .push(format!("Config: {}", changes.describe()));
+    }
+
+    // Runs the ROM again from the start once its file changes, with the
+    // settings in effect.
+    fn reload(&mut self) {
+        let Some(watch) = self.watch.as_mut() else {
+            return;
+        };
+        if !watch.changed(Instant::now()) {
+            return;
+        }
+        let path = watch.path().to_path_buf();
+        let started = std::fs::read(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|data| Cartridge::open(&path, data))
+            .and_then(|(rom, _)| self.start(&rom));
+        match started {
+            Ok(()) => {
+                self.saved = None;
+                self.notifications
+                    .push(format!("Reloaded {}", self.rom_name));
+            }
+            Err(error) => {
+                warn!("{}: {}", path.display(), error);
+                self.notifications
+                    .push(format!("{}: {}", path.display(), error));
+            }
+        }
+    }
+
+    // The system's file picker, or without one a window to type the path in.
+    #[cfg(feature = "dialog")]
+    fn pick_rom(&mut self) {
+        let Some(path) = pick_rom() else {
+            return;
+        };
+        if let Err(error) = self.open(&path.to_string_lossy()) {
+            self.notifications.push(error);
+        }
+    }
+
+    #[cfg(not(feature = "dialog"))]
+    fn pick_rom(&mut self) {
+        self.open_path = Some(String::new());
+        self.open_error = None;
+    }
+
+    // Opens a recent ROM with the speed and palette it was played with.
+    fn resume(&mut self, played: &Played) {
+        let path = played.path.to_string_lossy();
+        if let Err(error) = self.open(&path) {
+            self.notifications.push(error);
+            return;
+        }
+        self.set_speed(played.speed);
+        if self.palettes.select(&played.palette).is_ok() {
+            Recent::record(&played.path, self.speed, &played.palette);
+        }
+    }
+
+    fn handle_input(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        let focused = ctx.input(|input| input.focused);
+        if focused != self.focused {
+            self.focused = focused;
+            if self.focus.change(focused, self.paused) != self.paused {
+                self.toggle_pause();
+            }
+        }
+        // A ROM file dropped on the window is opened.
+        let dropped = ctx.input(|input| input.raw.dropped_files.clone());
+        for path in dropped.into_iter().filter_map(|file| file.path) {
+            if let Err(error) = self.open(&path.to_string_lossy()) {
+                self.notifications.push(error);
+            }
+        }
+        // Leave the keyboard alone while a text field in a dialog has focus.
+        if ctx.wants_keyboard_input() {
+            self.fast_forward = false;
+            self.slow_motion = false;
+            return;
+        }
+        // Holding Tab runs the emulation faster, without the buzzer, and
+        // holding Backspace slower.
+        self.fast_forward = ctx.input(|input| input.key_down(egui::Key::Tab));
+        self.slow_motion = ctx.input(|input| input.key_down(egui::Key::Backspace));
+        let events = ctx.input(|input| input.events.clone());
+        for event in events {
+            if let egui::Event::Key {
+                key,
+                pressed,
+                repeat: false,
+                modifiers,
+            } = event
+            {
+                if let Some(value) = self.rebinding {
+                    if pressed {
+                        self.rebinding = None;
+                        if key != egui::Key::Escape {
+                            self.rebind(key.name(), value);
+                        }
+                    }
+                    continue;
+                }
+                match key {
+                    egui::Key::Escape if pressed => frame.close(),
+                    egui::Key::Space if pressed => self.toggle_pause(),
+                    egui::Key::O if pressed && modifiers.command => self.pick_rom(),
+                    egui::Key::I if pressed => self.show_stats = !self.show_stats,
+                    egui::Key::F2 if pressed => self.restart(if modifiers.shift {
+                        Reset::Hard
+                    } else {
+                        Reset::Soft
+                    }),
+                    egui::Key::N if pressed => self.invert(),
+                    egui::Key::Minus | egui::Key::PlusEquals if pressed => {
+                        self.set_speed(step_speed(self.speed, key == egui::Key::PlusEquals));
+                        self.notifications
+                            .push(format!("Speed: {}", describe(self.speed)));
+                    }
+                    egui::Key::P if pressed => {
+                        self.palettes.cycle();
+                        let name = &self.palettes.current().name;
+                        self.notifications.push(format!("Palette: {}", name));
+                    }
+                    _ => {
+                        if self.macros.key(key.name(), pressed) {
+                            continue;
+                        }
+                        if let Some(x) = self.keymap.key(key.name()) {
+                            self.chip8.key_pressed(x, pressed);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn rebind(&mut self, name: &str, value: u8) {
+        let Some(name) = self.keymap.bind(name, value) else {
+            self.notifications.push(format!("{} cannot be bound", name));
+            return;
+        };
+        let saved = match &self.keys_file {
+            Some((path, table)) => self.keymap.save(path, *table),
+            None => Err(String::from("no configuration directory")),
+        };
+        match saved {
+            Ok(()) => self
+                .notifications
+                .push(format!("Key {:X} bound to {}", value, name)),
+            Err(error) => {
+                warn!("key binding not saved, {}", error);
+                self.notifications
+                    .push(format!("Key {:X} bound to {}, not saved", value, name));
+            }
+        }
+    }
+
+    fn set_speed(&mut self, speed: u32) {
+        self.speed = speed;
+        self.runner.set_speed(speed);
+    }
+
+    fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.notifications
+            .push(if self.paused { "Paused" } else { "Resumed" });
+    }
+
+    fn step(&mut self) {
+        let elapsed = self.last_frame.elapsed();
+        self.last_frame = Instant::now();
+        if self.paused {
+            self.audio.pause();
+            return;
+        }
+        let scale = self.hold.scale(self.fast_forward, self.slow_motion);
+        self.runner.set_scale(scale);
+        for _ in 0..self.runner.advance(&mut self.chip8, elapsed) {
+            self.macros.tick(&mut self.chip8);
+            if let Some(script) = self.script.as_mut() {
+                script.tick(&mut self.chip8);
+            }
+            if let Some(pokes) = &self.pokes {
+                pokes.tick(&mut self.chip8);
+            }
+            if let Some(phosphor) = self.phosphor.as_mut() {
+                phosphor.update(&self.chip8.display.as_buffer());
+            }
+        }
+        let timer = self.chip8.take_sound();
+        if self.fast_forward {
+            self.audio.pause();
+        } else {
+            self.audio.sound(timer);
+        }
+    }
+
+    fn menu_bar(&mut self, ui: &mut egui::Ui, frame: &mut eframe::Frame) {
+        egui::menu::bar(ui, |ui| {
+            ui.menu_button("File", |ui| {
+                if ui.button("Open ROM...").clicked() {
+                    ui.close_menu();
+                    self.pick_rom();
+                }
+                ui.menu_button("Open Recent", |ui| {
+                    let recent = Recent::find();
+                    if recent.roms.is_empty() {
+                        ui.label("Nothing played yet");
+                    }
+                    let now = now();
+                    for played in recent.roms {
+                        let label =
+                            format!("{}  ({})", played.path.display(), ago(played.time, now));
+                        if ui.button(label).clicked() {
+                            ui.close_menu();
+                            self.resume(&played);
+                        }
+                    }
+                });
+                ui.separator();
+                if ui.button("Quit").clicked() {
+                    frame.close();
+                }
+            });
+            ui.menu_button("Emulation", |ui| {
+                let mut paused = self.paused;
+                if ui.checkbox(&mut paused, "Pause").changed() {
+                    self.toggle_pause();
+                }
+                if ui.button("Reset").clicked() {
+                    self.restart(Reset::Soft);
+                    ui.close_menu();
+                }
+                if ui.button("Hard reset").clicked() {
+                    self.restart(Reset::Hard);
+                    ui.close_menu();
+                }
+                ui.separator();
+                if ui.button("Save state").clicked() {
+                    self.saved = Some(self.chip8.clone());
+                    self.notifications.push("State saved");
+                    ui.close_menu();
+                }
+                let load = ui.add_enabled(self.saved.is_some(), egui::Button::new("Load state"));
+                if load.clicked() {
+                    if let Some(saved) = &self.saved {
+                        self.chip8 = saved.clone();
+                        self.notifications.push("State loaded");
+                    }
+                    ui.close_menu();
+                }
+                ui.separator();
+                if ui.button("Settings...").clicked() {
+                    self.settings_open = true;
+                    ui.close_menu();
+                }
+            });
+            ui.menu_button("View", |ui| {
+                ui.label("Registers");
+                ui.radio_value(&mut self.registers, Dock::Hidden, "Hidden");
+                ui.radio_value(&mut self.registers, Dock::Left, "Left");
+                ui.radio_value(&mut self.registers, Dock::Right, "Right");
+                ui.radio_value(&mut self.registers, Dock::Bottom, "Bottom");
+                ui.separator();
+                ui.checkbox(&mut self.keypad, "Keypad");
+                ui.checkbox(&mut self.show_stats, "FPS and IPS");
+                ui.checkbox(&mut self.visual_beep, "Visual beep");
+                let mut inverted = self.palettes.inverted();
+                if ui.checkbox(&mut inverted, "Invert colors").changed() {
+                    self.invert();
+                }
+            });
+        });
+    }
+
+    fn register_view(&self, ui: &mut egui::Ui) {
+        let chip8 = &self.chip8;
+        egui::Grid::new("registers").striped(true).show(ui, |ui| {
+            for (index, value) in chip8.reg_v().iter().enumerate() {
+                ui.monospace(format!("V{:X}", index));
+                ui.monospace(format!("{:02X}", value));
+                if index % 4 == 3 {
+                    ui.end_row();
+                }
+            }
+            ui.monospace("PC");
+            ui.monospace(format!("{:03X}", chip8.pc));
+            ui.monospace("I");
+            ui.monospace(format!("{:03X}", chip8.reg_i()));
+            ui.end_row();
+            ui.monospace("DT");
+            ui.monospace(format!("{:02X}", chip8.delay_timer()));
+            ui.monospace("ST");
+            ui.monospace(format!("{:02X}", chip8.sound_timer()));
+            ui.end_row();
+        });
+        ui.separator();
+        ui.monospace(format!("Stack ({})", chip8.stack().len()));
+        for address in chip8.stack().iter().rev() {
+            ui.monospace(format!("{:03X}", address));
+        }
+    }
+
+    // Clickable keys, held down for as long as the pointer is.
+    fn keypad_view(&mut self, ui: &mut egui::Ui) {
+        let mut down = None;
+        egui::Grid::new("keypad").show(ui, |ui| {
+            for keys in KEYPAD {
+                for key in keys {
+                    let label = egui::RichText::new(format!("{:X}", key)).monospace();
+                    let button = egui::Button::new(label).min_size(egui::vec2(32.0, 32.0));
+                    if ui.add(button).is_pointer_button_down_on() {
+                        down = Some(key);
+                    }
+                }
+                ui.end_row();
+            }
+        });
+        self.hold(down);
+    }
+
+    fn hold(&mut self, key: Option<u8>) {
+        if key != self.clicked {
+            if let Some(released) = self.clicked {
+                self.chip8.key_pressed(released, false);
+            }
+            if let Some(pressed) = key {
+                self.chip8.key_pressed(pressed, true);
+            }
+            self.clicked = key;
+        }
+    }
+
+    fn settings_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.settings_open;
+        egui::Window::new("Settings")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Speed (IPS)");
+                    let mut speed = self.speed;
+                    let drag = egui::DragValue::new(&mut speed).clamp_range(1..=u16::MAX);
+                    if ui.add(drag).changed() {
+                        self.set_speed(speed);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Palette");
+                    let current = self.palettes.current().name.clone();
+                    let mut selected = current.clone();
+                    egui::ComboBox::from_id_source("palette")
+                        .selected_text(&current)
+                        .show_ui(ui, |ui| {
+                            for name in self.palettes.names() {
+                                ui.selectable_value(&mut selected, name.to_string(), name);
+                            }
+                        });
+                    if selected != current {
+                        self.palettes.select(&selected).unwrap();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Beep (Hz)");
+                    let mut frequency = self.audio.frequency();
+                    let range = MIN_FREQUENCY..=MAX_FREQUENCY;
+                    if ui.add(egui::Slider::new(&mut frequency, range)).changed() {
+                        self.audio
+                            .adjust_frequency(frequency - self.audio.frequency());
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Volume (%)");
+                    let mut volume = self.audio.volume();
+                    if ui.add(egui::Slider::new(&mut volume, 0..=100)).changed() {
+                        self.audio.set_volume(volume);
+                    }
+                });
+                let mut tone = self.palettes.tone();
+                ui.horizontal(|ui| {
+                    ui.label("Brightness");
+                    ui.add(egui::Slider::new(&mut tone.brightness, 0.1..=2.0));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Gamma");
+                    ui.add(egui::Slider::new(&mut tone.gamma, 0.5..=2.5));
+                });
+                if tone != self.palettes.tone() {
+                    self.palettes.set_tone(tone);
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Scaling");
+                    choice(ui, "scaling", &mut self.scaling);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Filter");
+                    choice(ui, "filter", &mut self.filter);
+                });
+                ui.separator();
+                ui.label(if self.rebinding.is_some() {
+                    "Press the key to bind, or Esc to leave it as it is"
+                } else {
+                    "Click a keypad key to bind it to another key"
+                });
+                egui::Grid::new("keys").show(ui, |ui| {
+                    for keys in KEYPAD {
+                        for key in keys {
+                            let waiting = self.rebinding == Some(key);
+                            let bound = if waiting {
+                                String::from("...")
+                            } else {
+                                self.keymap.keys(key).join(" ")
+                            };
+                            let label = egui::RichText::new(format!("{:X}: {}", key, bound));
+                            if ui.selectable_label(waiting, label.monospace()).clicked() {
+                                self.rebinding = Some(key);
+                            }
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+        self.settings_open = open;
+    }
+
+    fn open_window(&mut self, ctx: &egui::Context) {
+        let Some(mut path) = self.open_path.take() else {
+            return;
+        };
+        let mut open = true;
+        let mut confirmed = false;
+        egui::Window::new("Open ROM")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let field = ui.text_edit_singleline(&mut path);
+                let entered = field.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                if ui.button("Open").clicked() || entered {
+                    confirmed = true;
+                }
+                if let Some(error) = &self.open_error {
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+            });
+        if confirmed {
+            match self.open(&path) {
+                Ok(()) => return,
+                Err(error) => self.open_error = Some(error),
+            }
+        }
+        if open {
+            self.open_path = Some(path);
+        }
+    }
+
+    fn display(&mut self, ui: &mut egui::Ui) {
+        let palette = self.palettes.current();
+        let pixels = rgb24(
+            &levels(&self.chip8.display.as_buffer(), self.phosphor.as_ref()),
+            palette.background,
+            palette.foreground(),
+        );
+        let image = egui::ColorImage::from_rgb([RES_WIDTH, RES_HEIGHT], &pixels);
+        let options = match self.filter {
+            Filter::Nearest => egui::TextureOptions::NEAREST,
+            Filter::Linear => egui::TextureOptions::LINEAR,
+        };
+        let texture = match &mut self.texture {
+            Some(texture) => {
+                texture.set(image, options);
+                texture
+            }
+            None => self
+                .texture
+                .insert(ui.ctx().load_texture("display", image, options)),
+        };
+        // Placed in physical pixels, so that integer scaling stays exact with
+        // a fractional pixels_per_point.
+        let pixels_per_point = ui.ctx().pixels_per_point();
+        let available = ui.available_size() * pixels_per_point;
+        let viewport = place(
+            (available.x as u32, available.y as u32),
+            self.aspect.display(),
+            self.scaling,
+        );
+        let size = egui::vec2(viewport.width as f32, viewport.height as f32) / pixels_per_point;
+        let shown = ui.centered_and_justified(|ui| ui.image(texture.id(), size));
+
+        let painter = ui.painter();
+        if self.visual_beep && !self.paused && self.chip8.beep() {
+            let color = palette.foreground();
+            let stroke = egui::Stroke::new(4.0, egui::Color32::from_rgb(color.0, color.1, color.2));
+            painter.rect_stroke(shown.inner.rect.shrink(2.0), 0.0, stroke);
+        }
+        let mut position = ui.max_rect().min + egui::vec2(8.0, 8.0);
+        for message in self.notifications.messages() {
+            let galley = painter.layout_no_wrap(
+                message.to_string(),
+                egui::FontId::monospace(14.0),
+                egui::Color32::WHITE,
+            );
+            let rect = egui::Rect::from_min_size(position, galley.size()).expand(3.0);
+            painter.rect_filled(rect, 2.0, egui::Color32::from_black_alpha(160));
+            painter.galley(position, galley);
+            position.y += rect.height() + 2.0;
+        }
+
+        if self.show_stats {
+            let corner = ui.max_rect().right_top() + egui::vec2(-8.0, 8.0);
+            let galley = painter.layout_no_wrap(
+                self.stats.summary(self.speed),
+                egui::FontId::monospace(14.0),
+                egui::Color32::WHITE,
+            );
+            let position = corner - egui::vec2(galley.size().x, 0.0);
+            let rect = egui::Rect::from_min_size(position, galley.size()).expand(3.0);
+            painter.rect_filled(rect, 2.0, egui::Color32::from_black_alpha(160));
+            painter.galley(position, galley);
+        }
+    }
+}
+
+impl eframe::App for Emulator {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        self.handle_input(ctx, frame);
+        self.reload();
+        self.reload_config();
+        self.step();
+        let end = self
+            .limits
+            .as_ref()
+            .and_then(|limits| limits.check(&self.chip8, Instant::now()));
+        if let Some(end) = end {
+            *self.end.borrow_mut() = end.err();
+            frame.close();
+        }
+        self.notifications.expire(Instant::now());
+        self.stats.frame();
+        self.stats.update(Instant::now(), self.chip8.cycles());
+        let title = title(&self.rom_name, self.speed, self.paused);
+        if title != self.title {
+            frame.set_window_title(&title);
+            self.title = title;
+        }
+
+        egui::TopBottomPanel::top("menu").show(ctx, |ui| self.menu_bar(ui, frame));
+        match self.registers {
+            Dock::Hidden => {}
+            Dock::Left => {
+                egui::SidePanel::left("registers").show(ctx, |ui| self.register_view(ui));
+            }
+            Dock::Right => {
+                egui::SidePanel::right("registers").show(ctx, |ui| self.register_view(ui));
+            }
+            Dock::Bottom => {
+                egui::TopBottomPanel::bottom("registers").show(ctx, |ui| self.register_view(ui));
+            }
+        }
+        if self.keypad {
+            egui::TopBottomPanel::bottom("keypad").show(ctx, |ui| self.keypad_view(ui));
+        } else {
+            self.hold(None);
+        }
+        egui::CentralPanel::default()
+            .frame(egui::Frame::none().fill(egui::Color32::BLACK))
+            .show(ctx, |ui| self.display(ui));
+
+        self.settings_window(ctx);
+        self.open_window(ctx);
+
+        // The interpreter runs off wall-clock time, so keep frames coming.
+        ctx.request_repaint();
+    }
+}
+
+// A combo box listing every value of a command line option.
+fn choice<T: ValueEnum + PartialEq + Clone>(ui: &mut egui::Ui, id: &str, value: &mut T) {
+    let name = |value: &T| value.to_possible_value().unwrap().get_name().to_string();
+    egui::ComboBox::from_id_source(id)
+        .selected_text(name(value))
+        .show_ui(ui, |ui| {
+            for variant in T::value_variants() {
+                ui.selectable_value(value, variant.clone(), name(variant));
+            }
+        });
+}
+
+// A desktop GUI built with egui, with menus for loading ROMs and save states,
+// a register view that can be docked to either side or the bottom of the
+// window and a settings dialog.
+pub fn run(
+    args: &Args,
+    config: &Config,
+    palettes: Palettes,
+    chip8: Chip8,
+    audio: Box<dyn Audio>,
+) -> Result<(), Failure> {
+    let quirks = chip8.quirks();
+    // Rebound keys go to the keymap file in use, or else to the [keys] table
+    // of the configuration file.
+    let keys_file = match &args.keymap {
+        Some(path) => Some((path.clone(), None)),
+        None => args
+            .config
+            .clone()
+            .or_else(Config::default_path)
+            .map(|path| (path, Some("keys"))),
+    };
+    let end = Rc::new(RefCell::new(None));
+    let emulator = Emulator {
+        chip8,
+        rom_name: rom_name(args.rom().unwrap_or_default()),
+        title: String::new(),
+        saved: None,
+        palettes,
+        speed: args.ips,
+        runner: Runner::new(args.ips),
+        phosphor: Phosphor::from_args(args),
+        last_frame: Instant::now(),
+        paused: args.pause_on_start,
+        pause_on_start: args.pause_on_start,
+        focus: Focus::new(config.window.pause_on_focus_loss),
+        focused: true,
+        fast_forward: false,
+        slow_motion: false,
+        hold: Hold::from_args(args),
+        audio,
+        keymap: config.keys.clone(),
+        keys_file,
+        rebinding: None,
+        key_latch: args.key_latch,
+        seed: args.seed,
+        quirks,
+        platform: args.platform,
+        config_quirks: args.config_quirks.clone(),
+        quirk_overrides: args.quirks.clone(),
+        macros: Player::new(&config.macros),
+        script: Script::from_args(args),
+        pokes: Pokes::from_args(args),
+        limits: Limits::from_args(args),
+        end: Rc::clone(&end),
+        texture: None,
+        scaling: args.scaling,
+        aspect: args.pixel_aspect,
+        filter: args.filter,
+        notifications: Notifications::new(),
+        stats: Stats::new(Instant::now(), 0),
+        show_stats: args.stats,
+        visual_beep: args.visual_beep,
+        registers: Dock::Hidden,
+        keypad: args.keypad,
+        clicked: None,
+        settings_open: false,
+        open_path: None,
+        open_error: None,
+        live_config: LiveConfig::new(args.config.as_deref(), args.profile.as_deref()),
+        watching: args.watch,
+        watch: args
+            .rom()
+            .filter(|_| args.watch)
+            .map(|rom| Watch::new(Path::new(rom))),
+    };
+    let (width, height) = args.pixel_aspect.window_size(args.scale as u32);
+    let options = eframe::NativeOptions {
+        initial_window_size: Some(egui::vec2(width as f32, height as f32 + MENU_HEIGHT)),
+        vsync: args.vsync,
+        ..Default::default()
+    };
+    let dpi_scale = args.dpi_scale;
+    eframe::run_native(
+        &title(
+            &rom_name(args.rom().unwrap_or_default()),
+            args.ips,
+            args.pause_on_start,
+        ),
+        options,
+        Box::new(move |creation| {
+            // egui follows the system scale factor unless told otherwise.
+            if let Some(dpi_scale) = dpi_scale {
+                creation.egui_ctx.set_pixels_per_point(dpi_scale);
+            }
+            Box::new(emulator)
+        }),
+    )
+    .map_err(|e| Failure::Video(format!("unable to open the window: {}", e)))?;
+    let end = end.borrow_mut().take();
+    end.map_or(Ok(()), Err)
+}
@@ -0,0 +1,51 @@
+// Pauses emulation, and with it the buzzer, while the window is in the
+// background, and resumes it on coming back, unless it was already paused
+// by hand when the window lost focus.
+pub struct Focus {
+    enabled: bool,
+    paused: bool,
+}
+
+impl Focus {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            paused: false,
+        }
+    }
+
+    // Returns whether emulation is paused once the window gained or lost
+    // focus.
+    pub fn change(&mut self, focused: bool, paused: bool) -> bool {
+        if !self.enabled {
+            return paused;
+        }
+        if !focused && !paused {
+            self.paused = true;
+            return true;
+        }
+        if focused && self.paused {
+            self.paused = false;
+            return false;
+        }
+        paused
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frontend::focus::Focus;
+    #[test]
+    fn resumes_only_what_it_paused() {
+        let mut focus = Focus::new(true);
+        assert!(focus.change(false, false));
+        assert!(!focus.change(true, true));
+        assert!(focus.change(false, true));
+        assert!(focus.change(true, true));
+    }
+    #[test]
+    fn disabled_leaves_emulation_alone() {
+        let mut focus = Focus::new(false);
+        assert!(!focus.change(false, false));
+    }
+}
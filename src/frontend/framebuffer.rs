@@ -0,0 +1,59 @@
+use crate::chip8::display::DisplayBuffer;
+use crate::frontend::palette::Rgb;
+use crate::frontend::phosphor::Phosphor;
+
+pub fn levels(buffer: &DisplayBuffer, phosphor: Option<&Phosphor>) -> Vec<f32> {
+    buffer
+        .iter()
+        .enumerate()
+        .map(|(index, lit)| match phosphor {
+            Some(phosphor) => phosphor.level(index, *lit),
+            None if *lit => 1.0,
+            None => 0.0,
+        })
+        .collect()
+}
+
+// Packs the pixel intensities as RGB24 data, ready to be uploaded to a texture.
+pub fn rgb24(levels: &[f32], background: Rgb, foreground: Rgb) -> Vec<u8> {
+    let mut data = Vec::with_capacity(levels.len() * 3);
+    for level in levels {
+        let color = background.blend(foreground, *level);
+        data.extend_from_slice(&[color.0, color.1, color.2]);
+    }
+    data
+}
+
+// Packs the pixel intensities as the foreground color with the intensity as
+// alpha, for compositing the display over a backdrop.
+#[cfg(feature = "sdl")]
+pub fn rgba32(levels: &[f32], foreground: Rgb) -> Vec<u8> {
+    let mut data = Vec::with_capacity(levels.len() * 4);
+    for level in levels {
+        let alpha = (level * 255.0).round() as u8;
+        data.extend_from_slice(&[foreground.0, foreground.1, foreground.2, alpha]);
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frontend::framebuffer::rgb24;
+    #[cfg(feature = "sdl")]
+    use crate::frontend::framebuffer::rgba32;
+    use crate::frontend::palette::Rgb;
+    #[test]
+    fn rgb24_blends_between_background_and_foreground() {
+        let data = rgb24(&[0.0, 1.0, 0.5], Rgb(0, 0, 0), Rgb(200, 100, 50));
+        assert_eq!(data, vec![0, 0, 0, 200, 100, 50, 100, 50, 25]);
+    }
+    #[cfg(feature = "sdl")]
+    #[test]
+    fn rgba32_keeps_the_intensity_as_alpha() {
+        let data = rgba32(&[0.0, 1.0, 0.5], Rgb(200, 100, 50));
+        assert_eq!(
+            data,
+            vec![200, 100, 50, 0, 200, 100, 50, 255, 200, 100, 50, 128]
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use crate::frontend::palette::Palette;
+use crate::frontend::palette::Rgb;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Glyphs {
+    Block,
+    Half,
+    Braille,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cell {
+    pub ch: char,
+    pub fg: Rgb,
+    pub bg: Rgb,
+}
+
+// Braille dot bits indexed by [row][col] inside a 2x4 cell.
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+pub fn cells(
+    levels: &[f32],
+    width: usize,
+    height: usize,
+    glyphs: Glyphs,
+    palette: &Palette,
+) -> Vec<Vec<Cell>> {
+    let level = |col: usize, row: usize| {
+        if col < width && row < height {
+            levels[row * width + col]
+        } else {
+            0.0
+        }
+    };
+    let color = |level: f32| palette.background.blend(palette.foreground(), level);
+    match glyphs {
+        Glyphs::Block => (0..height)
+            .map(|row| {
+                (0..width * 2)
+                    .map(|col| Cell {
+                        ch: '\u{2588}',
+                        fg: color(level(col / 2, row)),
+                        bg: palette.background,
+                    })
+                    .collect()
+            })
+            .collect(),
+        Glyphs::Half => (0..height.div_ceil(2))
+            .map(|row| {
+                (0..width)
+                    .map(|col| Cell {
+                        ch: '\u{2580}',
+                        fg: color(level(col, row * 2)),
+                        bg: color(level(col, row * 2 + 1)),
+                    })
+                    .collect()
+            })
+            .collect(),
+        Glyphs::Braille => (0..height.div_ceil(4))
+            .map(|row| {
+                (0..width.div_ceil(2))
+                    .map(|col| {
+                        let mut dots = 0;
+                        let mut brightest: f32 = 0.0;
+                        for (dy, bits) in BRAILLE_DOTS.iter().enumerate() {
+                            for (dx, bit) in bits.iter().enumerate() {
+                                let value = level(col * 2 + dx, row * 4 + dy);
+                                if value >= 0.5 {
+                                    dots |= bit;
+                                }
+                                brightest = brightest.max(value);
+                            }
+                        }
+                        Cell {
+                            ch: char::from_u32(0x2800 + dots).unwrap(),
+                            fg: color(brightest),
+                            bg: palette.background,
+                        }
+                    })
+                    .collect()
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frontend::glyphs::cells;
+    use crate::frontend::glyphs::Glyphs;
+    use crate::frontend::palette::builtin_palettes;
+    use crate::frontend::palette::Rgb;
+    #[test]
+    fn half_blocks_stack_two_rows() {
+        let palette = &builtin_palettes()[0];
+        let levels = [1.0, 0.0, 0.0, 1.0];
+        let rows = cells(&levels, 2, 2, Glyphs::Half, palette);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0][0].fg, Rgb(255, 255, 255));
+        assert_eq!(rows[0][0].bg, Rgb(0, 0, 0));
+        assert_eq!(rows[0][1].fg, Rgb(0, 0, 0));
+        assert_eq!(rows[0][1].bg, Rgb(255, 255, 255));
+    }
+    #[test]
+    fn braille_sets_dots_for_lit_pixels() {
+        let palette = &builtin_palettes()[0];
+        let mut levels = [0.0; 8];
+        levels[0] = 1.0;
+        levels[7] = 1.0;
+        let rows = cells(&levels, 2, 4, Glyphs::Braille, palette);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0][0].ch, '\u{2881}');
+    }
+    #[test]
+    fn block_mode_doubles_columns() {
+        let palette = &builtin_palettes()[0];
+        let rows = cells(&[0.0, 1.0], 2, 1, Glyphs::Block, palette);
+        assert_eq!(rows[0].len(), 4);
+        assert_eq!(rows[0][2].fg, Rgb(255, 255, 255));
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// Kitty limits every escape sequence to 4096 bytes of payload.
+const KITTY_CHUNK: usize = 4096;
+
+pub fn base64(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_ALPHABET[(n >> (18 - i * 6) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+pub fn upscale_rgb24(data: &[u8], width: usize, height: usize, factor: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() * factor * factor);
+    for row in 0..height * factor {
+        for col in 0..width * factor {
+            let index = ((row / factor) * width + col / factor) * 3;
+            out.extend_from_slice(&data[index..index + 3]);
+        }
+    }
+    out
+}
+
+// Transmits the image with the kitty graphics protocol and places it over the
+// given number of cells, replacing the previous frame (same image id).
+pub fn kitty(data: &[u8], width: usize, height: usize, columns: usize, rows: usize) -> String {
+    let payload = base64(data);
+    let chunks: Vec<&[u8]> = payload.as_bytes().chunks(KITTY_CHUNK).collect();
+    let mut out = String::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        let more = if index + 1 < chunks.len() { 1 } else { 0 };
+        let chunk = std::str::from_utf8(chunk).unwrap();
+        if index == 0 {
+            write!(
+                out,
+                "\x1b_Ga=T,i=1,f=24,s={},v={},c={},r={},C=1,q=2,m={};{}\x1b\\",
+                width, height, columns, rows, more, chunk
+            )
+            .unwrap();
+        } else {
+            write!(out, "\x1b_Gm={};{}\x1b\\", more, chunk).unwrap();
+        }
+    }
+    out
+}
+
+pub fn kitty_clear() -> &'static str {
+    "\x1b_Ga=d,q=2\x1b\\"
+}
+
+// Encodes the image as sixels: one color register per distinct color, and
+// every band of six rows drawn once per color it contains.
+pub fn sixel(data: &[u8], width: usize, height: usize) -> String {
+    let mut registers: HashMap<[u8; 3], usize> = HashMap::new();
+    let mut out = String::from("\x1bPq");
+    write!(out, "\"1;1;{};{}", width, height).unwrap();
+    let pixels: Vec<usize> = data
+        .chunks(3)
+        .map(|rgb| {
+            let next = registers.len();
+            let color = [rgb[0], rgb[1], rgb[2]];
+            *registers.entry(color).or_insert_with(|| {
+                let percent = |c: u8| c as u32 * 100 / 255;
+                write!(
+                    out,
+                    "#{};2;{};{};{}",
+                    next,
+                    percent(color[0]),
+                    percent(color[1]),
+                    percent(color[2])
+                )
+                .unwrap();
+                next
+            })
+        })
+        .collect();
+    for band in (0..height).step_by(6) {
+        for register in 0..registers.len() {
+            let sixels: Vec<u8> = (0..width)
+                .map(|col| {
+                    (0..6)
+                        .filter(|dy| band + dy < height)
+                        .filter(|dy| pixels[(band + dy) * width + col] == register)
+                        .fold(0, |bits, dy| bits | 1 << dy)
+                })
+                .collect();
+            if sixels.iter().all(|bits| *bits == 0) {
+                continue;
+            }
+            write!(out, "#{}", register).unwrap();
+            let mut col = 0;
+            while col < width {
+                let run = sixels[col..]
+                    .iter()
+                    .take_while(|b| **b == sixels[col])
+                    .count();
+                let ch = (63 + sixels[col]) as char;
+                if run > 3 {
+                    write!(out, "!{}{}", run, ch).unwrap();
+                } else {
+                    (0..run).for_each(|_| out.push(ch));
+                }
+                col += run;
+            }
+            out.push('$');
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frontend::graphics::base64;
+    use crate::frontend::graphics::kitty;
+    use crate::frontend::graphics::sixel;
+    use crate::frontend::graphics::upscale_rgb24;
+    #[test]
+    fn base64_pads_output() {
+        assert_eq!(base64(b"Man"), "TWFu");
+        assert_eq!(base64(b"Ma"), "TWE=");
+        assert_eq!(base64(b"M"), "TQ==");
+    }
+    #[test]
+    fn upscale_repeats_pixels() {
+        let data = [1, 1, 1, 2, 2, 2];
+        let scaled = upscale_rgb24(&data, 2, 1, 2);
+        assert_eq!(
+            scaled,
+            vec![1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2]
+        );
+    }
+    #[test]
+    fn kitty_transmits_single_chunk() {
+        let out = kitty(&[255, 0, 0], 1, 1, 2, 1);
+        assert_eq!(
+            out,
+            "\x1b_Ga=T,i=1,f=24,s=1,v=1,c=2,r=1,C=1,q=2,m=0;/wAA\x1b\\"
+        );
+    }
+    #[test]
+    fn sixel_encodes_two_colors() {
+        let data = [0, 0, 0, 255, 255, 255];
+        let out = sixel(&data, 1, 2);
+        assert_eq!(
+            out,
+            "\x1bPq\"1;1;1;2#0;2;0;0;0#1;2;100;100;100#0@$#1A$-\x1b\\"
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use crate::chip8::Chip8;
+
+// Where a keypad press comes from. Each source holds keys on its own, so
+// that letting go of a key on one device leaves it pressed while another
+// still holds it, as when two players share a key.
+#[derive(Clone, Copy, Debug)]
+pub enum Source {
+    Keyboard,
+    Controller,
+    Stick,
+    Pointer,
+    Chat,
+    Midi,
+}
+
+// The keypad as pressed by all the sources together.
+#[derive(Default)]
+pub struct Inputs {
+    // The sources holding each key, one bit per source.
+    held: [u8; 16],
+    // Whether presses wait to be sent over netplay instead of reaching the
+    // machine at once.
+    deferred: bool,
+}
+
+impl Inputs {
+    // Returns whether the key is now held at all, if that changed.
+    fn update(&mut self, source: Source, key: u8, state: bool) -> Option<bool> {
+        let held = &mut self.held[key as usize];
+        let before = *held != 0;
+        if state {
+            *held |= 1 << source as u8;
+        } else {
+            *held &= !(1 << source as u8);
+        }
+        let after = *held != 0;
+        (before != after).then_some(after)
+    }
+
+    pub fn press(&mut self, chip8: &mut Chip8, source: Source, key: u8, state: bool) {
+        if let Some(state) = self.update(source, key, state).filter(|_| !self.deferred) {
+            chip8.key_pressed(key, state);
+        }
+    }
+
+    // Holds presses back from the machine, for netplay to send them.
+    pub fn defer(&mut self) {
+        self.deferred = true;
+    }
+
+    // Lets presses reach the machine again, pressing the keys held now.
+    pub fn resume(&mut self, chip8: &mut Chip8) {
+        self.deferred = false;
+        for key in 0..16 {
+            if self.held[key as usize] != 0 {
+                chip8.key_pressed(key, true);
+            }
+        }
+    }
+
+    // The keys held, one bit per key.
+    pub fn keys(&self) -> u16 {
+        self.held
+            .iter()
+            .enumerate()
+            .fold(0, |keys, (key, held)| keys | u16::from(*held != 0) << key)
+    }
+
+    // Lets go of every key the source holds, as when its device is gone.
+    pub fn release(&mut self, chip8: &mut Chip8, source: Source) {
+        for key in 0..16 {
+            self.press(chip8, source, key, false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::Chip8;
+    use crate::frontend::input::Inputs;
+    use crate::frontend::input::Source;
+    #[test]
+    fn key_is_held_until_every_source_lets_go() {
+        let mut inputs = Inputs::default();
+        assert_eq!(inputs.update(Source::Keyboard, 5, true), Some(true));
+        assert_eq!(inputs.update(Source::Controller, 5, true), None);
+        assert_eq!(inputs.update(Source::Keyboard, 5, false), None);
+        assert_eq!(inputs.update(Source::Keyboard, 5, false), None);
+        assert_eq!(inputs.update(Source::Controller, 5, false), Some(false));
+        assert_eq!(inputs.update(Source::Pointer, 6, false), None);
+    }
+    #[test]
+    fn release_lets_go_of_one_source() {
+        let mut chip8 = Chip8::new();
+        let mut inputs = Inputs::default();
+        inputs.press(&mut chip8, Source::Keyboard, 5, true);
+        inputs.press(&mut chip8, Source::Controller, 5, true);
+        inputs.press(&mut chip8, Source::Controller, 6, true);
+        inputs.release(&mut chip8, Source::Controller);
+        assert_eq!(inputs.held[5], 1);
+        assert_eq!(inputs.held[6], 0);
+    }
+    #[test]
+    fn deferred_presses_wait_for_resume() {
+        let mut chip8 = Chip8::new();
+        // Waits for a key with Fx0A, which a press would end at once.
+        chip8.load(&[0xF3, 0x0A]).unwrap();
+        chip8.fetch_execute();
+        let mut inputs = Inputs::default();
+        inputs.defer();
+        inputs.press(&mut chip8, Source::Keyboard, 5, true);
+        inputs.press(&mut chip8, Source::Pointer, 0xA, true);
+        assert_eq!(inputs.keys(), 1 << 5 | 1 << 0xA);
+        assert_eq!(chip8.reg_v()[3], 0);
+        inputs.resume(&mut chip8);
+        assert_eq!(chip8.reg_v()[3], 5);
+    }
+}
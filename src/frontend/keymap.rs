@@ -0,0 +1,235 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+#[cfg(feature = "egui")]
+use std::io::ErrorKind;
+use std::path::Path;
+#[cfg(feature = "egui")]
+use toml_edit::DocumentMut;
+#[cfg(feature = "egui")]
+use toml_edit::Item;
+#[cfg(feature = "egui")]
+use toml_edit::Table;
+
+// The keypad as laid out on the COSMAC VIP, row by row.
+pub const KEYPAD: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+const ARROWS: [&str; 4] = ["Up", "Down", "Left", "Right"];
+
+// The keypad as laid out on the left of a QWERTY keyboard.
+const QWERTY: [(&str, u8); 16] = [
+    ("1", 0x1),
+    ("2", 0x2),
+    ("3", 0x3),
+    ("4", 0xC),
+    ("Q", 0x4),
+    ("W", 0x5),
+    ("E", 0x6),
+    ("R", 0xD),
+    ("A", 0x7),
+    ("S", 0x8),
+    ("D", 0x9),
+    ("F", 0xE),
+    ("Z", 0xA),
+    ("X", 0x0),
+    ("C", 0xB),
+    ("V", 0xF),
+];
+
+// A keypad value written either as a number or as a hex digit.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum Value {
+    Number(i64),
+    Digit(String),
+}
+
+impl Value {
+    pub fn keypad(&self) -> Option<u8> {
+        match self {
+            Value::Number(number) => u8::try_from(*number).ok().filter(|n| *n < 16),
+            Value::Digit(digit) if digit.len() == 1 => u8::from_str_radix(digit, 16).ok(),
+            Value::Digit(_) => None,
+        }
+    }
+}
+
+// Which keyboard keys press which keypad values. The keys are named the same
+// for every frontend: the letters and digits, ignoring case, and the arrows.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "BTreeMap<String, Value>")]
+pub struct Keymap {
+    bindings: BTreeMap<String, u8>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            bindings: QWERTY
+                .iter()
+                .map(|(name, value)| (name.to_string(), *value))
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<BTreeMap<String, Value>> for Keymap {
+    type Error = String;
+
+    fn try_from(table: BTreeMap<String, Value>) -> Result<Self, String> {
+        let mut bindings = BTreeMap::new();
+        for (name, value) in table {
+            let key = canonical(&name).ok_or_else(|| {
+                format!(
+                    "unknown key \"{}\", keys are letters, digits, Up, Down, Left and Right",
+                    name
+                )
+            })?;
+            let value = value.keypad().ok_or_else(|| {
+                format!("key \"{}\" must be bound to a keypad value 0 to F", name)
+            })?;
+            if bindings.insert(key, value).is_some() {
+                return Err(format!("key \"{}\" is bound more than once", name));
+            }
+        }
+        Ok(Self { bindings })
+    }
+}
+
+impl Keymap {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("unable to read {}: {}", path.display(), e))?;
+        toml::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    // The keypad value the named key presses, if it is bound.
+    pub fn key(&self, name: &str) -> Option<u8> {
+        self.bindings.get(&canonical(name)?).copied()
+    }
+
+    // The keys that press the keypad value.
+    pub fn keys(&self, value: u8) -> Vec<&str> {
+        self.bindings
+            .iter()
+            .filter(|(_, bound)| **bound == value)
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    // Makes the named key, and only that key, press the keypad value,
+    // returning the name as it is stored, or None for keys that cannot be
+    // bound.
+    #[cfg(feature = "egui")]
+    pub fn bind(&mut self, name: &str, value: u8) -> Option<String> {
+        let name = canonical(name)?;
+        self.bindings.retain(|_, bound| *bound != value);
+        self.bindings.insert(name.clone(), value);
+        Some(name)
+    }
+
+    // Writes the bindings to the file, into the named table or as the whole
+    // of it, leaving everything else in the file, comments included, as is.
+    #[cfg(feature = "egui")]
+    pub fn save(&self, path: &Path, table: Option<&str>) -> Result<(), String> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(format!("unable to read {}: {}", path.display(), e)),
+        };
+        let content = self
+            .update(&content, table)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("unable to create {}: {}", dir.display(), e))?;
+        }
+        fs::write(path, content).map_err(|e| format!("unable to write {}: {}", path.display(), e))
+    }
+
+    #[cfg(feature = "egui")]
+    fn update(&self, content: &str, table: Option<&str>) -> Result<String, String> {
+        let mut document: DocumentMut = content.parse().map_err(|e| format!("{}", e))?;
+        let mut bindings = Table::new();
+        for (name, value) in &self.bindings {
+            bindings[name.as_str()] = if *value < 10 {
+                toml_edit::value(*value as i64)
+            } else {
+                toml_edit::value(format!("{:X}", value))
+            };
+        }
+        match table {
+            Some(table) => document[table] = Item::Table(bindings),
+            None => *document.as_table_mut() = bindings,
+        }
+        Ok(document.to_string())
+    }
+}
+
+pub fn canonical(name: &str) -> Option<String> {
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_alphanumeric() => Some(c.to_ascii_uppercase().to_string()),
+        _ => ARROWS
+            .iter()
+            .find(|arrow| arrow.eq_ignore_ascii_case(name))
+            .map(|arrow| arrow.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frontend::keymap::Keymap;
+    #[test]
+    fn default_is_the_qwerty_layout() {
+        let keymap = Keymap::default();
+        assert_eq!(keymap.key("4"), Some(0xC));
+        assert_eq!(keymap.key("x"), Some(0x0));
+        assert_eq!(keymap.key("P"), None);
+    }
+    #[test]
+    fn bindings_accept_numbers_and_hex_digits() {
+        let keymap: Keymap = toml::from_str("up = 5\nDown = \"8\"\nj = \"a\"").unwrap();
+        assert_eq!(keymap.key("Up"), Some(0x5));
+        assert_eq!(keymap.key("DOWN"), Some(0x8));
+        assert_eq!(keymap.key("J"), Some(0xA));
+        assert_eq!(keymap.key("Q"), None);
+    }
+    #[test]
+    fn invalid_bindings_are_explained() {
+        let error = |content| toml::from_str::<Keymap>(content).unwrap_err().to_string();
+        assert!(error("Tab = 1").contains("unknown key \"Tab\""));
+        assert!(error("q = 1\nQ = 2").contains("key \"q\" is bound more than once"));
+        assert!(error("Q = 16").contains("key \"Q\" must be bound to a keypad value 0 to F"));
+        assert!(error("Q = \"G\"").contains("keypad value 0 to F"));
+    }
+    #[cfg(feature = "egui")]
+    #[test]
+    fn bind_replaces_the_keys_of_a_value() {
+        let mut keymap = Keymap::default();
+        assert_eq!(keymap.bind("up", 0x5), Some(String::from("Up")));
+        assert_eq!(keymap.key("Up"), Some(0x5));
+        assert_eq!(keymap.key("W"), None);
+        assert_eq!(keymap.keys(0x5), vec!["Up"]);
+        assert_eq!(keymap.bind("Tab", 0x5), None);
+    }
+    #[cfg(feature = "egui")]
+    #[test]
+    fn update_keeps_the_rest_of_the_file() {
+        let keymap: Keymap = toml::from_str("Up = 5\nB = \"a\"").unwrap();
+        let config = "# my settings\n[audio]\nvolume = 40\n\n[keys]\nQ = 1\n";
+        assert_eq!(
+            keymap.update(config, Some("keys")).unwrap(),
+            "# my settings\n[audio]\nvolume = 40\n\n[keys]\nB = \"A\"\nUp = 5\n"
+        );
+        assert_eq!(
+            keymap.update("Q = 1\n", None).unwrap(),
+            "B = \"A\"\nUp = 5\n"
+        );
+    }
+}
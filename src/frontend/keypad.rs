@@ -0,0 +1,103 @@
+use crate::frontend::keymap::KEYPAD;
+use crate::frontend::layout::Viewport;
+use crate::frontend::palette::Rgb;
+use crate::frontend::text::Overlay;
+
+// Size of a key in overlay pixels, gaps included.
+const KEY_SIZE: usize = 10;
+const MARGIN: usize = 2;
+const KEY_ALPHA: u8 = 160;
+
+// A keypad drawn on the overlay in the bottom right corner of the display,
+// for clicking or tapping the keys of ROMs whose controls are unknown.
+pub struct Keypad {
+    x: usize,
+    y: usize,
+}
+
+impl Keypad {
+    pub fn new(overlay: &Overlay) -> Self {
+        Self {
+            x: overlay.width.saturating_sub(4 * KEY_SIZE + MARGIN),
+            y: overlay.height.saturating_sub(4 * KEY_SIZE + MARGIN),
+        }
+    }
+
+    // Draws every key, lighting up the one held down.
+    pub fn draw(&self, overlay: &mut Overlay, held: Option<u8>) {
+        for (row, keys) in KEYPAD.iter().enumerate() {
+            for (column, key) in keys.iter().enumerate() {
+                let x = self.x + column * KEY_SIZE;
+                let y = self.y + row * KEY_SIZE;
+                let (color, alpha) = if held == Some(*key) {
+                    (Rgb(255, 255, 255), 255)
+                } else {
+                    (Rgb(64, 64, 64), KEY_ALPHA)
+                };
+                overlay.fill(x, y, KEY_SIZE - 1, KEY_SIZE - 1, color, alpha);
+                overlay.draw_text(x + 2, y + 1, &format!("{:X}", key), Rgb(255, 255, 255));
+            }
+        }
+    }
+
+    // The key at the given point of the overlay.
+    pub fn key_at(&self, x: usize, y: usize) -> Option<u8> {
+        let column = x.checked_sub(self.x)? / KEY_SIZE;
+        let row = y.checked_sub(self.y)? / KEY_SIZE;
+        KEYPAD.get(row)?.get(column).copied()
+    }
+}
+
+// Where a point of the output falls on the overlay stretched over the
+// display viewport, if it is on the display at all.
+pub fn overlay_point(
+    viewport: Viewport,
+    point: (i32, i32),
+    overlay: &Overlay,
+) -> Option<(usize, usize)> {
+    let x = point.0 - viewport.x;
+    let y = point.1 - viewport.y;
+    if x < 0 || y < 0 || x as u32 >= viewport.width || y as u32 >= viewport.height {
+        return None;
+    }
+    Some((
+        x as usize * overlay.width / viewport.width as usize,
+        y as usize * overlay.height / viewport.height as usize,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frontend::keypad::overlay_point;
+    use crate::frontend::keypad::Keypad;
+    use crate::frontend::layout::Viewport;
+    use crate::frontend::text::Overlay;
+    #[test]
+    fn keys_are_found_in_the_corner() {
+        let overlay = Overlay::new(256, 128);
+        let keypad = Keypad::new(&overlay);
+        // The keypad starts at 214, 86.
+        assert_eq!(keypad.key_at(214, 86), Some(0x1));
+        assert_eq!(keypad.key_at(245, 117), Some(0xF));
+        assert_eq!(keypad.key_at(225, 106), Some(0x8));
+        assert_eq!(keypad.key_at(213, 100), None);
+        assert_eq!(keypad.key_at(255, 100), None);
+    }
+    #[test]
+    fn points_are_scaled_to_the_overlay() {
+        let overlay = Overlay::new(256, 128);
+        let viewport = Viewport {
+            x: 100,
+            y: 50,
+            width: 512,
+            height: 256,
+        };
+        assert_eq!(overlay_point(viewport, (100, 50), &overlay), Some((0, 0)));
+        assert_eq!(
+            overlay_point(viewport, (611, 305), &overlay),
+            Some((255, 127))
+        );
+        assert_eq!(overlay_point(viewport, (99, 60), &overlay), None);
+        assert_eq!(overlay_point(viewport, (612, 60), &overlay), None);
+    }
+}
@@ -0,0 +1,286 @@
+#[cfg(any(feature = "sdl", feature = "egui"))]
+use crate::chip8::display::RES_HEIGHT;
+#[cfg(any(feature = "sdl", feature = "egui"))]
+use crate::chip8::display::RES_WIDTH;
+use clap::ValueEnum;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Scaling {
+    /// Largest whole multiple of the resolution, for evenly sized pixels
+    Integer,
+    /// Fill the whole window, ignoring the aspect ratio
+    Stretch,
+    /// Largest size that keeps the aspect ratio
+    Fit,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Filter {
+    /// Sharp pixel edges
+    Nearest,
+    /// Smooth interpolation between pixels
+    Linear,
+}
+
+// Shape of the emulated pixels as width:height, for displays whose pixels
+// were not square.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PixelAspect {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for PixelAspect {
+    fn default() -> Self {
+        Self {
+            width: 1,
+            height: 1,
+        }
+    }
+}
+
+impl FromStr for PixelAspect {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let error = || format!("invalid pixel aspect '{}', expected W:H such as 2:1", value);
+        let (width, height) = value.split_once(':').ok_or_else(error)?;
+        let part = |part: &str| part.trim().parse::<u32>().ok().filter(|n| *n > 0);
+        match (part(width), part(height)) {
+            (Some(width), Some(height)) => Ok(Self { width, height }),
+            _ => Err(error()),
+        }
+    }
+}
+
+impl PixelAspect {
+    // The proportions of the whole display, which is what gets fit into the
+    // output in place of its resolution.
+    #[cfg(any(feature = "sdl", feature = "egui"))]
+    pub fn display(self) -> (u32, u32) {
+        (
+            RES_WIDTH as u32 * self.width,
+            RES_HEIGHT as u32 * self.height,
+        )
+    }
+
+    // A window of the given scale, stretched horizontally so that its height
+    // stays the same as with square pixels.
+    #[cfg(any(feature = "sdl", feature = "egui"))]
+    pub fn window_size(self, scale: u32) -> (u32, u32) {
+        (
+            RES_WIDTH as u32 * scale * self.width / self.height,
+            RES_HEIGHT as u32 * scale,
+        )
+    }
+}
+
+#[cfg(any(feature = "sdl", feature = "egui"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Viewport {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+// Finds the largest area with the display aspect ratio that fits the output,
+// centered so that any leftover space becomes letterbox bars.
+#[cfg(any(feature = "sdl", feature = "egui"))]
+pub fn fit(output: (u32, u32), display: (u32, u32)) -> Viewport {
+    let (output_w, output_h) = output;
+    let (display_w, display_h) = display;
+    let (width, height) = if output_w * display_h > output_h * display_w {
+        (output_h * display_w / display_h, output_h)
+    } else {
+        (output_w, output_w * display_h / display_w)
+    };
+    Viewport {
+        x: ((output_w - width) / 2) as i32,
+        y: ((output_h - height) / 2) as i32,
+        width,
+        height,
+    }
+}
+
+// Where the display goes in the output with the given scaling. Integer
+// scaling falls back to fit when the output is smaller than the display.
+#[cfg(any(feature = "sdl", feature = "egui"))]
+pub fn place(output: (u32, u32), display: (u32, u32), scaling: Scaling) -> Viewport {
+    let (output_w, output_h) = output;
+    let (display_w, display_h) = display;
+    let factor = (output_w / display_w).min(output_h / display_h);
+    match scaling {
+        Scaling::Stretch => Viewport {
+            x: 0,
+            y: 0,
+            width: output_w,
+            height: output_h,
+        },
+        Scaling::Integer if factor > 0 => Viewport {
+            x: ((output_w - display_w * factor) / 2) as i32,
+            y: ((output_h - display_h * factor) / 2) as i32,
+            width: display_w * factor,
+            height: display_h * factor,
+        },
+        _ => fit(output, display),
+    }
+}
+
+// How much larger than requested the window has to be for the display
+// density. When the drawable is larger than the window the system already
+// scales windows up, otherwise windows are sized in pixels and the factor
+// comes from the DPI of the display, taking 96 as the standard density.
+#[cfg(feature = "sdl")]
+pub fn density_scale(window: (u32, u32), drawable: (u32, u32), dpi: Option<f32>) -> f32 {
+    match dpi {
+        Some(dpi) if window == drawable => (dpi / 96.0).max(1.0),
+        _ => 1.0,
+    }
+}
+
+// Positions of the lines between cells when a length is split in equal cells,
+// leaving out the outer edges.
+#[cfg(feature = "sdl")]
+pub fn boundaries(start: i32, length: u32, cells: usize) -> impl Iterator<Item = i32> {
+    (1..cells).map(move |cell| start + (cell as u32 * length / cells as u32) as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "sdl")]
+    use crate::frontend::layout::boundaries;
+    #[cfg(feature = "sdl")]
+    use crate::frontend::layout::density_scale;
+    #[cfg(any(feature = "sdl", feature = "egui"))]
+    use crate::frontend::layout::fit;
+    #[cfg(any(feature = "sdl", feature = "egui"))]
+    use crate::frontend::layout::place;
+    use crate::frontend::layout::PixelAspect;
+    #[cfg(any(feature = "sdl", feature = "egui"))]
+    use crate::frontend::layout::Scaling;
+    #[cfg(any(feature = "sdl", feature = "egui"))]
+    use crate::frontend::layout::Viewport;
+    #[test]
+    fn pixel_aspect_parses_ratios() {
+        let aspect: PixelAspect = "2:1".parse().unwrap();
+        assert_eq!(
+            aspect,
+            PixelAspect {
+                width: 2,
+                height: 1
+            }
+        );
+        assert!("2".parse::<PixelAspect>().is_err());
+        assert!("0:1".parse::<PixelAspect>().is_err());
+    }
+    #[cfg(any(feature = "sdl", feature = "egui"))]
+    #[test]
+    fn pixel_aspect_stretches_the_window() {
+        let aspect: PixelAspect = "2:1".parse().unwrap();
+        assert_eq!(aspect.display(), (128, 32));
+        assert_eq!(aspect.window_size(8), (1024, 256));
+        assert_eq!(
+            fit((1024, 512), PixelAspect::default().display()),
+            fit((1024, 512), (64, 32))
+        );
+    }
+    #[cfg(any(feature = "sdl", feature = "egui"))]
+    #[test]
+    fn fit_exact_output() {
+        assert_eq!(
+            fit((1024, 512), (64, 32)),
+            Viewport {
+                x: 0,
+                y: 0,
+                width: 1024,
+                height: 512
+            }
+        );
+    }
+    #[cfg(any(feature = "sdl", feature = "egui"))]
+    #[test]
+    fn integer_scaling_uses_whole_multiples() {
+        assert_eq!(
+            place((1920, 1080), (64, 32), Scaling::Integer),
+            Viewport {
+                x: 0,
+                y: 60,
+                width: 1920,
+                height: 960
+            }
+        );
+        assert_eq!(
+            place((1000, 1000), (64, 32), Scaling::Integer),
+            Viewport {
+                x: 20,
+                y: 260,
+                width: 960,
+                height: 480
+            }
+        );
+        assert_eq!(
+            place((32, 32), (64, 32), Scaling::Integer),
+            fit((32, 32), (64, 32))
+        );
+    }
+    #[cfg(any(feature = "sdl", feature = "egui"))]
+    #[test]
+    fn stretch_scaling_fills_the_output() {
+        assert_eq!(
+            place((800, 800), (64, 32), Scaling::Stretch),
+            Viewport {
+                x: 0,
+                y: 0,
+                width: 800,
+                height: 800
+            }
+        );
+    }
+    #[cfg(feature = "sdl")]
+    #[test]
+    fn density_scale_only_applies_when_the_system_does_not_scale() {
+        assert_eq!(density_scale((640, 320), (640, 320), Some(192.0)), 2.0);
+        assert_eq!(density_scale((640, 320), (1280, 640), Some(192.0)), 1.0);
+        assert_eq!(density_scale((640, 320), (640, 320), Some(72.0)), 1.0);
+        assert_eq!(density_scale((640, 320), (640, 320), None), 1.0);
+    }
+    #[cfg(feature = "sdl")]
+    #[test]
+    fn boundaries_split_in_equal_cells() {
+        assert_eq!(boundaries(10, 100, 4).collect::<Vec<_>>(), [35, 60, 85]);
+        assert_eq!(boundaries(0, 10, 1).count(), 0);
+    }
+    #[cfg(any(feature = "sdl", feature = "egui"))]
+    #[test]
+    fn fit_adds_letterbox_bars() {
+        assert_eq!(
+            fit((1920, 1080), (64, 32)),
+            Viewport {
+                x: 0,
+                y: 60,
+                width: 1920,
+                height: 960
+            }
+        );
+        assert_eq!(
+            fit((800, 800), (64, 32)),
+            Viewport {
+                x: 0,
+                y: 200,
+                width: 800,
+                height: 400
+            }
+        );
+        assert_eq!(
+            fit((3000, 1000), (64, 32)),
+            Viewport {
+                x: 500,
+                y: 0,
+                width: 2000,
+                height: 1000
+            }
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use crate::chip8::Chip8;
+use crate::failure::Failure;
+use crate::Args;
+use std::time::Instant;
+use tracing::info;
+
+// Where a run given --max-cycles or --timeout ends, so that scripts and test
+// harnesses never wait on a ROM that goes on forever. A ROM that stops
+// itself first ends the run as well, with a status of its own.
+pub struct Limits {
+    max_cycles: Option<u64>,
+    deadline: Option<Instant>,
+}
+
+impl Limits {
+    pub fn from_args(args: &Args) -> Option<Self> {
+        if args.max_cycles.is_none() && args.timeout.is_none() {
+            return None;
+        }
+        Some(Self {
+            max_cycles: args.max_cycles,
+            deadline: args.timeout.map(|timeout| Instant::now() + timeout),
+        })
+    }
+
+    // How the run ends, if it has to now: completed once a limit is reached,
+    // or halted in a jump to the same instruction before that.
+    pub fn check(&self, chip8: &Chip8, now: Instant) -> Option<Result<(), Failure>> {
+        if chip8.halted() {
+            return Some(Err(Failure::Halted(format!(
+                "halted at {:#05X} after {} instructions",
+                chip8.pc,
+                chip8.cycles()
+            ))));
+        }
+        let cycles = self.max_cycles.is_some_and(|max| chip8.cycles() >= max);
+        let timeout = self.deadline.is_some_and(|deadline| now >= deadline);
+        if cycles || timeout {
+            info!("stopped after {} instructions", chip8.cycles());
+            return Some(Ok(()));
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::Chip8;
+    use crate::failure::Failure;
+    use crate::frontend::limits::Limits;
+    use std::time::Duration;
+    use std::time::Instant;
+    #[test]
+    fn check_ends_at_the_limits() {
+        let now = Instant::now();
+        let limits = Limits {
+            max_cycles: Some(2),
+            deadline: Some(now + Duration::from_secs(1)),
+        };
+        let mut chip8 = Chip8::new();
+        chip8
+            .load(&[0x70, 0x01, 0x70, 0x01, 0x70, 0x01, 0x12, 0x06])
+            .unwrap();
+        assert_eq!(limits.check(&chip8, now), None);
+        assert_eq!(
+            limits.check(&chip8, now + Duration::from_secs(1)),
+            Some(Ok(()))
+        );
+        chip8.fetch_execute();
+        assert_eq!(limits.check(&chip8, now), None);
+        chip8.fetch_execute();
+        assert_eq!(limits.check(&chip8, now), Some(Ok(())));
+    }
+    #[test]
+    fn check_ends_at_a_jump_to_the_same_instruction() {
+        let limits = Limits {
+            max_cycles: None,
+            deadline: None,
+        };
+        let mut chip8 = Chip8::new();
+        chip8.load(&[0x70, 0x01, 0x70, 0x01, 0x12, 0x04]).unwrap();
+        chip8.fetch_execute();
+        assert_eq!(limits.check(&chip8, Instant::now()), None);
+        chip8.fetch_execute();
+        assert_eq!(
+            limits.check(&chip8, Instant::now()),
+            Some(Err(Failure::Halted(String::from(
+                "halted at 0x204 after 2 instructions"
+            ))))
+        );
+    }
+}
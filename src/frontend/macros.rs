@@ -0,0 +1,312 @@
+use crate::chip8::Chip8;
+use crate::frontend::keymap::canonical;
+use crate::frontend::keymap::Value;
+use crate::frontend::runner::TIMER_SPEED;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+
+const DEFAULT_RATE: u32 = 10;
+const DEFAULT_FRAMES: u32 = 4;
+
+// What a host key does when bound to a macro instead of a keypad value.
+#[derive(Clone, Debug, PartialEq)]
+enum Macro {
+    // Presses and releases the key over and over for as long as the host key
+    // is held, taking this many frames for each press.
+    Autofire { key: u8, period: u32 },
+    // Taps the keys one after the other, each held and then released for
+    // this many frames, once every time the host key is pressed.
+    Sequence { keys: Vec<u8>, frames: u32 },
+}
+
+impl Macro {
+    // The keypad key held down on the given frame since the start, or None
+    // when the macro is done.
+    fn key(&self, frame: u32) -> Option<Option<u8>> {
+        match self {
+            Macro::Autofire { key, period } => {
+                Some((frame % period < period.div_ceil(2)).then_some(*key))
+            }
+            Macro::Sequence { keys, frames } => {
+                let key = keys.get((frame / (2 * frames)) as usize)?;
+                Some((frame % (2 * frames) < *frames).then_some(*key))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Macro {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Macro::Autofire { key, period } => {
+                write!(
+                    f,
+                    "autofire {:X}, {} times a second",
+                    key,
+                    TIMER_SPEED / period
+                )
+            }
+            Macro::Sequence { keys, frames } => {
+                let keys: Vec<String> = keys.iter().map(|key| format!("{:X}", key)).collect();
+                write!(f, "sequence {}, {} frames a key", keys.join(" "), frames)
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Spec {
+    autofire: Option<Value>,
+    // Presses per second.
+    rate: Option<u32>,
+    sequence: Option<Vec<Value>>,
+    frames: Option<u32>,
+}
+
+// Host keys that autofire a keypad key or play a short sequence of keypad
+// presses, named the same way as in the keymap.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(try_from = "BTreeMap<String, Spec>")]
+pub struct Macros {
+    bindings: BTreeMap<String, Macro>,
+}
+
+impl TryFrom<BTreeMap<String, Spec>> for Macros {
+    type Error = String;
+
+    fn try_from(table: BTreeMap<String, Spec>) -> Result<Self, String> {
+        let mut bindings = BTreeMap::new();
+        for (name, spec) in table {
+            let key = canonical(&name).ok_or_else(|| {
+                format!(
+                    "unknown macro key \"{}\", keys are letters, digits, Up, Down, Left and Right",
+                    name
+                )
+            })?;
+            let keypad = |value: &Value| {
+                value
+                    .keypad()
+                    .ok_or_else(|| format!("macro \"{}\" must press keypad values 0 to F", name))
+            };
+            let binding = match spec {
+                Spec {
+                    autofire: Some(value),
+                    sequence: None,
+                    frames: None,
+                    rate,
+                } => {
+                    let rate = rate.unwrap_or(DEFAULT_RATE);
+                    if !(1..=TIMER_SPEED / 2).contains(&rate) {
+                        return Err(format!(
+                            "macro \"{}\" must autofire 1 to {} times a second",
+                            name,
+                            TIMER_SPEED / 2
+                        ));
+                    }
+                    Macro::Autofire {
+                        key: keypad(&value)?,
+                        period: TIMER_SPEED / rate,
+                    }
+                }
+                Spec {
+                    autofire: None,
+                    sequence: Some(values),
+                    rate: None,
+                    frames,
+                } => {
+                    let frames = frames.unwrap_or(DEFAULT_FRAMES);
+                    if frames == 0 {
+                        return Err(format!("macro \"{}\" must hold keys for a frame", name));
+                    }
+                    Macro::Sequence {
+                        keys: values.iter().map(keypad).collect::<Result<_, _>>()?,
+                        frames,
+                    }
+                }
+                _ => {
+                    return Err(format!(
+                        "macro \"{}\" must be an autofire with a rate or a sequence with frames",
+                        name
+                    ))
+                }
+            };
+            if bindings.insert(key, binding).is_some() {
+                return Err(format!("macro key \"{}\" is bound more than once", name));
+            }
+        }
+        Ok(Self { bindings })
+    }
+}
+
+impl Macros {
+    // The host keys bound to macros, each with what its macro does.
+    pub fn describe(&self) -> Vec<(&str, String)> {
+        self.bindings
+            .iter()
+            .map(|(name, binding)| (name.as_str(), binding.to_string()))
+            .collect()
+    }
+}
+
+struct Running {
+    name: String,
+    frame: u32,
+    // Whether the host key is still held, which keeps autofire going.
+    held: bool,
+    pressed: Option<u8>,
+}
+
+// Plays the macros frame by frame as their host keys are pressed, taking
+// precedence over the keymap.
+pub struct Player {
+    macros: Macros,
+    running: Vec<Running>,
+}
+
+impl Player {
+    pub fn new(macros: &Macros) -> Self {
+        Self {
+            macros: macros.clone(),
+            running: Vec::new(),
+        }
+    }
+
+    // Returns whether the named key is bound to a macro.
+    pub fn key(&mut self, name: &str, down: bool) -> bool {
+        let Some(name) = canonical(name).filter(|name| self.macros.bindings.contains_key(name))
+        else {
+            return false;
+        };
+        let running = self.running.iter_mut().find(|running| running.name == name);
+        match (running, down) {
+            (Some(running), true) => running.held = true,
+            (Some(running), false) => running.held = false,
+            (None, true) => self.running.push(Running {
+                name,
+                frame: 0,
+                held: true,
+                pressed: None,
+            }),
+            (None, false) => {}
+        }
+        true
+    }
+
+    // The keypad presses and releases for the next frame.
+    fn step(&mut self) -> Vec<(u8, bool)> {
+        let mut changes = Vec::new();
+        let bindings = &self.macros.bindings;
+        self.running.retain_mut(|running| {
+            let binding = &bindings[&running.name];
+            let key = match binding {
+                Macro::Autofire { .. } if !running.held => None,
+                _ => binding.key(running.frame),
+            };
+            let pressed = key.flatten();
+            if pressed != running.pressed {
+                if let Some(x) = running.pressed {
+                    changes.push((x, false));
+                }
+                if let Some(x) = pressed {
+                    changes.push((x, true));
+                }
+                running.pressed = pressed;
+            }
+            running.frame += 1;
+            key.is_some()
+        });
+        changes
+    }
+
+    pub fn tick(&mut self, chip8: &mut Chip8) {
+        for (x, state) in self.step() {
+            chip8.key_pressed(x, state);
+        }
+    }
+
+    // Whether no macro is playing, so that nothing presses keys by itself.
+    #[cfg(feature = "sdl")]
+    pub fn is_idle(&self) -> bool {
+        self.running.is_empty()
+    }
+
+    // Lets go of the keys the running macros press and forgets them.
+    pub fn stop(&mut self, chip8: &mut Chip8) {
+        for running in self.running.drain(..) {
+            if let Some(x) = running.pressed {
+                chip8.key_pressed(x, false);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frontend::macros::Macros;
+    use crate::frontend::macros::Player;
+    #[test]
+    fn autofire_repeats_while_held() {
+        let macros: Macros = toml::from_str("t = { autofire = 5, rate = 20 }").unwrap();
+        let mut player = Player::new(&macros);
+        assert!(player.key("T", true));
+        assert!(!player.key("Q", true));
+        let frames: Vec<_> = (0..6).map(|_| player.step()).collect();
+        assert_eq!(
+            frames,
+            vec![
+                vec![(5, true)],
+                vec![],
+                vec![(5, false)],
+                vec![(5, true)],
+                vec![],
+                vec![(5, false)],
+            ]
+        );
+        player.key("T", false);
+        player.step();
+        assert_eq!(player.step(), vec![]);
+        assert!(player.running.is_empty());
+    }
+    #[test]
+    fn sequence_plays_to_the_end() {
+        let macros: Macros = toml::from_str("M = { sequence = [2, \"A\"], frames = 1 }").unwrap();
+        let mut player = Player::new(&macros);
+        player.key("m", true);
+        player.key("m", false);
+        let frames: Vec<_> = (0..5).map(|_| player.step()).collect();
+        assert_eq!(
+            frames,
+            vec![
+                vec![(2, true)],
+                vec![(2, false)],
+                vec![(0xA, true)],
+                vec![(0xA, false)],
+                vec![],
+            ]
+        );
+        assert!(player.running.is_empty());
+    }
+    #[test]
+    fn macros_describe_themselves() {
+        let macros: Macros =
+            toml::from_str("t = { autofire = 5 }\nM = { sequence = [2, \"A\"] }").unwrap();
+        assert_eq!(
+            macros.describe(),
+            vec![
+                ("M", "sequence 2 A, 4 frames a key".to_string()),
+                ("T", "autofire 5, 10 times a second".to_string()),
+            ]
+        );
+    }
+    #[test]
+    fn invalid_macros_are_explained() {
+        let error = |content| toml::from_str::<Macros>(content).unwrap_err().to_string();
+        assert!(error("Tab = { autofire = 1 }").contains("unknown macro key \"Tab\""));
+        assert!(error("T = { autofire = 16 }").contains("keypad values 0 to F"));
+        assert!(error("T = { autofire = 1, rate = 31 }").contains("1 to 30 times a second"));
+        assert!(error("T = { autofire = 1, frames = 2 }").contains("an autofire with a rate"));
+        assert!(error("T = { sequence = [1], frames = 0 }").contains("hold keys for a frame"));
+    }
+}
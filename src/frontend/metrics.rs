@@ -0,0 +1,136 @@
+use crate::chip8::Chip8;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+// What a long-running instance has done, for Prometheus to scrape from
+// /metrics like any other service.
+#[derive(Default)]
+pub struct Metrics {
+    frames: u64,
+    display_updates: u64,
+    viewers_dropped: u64,
+    // API requests answered with an error, and failures of the interpreter
+    // while running, by status code.
+    rejected: BTreeMap<String, u64>,
+}
+
+// A metric in the text format of Prometheus, with its help and type.
+fn metric(out: &mut String, name: &str, kind: &str, help: &str, samples: &[(&str, u64)]) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+    for (labels, value) in samples {
+        let _ = writeln!(out, "{}{} {}", name, labels, value);
+    }
+}
+
+impl Metrics {
+    pub fn frame(&mut self) {
+        self.frames += 1;
+    }
+
+    pub fn display_update(&mut self) {
+        self.display_updates += 1;
+    }
+
+    pub fn viewers_dropped(&mut self, viewers: usize) {
+        self.viewers_dropped += viewers as u64;
+    }
+
+    pub fn rejected(&mut self, status: &str) {
+        let code = status.split(' ').next().unwrap_or(status);
+        *self.rejected.entry(String::from(code)).or_default() += 1;
+    }
+
+    pub fn render(&self, chip8: &Chip8, viewers: usize, paused: bool) -> String {
+        let mut out = String::new();
+        let counters = [
+            (
+                "chip8_instructions_total",
+                "Instructions executed.",
+                chip8.cycles(),
+            ),
+            (
+                "chip8_frames_total",
+                "Frames emulated, 60 a second while running.",
+                self.frames,
+            ),
+            (
+                "chip8_display_updates_total",
+                "Frames that changed the display.",
+                self.display_updates,
+            ),
+            ("chip8_draws_total", "Sprites drawn.", chip8.draws()),
+            (
+                "chip8_collisions_total",
+                "Sprites drawn that turned pixels off.",
+                chip8.collisions(),
+            ),
+            (
+                "chip8_viewers_dropped_total",
+                "Viewers dropped for not keeping up or going away.",
+                self.viewers_dropped,
+            ),
+        ];
+        for (name, help, value) in counters {
+            metric(&mut out, name, "counter", help, &[("", value)]);
+        }
+        let labels: Vec<String> = self
+            .rejected
+            .keys()
+            .map(|code| format!("{{code=\"{}\"}}", code))
+            .collect();
+        let rejected: Vec<(&str, u64)> = labels
+            .iter()
+            .map(String::as_str)
+            .zip(self.rejected.values().copied())
+            .collect();
+        metric(
+            &mut out,
+            "chip8_api_errors_total",
+            "counter",
+            "Requests to the control API answered with an error, and interpreter failures while running, by status code.",
+            &rejected,
+        );
+        let gauges = [
+            (
+                "chip8_viewers",
+                "Browsers watching the display.",
+                viewers as u64,
+            ),
+            (
+                "chip8_paused",
+                "Whether the emulation is paused.",
+                u64::from(paused),
+            ),
+        ];
+        for (name, help, value) in gauges {
+            metric(&mut out, name, "gauge", help, &[("", value)]);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::Chip8;
+    use crate::frontend::metrics::Metrics;
+    #[test]
+    fn render_writes_the_text_format() {
+        let mut metrics = Metrics::default();
+        metrics.frame();
+        metrics.rejected("400 Bad Request");
+        metrics.rejected("400 Bad Request");
+        let mut chip8 = Chip8::new();
+        chip8.load(&[0x12, 0x00]).unwrap();
+        chip8.fetch_execute();
+        let text = metrics.render(&chip8, 2, false);
+        assert!(text.contains(
+            "# HELP chip8_instructions_total Instructions executed.\n\
+             # TYPE chip8_instructions_total counter\n\
+             chip8_instructions_total 1\n"
+        ));
+        assert!(text.contains("chip8_frames_total 1\n"));
+        assert!(text.contains("chip8_api_errors_total{code=\"400\"} 2\n"));
+        assert!(text.contains("chip8_viewers 2\n"));
+    }
+}
@@ -0,0 +1,269 @@
+use crate::frontend::keymap::Value;
+#[cfg(feature = "sdl")]
+use crate::frontend::keymap::KEYPAD;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+#[cfg(feature = "sdl")]
+use std::fs::File;
+#[cfg(feature = "sdl")]
+use std::io::Read;
+#[cfg(feature = "sdl")]
+use std::path::Path;
+#[cfg(feature = "sdl")]
+use std::sync::mpsc;
+#[cfg(feature = "sdl")]
+use std::sync::mpsc::Receiver;
+#[cfg(feature = "sdl")]
+use std::thread;
+#[cfg(feature = "sdl")]
+use tracing::info;
+#[cfg(feature = "sdl")]
+use tracing::warn;
+
+// The grids of 4 by 4 pads the keypad is laid out on by default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    // Drum pads, as on most pad controllers, from note 36 at the bottom left
+    // up to 51 at the top right.
+    #[default]
+    Pads,
+    // The top left corner of the 8 by 8 grid of a Novation Launchpad in
+    // programmer mode, where each pad is its row and column, counted from
+    // the bottom left.
+    Launchpad,
+}
+
+#[cfg(feature = "sdl")]
+impl Layout {
+    fn note(self, row: usize, column: usize) -> u8 {
+        match self {
+            Layout::Pads => (36 + (3 - row) * 4 + column) as u8,
+            Layout::Launchpad => ((8 - row) * 10 + column + 1) as u8,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MidiOptions {
+    pub layout: Layout,
+    // Notes bound to keypad values by number, in place of the layout.
+    pub notes: Option<Notes>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "BTreeMap<String, Value>")]
+pub struct Notes {
+    bindings: BTreeMap<u8, u8>,
+}
+
+impl TryFrom<BTreeMap<String, Value>> for Notes {
+    type Error = String;
+
+    fn try_from(table: BTreeMap<String, Value>) -> Result<Self, String> {
+        let mut bindings = BTreeMap::new();
+        for (name, value) in table {
+            let note = name
+                .parse::<u8>()
+                .ok()
+                .filter(|note| *note < 128)
+                .ok_or_else(|| format!("unknown note \"{}\", notes are 0 to 127", name))?;
+            let value = value
+                .keypad()
+                .ok_or_else(|| format!("note {} must be bound to a keypad value 0 to F", note))?;
+            bindings.insert(note, value);
+        }
+        Ok(Self { bindings })
+    }
+}
+
+#[cfg(feature = "sdl")]
+impl MidiOptions {
+    // Which keypad value each note presses.
+    fn bindings(&self) -> BTreeMap<u8, u8> {
+        if let Some(notes) = &self.notes {
+            return notes.bindings.clone();
+        }
+        let mut bindings = BTreeMap::new();
+        for (row, values) in KEYPAD.iter().enumerate() {
+            for (column, value) in values.iter().enumerate() {
+                bindings.insert(self.layout.note(row, column), *value);
+            }
+        }
+        bindings
+    }
+}
+
+// A note struck or released, on any channel.
+#[cfg(feature = "sdl")]
+#[derive(Debug, PartialEq)]
+struct Note {
+    note: u8,
+    down: bool,
+}
+
+// Reads notes out of a stream of MIDI bytes, where a message may leave out
+// its status byte when it repeats that of the one before.
+#[cfg(feature = "sdl")]
+#[derive(Default)]
+struct Parser {
+    status: Option<u8>,
+    data: Vec<u8>,
+}
+
+#[cfg(feature = "sdl")]
+impl Parser {
+    fn feed(&mut self, byte: u8) -> Option<Note> {
+        match byte {
+            // Clock and other real-time bytes can come anywhere, even in
+            // the middle of another message.
+            0xF8..=0xFF => return None,
+            // System messages, SysEx included, are ignored up to the next
+            // status byte.
+            0xF0..=0xF7 => {
+                self.status = None;
+                return None;
+            }
+            0x80..=0xEF => {
+                self.status = Some(byte);
+                self.data.clear();
+                return None;
+            }
+            _ => {}
+        }
+        let status = self.status?;
+        self.data.push(byte);
+        let length = match status & 0xF0 {
+            0xC0 | 0xD0 => 1,
+            _ => 2,
+        };
+        if self.data.len() < length {
+            return None;
+        }
+        let data = std::mem::take(&mut self.data);
+        match (status & 0xF0, data.as_slice()) {
+            (0x90, &[note, velocity]) => Some(Note {
+                note,
+                // A note on without velocity is how many devices let go.
+                down: velocity > 0,
+            }),
+            (0x80, &[note, _]) => Some(Note { note, down: false }),
+            _ => None,
+        }
+    }
+}
+
+// A MIDI controller playing the keypad, read from the device file the
+// system gives it, as /dev/snd/midiC1D0 on Linux.
+#[cfg(feature = "sdl")]
+pub struct Midi {
+    keys: Receiver<(u8, bool)>,
+}
+
+#[cfg(feature = "sdl")]
+impl Midi {
+    pub fn open(device: &Path, options: &MidiOptions) -> Result<Self, String> {
+        let mut file = File::open(device)
+            .map_err(|e| format!("unable to open {}: {}", device.display(), e))?;
+        info!("playing the keypad from {}", device.display());
+        let bindings = options.bindings();
+        let name = device.display().to_string();
+        let (sender, keys) = mpsc::channel();
+        thread::spawn(move || {
+            let mut parser = Parser::default();
+            let mut buffer = [0; 64];
+            loop {
+                let read = match file.read(&mut buffer) {
+                    Ok(0) => {
+                        warn!("MIDI input from {} stopped", name);
+                        return;
+                    }
+                    Ok(read) => read,
+                    Err(e) => {
+                        warn!("MIDI input from {} stopped: {}", name, e);
+                        return;
+                    }
+                };
+                for byte in &buffer[..read] {
+                    let Some(note) = parser.feed(*byte) else {
+                        continue;
+                    };
+                    if let Some(key) = bindings.get(&note.note) {
+                        if sender.send((*key, note.down)).is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        });
+        Ok(Self { keys })
+    }
+
+    // The keypad values pressed and released since the last frame.
+    pub fn keys(&self) -> impl Iterator<Item = (u8, bool)> + '_ {
+        self.keys.try_iter()
+    }
+}
+
+#[cfg(all(test, feature = "sdl"))]
+mod tests {
+    use crate::frontend::midi::Layout;
+    use crate::frontend::midi::MidiOptions;
+    use crate::frontend::midi::Note;
+    use crate::frontend::midi::Parser;
+    #[test]
+    fn parser_reads_notes_with_running_status() {
+        let mut parser = Parser::default();
+        let notes: Vec<Note> = [
+            0x90, 0x24, 0x64, 0x25, 0x64, 0xF8, 0x24, 0x00, 0xC0, 0x05, 0x80, 0x25, 0x40, 0xF0,
+            0x7E, 0x7F, 0xF7,
+        ]
+        .iter()
+        .filter_map(|byte| parser.feed(*byte))
+        .collect();
+        assert_eq!(
+            notes,
+            [
+                Note {
+                    note: 0x24,
+                    down: true
+                },
+                Note {
+                    note: 0x25,
+                    down: true
+                },
+                Note {
+                    note: 0x24,
+                    down: false
+                },
+                Note {
+                    note: 0x25,
+                    down: false
+                },
+            ]
+        );
+    }
+    #[test]
+    fn layouts_put_the_keypad_on_the_pads() {
+        let pads = MidiOptions::default().bindings();
+        assert_eq!(pads.get(&48), Some(&0x1));
+        assert_eq!(pads.get(&36), Some(&0xA));
+        assert_eq!(pads.get(&51), Some(&0xC));
+        let launchpad = MidiOptions {
+            layout: Layout::Launchpad,
+            notes: None,
+        }
+        .bindings();
+        assert_eq!(launchpad.get(&81), Some(&0x1));
+        assert_eq!(launchpad.get(&54), Some(&0xF));
+        let notes = MidiOptions {
+            notes: Some(toml::from_str("60 = 5\n62 = \"A\"").unwrap()),
+            ..Default::default()
+        }
+        .bindings();
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes.get(&62), Some(&0xA));
+        assert!(toml::from_str::<MidiOptions>("notes = { 128 = 1 }").is_err());
+    }
+}
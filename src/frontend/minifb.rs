@@ -0,0 +1,282 @@
+use crate::audio::Audio;
+use crate::audio::FREQUENCY_STEP;
+use crate::audio::VOLUME_STEP;
+use crate::chip8::display::RES_HEIGHT;
+use crate::chip8::display::RES_WIDTH;
+use crate::chip8::Chip8;
+use crate::chip8::Reset;
+use crate::config::Config;
+use crate::failure::Failure;
+use crate::frontend::focus::Focus;
+use crate::frontend::framebuffer::levels;
+use crate::frontend::layout::Scaling;
+use crate::frontend::limits::Limits;
+use crate::frontend::macros::Player;
+use crate::frontend::notifications::Notifications;
+use crate::frontend::palette::Palettes;
+use crate::frontend::palette::Rgb;
+use crate::frontend::palette::Tone;
+use crate::frontend::phosphor::Phosphor;
+use crate::frontend::pokes::Pokes;
+use crate::frontend::runner::step_speed;
+use crate::frontend::runner::Hold;
+use crate::frontend::runner::Runner;
+use crate::frontend::runner::TIMER_SPEED;
+use crate::frontend::script::Script;
+use crate::frontend::speed::describe;
+use crate::frontend::stats::Stats;
+use crate::frontend::text::Overlay;
+use crate::frontend::text::OVERLAY_SCALE;
+use crate::frontend::title::rom_name;
+use crate::frontend::title::title;
+use crate::Args;
+use minifb::Key;
+use minifb::KeyRepeat;
+use minifb::ScaleMode;
+use minifb::Window;
+use minifb::WindowOptions;
+use std::time::Duration;
+use std::time::Instant;
+
+// minifb names the digit keys Key0 to Key9, and the others as the keymap does.
+fn key_name(key: Key) -> String {
+    let name = format!("{:?}", key);
+    name.strip_prefix("Key").unwrap_or(&name).to_string()
+}
+
+// A window backed by minifb, which talks to the OS directly and therefore
+// does not need the SDL2 library. Scaling with the right aspect ratio is left
+// to minifb, while the CRT effects other than the pixel grid and shape are
+// only available with SDL. The buffer is drawn at the overlay resolution so
+// that notifications stay legible, which leaves four output pixels per cell
+// for the shape: enough for circles, but too few to round the corners.
+pub fn run(
+    args: &Args,
+    config: &Config,
+    mut palettes: Palettes,
+    mut chip8: Chip8,
+    audio: &mut dyn Audio,
+) -> Result<(), Failure> {
+    let grid = args.grid.unwrap_or(config.crt.grid).clamp(0.0, 1.0);
+    let dot = args
+        .pixel_shape
+        .unwrap_or(config.crt.shape)
+        .mask(OVERLAY_SCALE);
+
+    // minifb sizes windows in pixels and cannot tell the display density.
+    let dpi_scale = args.dpi_scale.unwrap_or(1.0);
+    let rom = rom_name(args.rom().unwrap_or_default());
+    let mut window = Window::new(
+        &title(&rom, args.ips, args.pause_on_start),
+        (RES_WIDTH as f32 * args.scale as f32 * dpi_scale) as usize,
+        (RES_HEIGHT as f32 * args.scale as f32 * dpi_scale) as usize,
+        WindowOptions {
+            resize: true,
+            // minifb has no integer scaling, so that falls back to fit.
+            scale_mode: match args.scaling {
+                Scaling::Stretch => ScaleMode::Stretch,
+                Scaling::Integer | Scaling::Fit => ScaleMode::AspectRatioStretch,
+            },
+            ..WindowOptions::default()
+        },
+    )
+    .map_err(|e| Failure::Video(format!("unable to open the window: {}", e)))?;
+    window.limit_update_rate(Some(Duration::from_secs(1) / TIMER_SPEED));
+
+    let mut phosphor = Phosphor::from_args(args);
+
+    let mut speed = args.ips;
+    let mut runner = Runner::new(speed);
+    let hold = Hold::from_args(args);
+    let mut macros = Player::new(&config.macros);
+    let mut script = Script::from_args(args);
+    let pokes = Pokes::from_args(args);
+    let limits = Limits::from_args(args);
+    let mut last_frame = Instant::now();
+    let mut pause_emulation = args.pause_on_start;
+    let mut focus = Focus::new(config.window.pause_on_focus_loss);
+    let mut focused = true;
+    let mut notifications = Notifications::new();
+    let width = RES_WIDTH * OVERLAY_SCALE;
+    let height = RES_HEIGHT * OVERLAY_SCALE;
+    let mut overlay = Overlay::new(width, height);
+    let mut stats = Stats::new(Instant::now(), chip8.cycles());
+    let mut show_stats = args.stats;
+    let mut buffer: Vec<u32> = vec![0; width * height];
+
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        if window.is_active() != focused {
+            focused = !focused;
+            let paused = focus.change(focused, pause_emulation);
+            if paused != pause_emulation {
+                pause_emulation = paused;
+                notifications.push(if paused { "Paused" } else { "Resumed" });
+                window.set_title(&title(&rom, speed, pause_emulation));
+            }
+        }
+        for key in window.get_keys_pressed(KeyRepeat::No) {
+            match key {
+                Key::Space => {
+                    pause_emulation = !pause_emulation;
+                    notifications.push(if pause_emulation { "Paused" } else { "Resumed" });
+                    window.set_title(&title(&rom, speed, pause_emulation));
+                }
+                Key::P => {
+                    palettes.cycle();
+                    notifications.push(format!("Palette: {}", palettes.current().name));
+                }
+                Key::N => notifications.push(if palettes.invert() {
+                    "Colors inverted"
+                } else {
+                    "Colors restored"
+                }),
+                Key::I => show_stats = !show_stats,
+                Key::F2 => {
+                    let hard =
+                        window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift);
+                    if hard {
+                        macros.stop(&mut chip8);
+                        if let Some(script) = script.as_mut() {
+                            script.rewind(&mut chip8);
+                        }
+                    }
+                    chip8.reset(if hard { Reset::Hard } else { Reset::Soft });
+                    if let Some(phosphor) = phosphor.as_mut() {
+                        phosphor.clear();
+                    }
+                    pause_emulation |= args.pause_on_start;
+                    notifications.push(if hard { "Hard reset" } else { "Reset" });
+                    window.set_title(&title(&rom, speed, pause_emulation));
+                }
+                Key::F5 | Key::F6 => {
+                    let step = if key == Key::F5 {
+                        -Tone::STEP
+                    } else {
+                        Tone::STEP
+                    };
+                    let brightness = palettes.adjust_brightness(step);
+                    notifications.push(format!("Brightness: {:.0}%", brightness * 100.0));
+                }
+                Key::F7 | Key::F8 => {
+                    let step = if key == Key::F7 {
+                        -Tone::STEP
+                    } else {
+                        Tone::STEP
+                    };
+                    notifications.push(format!("Gamma: {:.1}", palettes.adjust_gamma(step)));
+                }
+                Key::F9 | Key::F10 => {
+                    let step = if key == Key::F9 {
+                        -FREQUENCY_STEP
+                    } else {
+                        FREQUENCY_STEP
+                    };
+                    let frequency = audio.adjust_frequency(step);
+                    notifications.push(format!("Beep: {} Hz", frequency));
+                }
+                Key::LeftBracket | Key::RightBracket => {
+                    let step = if key == Key::LeftBracket {
+                        -VOLUME_STEP
+                    } else {
+                        VOLUME_STEP
+                    };
+                    notifications.push(format!("Volume: {}%", audio.adjust_volume(step)));
+                }
+                Key::Minus | Key::NumPadMinus | Key::Equal | Key::NumPadPlus => {
+                    speed = step_speed(speed, matches!(key, Key::Equal | Key::NumPadPlus));
+                    runner.set_speed(speed);
+                    notifications.push(format!("Speed: {}", describe(speed)));
+                    window.set_title(&title(&rom, speed, pause_emulation));
+                }
+                _ => {
+                    if macros.key(&key_name(key), true) {
+                        continue;
+                    }
+                    if let Some(x) = config.keys.key(&key_name(key)) {
+                        chip8.key_pressed(x, true);
+                    }
+                }
+            }
+        }
+        for key in window.get_keys_released() {
+            if macros.key(&key_name(key), false) {
+                continue;
+            }
+            if let Some(x) = config.keys.key(&key_name(key)) {
+                chip8.key_pressed(x, false);
+            }
+        }
+
+        // Holding Tab runs the emulation faster, without the buzzer, and
+        // holding Backspace slower.
+        let fast_forward = window.is_key_down(Key::Tab);
+        let slow_motion = window.is_key_down(Key::Backspace);
+        runner.set_scale(hold.scale(fast_forward, slow_motion));
+
+        let elapsed = last_frame.elapsed();
+        last_frame = Instant::now();
+        if pause_emulation {
+            audio.pause();
+        } else {
+            for _ in 0..runner.advance(&mut chip8, elapsed) {
+                macros.tick(&mut chip8);
+                if let Some(script) = script.as_mut() {
+                    script.tick(&mut chip8);
+                }
+                if let Some(pokes) = &pokes {
+                    pokes.tick(&mut chip8);
+                }
+                if let Some(phosphor) = phosphor.as_mut() {
+                    phosphor.update(&chip8.display.as_buffer());
+                }
+            }
+            let timer = chip8.take_sound();
+            if fast_forward {
+                audio.pause();
+            } else {
+                audio.sound(timer);
+            }
+        }
+        if let Some(end) = limits
+            .as_ref()
+            .and_then(|limits| limits.check(&chip8, Instant::now()))
+        {
+            return end;
+        }
+
+        notifications.expire(Instant::now());
+        stats.update(Instant::now(), chip8.cycles());
+        overlay.show(notifications.messages());
+        if show_stats {
+            overlay.draw_status(&stats.summary(speed));
+        }
+        if args.visual_beep && !pause_emulation && chip8.beep() {
+            overlay.draw_border(palettes.current().foreground());
+        }
+
+        let palette = palettes.current();
+        let levels = levels(&chip8.display.as_buffer(), phosphor.as_ref());
+        for (index, pixel) in buffer.iter_mut().enumerate() {
+            let (x, y) = (index % width, index / width);
+            let level = levels[y / OVERLAY_SCALE * RES_WIDTH + x / OVERLAY_SCALE];
+            let (dot_x, dot_y) = (x % OVERLAY_SCALE, y % OVERLAY_SCALE);
+            let mut color = if dot[(dot_y * OVERLAY_SCALE + dot_x) * 4 + 3] == 0 {
+                palette.background
+            } else {
+                palette.background.blend(palette.foreground(), level)
+            };
+            let gap = (dot_x == 0 && x > 0) || (dot_y == 0 && y > 0);
+            if grid > 0.0 && gap {
+                color = color.blend(palette.background, grid);
+            }
+            if !overlay.is_empty() {
+                let osd = &overlay.pixels[index * 4..index * 4 + 4];
+                color = color.blend(Rgb(osd[0], osd[1], osd[2]), osd[3] as f32 / 255.0);
+            }
+            *pixel = (color.0 as u32) << 16 | (color.1 as u32) << 8 | color.2 as u32;
+        }
+        window.update_with_buffer(&buffer, width, height).unwrap();
+        stats.frame();
+    }
+    Ok(())
+}
@@ -0,0 +1,119 @@
+#[cfg(feature = "sdl")]
+use crate::frontend::layout::Viewport;
+use serde::Deserialize;
+#[cfg(feature = "sdl")]
+use std::fs;
+#[cfg(feature = "sdl")]
+use std::io::ErrorKind;
+#[cfg(feature = "sdl")]
+use std::path::Path;
+#[cfg(feature = "sdl")]
+use toml_edit::DocumentMut;
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowOptions {
+    // Index of the monitor the window opens on, in the order the system
+    // lists them, so that the emulator keeps coming up on the same screen.
+    pub monitor: Option<u32>,
+    // Covers the whole monitor with a window without decorations instead of
+    // switching the display to fullscreen.
+    pub borderless: bool,
+    // Pauses emulation while another window has the focus, so that the game
+    // does not go on unseen.
+    pub pause_on_focus_loss: bool,
+}
+
+// Checks the requested monitor against the connected ones, listing them when
+// it is not there.
+#[cfg(feature = "sdl")]
+pub fn select(requested: u32, names: &[String]) -> Result<i32, String> {
+    if (requested as usize) < names.len() {
+        return Ok(requested as i32);
+    }
+    let available: Vec<String> = names
+        .iter()
+        .enumerate()
+        .map(|(index, name)| format!("{}: {}", index, name))
+        .collect();
+    Err(format!(
+        "no monitor {}, the available ones are {}",
+        requested,
+        available.join(", ")
+    ))
+}
+
+// Where a window of the given size goes to be centered on the monitor.
+#[cfg(feature = "sdl")]
+pub fn centered(bounds: Viewport, size: (u32, u32)) -> (i32, i32) {
+    (
+        bounds.x + (bounds.width as i32 - size.0 as i32) / 2,
+        bounds.y + (bounds.height as i32 - size.1 as i32) / 2,
+    )
+}
+
+// Writes the monitor into the window table of the configuration file, so
+// that the emulator comes up on it from then on, leaving everything else in
+// the file, comments included, as is.
+#[cfg(feature = "sdl")]
+pub fn remember(path: &Path, monitor: u32) -> Result<(), String> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("unable to read {}: {}", path.display(), e)),
+    };
+    let content =
+        remembered(&content, monitor).map_err(|e| format!("{}: {}", path.display(), e))?;
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("unable to create {}: {}", dir.display(), e))?;
+    }
+    fs::write(path, content).map_err(|e| format!("unable to write {}: {}", path.display(), e))
+}
+
+#[cfg(feature = "sdl")]
+fn remembered(content: &str, monitor: u32) -> Result<String, String> {
+    let mut document: DocumentMut = content.parse().map_err(|e| format!("{}", e))?;
+    let window = document.entry("window").or_insert(toml_edit::table());
+    window["monitor"] = toml_edit::value(monitor as i64);
+    Ok(document.to_string())
+}
+
+#[cfg(all(test, feature = "sdl"))]
+mod tests {
+    use crate::frontend::layout::Viewport;
+    use crate::frontend::monitor::centered;
+    use crate::frontend::monitor::remembered;
+    use crate::frontend::monitor::select;
+    #[test]
+    fn select_lists_the_monitors_when_missing() {
+        let names = vec![String::from("DELL U2415"), String::from("HDMI-1")];
+        assert_eq!(select(1, &names), Ok(1));
+        assert_eq!(
+            select(2, &names),
+            Err(String::from(
+                "no monitor 2, the available ones are 0: DELL U2415, 1: HDMI-1"
+            ))
+        );
+    }
+    #[test]
+    fn centered_on_a_secondary_monitor() {
+        let bounds = Viewport {
+            x: 1920,
+            y: -200,
+            width: 1280,
+            height: 1024,
+        };
+        assert_eq!(centered(bounds, (640, 320)), (2240, 152));
+    }
+    #[test]
+    fn remembered_keeps_the_rest_of_the_file() {
+        let config =
+            "# my settings\n[audio]\nvolume = 40\n\n[window]\nmonitor = 0\nborderless = true\n";
+        assert_eq!(
+            remembered(config, 1).unwrap(),
+            "# my settings\n[audio]\nvolume = 40\n\n[window]\nmonitor = 1\nborderless = true\n"
+        );
+        assert_eq!(remembered("", 2).unwrap(), "[window]\nmonitor = 2\n");
+    }
+}
@@ -0,0 +1,267 @@
+use crate::chip8::hash::state;
+use crate::chip8::quirks::Quirks;
+use crate::chip8::Chip8;
+use crate::frontend::runner::frame_instructions;
+use serde::Deserialize;
+use serde::Serialize;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::time::Duration;
+use tracing::info;
+
+// How long to wait for the other player before giving up on them.
+const PATIENCE: Duration = Duration::from_secs(10);
+
+// Frames between comparisons of the two machines, to find out that they
+// went different ways.
+const CHECK_EVERY: u64 = 60;
+
+// What the two instances send each other, a JSON object a line. Both say
+// first what they run, then the keys pressed for each frame in turn.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Message {
+    Hello {
+        rom: String,
+        seed: u64,
+        ips: u32,
+        quirks: u8,
+    },
+    Frame {
+        frame: u64,
+        keys: u16,
+        state: Option<u32>,
+    },
+}
+
+// Two instances running the same ROM frame by frame over TCP, each sending
+// the keys its player holds and running the frame only once it has those of
+// the other player, so that both machines go exactly the same way. The host
+// picks the seed and the speed, and the ROMs have to be the same.
+pub struct Netplay {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    ips: u32,
+    frame: u64,
+    // The keypad as both players last held it.
+    held: u16,
+}
+
+fn quirk_bits(quirks: Quirks) -> u8 {
+    [
+        quirks.vf_reset,
+        quirks.memory,
+        quirks.display_wait,
+        quirks.clipping,
+        quirks.shifting,
+        quirks.jumping,
+    ]
+    .iter()
+    .enumerate()
+    .fold(0, |bits, (index, on)| bits | u8::from(*on) << index)
+}
+
+impl Netplay {
+    fn new(stream: TcpStream, ips: u32) -> Result<Self, String> {
+        let error = |e: std::io::Error| format!("netplay: {}", e);
+        stream.set_nodelay(true).map_err(error)?;
+        stream.set_read_timeout(Some(PATIENCE)).map_err(error)?;
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone().map_err(error)?),
+            writer: stream,
+            ips,
+            frame: 0,
+            held: 0,
+        })
+    }
+
+    fn send(&mut self, message: &Message) -> Result<(), String> {
+        let mut line = serde_json::to_string(message).unwrap();
+        line.push('\n');
+        self.writer
+            .write_all(line.as_bytes())
+            .map_err(|e| format!("the other player is gone: {}", e))
+    }
+
+    fn receive(&mut self) -> Result<Message, String> {
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) => Err(String::from("the other player left")),
+            Ok(_) => serde_json::from_str(&line)
+                .map_err(|e| format!("the other player sent something else: {}", e)),
+            Err(e) => Err(format!("the other player stopped answering: {}", e)),
+        }
+    }
+
+    // Waits for the other player on the port, then tells them what to run.
+    pub fn host(port: u16, chip8: &Chip8, rom: &str, ips: u32) -> Result<Self, String> {
+        let listener = TcpListener::bind(("0.0.0.0", port))
+            .map_err(|e| format!("netplay: unable to listen on port {}: {}", port, e))?;
+        info!("waiting for the other player on port {}", port);
+        let (stream, peer) = listener.accept().map_err(|e| format!("netplay: {}", e))?;
+        info!("{} joined", peer);
+        let mut netplay = Self::new(stream, ips)?;
+        let hello = Message::Hello {
+            rom: String::from(rom),
+            seed: chip8.seed(),
+            ips,
+            quirks: quirk_bits(chip8.quirks()),
+        };
+        netplay.send(&hello)?;
+        match netplay.receive()? {
+            Message::Hello { rom: theirs, .. } if theirs != rom => {
+                Err(String::from("netplay: the other player has another ROM"))
+            }
+            Message::Hello { .. } => Ok(netplay),
+            _ => Err(String::from("netplay: the other player did not say hello")),
+        }
+    }
+
+    // Joins the host at the address, taking its seed and speed, which it
+    // returns along with the session.
+    pub fn join(address: &str, chip8: &mut Chip8, rom: &str) -> Result<(Self, u32), String> {
+        let stream = TcpStream::connect(address)
+            .map_err(|e| format!("netplay: unable to join {}: {}", address, e))?;
+        info!("joined {}", address);
+        let mut netplay = Self::new(stream, 0)?;
+        let Message::Hello {
+            rom: theirs,
+            seed,
+            ips,
+            quirks,
+        } = netplay.receive()?
+        else {
+            return Err(String::from("netplay: the host did not say hello"));
+        };
+        let hello = Message::Hello {
+            rom: String::from(rom),
+            seed,
+            ips,
+            quirks: quirk_bits(chip8.quirks()),
+        };
+        netplay.send(&hello)?;
+        if theirs != rom {
+            return Err(String::from("netplay: the host runs another ROM"));
+        }
+        if quirks != quirk_bits(chip8.quirks()) {
+            return Err(String::from(
+                "netplay: the host runs other quirks, give the same --platform and --quirk",
+            ));
+        }
+        chip8.set_seed(seed);
+        netplay.ips = ips;
+        Ok((netplay, ips))
+    }
+
+    // Runs the next frame with the keys of both players, once the other
+    // player's are in, comparing the machines every so often.
+    pub fn frame(&mut self, chip8: &mut Chip8, keys: u16) -> Result<(), String> {
+        let frame = self.frame;
+        let state = frame.is_multiple_of(CHECK_EVERY).then(|| state(chip8));
+        self.send(&Message::Frame { frame, keys, state })?;
+        let Message::Frame {
+            frame: their_frame,
+            keys: their_keys,
+            state: their_state,
+        } = self.receive()?
+        else {
+            return Err(String::from("the other player said hello again"));
+        };
+        if their_frame != frame {
+            return Err(format!(
+                "the other player is at frame {} instead of {}",
+                their_frame, frame
+            ));
+        }
+        if state != their_state {
+            return Err(format!(
+                "the machines went different ways by frame {}",
+                frame
+            ));
+        }
+        let held = keys | their_keys;
+        for key in 0..16 {
+            let down = held & 1 << key != 0;
+            if down != (self.held & 1 << key != 0) {
+                chip8.key_pressed(key, down);
+            }
+        }
+        self.held = held;
+        for _ in 0..frame_instructions(frame, self.ips) {
+            chip8.fetch_execute();
+        }
+        chip8.dec_timers();
+        self.frame += 1;
+        Ok(())
+    }
+
+    // Lets go of the keys held when the session ends, leaving the machine to
+    // the keys of this instance.
+    pub fn leave(self, chip8: &mut Chip8) {
+        for key in 0..16 {
+            if self.held & 1 << key != 0 {
+                chip8.key_pressed(key, false);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::hash::state;
+    use crate::chip8::Chip8;
+    use crate::frontend::netplay::Netplay;
+    use std::net::TcpListener;
+    use std::net::TcpStream;
+    use std::thread;
+    // Waits for a key with Fx0A, then counts instructions up from it in V0.
+    const ROM: [u8; 6] = [0xF0, 0x0A, 0x70, 0x01, 0x12, 0x02];
+    fn pair() -> (Netplay, Netplay) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let host = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            Netplay::new(stream, 120).unwrap()
+        });
+        let guest = Netplay::new(TcpStream::connect(address).unwrap(), 120).unwrap();
+        (host.join().unwrap(), guest)
+    }
+    #[test]
+    fn frame_runs_both_machines_with_both_players_keys() {
+        let (mut host, mut guest) = pair();
+        let mut chip8 = Chip8::new();
+        chip8.load(&ROM).unwrap();
+        let mut other = chip8.clone();
+        let remote = thread::spawn(move || {
+            for frame in 0..3 {
+                guest
+                    .frame(&mut other, if frame == 1 { 1 << 5 } else { 0 })
+                    .unwrap();
+            }
+            other
+        });
+        for _ in 0..3 {
+            host.frame(&mut chip8, 0).unwrap();
+        }
+        let mut other = remote.join().unwrap();
+        assert_eq!(chip8.reg_v()[0], 7);
+        assert_eq!(state(&mut chip8), state(&mut other));
+    }
+    #[test]
+    fn frame_finds_machines_gone_different_ways() {
+        let (mut host, mut guest) = pair();
+        let mut chip8 = Chip8::new();
+        chip8.load(&ROM).unwrap();
+        let mut other = chip8.clone();
+        other.ram[0x300] = 1;
+        let remote = thread::spawn(move || guest.frame(&mut other, 0));
+        assert_eq!(
+            host.frame(&mut chip8, 0),
+            Err(String::from("the machines went different ways by frame 0"))
+        );
+        assert!(remote.join().unwrap().is_err());
+    }
+}
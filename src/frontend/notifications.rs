@@ -0,0 +1,65 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+use std::time::Instant;
+
+const DURATION: Duration = Duration::from_secs(2);
+const MAX_VISIBLE: usize = 3;
+
+// Short-lived messages such as "Paused", shown on top of the display for a
+// couple of seconds. Only the most recent few are kept.
+pub struct Notifications {
+    messages: VecDeque<(String, Instant)>,
+}
+
+impl Notifications {
+    pub fn new() -> Self {
+        Self {
+            messages: VecDeque::new(),
+        }
+    }
+
+    pub fn push(&mut self, message: impl Into<String>) {
+        if self.messages.len() == MAX_VISIBLE {
+            self.messages.pop_front();
+        }
+        self.messages.push_back((message.into(), Instant::now()));
+    }
+
+    // Drops the messages that have been shown long enough, returning whether
+    // any went away so that the frontend knows to redraw.
+    pub fn expire(&mut self, now: Instant) -> bool {
+        let before = self.messages.len();
+        self.messages
+            .retain(|(_, shown)| now.saturating_duration_since(*shown) < DURATION);
+        self.messages.len() != before
+    }
+
+    pub fn messages(&self) -> impl Iterator<Item = &str> {
+        self.messages.iter().map(|(message, _)| message.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frontend::notifications::Notifications;
+    use std::time::Duration;
+    use std::time::Instant;
+    #[test]
+    fn messages_expire_after_a_while() {
+        let mut notifications = Notifications::new();
+        notifications.push("Paused");
+        assert!(!notifications.expire(Instant::now()));
+        assert_eq!(notifications.messages().count(), 1);
+        assert!(notifications.expire(Instant::now() + Duration::from_secs(3)));
+        assert_eq!(notifications.messages().count(), 0);
+    }
+    #[test]
+    fn only_the_most_recent_messages_are_kept() {
+        let mut notifications = Notifications::new();
+        for message in ["one", "two", "three", "four"] {
+            notifications.push(message);
+        }
+        let messages: Vec<&str> = notifications.messages().collect();
+        assert_eq!(messages, ["two", "three", "four"]);
+    }
+}
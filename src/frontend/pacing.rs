@@ -0,0 +1,147 @@
+#[cfg(feature = "sdl")]
+use std::hint;
+#[cfg(feature = "sdl")]
+use std::thread;
+use std::time::Duration;
+#[cfg(feature = "sdl")]
+use std::time::Instant;
+
+// Longest stretch of time that is caught up on at once, so that a stall
+// (e.g. dragging the window) does not turn into a burst of emulation.
+const MAX_CATCH_UP: Duration = Duration::from_millis(250);
+
+// Converts elapsed wall-clock time into a whole number of events happening at
+// a fixed rate, carrying the fractional remainder over to the next call.
+#[derive(Clone)]
+pub struct Budget {
+    rate: f64,
+    pending: f64,
+}
+
+impl Budget {
+    pub fn new(rate: f64) -> Self {
+        Self { rate, pending: 0.0 }
+    }
+
+    pub fn advance(&mut self, elapsed: Duration) -> u32 {
+        self.pending += elapsed.min(MAX_CATCH_UP).as_secs_f64() * self.rate;
+        let whole = self.pending.floor();
+        self.pending -= whole;
+        whole as u32
+    }
+}
+
+// Drops the drawing of frames while the loop runs late, so that a host too
+// slow to draw every frame still runs the ROM at its speed, drawing at least
+// one frame after so many dropped in a row.
+#[cfg(feature = "sdl")]
+pub struct FrameSkip {
+    max: u32,
+    frame: Duration,
+    in_a_row: u32,
+}
+
+#[cfg(feature = "sdl")]
+impl FrameSkip {
+    pub fn new(max: u32, frame: Duration) -> Self {
+        Self {
+            max,
+            frame,
+            in_a_row: 0,
+        }
+    }
+
+    // Whether to leave out drawing the frame, running as late as it is.
+    pub fn skip(&mut self, late: Duration) -> bool {
+        if late >= self.frame && self.in_a_row < self.max {
+            self.in_a_row += 1;
+            return true;
+        }
+        self.in_a_row = 0;
+        false
+    }
+}
+
+// Waits for deadlines more precisely than a sleep does, as sleeps can last
+// milliseconds longer than asked for, on Windows especially: it sleeps a
+// millisecond at a time while there is time for a sleep as long as the
+// longest one lately, and spins through the rest.
+#[cfg(feature = "sdl")]
+pub struct Pacer {
+    // How long a sleep of a millisecond takes at worst, forgotten slowly so
+    // that a single hiccup does not keep the loop spinning.
+    oversleep: Duration,
+}
+
+#[cfg(feature = "sdl")]
+impl Pacer {
+    pub fn new() -> Self {
+        Self {
+            oversleep: Duration::from_millis(2),
+        }
+    }
+
+    pub fn wait_until(&mut self, deadline: Instant) {
+        loop {
+            let start = Instant::now();
+            if deadline.saturating_duration_since(start) <= self.oversleep {
+                break;
+            }
+            thread::sleep(Duration::from_millis(1));
+            self.oversleep = start
+                .elapsed()
+                .max(self.oversleep.mul_f64(0.99))
+                .max(Duration::from_millis(1));
+        }
+        while Instant::now() < deadline {
+            hint::spin_loop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frontend::pacing::Budget;
+    #[cfg(feature = "sdl")]
+    use crate::frontend::pacing::FrameSkip;
+    #[cfg(feature = "sdl")]
+    use crate::frontend::pacing::Pacer;
+    use std::time::Duration;
+    #[cfg(feature = "sdl")]
+    use std::time::Instant;
+    #[test]
+    fn advance_carries_fractional_events() {
+        let mut budget = Budget::new(60.0);
+        assert_eq!(budget.advance(Duration::from_millis(10)), 0);
+        assert_eq!(budget.advance(Duration::from_millis(10)), 1);
+        assert_eq!(budget.advance(Duration::from_millis(1000 / 6)), 10);
+    }
+    #[test]
+    fn advance_caps_long_stalls() {
+        let mut budget = Budget::new(1000.0);
+        assert_eq!(budget.advance(Duration::from_secs(5)), 250);
+    }
+    #[cfg(feature = "sdl")]
+    #[test]
+    fn frame_skip_draws_after_the_most_dropped() {
+        let frame = Duration::from_millis(16);
+        let mut skip = FrameSkip::new(2, frame);
+        assert!(!skip.skip(Duration::from_millis(5)));
+        assert!(skip.skip(Duration::from_millis(20)));
+        assert!(skip.skip(Duration::from_millis(20)));
+        assert!(!skip.skip(Duration::from_millis(20)));
+        assert!(skip.skip(Duration::from_millis(20)));
+        assert!(!FrameSkip::new(0, frame).skip(Duration::from_secs(1)));
+    }
+    #[cfg(feature = "sdl")]
+    #[test]
+    fn pacer_waits_until_the_deadline() {
+        let mut pacer = Pacer::new();
+        let deadline = Instant::now() + Duration::from_millis(10);
+        pacer.wait_until(deadline);
+        let now = Instant::now();
+        assert!(now >= deadline);
+        assert!(now - deadline < Duration::from_millis(50));
+        assert!(pacer.oversleep >= Duration::from_millis(1));
+    }
+}
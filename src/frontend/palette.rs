@@ -0,0 +1,428 @@
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    pub fn blend(self, other: Rgb, amount: f32) -> Rgb {
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * amount).round() as u8;
+        Rgb(
+            mix(self.0, other.0),
+            mix(self.1, other.1),
+            mix(self.2, other.2),
+        )
+    }
+}
+
+impl TryFrom<String> for Rgb {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let hex = value.trim_start_matches('#');
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("invalid color '{}', expected #RRGGBB", value));
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+        Ok(Rgb(channel(0), channel(2), channel(4)))
+    }
+}
+
+// For colors given on the command line.
+pub fn parse_color(value: &str) -> Result<Rgb, String> {
+    Rgb::try_from(value.to_string())
+}
+
+// Plane colors are ordered as plane 1, plane 2 and both planes lit, so that
+// classic ROMs only ever use the first entry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Palette {
+    pub name: String,
+    pub background: Rgb,
+    pub planes: [Rgb; 3],
+}
+
+impl Palette {
+    pub fn foreground(&self) -> Rgb {
+        self.plane_color(0b01)
+    }
+
+    // Color of a pixel given the planes it is lit in, as a bit mask with
+    // plane 1 in the lowest bit.
+    pub fn plane_color(&self, planes: u8) -> Rgb {
+        match planes & 0b11 {
+            0 => self.background,
+            mask => self.planes[mask as usize - 1],
+        }
+    }
+
+    // Swaps the background with the first plane color, which is what classic
+    // ROMs draw with.
+    fn invert(&mut self) {
+        std::mem::swap(&mut self.background, &mut self.planes[0]);
+    }
+}
+
+// Brightness scales every channel, while gamma bends the curve between black
+// and white with values above 1 lifting the midtones. Both apply to the colors
+// sent to the screen, so that harsh palettes can be toned down per monitor.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Tone {
+    pub brightness: f32,
+    pub gamma: f32,
+}
+
+impl Default for Tone {
+    fn default() -> Self {
+        Self {
+            brightness: 1.0,
+            gamma: 1.0,
+        }
+    }
+}
+
+impl Tone {
+    // How much the hotkeys change either setting.
+    pub const STEP: f32 = 0.1;
+
+    pub fn clamped(self) -> Self {
+        Self {
+            brightness: self.brightness.clamp(0.1, 2.0),
+            gamma: self.gamma.clamp(0.5, 2.5),
+        }
+    }
+
+    pub fn apply(self, color: Rgb) -> Rgb {
+        let channel = |c: u8| {
+            let level = (c as f32 / 255.0).powf(1.0 / self.gamma) * self.brightness;
+            (level.min(1.0) * 255.0).round() as u8
+        };
+        Rgb(channel(color.0), channel(color.1), channel(color.2))
+    }
+
+    fn adjust(self, palette: &Palette) -> Palette {
+        Palette {
+            name: palette.name.clone(),
+            background: self.apply(palette.background),
+            planes: palette.planes.map(|plane| self.apply(plane)),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ThemeEntry {
+    name: String,
+    background: Rgb,
+    planes: Vec<Rgb>,
+}
+
+#[derive(Deserialize)]
+struct ThemeFile {
+    themes: Vec<ThemeEntry>,
+}
+
+impl TryFrom<ThemeEntry> for Palette {
+    type Error = String;
+
+    fn try_from(entry: ThemeEntry) -> Result<Self, Self::Error> {
+        let first = match entry.planes.first() {
+            Some(color) => *color,
+            None => return Err(format!("theme '{}' has no plane colors", entry.name)),
+        };
+        if entry.planes.len() > 3 {
+            return Err(format!(
+                "theme '{}' has more than 3 plane colors",
+                entry.name
+            ));
+        }
+        let plane = |i: usize| entry.planes.get(i).copied().unwrap_or(first);
+        Ok(Palette {
+            name: entry.name.clone(),
+            background: entry.background,
+            planes: [first, plane(1), plane(2)],
+        })
+    }
+}
+
+// Besides the classic look, high contrast palettes for low vision and
+// palettes that keep the planes apart with color vision deficiencies. The
+// latter take their colors from the Okabe-Ito set, avoiding red/green pairs.
+pub fn builtin_palettes() -> Vec<Palette> {
+    let palette = |name: &str, background: Rgb, planes: [Rgb; 3]| Palette {
+        name: String::from(name),
+        background,
+        planes,
+    };
+    vec![
+        palette(
+            "classic",
+            Rgb(0, 0, 0),
+            [Rgb(255, 255, 255), Rgb(170, 170, 170), Rgb(85, 85, 85)],
+        ),
+        palette(
+            "high-contrast",
+            Rgb(0, 0, 0),
+            [Rgb(255, 255, 0), Rgb(0, 255, 255), Rgb(255, 255, 255)],
+        ),
+        palette(
+            "high-contrast-light",
+            Rgb(255, 255, 255),
+            [Rgb(0, 0, 0), Rgb(0, 0, 160), Rgb(128, 0, 96)],
+        ),
+        palette(
+            "deuteranopia",
+            Rgb(0, 0, 0),
+            [Rgb(230, 159, 0), Rgb(86, 180, 233), Rgb(240, 228, 66)],
+        ),
+        palette(
+            "protanopia",
+            Rgb(0, 0, 0),
+            [Rgb(240, 228, 66), Rgb(86, 180, 233), Rgb(204, 121, 167)],
+        ),
+    ]
+}
+
+pub fn parse_themes(content: &str, json: bool) -> Result<Vec<Palette>, String> {
+    let file: ThemeFile = if json {
+        serde_json::from_str(content).map_err(|e| e.to_string())?
+    } else {
+        toml::from_str(content).map_err(|e| e.to_string())?
+    };
+    file.themes.into_iter().map(Palette::try_from).collect()
+}
+
+pub fn load_themes(path: &Path) -> Result<Vec<Palette>, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("unable to read {}: {}", path.display(), e))?;
+    let json = path.extension().is_some_and(|ext| ext == "json");
+    parse_themes(&content, json)
+}
+
+pub struct Palettes {
+    palettes: Vec<Palette>,
+    current: usize,
+    inverted: bool,
+    tone: Tone,
+    // The current palette with the tone applied, which is what gets drawn.
+    shown: Palette,
+}
+
+impl Palettes {
+    pub fn new(palettes: Vec<Palette>) -> Self {
+        let shown = palettes[0].clone();
+        Self {
+            palettes,
+            current: 0,
+            inverted: false,
+            tone: Tone::default(),
+            shown,
+        }
+    }
+
+    fn refresh(&mut self) {
+        self.shown = self.tone.adjust(&self.palettes[self.current]);
+    }
+
+    pub fn select(&mut self, name: &str) -> Result<(), String> {
+        match self.palettes.iter().position(|p| p.name == name) {
+            Some(index) => {
+                self.current = index;
+                self.refresh();
+                Ok(())
+            }
+            None => Err(format!(
+                "unknown palette '{}' (available: {})",
+                name,
+                self.names().collect::<Vec<_>>().join(", ")
+            )),
+        }
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.palettes.iter().map(|p| p.name.as_str())
+    }
+
+    pub fn cycle(&mut self) {
+        self.current = (self.current + 1) % self.palettes.len();
+        self.refresh();
+    }
+
+    pub fn current(&self) -> &Palette {
+        &self.shown
+    }
+
+    // Inverts every palette, so that the choice survives cycling through
+    // them, returning whether they are now inverted.
+    pub fn invert(&mut self) -> bool {
+        self.palettes.iter_mut().for_each(Palette::invert);
+        self.inverted = !self.inverted;
+        self.refresh();
+        self.inverted
+    }
+
+    #[cfg(feature = "egui")]
+    pub fn inverted(&self) -> bool {
+        self.inverted
+    }
+
+    #[cfg(feature = "egui")]
+    pub fn tone(&self) -> Tone {
+        self.tone
+    }
+
+    pub fn set_tone(&mut self, tone: Tone) {
+        self.tone = tone.clamped();
+        self.refresh();
+    }
+
+    // Returns the new brightness, for the hotkeys to show.
+    pub fn adjust_brightness(&mut self, delta: f32) -> f32 {
+        self.set_tone(Tone {
+            brightness: self.tone.brightness + delta,
+            ..self.tone
+        });
+        self.tone.brightness
+    }
+
+    pub fn adjust_gamma(&mut self, delta: f32) -> f32 {
+        self.set_tone(Tone {
+            gamma: self.tone.gamma + delta,
+            ..self.tone
+        });
+        self.tone.gamma
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frontend::palette::builtin_palettes;
+    use crate::frontend::palette::parse_themes;
+    use crate::frontend::palette::Palettes;
+    use crate::frontend::palette::Rgb;
+    use crate::frontend::palette::Tone;
+    #[test]
+    fn parse_toml_theme_fills_missing_planes() {
+        let content = r##"
+            [[themes]]
+            name = "amber"
+            background = "#100800"
+            planes = ["#FFB000"]
+        "##;
+        let themes = parse_themes(content, false).unwrap();
+        assert_eq!(themes[0].name, "amber");
+        assert_eq!(themes[0].background, Rgb(0x10, 0x08, 0x00));
+        assert_eq!(themes[0].planes, [Rgb(0xFF, 0xB0, 0x00); 3]);
+    }
+    #[test]
+    fn parse_json_theme_with_all_planes() {
+        let content = r##"{"themes": [{"name": "xo", "background": "#000000",
+            "planes": ["#ff0000", "#00ff00", "#0000ff"]}]}"##;
+        let themes = parse_themes(content, true).unwrap();
+        assert_eq!(themes[0].planes[1], Rgb(0, 255, 0));
+        assert_eq!(themes[0].planes[2], Rgb(0, 0, 255));
+    }
+    #[test]
+    fn parse_theme_rejects_invalid_color() {
+        let content = r##"
+            [[themes]]
+            name = "broken"
+            background = "#12345"
+            planes = ["#FFFFFF"]
+        "##;
+        assert!(parse_themes(content, false).is_err());
+    }
+    #[test]
+    fn cycle_wraps_around() {
+        let mut themes = builtin_palettes();
+        themes.extend(
+            parse_themes(
+                "[[themes]]\nname = \"green\"\nbackground = \"#000000\"\nplanes = [\"#00FF00\"]",
+                false,
+            )
+            .unwrap(),
+        );
+        let mut palettes = Palettes::new(themes);
+        palettes.select("green").unwrap();
+        assert_eq!(palettes.current().name, "green");
+        palettes.cycle();
+        assert_eq!(palettes.current().name, "classic");
+        let error = palettes.select("missing").unwrap_err();
+        assert!(error.contains("classic, high-contrast,"));
+        assert!(error.contains("protanopia, green"));
+    }
+    #[test]
+    fn builtin_palettes_are_readable() {
+        // WCAG contrast ratio between each plane color and the background,
+        // except for the classic palette which dims planes 2 and 3 on purpose.
+        let luminance = |color: Rgb| {
+            let linear = |c: u8| {
+                let c = c as f32 / 255.0;
+                if c <= 0.03928 {
+                    c / 12.92
+                } else {
+                    ((c + 0.055) / 1.055).powf(2.4)
+                }
+            };
+            0.2126 * linear(color.0) + 0.7152 * linear(color.1) + 0.0722 * linear(color.2)
+        };
+        for palette in builtin_palettes().into_iter().skip(1) {
+            let background = luminance(palette.background);
+            for plane in palette.planes {
+                let (light, dark) = match luminance(plane) {
+                    l if l > background => (l, background),
+                    l => (background, l),
+                };
+                let ratio = (light + 0.05) / (dark + 0.05);
+                assert!(
+                    ratio >= 4.5,
+                    "{}: {:?} has contrast {}",
+                    palette.name,
+                    plane,
+                    ratio
+                );
+            }
+        }
+    }
+    #[test]
+    fn plane_color_maps_plane_combinations() {
+        let palette = &builtin_palettes()[0];
+        assert_eq!(palette.plane_color(0b00), palette.background);
+        assert_eq!(palette.plane_color(0b01), palette.planes[0]);
+        assert_eq!(palette.plane_color(0b10), palette.planes[1]);
+        assert_eq!(palette.plane_color(0b11), palette.planes[2]);
+    }
+    #[test]
+    fn invert_swaps_background_and_foreground() {
+        let mut palettes = Palettes::new(builtin_palettes());
+        assert!(palettes.invert());
+        assert_eq!(palettes.current().background, Rgb(255, 255, 255));
+        assert_eq!(palettes.current().foreground(), Rgb(0, 0, 0));
+        assert!(!palettes.invert());
+        assert_eq!(palettes.current(), &builtin_palettes()[0]);
+    }
+    #[test]
+    fn tone_dims_and_lifts_midtones() {
+        let dim = Tone {
+            brightness: 0.5,
+            gamma: 1.0,
+        };
+        assert_eq!(dim.apply(Rgb(255, 100, 0)), Rgb(128, 50, 0));
+        let lifted = Tone {
+            brightness: 1.0,
+            gamma: 2.0,
+        };
+        assert_eq!(lifted.apply(Rgb(255, 64, 0)), Rgb(255, 128, 0));
+    }
+    #[test]
+    fn tone_applies_to_every_palette() {
+        let mut palettes = Palettes::new(builtin_palettes());
+        assert_eq!(palettes.adjust_brightness(-0.5), 0.5);
+        assert_eq!(palettes.current().foreground(), Rgb(128, 128, 128));
+        palettes.cycle();
+        assert_eq!(palettes.current().foreground(), Rgb(128, 128, 0));
+        assert_eq!(palettes.adjust_brightness(-1.0), 0.1);
+    }
+}
@@ -0,0 +1,134 @@
+use crate::chip8::display::DisplayBuffer;
+use crate::chip8::display::RES_HEIGHT;
+use crate::chip8::display::RES_WIDTH;
+use crate::Args;
+
+enum Mode {
+    // Pixels that are turned off lose this much intensity every frame.
+    Fade(f32),
+    // Every frame is mixed with the previous one, which gets this weight.
+    // The intensity holds the previous frame and latest the current one.
+    Blend { weight: f32, latest: Vec<bool> },
+}
+
+// Keeps a per-pixel intensity so that pixels do not vanish as soon as they
+// are turned off: either they fade out over a fixed number of frames, or the
+// previous frame is blended into the current one, which hides the flicker of
+// sprites being redrawn without leaving long trails behind moving ones.
+pub struct Phosphor {
+    intensity: Vec<f32>,
+    mode: Mode,
+}
+
+impl Phosphor {
+    pub fn new(frames: u8, len: usize) -> Self {
+        Self {
+            intensity: vec![0.0; len],
+            mode: Mode::Fade(1.0 / frames.max(1) as f32),
+        }
+    }
+
+    pub fn blend(weight: f32, len: usize) -> Self {
+        Self {
+            intensity: vec![0.0; len],
+            mode: Mode::Blend {
+                weight: weight.clamp(0.0, 1.0),
+                latest: vec![false; len],
+            },
+        }
+    }
+
+    pub fn from_args(args: &Args) -> Option<Self> {
+        let len = RES_WIDTH * RES_HEIGHT;
+        match (args.phosphor, args.frame_blend) {
+            (Some(frames), _) => Some(Self::new(frames, len)),
+            (None, Some(weight)) => Some(Self::blend(weight, len)),
+            (None, None) => None,
+        }
+    }
+
+    // Forgets the afterglow, as when the interpreter is reset.
+    pub fn clear(&mut self) {
+        self.intensity.fill(0.0);
+        if let Mode::Blend { latest, .. } = &mut self.mode {
+            latest.fill(false);
+        }
+    }
+
+    // Returns whether any pixel is still fading, that is whether the next
+    // frame will look different even if the display does not change.
+    pub fn update(&mut self, buffer: &DisplayBuffer) -> bool {
+        let mut fading = false;
+        match &mut self.mode {
+            Mode::Fade(decay) => {
+                for (level, lit) in self.intensity.iter_mut().zip(buffer.iter()) {
+                    if *lit {
+                        *level = 1.0;
+                    } else if *level > 0.0 {
+                        *level = (*level - *decay).max(0.0);
+                        fading = true;
+                    }
+                }
+            }
+            Mode::Blend { latest, .. } => {
+                for ((level, previous), lit) in self
+                    .intensity
+                    .iter_mut()
+                    .zip(latest.iter_mut())
+                    .zip(buffer.iter())
+                {
+                    *level = if *previous { 1.0 } else { 0.0 };
+                    fading |= *previous != *lit;
+                    *previous = *lit;
+                }
+            }
+        }
+        fading
+    }
+
+    pub fn level(&self, index: usize, lit: bool) -> f32 {
+        match self.mode {
+            Mode::Fade(_) if lit => 1.0,
+            Mode::Fade(_) => self.intensity[index],
+            Mode::Blend { weight, .. } => {
+                let current = if lit { 1.0 } else { 0.0 };
+                current * (1.0 - weight) + self.intensity[index] * weight
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::display::RES_HEIGHT;
+    use crate::chip8::display::RES_WIDTH;
+    use crate::frontend::phosphor::Phosphor;
+    #[test]
+    fn unlit_pixel_fades_over_frames() {
+        let mut phosphor = Phosphor::new(4, RES_WIDTH * RES_HEIGHT);
+        let mut buffer = [false; RES_WIDTH * RES_HEIGHT];
+        buffer[3] = true;
+        phosphor.update(&buffer);
+        assert_eq!(phosphor.level(3, true), 1.0);
+        buffer[3] = false;
+        phosphor.update(&buffer);
+        assert_eq!(phosphor.level(3, false), 0.75);
+        for _ in 0..3 {
+            phosphor.update(&buffer);
+        }
+        assert_eq!(phosphor.level(3, false), 0.0);
+    }
+    #[test]
+    fn blend_mixes_the_previous_frame() {
+        let mut phosphor = Phosphor::blend(0.25, RES_WIDTH * RES_HEIGHT);
+        let mut buffer = [false; RES_WIDTH * RES_HEIGHT];
+        buffer[3] = true;
+        assert!(phosphor.update(&buffer));
+        assert_eq!(phosphor.level(3, true), 0.75);
+        buffer[3] = false;
+        assert!(phosphor.update(&buffer));
+        assert_eq!(phosphor.level(3, false), 0.25);
+        assert!(!phosphor.update(&buffer));
+        assert_eq!(phosphor.level(3, false), 0.0);
+    }
+}
@@ -0,0 +1,105 @@
+#[cfg(feature = "sdl")]
+use crate::chip8::Chip8;
+use crate::download;
+use std::fs;
+use std::path::Path;
+#[cfg(feature = "sdl")]
+use std::path::PathBuf;
+
+// The ROMs in the arguments, with the lists among them, as .m3u files name
+// them, replaced by what they list.
+pub fn expand(args: &[String]) -> Result<Vec<String>, String> {
+    let mut roms = Vec::new();
+    for arg in args {
+        let path = Path::new(arg);
+        let list = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("m3u"));
+        if !list || download::is_url(arg) {
+            roms.push(arg.clone());
+            continue;
+        }
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("unable to read {}: {}", path.display(), e))?;
+        roms.extend(parse(&content, path.parent().unwrap_or(Path::new(""))));
+    }
+    Ok(roms)
+}
+
+// One ROM a line, relative to the directory of the list unless absolute,
+// skipping blank lines and the comments and directives that start with #.
+fn parse(content: &str, dir: &Path) -> Vec<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| dir.join(line).to_string_lossy().into_owned())
+        .collect()
+}
+
+// The ROMs to switch between while playing, each picking up where it was
+// left when it comes round again.
+#[cfg(feature = "sdl")]
+pub struct Playlist {
+    roms: Vec<PathBuf>,
+    current: usize,
+    // The machine of each ROM left for another one.
+    saved: Vec<Option<Chip8>>,
+}
+
+#[cfg(feature = "sdl")]
+impl Playlist {
+    pub fn new(roms: &[String]) -> Self {
+        Self {
+            roms: roms.iter().map(PathBuf::from).collect(),
+            current: 0,
+            saved: vec![None; roms.len()],
+        }
+    }
+
+    // Keeps the machine of the ROM playing and turns to the one steps away,
+    // wrapping around at either end, along with its machine if it was played
+    // already.
+    pub fn switch(&mut self, steps: isize, chip8: &Chip8) -> (&Path, Option<Chip8>) {
+        self.saved[self.current] = Some(chip8.clone());
+        let len = self.roms.len() as isize;
+        self.current = (self.current as isize + steps).rem_euclid(len) as usize;
+        (&self.roms[self.current], self.saved[self.current].take())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "sdl")]
+    use crate::chip8::Chip8;
+    use crate::frontend::playlist::parse;
+    #[cfg(feature = "sdl")]
+    use crate::frontend::playlist::Playlist;
+    use std::path::Path;
+    #[test]
+    fn parse_resolves_entries_next_to_the_list() {
+        let content = "#EXTM3U\n\nPONG\n  /roms/TETRIS \n# games\n";
+        assert_eq!(
+            parse(content, Path::new("night")),
+            ["night/PONG", "/roms/TETRIS"]
+        );
+    }
+    #[cfg(feature = "sdl")]
+    #[test]
+    fn switch_resumes_the_roms_played_already() {
+        let roms = [String::from("PONG"), String::from("TETRIS")];
+        let mut playlist = Playlist::new(&roms);
+        let mut pong = Chip8::new();
+        pong.load(&[0x12, 0x00]).unwrap();
+        pong.fetch_execute();
+        let (path, saved) = playlist.switch(1, &pong);
+        assert_eq!(path, Path::new("TETRIS"));
+        assert!(saved.is_none());
+        let (path, saved) = playlist.switch(-1, &Chip8::new());
+        assert_eq!(path, Path::new("PONG"));
+        assert_eq!(saved.unwrap().cycles(), 1);
+        let (path, saved) = playlist.switch(-1, &pong);
+        assert_eq!(path, Path::new("TETRIS"));
+        assert_eq!(saved.unwrap().cycles(), 0);
+    }
+}
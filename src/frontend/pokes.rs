@@ -0,0 +1,88 @@
+use crate::chip8::Chip8;
+use crate::chip8::RAM_SIZE;
+use crate::Args;
+
+// Bytes written into memory once the ROM is loaded, to try a change or work
+// around a known bug without editing the file. Frozen, they are written
+// again every frame, so that the program cannot change them back, as for
+// infinite lives.
+pub struct Pokes {
+    pokes: Vec<(u16, u8)>,
+    freeze: bool,
+}
+
+fn hex(text: &str) -> Option<u32> {
+    let digits = text.strip_prefix("0x").unwrap_or(text);
+    u32::from_str_radix(digits, 16).ok()
+}
+
+// Reads an address and the byte to write there, both in hexadecimal, as in
+// 0x2A4=0x00.
+pub fn parse_poke(text: &str) -> Result<(u16, u8), String> {
+    let (address, value) = text
+        .split_once('=')
+        .ok_or_else(|| String::from("expected ADDR=VAL"))?;
+    let address = hex(address)
+        .filter(|address| (*address as usize) < RAM_SIZE)
+        .ok_or_else(|| format!("'{}' is not an address from 0x000 to 0xFFF", address))?;
+    let value = hex(value)
+        .and_then(|value| u8::try_from(value).ok())
+        .ok_or_else(|| format!("'{}' is not a byte from 0x00 to 0xFF", value))?;
+    Ok((address as u16, value))
+}
+
+impl Pokes {
+    pub fn from_args(args: &Args) -> Option<Self> {
+        if args.pokes.is_empty() {
+            return None;
+        }
+        Some(Self {
+            pokes: args.pokes.clone(),
+            freeze: args.freeze,
+        })
+    }
+
+    pub fn apply(&self, chip8: &mut Chip8) {
+        for &(address, value) in &self.pokes {
+            chip8.ram[address as usize] = value;
+        }
+    }
+
+    // Writes the bytes again at the end of a frame when they are frozen.
+    pub fn tick(&self, chip8: &mut Chip8) {
+        if self.freeze {
+            self.apply(chip8);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::Chip8;
+    use crate::frontend::pokes::parse_poke;
+    use crate::frontend::pokes::Pokes;
+    #[test]
+    fn parse_poke_reads_hexadecimal() {
+        assert_eq!(parse_poke("0x2A4=0x00"), Ok((0x2A4, 0x00)));
+        assert_eq!(parse_poke("3ff=Ff"), Ok((0x3FF, 0xFF)));
+        assert!(parse_poke("0x1000=1").is_err());
+        assert!(parse_poke("0x200=0x100").is_err());
+        assert!(parse_poke("0x200").is_err());
+    }
+    #[test]
+    fn tick_writes_again_only_when_frozen() {
+        let mut chip8 = Chip8::new();
+        let mut pokes = Pokes {
+            pokes: vec![(0x300, 9)],
+            freeze: false,
+        };
+        pokes.apply(&mut chip8);
+        assert_eq!(chip8.ram[0x300], 9);
+        chip8.ram[0x300] = 0;
+        pokes.tick(&mut chip8);
+        assert_eq!(chip8.ram[0x300], 0);
+        pokes.freeze = true;
+        pokes.tick(&mut chip8);
+        assert_eq!(chip8.ram[0x300], 9);
+    }
+}
@@ -0,0 +1,226 @@
+use crate::config::DiscordOptions;
+use serde_json::json;
+use serde_json::Value;
+use std::env;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use tracing::debug;
+use tracing::info;
+use tracing::warn;
+
+// The opcodes of the frames Discord's local IPC speaks.
+const HANDSHAKE: u32 = 0;
+const FRAME: u32 = 1;
+
+// How long to wait before looking for Discord again when it is not running
+// or goes away.
+const RETRY: Duration = Duration::from_secs(15);
+
+// A message to Discord: the opcode, the length and then the JSON, with the
+// numbers little-endian.
+fn frame(op: u32, payload: &Value) -> Vec<u8> {
+    let payload = payload.to_string().into_bytes();
+    let mut frame = Vec::with_capacity(8 + payload.len());
+    frame.extend_from_slice(&op.to_le_bytes());
+    frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    frame.extend_from_slice(&payload);
+    frame
+}
+
+// Reads the answer to a frame, which Discord sends for each of them.
+fn read_frame(input: &mut impl Read) -> std::io::Result<(u32, Value)> {
+    let mut header = [0; 8];
+    input.read_exact(&mut header)?;
+    let op = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+    let length = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    let mut payload = Vec::new();
+    input.take(length as u64).read_to_end(&mut payload)?;
+    Ok((op, serde_json::from_slice(&payload).unwrap_or(Value::Null)))
+}
+
+// The activity showing the ROM and the time played since it started, or
+// clearing it when nothing is played.
+fn set_activity(rom: Option<&str>, started: u64, nonce: u64) -> Value {
+    let activity = rom.map(|rom| {
+        json!({
+            "details": rom,
+            "state": "CHIP-8",
+            "timestamps": { "start": started },
+        })
+    });
+    json!({
+        "cmd": "SET_ACTIVITY",
+        "args": { "pid": std::process::id(), "activity": activity },
+        "nonce": nonce.to_string(),
+    })
+}
+
+// Where Discord listens for local clients: a socket in the runtime or
+// temporary directory on Unix, a named pipe on Windows, numbered from 0 for
+// each instance running.
+#[cfg(unix)]
+fn connect() -> io::Result<Box<dyn Pipe>> {
+    let dir = ["XDG_RUNTIME_DIR", "TMPDIR", "TMP", "TEMP"]
+        .iter()
+        .find_map(env::var_os)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("/tmp"));
+    let mut last = io::Error::from(io::ErrorKind::NotFound);
+    for index in 0..10 {
+        match std::os::unix::net::UnixStream::connect(dir.join(format!("discord-ipc-{}", index))) {
+            Ok(stream) => return Ok(Box::new(stream)),
+            Err(e) => last = e,
+        }
+    }
+    Err(last)
+}
+
+#[cfg(windows)]
+fn connect() -> io::Result<Box<dyn Pipe>> {
+    let mut last = io::Error::from(io::ErrorKind::NotFound);
+    for index in 0..10 {
+        match std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(format!(r"\\.\pipe\discord-ipc-{}", index))
+        {
+            Ok(pipe) => return Ok(Box::new(pipe)),
+            Err(e) => last = e,
+        }
+    }
+    Err(last)
+}
+
+trait Pipe: Read + Write + Send {}
+
+impl<T: Read + Write + Send> Pipe for T {}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+// Takes the ROMs played since Discord was last told, so that it is only told
+// of the last one.
+fn catch_up(playing: &Receiver<Option<String>>, rom: &mut Option<String>, started: &mut u64) {
+    for next in playing.try_iter() {
+        *rom = next;
+        *started = now();
+    }
+}
+
+// Tells Discord what is played until the emulator quits, looking for it
+// again whenever it is not there.
+fn publish(client_id: &str, playing: Receiver<Option<String>>) {
+    let mut rom: Option<String> = None;
+    let mut started = now();
+    let mut nonce = 0;
+    loop {
+        let mut pipe = match connect() {
+            Ok(pipe) => pipe,
+            Err(e) => {
+                debug!("Discord is not running: {}", e);
+                thread::sleep(RETRY);
+                catch_up(&playing, &mut rom, &mut started);
+                continue;
+            }
+        };
+        let handshake = json!({ "v": 1, "client_id": client_id });
+        if pipe.write_all(&frame(HANDSHAKE, &handshake)).is_err() {
+            thread::sleep(RETRY);
+            continue;
+        }
+        match read_frame(&mut pipe) {
+            Ok((FRAME, ready)) if ready["evt"] == "READY" => info!("showing the ROM on Discord"),
+            Ok((_, refused)) => {
+                warn!("Discord presence disabled: {}", refused["message"]);
+                return;
+            }
+            Err(_) => {
+                thread::sleep(RETRY);
+                continue;
+            }
+        }
+        catch_up(&playing, &mut rom, &mut started);
+        loop {
+            nonce += 1;
+            let activity = set_activity(rom.as_deref(), started, nonce);
+            if pipe.write_all(&frame(FRAME, &activity)).is_err() || read_frame(&mut pipe).is_err() {
+                debug!("Discord went away");
+                break;
+            }
+            match playing.recv() {
+                Ok(next) => {
+                    rom = next;
+                    started = now();
+                }
+                Err(_) => return,
+            }
+        }
+    }
+}
+
+// The ROM being played and for how long, shown on the user's Discord
+// profile for friends to see.
+pub struct Presence {
+    playing: Sender<Option<String>>,
+}
+
+impl Presence {
+    pub fn from_config(options: &DiscordOptions) -> Option<Self> {
+        if !options.presence {
+            return None;
+        }
+        let Some(client_id) = options.client_id.clone() else {
+            warn!("Discord presence disabled, [discord] needs a client_id");
+            return None;
+        };
+        let (sender, playing) = mpsc::channel();
+        thread::spawn(move || publish(&client_id, playing));
+        Some(Self { playing: sender })
+    }
+
+    // Shows a ROM from now on, restarting the time played, or nothing.
+    pub fn playing(&self, rom: &str) {
+        let rom = (!rom.is_empty()).then(|| String::from(rom));
+        let _ = self.playing.send(rom);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frontend::presence::frame;
+    use crate::frontend::presence::read_frame;
+    use crate::frontend::presence::set_activity;
+    use crate::frontend::presence::FRAME;
+    use serde_json::json;
+    #[test]
+    fn frames_carry_the_opcode_and_length() {
+        let payload = json!({ "v": 1 });
+        let bytes = frame(FRAME, &payload);
+        assert_eq!(&bytes[..8], &[1, 0, 0, 0, 7, 0, 0, 0]);
+        assert_eq!(read_frame(&mut &bytes[..]).unwrap(), (FRAME, payload));
+    }
+    #[test]
+    fn set_activity_shows_the_rom_and_time() {
+        let activity = set_activity(Some("pong.ch8"), 1_700_000_000, 3);
+        assert_eq!(activity["cmd"], "SET_ACTIVITY");
+        assert_eq!(activity["nonce"], "3");
+        assert_eq!(activity["args"]["activity"]["details"], "pong.ch8");
+        assert_eq!(
+            activity["args"]["activity"]["timestamps"]["start"],
+            1_700_000_000
+        );
+        assert!(set_activity(None, 0, 4)["args"]["activity"].is_null());
+    }
+}
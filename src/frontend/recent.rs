@@ -0,0 +1,152 @@
+use crate::config::portable;
+use serde::Deserialize;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use tracing::warn;
+
+// How many ROMs the list keeps.
+const LIMIT: usize = 10;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Played {
+    pub path: PathBuf,
+    // Seconds since the Unix epoch.
+    pub time: u64,
+    pub speed: u32,
+    pub palette: String,
+}
+
+// The ROMs played last, the latest first, along with the settings they
+// started with.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Recent {
+    #[serde(rename = "rom")]
+    pub roms: Vec<Played>,
+}
+
+impl Recent {
+    pub fn default_path() -> Option<PathBuf> {
+        portable::data_dir().map(|dir| dir.join("recent.toml"))
+    }
+
+    // Nothing has been played yet when the file is missing.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("unable to read {}: {}", path.display(), e))?;
+        toml::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let error = |e: std::io::Error| format!("unable to write {}: {}", path.display(), e);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(error)?;
+        }
+        fs::write(path, toml::to_string(self).unwrap()).map_err(error)
+    }
+
+    // Puts the ROM at the top, dropping the oldest ones past the limit.
+    pub fn add(&mut self, played: Played) {
+        self.roms.retain(|rom| rom.path != played.path);
+        self.roms.insert(0, played);
+        self.roms.truncate(LIMIT);
+    }
+
+    // The list at the usual location, empty if it cannot be read.
+    pub fn find() -> Self {
+        let Some(path) = Self::default_path() else {
+            return Self::default();
+        };
+        Self::load(&path).unwrap_or_else(|error| {
+            warn!("recent ROMs ignored, {}", error);
+            Self::default()
+        })
+    }
+
+    // Adds the ROM to the list at the usual location, by its full path so
+    // that it can be found again from anywhere.
+    pub fn record(rom: &Path, speed: u32, palette: &str) {
+        let Some(path) = Self::default_path() else {
+            return;
+        };
+        let mut recent = Self::find();
+        recent.add(Played {
+            path: fs::canonicalize(rom).unwrap_or(rom.to_path_buf()),
+            time: now(),
+            speed,
+            palette: String::from(palette),
+        });
+        if let Err(error) = recent.save(&path) {
+            warn!("recent ROMs not saved, {}", error);
+        }
+    }
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default()
+}
+
+// How long ago something happened, roughly, as in "3 hours ago".
+pub fn ago(time: u64, now: u64) -> String {
+    let seconds = now.saturating_sub(time);
+    let (count, unit) = match seconds {
+        0..=59 => return String::from("just now"),
+        60..=3599 => (seconds / 60, "minute"),
+        3600..=86399 => (seconds / 3600, "hour"),
+        _ => (seconds / 86400, "day"),
+    };
+    format!(
+        "{} {}{} ago",
+        count,
+        unit,
+        if count == 1 { "" } else { "s" }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frontend::recent::ago;
+    use crate::frontend::recent::Played;
+    use crate::frontend::recent::Recent;
+    use std::path::Path;
+    use std::path::PathBuf;
+    #[test]
+    fn add_keeps_the_latest_first() {
+        let played = |name: &str, time| Played {
+            path: PathBuf::from(name),
+            time,
+            speed: 1000,
+            palette: String::from("classic"),
+        };
+        let mut recent = Recent::default();
+        for time in 0..12 {
+            recent.add(played(&format!("rom{}", time), time));
+        }
+        recent.add(played("rom5", 12));
+        assert_eq!(recent.roms.len(), 10);
+        assert_eq!(recent.roms[0], played("rom5", 12));
+        assert_eq!(recent.roms[1].path, PathBuf::from("rom11"));
+        assert!(recent.roms.iter().all(|rom| rom.path != Path::new("rom1")));
+        let saved = toml::to_string(&recent).unwrap();
+        assert!(saved.starts_with("[[rom]]\npath = \"rom5\"\ntime = 12\n"));
+        assert_eq!(toml::from_str::<Recent>(&saved).unwrap(), recent);
+    }
+    #[test]
+    fn ago_rounds_down() {
+        assert_eq!(ago(100, 130), "just now");
+        assert_eq!(ago(0, 60), "1 minute ago");
+        assert_eq!(ago(0, 7300), "2 hours ago");
+        assert_eq!(ago(0, 86400 * 3 + 5), "3 days ago");
+        assert_eq!(ago(200, 100), "just now");
+    }
+}
@@ -0,0 +1,17 @@
+use crate::frontend::crt::CrtOptions;
+use crate::frontend::layout::Viewport;
+use crate::frontend::palette::Palette;
+use crate::frontend::text::Overlay;
+use sdl2::video::Window;
+
+// Draws frames into the SDL window. The window itself stays with the renderer
+// because the canvas backend needs to own it, so window management such as
+// fullscreen toggling goes through window_mut.
+pub trait Renderer {
+    fn window_mut(&mut self) -> &mut Window;
+
+    // Where the display is drawn, in pixels of the drawable.
+    fn viewport(&self) -> Viewport;
+
+    fn render(&mut self, levels: &[f32], palette: &Palette, crt: &CrtOptions, overlay: &Overlay);
+}
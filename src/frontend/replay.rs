@@ -0,0 +1,221 @@
+use crate::chip8::quirks::Quirks;
+use crate::chip8::Chip8;
+use crate::frontend::runner::Runner;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+// How often the machine is kept to replay from.
+const CHECKPOINT_EVERY: Duration = Duration::from_secs(1);
+
+// What a frame ran with: the time it made up for, how much faster than real
+// time, and the keypad as the player held it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Step {
+    pub elapsed: Duration,
+    pub scale: f64,
+    pub keys: u16,
+}
+
+// The machine and the runner as they were, with the frames run from there.
+struct Checkpoint {
+    chip8: Chip8,
+    runner: Runner,
+    steps: Vec<Step>,
+    // The time the steps make up.
+    length: Duration,
+}
+
+// The last seconds of play, kept as checkpoints a second apart along with
+// the frames run since each, so that when a quirk is turned on or off they
+// run again from far enough back under it, with the keys as they were
+// pressed, showing what the quirk changes. Keys pressed by macros and input
+// scripts straight on the machine are not part of it.
+pub struct Replay {
+    span: Duration,
+    checkpoints: VecDeque<Checkpoint>,
+    // The frames left to run again, while replaying.
+    pending: VecDeque<Step>,
+    replaying: bool,
+    // The keypad as the replay holds it.
+    held: u16,
+}
+
+impl Replay {
+    pub fn new(span: Duration) -> Self {
+        Self {
+            span,
+            checkpoints: VecDeque::new(),
+            pending: VecDeque::new(),
+            replaying: false,
+            held: 0,
+        }
+    }
+
+    // Whether the frames run are those of a replay, which the player's keys
+    // are held back from.
+    pub fn replaying(&self) -> bool {
+        self.replaying
+    }
+
+    // Forgets what was played, as when the machine starts over. A replay
+    // under way ends with the next frame.
+    pub fn clear(&mut self) {
+        self.checkpoints.clear();
+        self.pending.clear();
+    }
+
+    fn hold(&mut self, chip8: &mut Chip8, keys: u16) {
+        for key in 0..16 {
+            let down = keys & 1 << key != 0;
+            if down != (self.held & 1 << key != 0) {
+                chip8.key_pressed(key, down);
+            }
+        }
+        self.held = keys;
+    }
+
+    // The frame to run next: the one recorded next while replaying, or else
+    // the one played, which is recorded along with a checkpoint every second.
+    // The keypad is held as the frame replayed has it, and as the player
+    // holds it once the replay is over.
+    pub fn next(&mut self, chip8: &mut Chip8, runner: &Runner, played: Step) -> Step {
+        let step = match self.pending.pop_front() {
+            Some(step) => {
+                self.hold(chip8, step.keys);
+                step
+            }
+            None if self.replaying => {
+                self.replaying = false;
+                self.hold(chip8, played.keys);
+                played
+            }
+            None => played,
+        };
+        let due = self
+            .checkpoints
+            .back()
+            .is_none_or(|last| last.length >= CHECKPOINT_EVERY);
+        if due {
+            self.checkpoints.push_back(Checkpoint {
+                chip8: chip8.clone(),
+                runner: runner.clone(),
+                steps: Vec::new(),
+                length: Duration::ZERO,
+            });
+        }
+        let last = self.checkpoints.back_mut().unwrap();
+        last.steps.push(step);
+        last.length += step.elapsed;
+        // The oldest checkpoint goes once the ones after it go back far
+        // enough by themselves.
+        while self.checkpoints.len() > 1
+            && self
+                .checkpoints
+                .iter()
+                .skip(1)
+                .map(|checkpoint| checkpoint.length)
+                .sum::<Duration>()
+                >= self.span
+        {
+            self.checkpoints.pop_front();
+        }
+        step
+    }
+
+    // Goes back to the last checkpoint at least the span ago, or else the
+    // oldest, with the quirks given, to run the frames from there again.
+    // Answers with how far back that is, nothing when there is nowhere to
+    // go back to.
+    pub fn rewind(
+        &mut self,
+        chip8: &mut Chip8,
+        runner: &mut Runner,
+        quirks: Quirks,
+    ) -> Option<Duration> {
+        let mut back = Duration::ZERO;
+        let mut start = None;
+        for (index, checkpoint) in self.checkpoints.iter().enumerate().rev() {
+            back += checkpoint.length;
+            start = Some(index);
+            if back >= self.span {
+                break;
+            }
+        }
+        let mut checkpoints = self.checkpoints.split_off(start?);
+        self.checkpoints.clear();
+        let first = checkpoints.pop_front().unwrap();
+        // Frames not yet replayed when rewinding again come after the rest.
+        let mut pending: VecDeque<Step> = first.steps.into_iter().collect();
+        for checkpoint in checkpoints {
+            pending.extend(checkpoint.steps);
+        }
+        pending.append(&mut self.pending);
+        self.held = pending.front().map_or(0, |step| step.keys);
+        self.pending = pending;
+        self.replaying = true;
+        *chip8 = first.chip8;
+        chip8.set_quirks(quirks);
+        *runner = first.runner;
+        Some(back)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::hash::state;
+    use crate::chip8::Chip8;
+    use crate::frontend::replay::Replay;
+    use crate::frontend::replay::Step;
+    use crate::frontend::runner::Runner;
+    use crate::frontend::runner::TIMER_SPEED;
+    use std::time::Duration;
+    // Waits for a key with Fx0A, then counts instructions up from it in V0.
+    const ROM: [u8; 6] = [0xF0, 0x0A, 0x70, 0x01, 0x12, 0x02];
+    fn frame(replay: &mut Replay, chip8: &mut Chip8, runner: &mut Runner, keys: u16) {
+        let step = replay.next(
+            chip8,
+            runner,
+            Step {
+                elapsed: Duration::from_secs(1) / TIMER_SPEED,
+                scale: 1.0,
+                keys,
+            },
+        );
+        runner.set_scale(step.scale);
+        runner.advance(chip8, step.elapsed);
+    }
+    #[test]
+    fn rewind_runs_the_frames_again_the_same_way() {
+        let mut chip8 = Chip8::new();
+        chip8.load(&ROM).unwrap();
+        let mut runner = Runner::new(600);
+        let mut replay = Replay::new(Duration::from_secs(1));
+        for index in 0..150 {
+            let keys = if index == 100 { 1 << 5 } else { 0 };
+            chip8.key_pressed(5, keys != 0);
+            frame(&mut replay, &mut chip8, &mut runner, keys);
+        }
+        assert!(replay.checkpoints.len() <= 3);
+        let expected = state(&mut chip8);
+        let quirks = chip8.quirks();
+        let back = replay.rewind(&mut chip8, &mut runner, quirks).unwrap();
+        assert!(back >= Duration::from_secs(1));
+        assert!(replay.replaying());
+        let frames = back.as_nanos() / (Duration::from_secs(1) / TIMER_SPEED).as_nanos();
+        for _ in 0..frames {
+            frame(&mut replay, &mut chip8, &mut runner, 0);
+        }
+        assert!(replay.replaying());
+        assert_eq!(state(&mut chip8), expected);
+        frame(&mut replay, &mut chip8, &mut runner, 0);
+        assert!(!replay.replaying());
+    }
+    #[test]
+    fn rewind_needs_a_checkpoint() {
+        let mut chip8 = Chip8::new();
+        let mut runner = Runner::new(600);
+        let mut replay = Replay::new(Duration::from_secs(1));
+        let quirks = chip8.quirks();
+        assert_eq!(replay.rewind(&mut chip8, &mut runner, quirks), None);
+    }
+}
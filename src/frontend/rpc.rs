@@ -0,0 +1,414 @@
+use crate::chip8::disasm::disassemble;
+use crate::chip8::Chip8;
+use crate::chip8::RAM_SIZE;
+use crate::failure::Failure;
+use crate::frontend::control::State;
+use crate::frontend::control::MAX_STEP;
+use crate::frontend::runner::Runner;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::json;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::thread;
+use tracing::debug;
+use tracing::info;
+
+// The error codes of JSON-RPC 2.0.
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+// The first of the codes left to servers, for a machine not profiled.
+const NOT_PROFILED: i64 = -32000;
+// An instruction the interpreter failed on while stepping.
+const CRASHED: i64 = -32001;
+
+#[derive(Deserialize)]
+struct Request {
+    // Notifications have none, and get no answer.
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Deserialize)]
+struct Count {
+    #[serde(default = "one")]
+    count: u32,
+}
+
+fn one() -> u32 {
+    1
+}
+
+#[derive(Deserialize)]
+struct Top {
+    #[serde(default = "ten")]
+    count: usize,
+}
+
+fn ten() -> usize {
+    10
+}
+
+#[derive(Deserialize)]
+struct Range {
+    address: u16,
+    #[serde(alias = "count")]
+    length: usize,
+}
+
+#[derive(Deserialize)]
+struct Breakpoints {
+    addresses: Vec<u16>,
+}
+
+fn params<T: DeserializeOwned>(params: &Value) -> Result<T, (i64, String)> {
+    // No params at all stand for the defaults, where there are any.
+    let params = if params.is_null() {
+        json!({})
+    } else {
+        params.clone()
+    };
+    serde_json::from_value(params).map_err(|e| (INVALID_PARAMS, e.to_string()))
+}
+
+// Carries out a method, answering with its result or with an error code
+// and message.
+fn call(
+    method: &str,
+    raw: &Value,
+    chip8: &mut Chip8,
+    runner: &mut Runner,
+    paused: &mut bool,
+) -> Result<Value, (i64, String)> {
+    let state = |chip8: &Chip8, paused| serde_json::to_value(State::of(chip8, paused)).unwrap();
+    match method {
+        "pause" => *paused = true,
+        "continue" => *paused = false,
+        "step" => {
+            let Count { count } = params(raw)?;
+            *paused = true;
+            // As many as the control API steps at once, at most.
+            let stepped = panic::catch_unwind(AssertUnwindSafe(|| {
+                for _ in 0..count.min(MAX_STEP) {
+                    chip8.fetch_execute();
+                }
+            }));
+            if let Err(cause) = stepped {
+                return Err((CRASHED, Failure::crashed(cause).to_string()));
+            }
+        }
+        "registers" => {}
+        "readMemory" => {
+            let Range { address, length } = params(raw)?;
+            let start = (address as usize).min(RAM_SIZE);
+            let end = start.saturating_add(length).min(RAM_SIZE);
+            return Ok(json!({ "address": start, "data": &chip8.ram[start..end] }));
+        }
+        "disassemble" => {
+            let Range { address, length } = params(raw)?;
+            let lines: Vec<Value> = (address as usize..RAM_SIZE - 1)
+                .step_by(2)
+                .take(length)
+                .map(|address| {
+                    let opcode = u16::from_be_bytes([chip8.ram[address], chip8.ram[address + 1]]);
+                    json!({
+                        "address": address,
+                        "opcode": opcode,
+                        "instruction": disassemble(opcode),
+                    })
+                })
+                .collect();
+            return Ok(Value::from(lines));
+        }
+        "setBreakpoints" => {
+            let Breakpoints { addresses } = params(raw)?;
+            *runner.breakpoints() = addresses.into_iter().collect();
+            return Ok(json!({ "addresses": runner.breakpoints() }));
+        }
+        "profile" => {
+            let Top { count } = params(raw)?;
+            let profile = chip8
+                .profile()
+                .ok_or_else(|| (NOT_PROFILED, String::from("the machine is not profiled")))?
+                .lock()
+                .unwrap();
+            let families: Vec<Value> = profile
+                .families()
+                .into_iter()
+                .map(|(family, counts)| {
+                    json!({
+                        "family": family,
+                        "executed": counts.executed,
+                        "vip_cycles": counts.vip_cycles,
+                    })
+                })
+                .collect();
+            let routines: Vec<Value> = profile
+                .routines()
+                .into_iter()
+                .take(count)
+                .map(|(address, counts)| {
+                    json!({
+                        "address": address,
+                        "executed": counts.executed,
+                        "vip_cycles": counts.vip_cycles,
+                    })
+                })
+                .collect();
+            let addresses: Vec<Value> = profile
+                .addresses()
+                .into_iter()
+                .take(count)
+                .map(|(address, opcode, counts)| {
+                    json!({
+                        "address": address,
+                        "opcode": opcode,
+                        "instruction": disassemble(opcode),
+                        "executed": counts.executed,
+                        "vip_cycles": counts.vip_cycles,
+                    })
+                })
+                .collect();
+            let total = profile.total();
+            return Ok(json!({
+                "executed": total.executed,
+                "vip_cycles": total.vip_cycles,
+                "families": families,
+                "routines": routines,
+                "addresses": addresses,
+            }));
+        }
+        _ => return Err((METHOD_NOT_FOUND, format!("no method {}", method))),
+    }
+    Ok(state(chip8, *paused))
+}
+
+// The answer to a line from a client, if it is not a notification.
+fn answer(line: &str, chip8: &mut Chip8, runner: &mut Runner, paused: &mut bool) -> Option<Value> {
+    let request: Request = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => {
+            return Some(json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": { "code": PARSE_ERROR, "message": e.to_string() },
+            }))
+        }
+    };
+    let result = call(&request.method, &request.params, chip8, runner, paused);
+    let id = request.id?;
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": code, "message": message },
+        }),
+    })
+}
+
+enum Event {
+    Joined(usize, TcpStream),
+    Line(usize, String),
+    Left(usize),
+}
+
+// A debugger for editors and web pages to drive, speaking JSON-RPC 2.0 a
+// line at a time over TCP. Clients can pause and step the machine, read its
+// registers and memory, disassemble it and set breakpoints, and are told
+// when one is reached.
+pub struct Rpc {
+    events: Receiver<Event>,
+    clients: BTreeMap<usize, TcpStream>,
+}
+
+impl Rpc {
+    pub fn bind(address: &str) -> Result<Self, String> {
+        let listener = TcpListener::bind(address)
+            .map_err(|e| format!("unable to debug on {}: {}", address, e))?;
+        info!("debugger listening on {}", address);
+        let (sender, events) = mpsc::channel();
+        thread::spawn(move || {
+            for (id, stream) in listener.incoming().flatten().enumerate() {
+                let Ok(writer) = stream.try_clone() else {
+                    continue;
+                };
+                let sender = sender.clone();
+                let _ = sender.send(Event::Joined(id, writer));
+                thread::spawn(move || {
+                    for line in BufReader::new(stream).lines().map_while(Result::ok) {
+                        let _ = sender.send(Event::Line(id, line));
+                    }
+                    let _ = sender.send(Event::Left(id));
+                });
+            }
+        });
+        Ok(Self {
+            events,
+            clients: BTreeMap::new(),
+        })
+    }
+
+    fn send(client: &mut TcpStream, message: &Value) -> bool {
+        writeln!(client, "{}", message).is_ok()
+    }
+
+    // Answers what the clients asked since the last frame, and tells them
+    // when running stopped at a breakpoint, pausing the emulation.
+    pub fn poll(&mut self, chip8: &mut Chip8, runner: &mut Runner, paused: &mut bool) {
+        for event in self.events.try_iter() {
+            match event {
+                Event::Joined(id, client) => {
+                    debug!("debugger client {} connected", id);
+                    self.clients.insert(id, client);
+                }
+                Event::Line(id, line) => {
+                    let Some(answer) = answer(&line, chip8, runner, paused) else {
+                        continue;
+                    };
+                    if let Some(client) = self.clients.get_mut(&id) {
+                        if !Self::send(client, &answer) {
+                            self.clients.remove(&id);
+                        }
+                    }
+                }
+                Event::Left(id) => {
+                    self.clients.remove(&id);
+                }
+            }
+        }
+        if let Some(pc) = runner.take_hit() {
+            *paused = true;
+            let stopped = json!({
+                "jsonrpc": "2.0",
+                "method": "stopped",
+                "params": { "reason": "breakpoint", "pc": pc },
+            });
+            self.clients
+                .retain(|_, client| Self::send(client, &stopped));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::profile::Profile;
+    use crate::chip8::Chip8;
+    use crate::frontend::rpc::answer;
+    use crate::frontend::runner::Runner;
+    use serde_json::json;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use std::time::Duration;
+    #[test]
+    fn answer_follows_json_rpc() {
+        let mut chip8 = Chip8::new();
+        chip8.load(&[0x6A, 0x07, 0x12, 0x00]).unwrap();
+        let mut runner = Runner::new(600);
+        let mut paused = false;
+        let mut answer = |line: &str| answer(line, &mut chip8, &mut runner, &mut paused);
+        let step = answer(r#"{"jsonrpc": "2.0", "id": 1, "method": "step"}"#).unwrap();
+        assert_eq!(step["id"], 1);
+        assert_eq!(step["result"]["v"][10], 7);
+        assert_eq!(step["result"]["paused"], true);
+        let memory = answer(
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "readMemory", "params": {"address": 512, "length": 2}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            memory["result"],
+            json!({"address": 512, "data": [0x6A, 0x07]})
+        );
+        let code = answer(
+            r#"{"jsonrpc": "2.0", "id": 3, "method": "disassemble", "params": {"address": 514, "count": 1}}"#,
+        )
+        .unwrap();
+        assert_eq!(code["result"][0]["instruction"], "JP 200");
+        assert_eq!(answer(r#"{"jsonrpc": "2.0", "method": "pause"}"#), None);
+        let unknown = answer(r#"{"jsonrpc": "2.0", "id": 4, "method": "reboot"}"#).unwrap();
+        assert_eq!(unknown["error"]["code"], -32601);
+        assert_eq!(answer("{").unwrap()["error"]["code"], -32700);
+    }
+    #[test]
+    fn step_answers_an_interpreter_failure() {
+        let mut chip8 = Chip8::new();
+        chip8.load(&[0x60, 0x01, 0xE0, 0x00]).unwrap();
+        let mut runner = Runner::new(600);
+        let mut paused = false;
+        let request =
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "step", "params": {"count": 4000000000}}"#;
+        let failed = answer(request, &mut chip8, &mut runner, &mut paused).unwrap();
+        assert_eq!(failed["error"]["code"], -32001);
+        assert_eq!(
+            failed["error"]["message"],
+            "the interpreter failed: unimplemented 0xe000"
+        );
+        assert!(paused);
+        assert_eq!(chip8.reg_v()[0], 1);
+    }
+    #[test]
+    fn profile_answers_with_the_instructions_most_run() {
+        let mut chip8 = Chip8::new();
+        chip8.load(&[0x70, 0x01, 0x12, 0x00]).unwrap();
+        let mut runner = Runner::new(600);
+        let mut paused = false;
+        let request = r#"{"jsonrpc": "2.0", "id": 1, "method": "profile", "params": {"count": 1}}"#;
+        let unprofiled = answer(request, &mut chip8, &mut runner, &mut paused).unwrap();
+        assert_eq!(unprofiled["error"]["code"], -32000);
+        chip8.set_profile(Arc::new(Mutex::new(Profile::new())));
+        for _ in 0..3 {
+            chip8.fetch_execute();
+        }
+        let profile = answer(request, &mut chip8, &mut runner, &mut paused).unwrap();
+        assert_eq!(profile["result"]["executed"], 3);
+        assert_eq!(profile["result"]["vip_cycles"], 43);
+        assert_eq!(
+            profile["result"]["families"],
+            json!([
+                {"family": "7xkk", "executed": 2, "vip_cycles": 20},
+                {"family": "1nnn", "executed": 1, "vip_cycles": 23}
+            ])
+        );
+        assert_eq!(
+            profile["result"]["addresses"],
+            json!([{
+                "address": 512,
+                "opcode": 0x7001,
+                "instruction": "ADD V0, 01",
+                "executed": 2,
+                "vip_cycles": 20
+            }])
+        );
+    }
+    #[test]
+    fn breakpoints_stop_the_runner_before_the_instruction() {
+        let mut chip8 = Chip8::new();
+        chip8
+            .load(&[0x70, 0x01, 0x70, 0x01, 0x70, 0x01, 0x12, 0x00])
+            .unwrap();
+        let mut runner = Runner::new(600);
+        let mut paused = false;
+        answer(
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "setBreakpoints", "params": {"addresses": [516]}}"#,
+            &mut chip8,
+            &mut runner,
+            &mut paused,
+        );
+        runner.advance(&mut chip8, Duration::from_millis(100));
+        assert_eq!(chip8.pc, 516);
+        assert_eq!(chip8.reg_v()[0], 2);
+        assert_eq!(runner.take_hit(), Some(516));
+    }
+}
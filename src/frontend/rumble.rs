@@ -0,0 +1,74 @@
+use crate::frontend::runner::TIMER_SPEED;
+use sdl2::haptic::Haptic;
+use sdl2::HapticSubsystem;
+use sdl2::JoystickSubsystem;
+use sdl2::Sdl;
+
+// Strong enough to be felt, not so strong it rattles the controller.
+const STRENGTH: f32 = 0.5;
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    // Runs the motor for this many milliseconds.
+    Play(u32),
+    Stop,
+}
+
+// Follows the sound timer, starting the motor for as long as the timer will
+// run whenever it is loaded, and stopping it early if the buzzer is cut.
+fn command(last: u8, timer: u8) -> Option<Command> {
+    if timer > last {
+        Some(Command::Play(timer as u32 * 1000 / TIMER_SPEED))
+    } else if timer == 0 && last > 0 {
+        Some(Command::Stop)
+    } else {
+        None
+    }
+}
+
+// Shakes the first connected controller that can while the buzzer sounds.
+pub struct Rumble {
+    haptic: Haptic,
+    last: u8,
+    _haptic_subsystem: HapticSubsystem,
+    _joystick_subsystem: JoystickSubsystem,
+}
+
+impl Rumble {
+    pub fn open(sdl_context: &Sdl) -> Result<Self, String> {
+        let joystick_subsystem = sdl_context.joystick()?;
+        let haptic_subsystem = sdl_context.haptic()?;
+        let haptic = (0..joystick_subsystem.num_joysticks()?)
+            .find_map(|index| haptic_subsystem.open_from_joystick_id(index).ok())
+            .ok_or("no controller that can rumble found")?;
+        Ok(Self {
+            haptic,
+            last: 0,
+            _haptic_subsystem: haptic_subsystem,
+            _joystick_subsystem: joystick_subsystem,
+        })
+    }
+
+    pub fn update(&mut self, timer: u8) {
+        match command(self.last, timer) {
+            Some(Command::Play(duration)) => self.haptic.rumble_play(STRENGTH, duration),
+            Some(Command::Stop) => self.haptic.rumble_stop(),
+            None => {}
+        }
+        self.last = timer;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frontend::rumble::command;
+    use crate::frontend::rumble::Command;
+    #[test]
+    fn rumbles_for_as_long_as_the_timer() {
+        assert_eq!(command(0, 6), Some(Command::Play(100)));
+        assert_eq!(command(6, 5), None);
+        assert_eq!(command(5, 30), Some(Command::Play(500)));
+        assert_eq!(command(4, 0), Some(Command::Stop));
+        assert_eq!(command(0, 0), None);
+    }
+}
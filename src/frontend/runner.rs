@@ -0,0 +1,194 @@
+use crate::chip8::timing::vip_time;
+use crate::chip8::Chip8;
+use crate::frontend::pacing::Budget;
+use crate::Args;
+use std::collections::BTreeSet;
+use std::time::Duration;
+use std::time::Instant;
+use tracing::trace;
+
+pub const TIMER_SPEED: u32 = 60;
+
+// The instruction rates the speed hotkeys go through, from about as slow as
+// the original hardware to far beyond what any game needs.
+const SPEEDS: [u32; 20] = [
+    100, 200, 300, 400, 500, 600, 700, 800, 900, 1000, 1200, 1500, 2000, 2500, 3000, 4000, 5000,
+    10000, 20000, 50000,
+];
+
+// The next speed up or down from the current one, which need not be one of
+// the steps itself.
+pub fn step_speed(speed: u32, up: bool) -> u32 {
+    if up {
+        SPEEDS
+            .into_iter()
+            .find(|step| *step > speed)
+            .unwrap_or(speed)
+    } else {
+        SPEEDS
+            .into_iter()
+            .rev()
+            .find(|step| *step < speed)
+            .unwrap_or(speed)
+    }
+}
+
+// The instructions of a frame at the speed, spread over the frames of each
+// second so that the second runs exactly as many as the speed.
+pub fn frame_instructions(frame: u64, ips: u32) -> u64 {
+    let (ips, timer_speed) = (ips as u64, TIMER_SPEED as u64);
+    (frame + 1) * ips / timer_speed - frame * ips / timer_speed
+}
+
+// How much the fast forward and slow motion keys change the speed while
+// they are held.
+#[derive(Clone, Copy)]
+pub struct Hold {
+    pub fast_forward: f64,
+    pub slow_motion: f64,
+}
+
+impl Hold {
+    pub fn from_args(args: &Args) -> Self {
+        Self {
+            fast_forward: args.fast_forward,
+            slow_motion: args.slow_motion,
+        }
+    }
+
+    pub fn scale(self, fast_forward: bool, slow_motion: bool) -> f64 {
+        let mut scale = 1.0;
+        if fast_forward {
+            scale *= self.fast_forward;
+        }
+        if slow_motion {
+            scale *= self.slow_motion;
+        }
+        scale
+    }
+}
+
+// Drives the interpreter from elapsed wall-clock time, executing instructions
+// at the configured speed and ticking the timers at 60Hz.
+#[derive(Clone)]
+pub struct Runner {
+    instructions: Budget,
+    timers: Budget,
+    // How much faster than real time emulation runs, timers included.
+    scale: f64,
+    // The addresses where running stops for a debugger, before the
+    // instruction there, and the one reached last.
+    breakpoints: BTreeSet<u16>,
+    hit: Option<u16>,
+}
+
+impl Runner {
+    pub fn new(speed: u32) -> Self {
+        Self {
+            instructions: Budget::new(speed as f64),
+            timers: Budget::new(TIMER_SPEED as f64),
+            scale: 1.0,
+            breakpoints: BTreeSet::new(),
+            hit: None,
+        }
+    }
+
+    pub fn set_speed(&mut self, speed: u32) {
+        self.instructions = Budget::new(speed as f64);
+    }
+
+    pub fn set_scale(&mut self, scale: f64) {
+        self.scale = scale;
+    }
+
+    pub fn breakpoints(&mut self) -> &mut BTreeSet<u16> {
+        &mut self.breakpoints
+    }
+
+    // Whether the instruction executed last led to a breakpoint, which is
+    // remembered for the debugger to take.
+    pub fn reached(&mut self, chip8: &Chip8) -> bool {
+        let reached = self.breakpoints.contains(&chip8.pc);
+        if reached {
+            self.hit = Some(chip8.pc);
+        }
+        reached
+    }
+
+    pub fn take_hit(&mut self) -> Option<u16> {
+        self.hit.take()
+    }
+
+    // Returns how many timer ticks (frames) went by, running instructions
+    // up to a breakpoint at most.
+    pub fn advance(&mut self, chip8: &mut Chip8, elapsed: Duration) -> u32 {
+        let start = Instant::now();
+        let elapsed = elapsed.mul_f64(self.scale);
+        let instructions = self.instructions.advance(elapsed);
+        let vip_cycles = chip8.vip_cycles();
+        for _ in 0..instructions {
+            chip8.fetch_execute();
+            if self.reached(chip8) {
+                break;
+            }
+        }
+        let ticks = self.timers.advance(elapsed);
+        for _ in 0..ticks {
+            chip8.dec_timers();
+        }
+        let vip = vip_time(chip8.vip_cycles() - vip_cycles);
+        trace!(?elapsed, instructions, ?vip, ticks, took = ?start.elapsed(), "emulated");
+        ticks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::Chip8;
+    use crate::frontend::runner::frame_instructions;
+    use crate::frontend::runner::step_speed;
+    use crate::frontend::runner::Hold;
+    use crate::frontend::runner::Runner;
+    use std::time::Duration;
+    #[test]
+    fn scale_speeds_up_instructions_and_timers() {
+        let mut chip8 = Chip8::new();
+        // JP 200
+        chip8.load(&[0x12, 0x00]).unwrap();
+        let mut runner = Runner::new(600);
+        runner.set_scale(4.0);
+        assert_eq!(runner.advance(&mut chip8, Duration::from_millis(50)), 12);
+        assert_eq!(chip8.cycles(), 120);
+    }
+    #[test]
+    fn held_keys_combine() {
+        let hold = Hold {
+            fast_forward: 4.0,
+            slow_motion: 0.25,
+        };
+        assert_eq!(hold.scale(false, false), 1.0);
+        assert_eq!(hold.scale(true, false), 4.0);
+        assert_eq!(hold.scale(false, true), 0.25);
+        assert_eq!(hold.scale(true, true), 1.0);
+    }
+    #[test]
+    fn frame_instructions_add_up_to_the_speed() {
+        assert_eq!(
+            (0..60)
+                .map(|frame| frame_instructions(frame, 700))
+                .sum::<u64>(),
+            700
+        );
+        assert_eq!(frame_instructions(0, 700), 11);
+        assert_eq!(frame_instructions(5, 700), 12);
+    }
+    #[test]
+    fn speed_steps_from_anywhere() {
+        assert_eq!(step_speed(1000, true), 1200);
+        assert_eq!(step_speed(1000, false), 900);
+        assert_eq!(step_speed(1100, false), 1000);
+        assert_eq!(step_speed(50000, true), 50000);
+        assert_eq!(step_speed(60, false), 60);
+        assert_eq!(step_speed(60, true), 100);
+    }
+}
@@ -0,0 +1,152 @@
+use crate::chip8::Chip8;
+use crate::Args;
+use std::fs;
+use std::path::Path;
+
+const DEFAULT_FRAMES: u64 = 4;
+
+#[derive(Debug, PartialEq)]
+struct Press {
+    frame: u64,
+    key: u8,
+    frames: u64,
+}
+
+// Keypad presses at set frames, counted from the start of emulation, for
+// driving ROMs through their menus without anyone at the keyboard. Each line
+// reads "at frame 120 press 5 for 10 frames", the duration being optional.
+#[derive(Debug, PartialEq)]
+pub struct Script {
+    presses: Vec<Press>,
+    frame: u64,
+}
+
+fn parse_line(line: &str) -> Option<Press> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let (frame, key, frames) = match words.as_slice() {
+        ["at", "frame", frame, "press", key] => (frame, key, None),
+        ["at", "frame", frame, "press", key, "for", frames, "frame" | "frames"] => {
+            (frame, key, Some(frames))
+        }
+        _ => return None,
+    };
+    let frames = match frames {
+        Some(frames) => frames.parse().ok().filter(|frames| *frames > 0)?,
+        None => DEFAULT_FRAMES,
+    };
+    Some(Press {
+        frame: frame.parse().ok()?,
+        key: u8::from_str_radix(key, 16)
+            .ok()
+            .filter(|_| key.len() == 1)?,
+        frames,
+    })
+}
+
+impl Script {
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut presses = Vec::new();
+        for (index, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            presses.push(parse_line(line).ok_or_else(|| {
+                format!(
+                    "line {}: expected \"at frame N press K for N frames\", with K a keypad value 0 to F",
+                    index + 1
+                )
+            })?);
+        }
+        Ok(Self { presses, frame: 0 })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("unable to read {}: {}", path.display(), e))?;
+        Self::parse(&content).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn from_args(args: &Args) -> Option<Self> {
+        args.input_script
+            .as_deref()
+            .map(|path| Self::load(path).expect("Unable to load input script!"))
+    }
+
+    // The keypad presses and releases for the next frame.
+    fn step(&mut self) -> Vec<(u8, bool)> {
+        let mut changes = Vec::new();
+        for press in &self.presses {
+            if self.frame == press.frame + press.frames {
+                changes.push((press.key, false));
+            }
+        }
+        for press in &self.presses {
+            if self.frame == press.frame {
+                changes.push((press.key, true));
+            }
+        }
+        self.frame += 1;
+        changes
+    }
+
+    pub fn tick(&mut self, chip8: &mut Chip8) {
+        for (x, state) in self.step() {
+            chip8.key_pressed(x, state);
+        }
+    }
+
+    // Starts over from the first frame, letting go of the keys still down.
+    pub fn rewind(&mut self, chip8: &mut Chip8) {
+        for press in &self.presses {
+            if press.frame < self.frame && self.frame <= press.frame + press.frames {
+                chip8.key_pressed(press.key, false);
+            }
+        }
+        self.frame = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::Chip8;
+    use crate::frontend::script::Script;
+    #[test]
+    fn presses_are_held_for_their_frames() {
+        let mut script =
+            Script::parse("# menu\nat frame 1 press 5 for 2 frames\n\nat frame 3 press a # go")
+                .unwrap();
+        let frames: Vec<_> = (0..8).map(|_| script.step()).collect();
+        assert_eq!(
+            frames,
+            vec![
+                vec![],
+                vec![(5, true)],
+                vec![],
+                vec![(5, false), (0xA, true)],
+                vec![],
+                vec![],
+                vec![],
+                vec![(0xA, false)],
+            ]
+        );
+    }
+    #[test]
+    fn rewind_starts_over() {
+        let mut script = Script::parse("at frame 1 press 5").unwrap();
+        let mut chip8 = Chip8::new();
+        script.tick(&mut chip8);
+        script.tick(&mut chip8);
+        script.rewind(&mut chip8);
+        assert_eq!(script.step(), vec![]);
+        assert_eq!(script.step(), vec![(5, true)]);
+    }
+    #[test]
+    fn invalid_lines_are_reported() {
+        let error = |content| Script::parse(content).unwrap_err();
+        assert!(error("at frame 1 press 5\npress 5").starts_with("line 2: expected"));
+        assert!(error("at frame 1 press G").starts_with("line 1:"));
+        assert!(error("at frame 1 press 5 for 0 frames").starts_with("line 1:"));
+        assert!(error("at frame -1 press 5").starts_with("line 1:"));
+    }
+}
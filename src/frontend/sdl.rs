@@ -0,0 +1,1224 @@
+use crate::audio::Audio;
+use crate::audio::FREQUENCY_STEP;
+use crate::audio::VOLUME_STEP;
+use crate::chip8::display::RES_HEIGHT;
+use crate::chip8::display::RES_WIDTH;
+use crate::chip8::hash::RomHash;
+use crate::chip8::quirks::NAMES;
+use crate::chip8::Chip8;
+use crate::chip8::Reset;
+use crate::config::sidecar::Sidecar;
+use crate::config::Config;
+use crate::config::LiveConfig;
+use crate::failure::Failure;
+use crate::frontend::achievements::Achievements;
+use crate::frontend::backdrop::Backdrop;
+use crate::frontend::bezel::Bezel;
+use crate::frontend::browser::Browser;
+use crate::frontend::canvas::CanvasRenderer;
+use crate::frontend::cartridge::Cartridge;
+use crate::frontend::chat::Chat;
+use crate::frontend::controller::Controller;
+use crate::frontend::crt::CrtOptions;
+use crate::frontend::debugger::DebugWindow;
+#[cfg(feature = "dialog")]
+use crate::frontend::dialog::pick_rom;
+use crate::frontend::focus::Focus;
+use crate::frontend::framebuffer::levels;
+use crate::frontend::input::Inputs;
+use crate::frontend::input::Source;
+use crate::frontend::keypad::overlay_point;
+use crate::frontend::keypad::Keypad;
+use crate::frontend::layout::density_scale;
+use crate::frontend::layout::Viewport;
+use crate::frontend::limits::Limits;
+use crate::frontend::macros::Player;
+use crate::frontend::midi::Midi;
+use crate::frontend::monitor::centered;
+use crate::frontend::monitor::remember;
+use crate::frontend::monitor::select;
+use crate::frontend::netplay::Netplay;
+use crate::frontend::notifications::Notifications;
+use crate::frontend::pacing::FrameSkip;
+use crate::frontend::pacing::Pacer;
+use crate::frontend::palette::Palettes;
+use crate::frontend::palette::Rgb;
+use crate::frontend::palette::Tone;
+use crate::frontend::phosphor::Phosphor;
+use crate::frontend::playlist::Playlist;
+use crate::frontend::pokes::Pokes;
+#[cfg(feature = "discord")]
+use crate::frontend::presence::Presence;
+use crate::frontend::recent::Played;
+use crate::frontend::recent::Recent;
+use crate::frontend::renderer::Renderer;
+use crate::frontend::replay::Replay;
+use crate::frontend::replay::Step;
+use crate::frontend::rpc::Rpc;
+use crate::frontend::rumble::Rumble;
+use crate::frontend::runner::step_speed;
+use crate::frontend::runner::Hold;
+use crate::frontend::runner::Runner;
+use crate::frontend::runner::TIMER_SPEED;
+use crate::frontend::script::Script;
+use crate::frontend::speed::describe;
+use crate::frontend::splash;
+use crate::frontend::stats::Stats;
+use crate::frontend::stick::Stick;
+use crate::frontend::text::Overlay;
+use crate::frontend::text::OVERLAY_SCALE;
+use crate::frontend::title::rom_name;
+use crate::frontend::title::title;
+use crate::frontend::watch::Watch;
+use crate::frontend::watch::INTERVAL;
+#[cfg(feature = "wgpu")]
+use crate::frontend::wgpu::WgpuRenderer;
+use crate::Args;
+use crate::RendererKind;
+use sdl2::controller::Axis;
+use sdl2::controller::Button;
+use sdl2::event::Event;
+use sdl2::event::WindowEvent;
+use sdl2::keyboard::Keycode;
+use sdl2::keyboard::Mod;
+use sdl2::keyboard::Scancode;
+use sdl2::mouse::MouseButton;
+use sdl2::video::FullscreenType;
+use sdl2::video::Window;
+use sdl2::video::WindowPos;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::Instant;
+use tracing::info;
+use tracing::trace;
+use tracing::warn;
+
+// Milliseconds the loop waits for an event at most when idle, well within
+// the time between looks at the watched files, so that their changes are
+// seen as soon as when running.
+const IDLE_WAIT: u32 = INTERVAL.as_millis() as u32 / 2;
+
+pub fn run(
+    args: &Args,
+    config: &Config,
+    mut palettes: Palettes,
+    mut chip8: Chip8,
+    audio: &mut dyn Audio,
+    mut netplay: Option<Netplay>,
+) -> Result<(), Failure> {
+    let crt = CrtOptions {
+        scanlines: args.scanlines.unwrap_or(config.crt.scanlines),
+        curvature: args.curvature.unwrap_or(config.crt.curvature),
+        bloom: args.bloom.unwrap_or(config.crt.bloom),
+        grid: args.grid.unwrap_or(config.crt.grid),
+        shape: args.pixel_shape.unwrap_or(config.crt.shape),
+    }
+    .clamped();
+    let bezel = args
+        .bezel
+        .as_ref()
+        .or(config.bezel.image.as_ref())
+        .map(|path| Bezel::load(path, config.bezel.viewport))
+        .transpose()
+        .map_err(Failure::Input)?;
+    let gradient = match args.background_gradient.as_slice() {
+        [top, bottom] => Some([*top, *bottom]),
+        _ => None,
+    };
+    let backdrop = Backdrop::select(args.background.as_deref(), gradient, &config.background)
+        .map_err(Failure::Input)?;
+
+    let video_failure = |e: String| Failure::Video(format!("SDL video unavailable: {}", e));
+    let sdl_context = sdl2::init().map_err(video_failure)?;
+    let video_subsystem = sdl_context.video().map_err(video_failure)?;
+
+    let mut pause_emulation = args.pause_on_start;
+
+    let monitor = match args.monitor.or(config.window.monitor) {
+        Some(requested) => {
+            let names: Vec<String> = (0..video_subsystem
+                .num_video_displays()
+                .map_err(video_failure)?)
+                .map(|index| video_subsystem.display_name(index).unwrap_or_default())
+                .collect();
+            Some(select(requested, &names).map_err(Failure::Input)?)
+        }
+        None => None,
+    };
+    // A monitor picked on the command line goes into the configuration
+    // file, for the emulator to come up on it from then on.
+    if let Some(requested) = args
+        .monitor
+        .filter(|requested| config.window.monitor != Some(*requested))
+    {
+        if let Some(path) = args.config.clone().or_else(Config::default_path) {
+            match remember(&path, requested) {
+                Ok(()) => info!("monitor {} saved to {}", requested, path.display()),
+                Err(error) => warn!("monitor not saved, {}", error),
+            }
+        }
+    }
+    let bounds = video_subsystem
+        .display_bounds(monitor.unwrap_or(0))
+        .map_err(video_failure)?;
+    let bounds = Viewport {
+        x: bounds.x(),
+        y: bounds.y(),
+        width: bounds.width(),
+        height: bounds.height(),
+    };
+    let borderless = args.borderless || config.window.borderless;
+
+    let mut rom = args.rom().map(rom_name).unwrap_or_default();
+    let (width, height) = if borderless {
+        (bounds.width, bounds.height)
+    } else {
+        args.pixel_aspect.window_size(args.scale as u32)
+    };
+    let (x, y) = centered(bounds, (width, height));
+    let mut window_builder =
+        video_subsystem.window(&title(&rom, args.ips, args.pause_on_start), width, height);
+    window_builder.position(x, y).resizable().allow_highdpi();
+    if borderless {
+        window_builder.borderless();
+    }
+    let mut window = window_builder
+        .build()
+        .map_err(|e| video_failure(e.to_string()))?;
+    let dpi_scale = args.dpi_scale.unwrap_or_else(|| {
+        let dpi = window
+            .display_index()
+            .and_then(|index| video_subsystem.display_dpi(index))
+            .ok()
+            .map(|(_, horizontal, _)| horizontal);
+        density_scale(window.size(), window.drawable_size(), dpi)
+    });
+    if dpi_scale != 1.0 && !borderless {
+        let size = (
+            (width as f32 * dpi_scale).round() as u32,
+            (height as f32 * dpi_scale).round() as u32,
+        );
+        window
+            .set_size(size.0, size.1)
+            .map_err(|e| video_failure(e.to_string()))?;
+        let (x, y) = centered(bounds, size);
+        window.set_position(WindowPos::Positioned(x), WindowPos::Positioned(y));
+    }
+
+    let texture_creator;
+    let mut renderer: Box<dyn Renderer> = match args.renderer {
+        RendererKind::Canvas => {
+            let mut canvas_builder = window.into_canvas();
+            if args.vsync {
+                canvas_builder = canvas_builder.present_vsync();
+            }
+            let canvas = canvas_builder
+                .build()
+                .map_err(|e| video_failure(e.to_string()))?;
+            texture_creator = canvas.texture_creator();
+            Box::new(CanvasRenderer::new(
+                canvas,
+                &texture_creator,
+                args.scaling,
+                args.filter,
+                bezel,
+                backdrop,
+                args.pixel_aspect,
+            ))
+        }
+        #[cfg(feature = "wgpu")]
+        RendererKind::Wgpu => Box::new(WgpuRenderer::new(
+            window,
+            args.vsync,
+            args.scaling,
+            args.filter,
+            bezel,
+            backdrop,
+            args.pixel_aspect,
+        )),
+    };
+    if args.fullscreen {
+        toggle_fullscreen(renderer.window_mut());
+    }
+
+    let mut speed = args.ips;
+
+    let mut phosphor = Phosphor::from_args(args);
+
+    let mut redraw = true;
+
+    let mut runner = Runner::new(speed);
+    let mut replay = Replay::new(args.quirk_replay);
+    let mut rpc = args
+        .debug_rpc
+        .as_deref()
+        .map(Rpc::bind)
+        .transpose()
+        .map_err(Failure::Input)?;
+    let hold = Hold::from_args(args);
+    let mut macros = Player::new(&config.macros);
+    let mut script = Script::from_args(args);
+    let mut pokes = Pokes::from_args(args);
+    let limits = Limits::from_args(args);
+    let mut last_frame = Instant::now();
+    let mut pacer = Pacer::new();
+    let mut frame_skip = FrameSkip::new(args.max_frame_skip, Duration::from_secs(1) / TIMER_SPEED);
+
+    let mut notifications = Notifications::new();
+    let mut overlay = Overlay::new(RES_WIDTH * OVERLAY_SCALE, RES_HEIGHT * OVERLAY_SCALE);
+    let mut stats = Stats::new(Instant::now(), chip8.cycles());
+    let mut show_stats = args.stats;
+    let keypad = Keypad::new(&overlay);
+    let mut show_keypad = args.keypad;
+    // The keypad key held down with the mouse or a finger.
+    let mut clicked = None;
+    let mut inputs = Inputs::default();
+    let mut chat = Chat::from_args(args);
+    let midi = args
+        .midi
+        .as_ref()
+        .map(|device| Midi::open(device, &config.midi))
+        .transpose()
+        .map_err(Failure::Input)?;
+    #[cfg(feature = "discord")]
+    let presence = Presence::from_config(&config.discord);
+    #[cfg(feature = "discord")]
+    if let Some(presence) = &presence {
+        presence.playing(&rom);
+    }
+    #[cfg(not(feature = "discord"))]
+    if config.discord.presence {
+        warn!("Discord presence disabled, built without the discord feature");
+    }
+    let mut achievements = args
+        .rom()
+        .and_then(|rom| find_achievements(Path::new(rom), &mut notifications));
+    // Over the network the keys go to the other player first, and frames run
+    // at 60 a second whatever the display.
+    let mut next_frame = Instant::now();
+    if netplay.is_some() {
+        inputs.defer();
+    }
+    let mut focus = Focus::new(config.window.pause_on_focus_loss);
+    let mut stick = Stick::new(&config.stick);
+    let mut controller = Controller::open(&sdl_context)
+        .map_err(|error| warn!("controller disabled, {}", error))
+        .ok();
+    let mut shown_beep = false;
+    let mut debug_window = args
+        .debug_window
+        .then(|| DebugWindow::open(&video_subsystem));
+
+    let mut rumble = if args.rumble {
+        Rumble::open(&sdl_context)
+            .map_err(|error| warn!("rumble disabled, {}", error))
+            .ok()
+    } else {
+        None
+    };
+
+    // Started without a ROM, the window lists those in a directory to pick
+    // one from, with emulation held until then.
+    // The ROMs played last come first, when there are any, with Tab turning
+    // to the whole directory and back.
+    let (mut browser, mut other_list) = if args.rom().is_none() {
+        let dir = args
+            .rom_dir
+            .as_deref()
+            .or(config.browser.dir.as_deref())
+            .unwrap_or(Path::new("."));
+        let all = Browser::scan(dir).map_err(Failure::Input)?;
+        let recent = Browser::recent(&Recent::find());
+        // With nothing to list, the splash screen runs until a ROM is
+        // dropped on the window or opened.
+        if all.is_empty() && recent.is_empty() {
+            chip8
+                .load(&splash::rom())
+                .expect("the splash screen fits in memory");
+            (None, None)
+        } else if recent.is_empty() {
+            (Some(all), Some(recent))
+        } else {
+            (Some(recent), Some(all))
+        }
+    } else {
+        (None, None)
+    };
+    let page = overlay.rows() as isize - 2;
+    // The ROM to start next, with the settings it had when picked from the
+    // recent ones.
+    let mut picked: Option<(PathBuf, Option<Played>)> = None;
+    let mut keys = config.keys.clone();
+    let mut watch = args
+        .rom()
+        .filter(|_| args.watch)
+        .map(|rom| Watch::new(Path::new(rom)));
+    let mut live_config = LiveConfig::new(args.config.as_deref(), args.profile.as_deref());
+    let mut playlist = (args.files.len() > 1).then(|| Playlist::new(&args.files));
+
+    let mut event_pump = sdl_context.event_pump().map_err(video_failure)?;
+    // The event that woke the loop up from waiting, handled first.
+    let mut woken = None;
+    'running: loop {
+        let debug_id = debug_window.as_ref().map(DebugWindow::id);
+        for event in woken.take().into_iter().chain(event_pump.poll_iter()) {
+            if let Some(list) = browser.as_mut() {
+                match event {
+                    Event::Quit { .. }
+                    | Event::Window {
+                        win_event: WindowEvent::Close,
+                        ..
+                    }
+                    | Event::KeyDown {
+                        keycode: Some(Keycode::Escape),
+                        ..
+                    } => break 'running,
+                    Event::KeyDown {
+                        keycode: Some(Keycode::Up),
+                        ..
+                    }
+                    | Event::ControllerButtonDown {
+                        button: Button::DPadUp,
+                        ..
+                    } => list.step(-1),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::Down),
+                        ..
+                    }
+                    | Event::ControllerButtonDown {
+                        button: Button::DPadDown,
+                        ..
+                    } => list.step(1),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::PageUp),
+                        ..
+                    }
+                    | Event::ControllerButtonDown {
+                        button: Button::LeftShoulder,
+                        ..
+                    } => list.step(-page),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::PageDown),
+                        ..
+                    }
+                    | Event::ControllerButtonDown {
+                        button: Button::RightShoulder,
+                        ..
+                    } => list.step(page),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::Tab),
+                        ..
+                    }
+                    | Event::ControllerButtonDown {
+                        button: Button::Y, ..
+                    } => {
+                        if let Some(other) = other_list.as_mut() {
+                            std::mem::swap(list, other);
+                        }
+                    }
+                    Event::KeyDown {
+                        keycode: Some(Keycode::Return | Keycode::KpEnter),
+                        ..
+                    }
+                    | Event::ControllerButtonDown {
+                        button: Button::A | Button::Start,
+                        ..
+                    } => {
+                        picked = list
+                            .selected()
+                            .map(|entry| (entry.path.clone(), entry.played.clone()))
+                    }
+                    Event::DropFile { filename, .. } => {
+                        picked = Some((PathBuf::from(filename), None))
+                    }
+                    _ => {}
+                }
+                redraw = true;
+                continue;
+            }
+            match event {
+                // Closing the debugger leaves the emulator running.
+                Event::Window {
+                    window_id,
+                    win_event: WindowEvent::Close,
+                    ..
+                }
+                | Event::KeyDown {
+                    window_id,
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } if Some(window_id) == debug_id => debug_window = None,
+                Event::Quit { .. }
+                | Event::Window {
+                    win_event: WindowEvent::Close,
+                    ..
+                }
+                | Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } => break 'running,
+                Event::KeyDown {
+                    keycode: Some(Keycode::F12),
+                    ..
+                } => {
+                    debug_window = match debug_window {
+                        Some(_) => None,
+                        None => Some(DebugWindow::open(&video_subsystem)),
+                    };
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Space),
+                    ..
+                } => {
+                    pause_emulation = !pause_emulation;
+                    notifications.push(if pause_emulation { "Paused" } else { "Resumed" });
+                    renderer
+                        .window_mut()
+                        .set_title(&title(&rom, speed, pause_emulation))
+                        .unwrap();
+                    redraw = true;
+                }
+                // F2 runs the ROM again from the start, and Shift+F2 also
+                // reloads it into cleared memory and starts the macros and
+                // the input script over.
+                Event::KeyDown {
+                    keycode: Some(Keycode::F2),
+                    keymod,
+                    ..
+                } => {
+                    let reset = if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                        Reset::Hard
+                    } else {
+                        Reset::Soft
+                    };
+                    if reset == Reset::Hard {
+                        macros.stop(&mut chip8);
+                        if let Some(script) = script.as_mut() {
+                            script.rewind(&mut chip8);
+                        }
+                    }
+                    chip8.reset(reset);
+                    replay.clear();
+                    if let Some(phosphor) = phosphor.as_mut() {
+                        phosphor.clear();
+                    }
+                    pause_emulation |= args.pause_on_start;
+                    notifications.push(match reset {
+                        Reset::Soft => "Reset",
+                        Reset::Hard => "Hard reset",
+                    });
+                    renderer
+                        .window_mut()
+                        .set_title(&title(&rom, speed, pause_emulation))
+                        .unwrap();
+                    redraw = true;
+                }
+                // PageDown and PageUp switch to the next and the previous
+                // ROM given, which pick up where they were left, or else
+                // start like one opened.
+                Event::KeyDown {
+                    keycode: Some(key @ (Keycode::PageUp | Keycode::PageDown)),
+                    ..
+                } => {
+                    if let Some(playlist) = playlist.as_mut() {
+                        let steps = if key == Keycode::PageDown { 1 } else { -1 };
+                        macros.stop(&mut chip8);
+                        match playlist.switch(steps, &chip8) {
+                            (path, Some(saved)) => {
+                                chip8 = saved;
+                                replay.clear();
+                                rom = rom_name(&path.to_string_lossy());
+                                #[cfg(feature = "discord")]
+                                if let Some(presence) = &presence {
+                                    presence.playing(&rom);
+                                }
+                                achievements = find_achievements(path, &mut notifications);
+                                if let Some(phosphor) = phosphor.as_mut() {
+                                    phosphor.clear();
+                                }
+                                if args.watch {
+                                    watch = Some(Watch::new(path));
+                                }
+                                notifications.push(format!("Resumed {}", rom));
+                                renderer
+                                    .window_mut()
+                                    .set_title(&title(&rom, speed, pause_emulation))
+                                    .unwrap();
+                                redraw = true;
+                            }
+                            (path, None) => picked = Some((path.to_path_buf(), None)),
+                        }
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F11),
+                    ..
+                } => {
+                    toggle_fullscreen(renderer.window_mut());
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Return),
+                    keymod,
+                    ..
+                } if keymod.intersects(Mod::LALTMOD | Mod::RALTMOD) => {
+                    toggle_fullscreen(renderer.window_mut());
+                }
+                #[cfg(feature = "dialog")]
+                Event::KeyDown {
+                    keycode: Some(Keycode::O),
+                    keymod,
+                    ..
+                } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    picked = pick_rom().map(|path| (path, None));
+                }
+                // A ROM file dropped on the window is opened.
+                Event::DropFile { filename, .. } => {
+                    picked = Some((PathBuf::from(filename), None));
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::P),
+                    ..
+                } => {
+                    palettes.cycle();
+                    notifications.push(format!("Palette: {}", palettes.current().name));
+                    redraw = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::N),
+                    ..
+                } => {
+                    notifications.push(if palettes.invert() {
+                        "Colors inverted"
+                    } else {
+                        "Colors restored"
+                    });
+                    redraw = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::I),
+                    ..
+                } => {
+                    show_stats = !show_stats;
+                    redraw = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F1),
+                    ..
+                } => {
+                    show_keypad = !show_keypad;
+                    redraw = true;
+                }
+                Event::MouseButtonDown {
+                    window_id,
+                    mouse_btn: MouseButton::Left,
+                    x,
+                    y,
+                    ..
+                } if show_keypad && Some(window_id) != debug_id => {
+                    let window = renderer.window_mut();
+                    // Mouse positions are in window coordinates, which on
+                    // high density displays are smaller than the drawable.
+                    let scale = window.drawable_size().0 as f32 / window.size().0 as f32;
+                    let point = ((x as f32 * scale) as i32, (y as f32 * scale) as i32);
+                    clicked = overlay_point(renderer.viewport(), point, &overlay)
+                        .and_then(|(x, y)| keypad.key_at(x, y));
+                    if let Some(key) = clicked {
+                        inputs.press(&mut chip8, Source::Pointer, key, true);
+                        redraw = true;
+                    }
+                }
+                Event::MouseButtonUp {
+                    mouse_btn: MouseButton::Left,
+                    ..
+                } => {
+                    if let Some(key) = clicked.take() {
+                        inputs.press(&mut chip8, Source::Pointer, key, false);
+                        redraw = true;
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(key @ (Keycode::F5 | Keycode::F6)),
+                    ..
+                } => {
+                    let step = if key == Keycode::F5 {
+                        -Tone::STEP
+                    } else {
+                        Tone::STEP
+                    };
+                    let brightness = palettes.adjust_brightness(step);
+                    notifications.push(format!("Brightness: {:.0}%", brightness * 100.0));
+                    redraw = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(key @ (Keycode::F7 | Keycode::F8)),
+                    ..
+                } => {
+                    let step = if key == Keycode::F7 {
+                        -Tone::STEP
+                    } else {
+                        Tone::STEP
+                    };
+                    notifications.push(format!("Gamma: {:.1}", palettes.adjust_gamma(step)));
+                    redraw = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(key @ (Keycode::F9 | Keycode::F10)),
+                    ..
+                } => {
+                    let step = if key == Keycode::F9 {
+                        -FREQUENCY_STEP
+                    } else {
+                        FREQUENCY_STEP
+                    };
+                    let frequency = audio.adjust_frequency(step);
+                    notifications.push(format!("Beep: {} Hz", frequency));
+                    redraw = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(key @ (Keycode::LeftBracket | Keycode::RightBracket)),
+                    ..
+                } => {
+                    let step = if key == Keycode::LeftBracket {
+                        -VOLUME_STEP
+                    } else {
+                        VOLUME_STEP
+                    };
+                    notifications.push(format!("Volume: {}%", audio.adjust_volume(step)));
+                    redraw = true;
+                }
+                Event::KeyDown {
+                    keycode:
+                        Some(
+                            key @ (Keycode::Minus
+                            | Keycode::KpMinus
+                            | Keycode::Equals
+                            | Keycode::KpPlus),
+                        ),
+                    ..
+                } => {
+                    speed = step_speed(speed, matches!(key, Keycode::Equals | Keycode::KpPlus));
+                    runner.set_speed(speed);
+                    // Frames run at another speed would not replay the same.
+                    replay.clear();
+                    notifications.push(format!("Speed: {}", describe(speed)));
+                    renderer
+                        .window_mut()
+                        .set_title(&title(&rom, speed, pause_emulation))
+                        .unwrap();
+                    redraw = true;
+                }
+                // Ctrl and a number from 1 to 6 turns the quirk of that
+                // number on or off, in the order --quirk lists them, and
+                // runs the last seconds of play again under it.
+                Event::KeyDown {
+                    keycode: Some(key),
+                    keymod,
+                    ..
+                } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD)
+                    && quirk_number(key).is_some() =>
+                {
+                    if netplay.is_some() {
+                        notifications.push("Quirks cannot change during netplay");
+                        redraw = true;
+                        continue;
+                    }
+                    let index = quirk_number(key).unwrap();
+                    let (name, quirk) = NAMES[index];
+                    let on = !chip8.quirks().listed()[index].1;
+                    let quirks = chip8.quirks().overridden(&[(quirk, on)]);
+                    let state = if on { "on" } else { "off" };
+                    match replay.rewind(&mut chip8, &mut runner, quirks) {
+                        Some(back) => {
+                            inputs.defer();
+                            if let Some(phosphor) = phosphor.as_mut() {
+                                phosphor.clear();
+                            }
+                            notifications.push(format!(
+                                "Quirk {} {}, replaying {:.0} s",
+                                name,
+                                state,
+                                back.as_secs_f64()
+                            ));
+                        }
+                        None => {
+                            chip8.set_quirks(quirks);
+                            notifications.push(format!("Quirk {} {}", name, state));
+                        }
+                    }
+                    info!("quirk {} turned {}", name, state);
+                    redraw = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(key),
+                    repeat: false,
+                    ..
+                } => {
+                    if macros.key(&key.name(), true) {
+                        continue;
+                    }
+                    if let Some(x) = keys.key(&key.name()) {
+                        inputs.press(&mut chip8, Source::Keyboard, x, true);
+                    }
+                }
+                Event::KeyUp {
+                    keycode: Some(key), ..
+                } => {
+                    if macros.key(&key.name(), false) {
+                        continue;
+                    }
+                    if let Some(x) = keys.key(&key.name()) {
+                        inputs.press(&mut chip8, Source::Keyboard, x, false);
+                    }
+                }
+                Event::ControllerDeviceAdded { which, .. } => {
+                    let Some(controller) = controller.as_mut() else {
+                        continue;
+                    };
+                    if controller.added(which) {
+                        let name = controller.name().unwrap_or_default();
+                        notifications.push(format!("Controller connected: {}", name));
+                        redraw = true;
+                    }
+                }
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    let Some(controller) = controller.as_mut() else {
+                        continue;
+                    };
+                    if !controller.removed(which) {
+                        continue;
+                    }
+                    inputs.release(&mut chip8, Source::Controller);
+                    inputs.release(&mut chip8, Source::Stick);
+                    stick = Stick::new(&config.stick);
+                    notifications.push("Controller disconnected");
+                    if let Some(name) = controller.name() {
+                        notifications.push(format!("Controller connected: {}", name));
+                    } else if args.pause_on_disconnect && !pause_emulation {
+                        pause_emulation = true;
+                        notifications.push("Paused");
+                        renderer
+                            .window_mut()
+                            .set_title(&title(&rom, speed, pause_emulation))
+                            .unwrap();
+                    }
+                    redraw = true;
+                }
+                Event::ControllerButtonDown { button, .. } => {
+                    if let Some(x) = config.buttons.key(&format!("{:?}", button)) {
+                        inputs.press(&mut chip8, Source::Controller, x, true);
+                    }
+                }
+                Event::ControllerAxisMotion {
+                    axis: axis @ (Axis::LeftX | Axis::LeftY),
+                    value,
+                    ..
+                } => {
+                    let value = value as f32 / i16::MAX as f32;
+                    for (x, state) in stick.axis(axis == Axis::LeftX, value) {
+                        inputs.press(&mut chip8, Source::Stick, x, state);
+                    }
+                }
+                Event::ControllerButtonUp { button, .. } => {
+                    if let Some(x) = config.buttons.key(&format!("{:?}", button)) {
+                        inputs.press(&mut chip8, Source::Controller, x, false);
+                    }
+                }
+                Event::Window {
+                    win_event: win_event @ (WindowEvent::FocusGained | WindowEvent::FocusLost),
+                    ..
+                } => {
+                    let focused = matches!(win_event, WindowEvent::FocusGained);
+                    let paused = focus.change(focused, pause_emulation);
+                    if paused != pause_emulation {
+                        pause_emulation = paused;
+                        notifications.push(if paused { "Paused" } else { "Resumed" });
+                        renderer
+                            .window_mut()
+                            .set_title(&title(&rom, speed, pause_emulation))
+                            .unwrap();
+                    }
+                    redraw = true;
+                }
+                Event::Window { .. } => redraw = true,
+                _ => {}
+            }
+        }
+        // A ROM chosen in the browser or the file picker starts on a fresh
+        // machine, with the settings of its sidecar file, if any.
+        if let Some((path, played)) = picked.take() {
+            match boot(&path, args) {
+                Ok((booted, sidecar)) => {
+                    chip8 = booted;
+                    replay.clear();
+                    rom = rom_name(&path.to_string_lossy());
+                    #[cfg(feature = "discord")]
+                    if let Some(presence) = &presence {
+                        presence.playing(&rom);
+                    }
+                    achievements = find_achievements(&path, &mut notifications);
+                    // The bytes of --poke are meant for the ROM given.
+                    pokes = None;
+                    if let Some(phosphor) = phosphor.as_mut() {
+                        phosphor.clear();
+                    }
+                    // A recent ROM picks up where it was left, over its
+                    // sidecar file.
+                    let (new_speed, palette) = match played {
+                        Some(played) => (Some(played.speed), Some(played.palette)),
+                        None => (sidecar.speed.map(u32::from), sidecar.palette),
+                    };
+                    if let Some(new_speed) = new_speed {
+                        speed = new_speed;
+                        runner.set_speed(speed);
+                    }
+                    if let Some(palette) = palette {
+                        if let Err(error) = palettes.select(&palette) {
+                            notifications.push(error);
+                        }
+                    }
+                    if let Some(sidecar_keys) = sidecar.keys {
+                        keys = sidecar_keys;
+                    }
+                    Recent::record(&path, speed, &palettes.current().name);
+                    if args.watch {
+                        watch = Some(Watch::new(&path));
+                    }
+                    pause_emulation |= args.pause_on_start;
+                    other_list = None;
+                    if browser.take().is_none() {
+                        notifications.push(format!("Opened {}", rom));
+                    }
+                    renderer
+                        .window_mut()
+                        .set_title(&title(&rom, speed, pause_emulation))
+                        .unwrap();
+                }
+                Err(error) => notifications.push(error),
+            }
+            redraw = true;
+        }
+        // A watched ROM whose file changed runs again from the start, with
+        // the speed, palette and keys in effect.
+        if let Some(watch) = watch.as_mut() {
+            if watch.changed(Instant::now()) {
+                match boot(watch.path(), args) {
+                    Ok((booted, _)) => {
+                        chip8 = booted;
+                        replay.clear();
+                        if let Some(pokes) = &pokes {
+                            pokes.apply(&mut chip8);
+                        }
+                        if let Some(phosphor) = phosphor.as_mut() {
+                            phosphor.clear();
+                        }
+                        pause_emulation |= args.pause_on_start;
+                        notifications.push(format!("Reloaded {}", rom));
+                        renderer
+                            .window_mut()
+                            .set_title(&title(&rom, speed, pause_emulation))
+                            .unwrap();
+                    }
+                    Err(error) => {
+                        warn!("{}: {}", watch.path().display(), error);
+                        notifications.push(error);
+                    }
+                }
+                redraw = true;
+            }
+        }
+        // Edits to the configuration file take effect as they are saved.
+        let changes = live_config.as_mut().map(|live| live.poll(Instant::now()));
+        match changes {
+            Some(Ok(Some(changes))) => {
+                if let Some(new_speed) = changes.speed {
+                    speed = new_speed as u32;
+                    runner.set_speed(speed);
+                    renderer
+                        .window_mut()
+                        .set_title(&title(&rom, speed, pause_emulation))
+                        .unwrap();
+                }
+                if let Some(palette) = &changes.palette {
+                    if let Err(error) = palettes.select(palette) {
+                        notifications.push(error);
+                    }
+                }
+                if let Some(tone) = changes.tone {
+                    palettes.set_tone(tone);
+                }
+                if let Some(config_keys) = changes.keys.clone() {
+                    keys = config_keys;
+                }
+                notifications.push(format!("Config: {}", changes.describe()));
+                redraw = true;
+            }
+            Some(Err(error)) => {
+                warn!("{}", error);
+                notifications.push(error);
+                redraw = true;
+            }
+            _ => {}
+        }
+        if let Some(rpc) = rpc.as_mut() {
+            rpc.poll(&mut chip8, &mut runner, &mut pause_emulation);
+        }
+        if let Some(midi) = &midi {
+            for (key, down) in midi.keys() {
+                inputs.press(&mut chip8, Source::Midi, key, down);
+            }
+        }
+        if let Some(achievements) = achievements.as_mut() {
+            for title in achievements.check(&chip8) {
+                info!("achievement unlocked: {}", title);
+                notifications.push(format!("Achievement unlocked: {}", title));
+                redraw = true;
+            }
+        }
+        // The key the viewers voted for is held until the next vote.
+        if let Some(verdict) = chat
+            .as_mut()
+            .and_then(|chat| chat.update(Instant::now(), &keys))
+        {
+            inputs.release(&mut chip8, Source::Chat);
+            if let Some(verdict) = verdict {
+                inputs.press(&mut chip8, Source::Chat, verdict.key, true);
+                notifications.push(format!(
+                    "Chat: {} ({} vote{})",
+                    verdict.name,
+                    verdict.votes,
+                    if verdict.votes == 1 { "" } else { "s" }
+                ));
+                redraw = true;
+            }
+        }
+        // Holding Tab runs the emulation faster, without the buzzer, and
+        // holding Backspace slower.
+        let keyboard = event_pump.keyboard_state();
+        let fast_forward = keyboard.is_scancode_pressed(Scancode::Tab);
+        let slow_motion = keyboard.is_scancode_pressed(Scancode::Backspace);
+        let scale = hold.scale(fast_forward, slow_motion);
+        runner.set_scale(scale);
+
+        redraw |= notifications.expire(Instant::now());
+        redraw |= stats.update(Instant::now(), chip8.cycles()) && show_stats;
+
+        let due = Instant::now() >= next_frame && !pause_emulation;
+        if let Some(session) = netplay.as_mut().filter(|_| due) {
+            // A frame late, as after a pause, the next one is not hurried.
+            next_frame = (next_frame + Duration::from_secs(1) / TIMER_SPEED).max(Instant::now());
+            let result = session.frame(&mut chip8, inputs.keys());
+            if let Some(phosphor) = phosphor.as_mut() {
+                redraw |= phosphor.update(&chip8.display.as_buffer());
+            }
+            let timer = chip8.take_sound();
+            sound(Some(timer), audio, &mut rumble);
+            // Alone again, the machine keeps running as it was.
+            if let Err(error) = result {
+                warn!("netplay ended, {}", error);
+                notifications.push(format!("Netplay ended: {}", error));
+                if let Some(session) = netplay.take() {
+                    session.leave(&mut chip8);
+                }
+                inputs.resume(&mut chip8);
+                last_frame = Instant::now();
+            }
+        }
+
+        // Instructions run a frame's worth at a time, 60 times a second or
+        // at each display refresh with vsync, whatever the speed, so that
+        // the display is only ever drawn once a frame.
+        let frame_due = args.vsync || Instant::now() >= next_frame;
+        let mut skip = false;
+        if netplay.is_none() && frame_due {
+            if !args.vsync {
+                skip = frame_skip.skip(Instant::now().saturating_duration_since(next_frame));
+                next_frame =
+                    (next_frame + Duration::from_secs(1) / TIMER_SPEED).max(Instant::now());
+            }
+            let elapsed = last_frame.elapsed();
+            last_frame = Instant::now();
+            if pause_emulation || browser.is_some() {
+                sound(None, audio, &mut rumble);
+            } else {
+                let replaying = replay.replaying();
+                let played = Step {
+                    elapsed,
+                    scale,
+                    keys: inputs.keys(),
+                };
+                let step = replay.next(&mut chip8, &runner, played);
+                if replaying && !replay.replaying() {
+                    inputs.resume(&mut chip8);
+                    notifications.push("Replay over");
+                    redraw = true;
+                }
+                runner.set_scale(step.scale);
+                for _ in 0..runner.advance(&mut chip8, step.elapsed) {
+                    macros.tick(&mut chip8);
+                    if let Some(script) = script.as_mut() {
+                        script.tick(&mut chip8);
+                    }
+                    if let Some(pokes) = &pokes {
+                        pokes.tick(&mut chip8);
+                    }
+                    if let Some(phosphor) = phosphor.as_mut() {
+                        redraw |= phosphor.update(&chip8.display.as_buffer());
+                    }
+                }
+                let timer = chip8.take_sound();
+                sound((!fast_forward).then_some(timer), audio, &mut rumble);
+            }
+        }
+
+        let end = limits
+            .as_ref()
+            .filter(|_| browser.is_none())
+            .and_then(|limits| limits.check(&chip8, Instant::now()));
+        if let Some(end) = end {
+            return end;
+        }
+
+        redraw |= chip8.display.take_dirty();
+        let beeping = args.visual_beep && !pause_emulation && chip8.beep();
+        redraw |= beeping != shown_beep;
+        // With vsync presenting blocks until the next display refresh, which
+        // is what paces this loop, so a frame is drawn every time around.
+        if let Some(list) = browser.as_ref().filter(|_| redraw || args.vsync) {
+            overlay.clear();
+            for (line, (text, selected)) in list.lines(overlay.rows()).iter().enumerate() {
+                let color = if *selected {
+                    palettes.current().foreground()
+                } else {
+                    Rgb(255, 255, 255)
+                };
+                overlay.draw_line(line, text, color);
+            }
+            if let Some(message) = notifications.messages().last() {
+                overlay.draw_status(message);
+            }
+        } else if skip && redraw {
+            stats.skip();
+        } else if redraw || args.vsync {
+            overlay.show(notifications.messages());
+            if show_stats {
+                overlay.draw_status(&stats.summary(speed));
+            }
+            if beeping {
+                overlay.draw_border(palettes.current().foreground());
+            }
+            if show_keypad {
+                keypad.draw(&mut overlay, clicked);
+            }
+            shown_beep = beeping;
+            let start = Instant::now();
+            renderer.render(
+                &levels(&chip8.display.as_buffer(), phosphor.as_ref()),
+                palettes.current(),
+                &crt,
+                &overlay,
+            );
+            trace!(took = ?start.elapsed(), "drawn");
+            stats.frame();
+            redraw = false;
+        }
+        if let Some(debug_window) = debug_window.as_mut() {
+            debug_window.update(&chip8, Instant::now());
+        }
+        // With nothing to run, as while paused or while the ROM waits for a
+        // key, the loop waits for an event instead of going around at every
+        // frame, looking at the rest now and then. Sources of keys other
+        // than SDL's, replays, scripts and macros included, and a debugger
+        // waiting for answers keep it going around.
+        let idle = pause_emulation
+            || browser.is_some()
+            || ((chip8.waiting_for_key() || chip8.halted()) && chip8.sound_timer() == 0);
+        let polled = netplay.is_some()
+            || chat.is_some()
+            || midi.is_some()
+            || rpc.is_some()
+            || script.is_some()
+            || !macros.is_idle()
+            || replay.replaying();
+        if idle && !polled {
+            woken = event_pump.wait_event_timeout(IDLE_WAIT);
+            continue;
+        }
+        if !args.vsync {
+            // Over the network the loop wakes up often to hear from the
+            // other player in time.
+            let deadline = match netplay {
+                Some(_) => next_frame.min(Instant::now() + Duration::from_millis(5)),
+                None => next_frame,
+            };
+            pacer.wait_until(deadline);
+        }
+    }
+    Ok(())
+}
+
+// A fresh machine running the ROM along with its settings, or why it
+// cannot. The speed of a cartridge counts as one from a sidecar file.
+fn boot(path: &Path, args: &Args) -> Result<(Chip8, Sidecar), String> {
+    let data = std::fs::read(path).map_err(|e| format!("Unable to open: {}", e))?;
+    let mut sidecar = Sidecar::find(path, &RomHash::of(&data))?;
+    let (data, cartridge) = Cartridge::open(path, data)?;
+    let mut chip8 = Chip8::new();
+    if let Some(options) = &cartridge {
+        sidecar.speed = sidecar.speed.or(options.speed());
+    }
+    match args.seed {
+        Some(seed) => chip8.set_seed(seed),
+        None => info!("random seed {}", chip8.seed()),
+    }
+    chip8.set_latch(args.key_latch);
+    chip8.set_quirks(args.quirks(sidecar.platform, &sidecar.quirks, cartridge.as_ref()));
+    chip8.load(&data)?;
+    Ok((chip8, sidecar))
+}
+
+// The achievements of the ROM, with what is wrong with their file shown over
+// the display.
+fn find_achievements(rom: &Path, notifications: &mut Notifications) -> Option<Achievements> {
+    Achievements::find(rom).unwrap_or_else(|error| {
+        warn!("{}", error);
+        notifications.push(error);
+        None
+    })
+}
+
+// The quirk Ctrl and the number key turn on or off, counting from 0.
+fn quirk_number(key: Keycode) -> Option<usize> {
+    [
+        Keycode::Num1,
+        Keycode::Num2,
+        Keycode::Num3,
+        Keycode::Num4,
+        Keycode::Num5,
+        Keycode::Num6,
+    ]
+    .iter()
+    .position(|number| *number == key)
+}
+
+fn toggle_fullscreen(window: &mut Window) {
+    let mode = match window.fullscreen_state() {
+        FullscreenType::Off => FullscreenType::Desktop,
+        _ => FullscreenType::Off,
+    };
+    if let Err(error) = window.set_fullscreen(mode) {
+        warn!("unable to toggle fullscreen, {}", error);
+    }
+}
+
+// Hands the sound timer to the buzzer and the rumbling controller, or
+// silences both at once without one.
+fn sound(timer: Option<u8>, audio: &dyn Audio, rumble: &mut Option<Rumble>) {
+    match timer {
+        Some(timer) => audio.sound(timer),
+        None => audio.pause(),
+    }
+    if let Some(rumble) = rumble {
+        rumble.update(timer.unwrap_or(0));
+    }
+}
@@ -0,0 +1,85 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+
+// Circles and rounded squares leave this much of the cell free on every side,
+// so that neighbouring dots do not merge.
+#[cfg(any(feature = "sdl", feature = "minifb"))]
+const EXTENT: f32 = 0.45;
+#[cfg(any(feature = "sdl", feature = "minifb"))]
+const CORNER_RADIUS: f32 = 0.2;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum PixelShape {
+    /// Plain square pixels
+    #[default]
+    Square,
+    /// Round dots, like an LED matrix
+    Circle,
+    /// Squares with rounded corners
+    Rounded,
+}
+
+#[cfg(any(feature = "sdl", feature = "minifb"))]
+impl PixelShape {
+    // Whether a point inside a cell, with both coordinates in -0.5..=0.5 from
+    // its center, is part of the pixel.
+    pub fn covers(self, x: f32, y: f32) -> bool {
+        match self {
+            PixelShape::Square => true,
+            PixelShape::Circle => x * x + y * y <= EXTENT * EXTENT,
+            PixelShape::Rounded => {
+                let inner = EXTENT - CORNER_RADIUS;
+                let dx = (x.abs() - inner).max(0.0);
+                let dy = (y.abs() - inner).max(0.0);
+                x.abs() <= EXTENT
+                    && y.abs() <= EXTENT
+                    && dx * dx + dy * dy <= CORNER_RADIUS * CORNER_RADIUS
+            }
+        }
+    }
+
+    // A white RGBA image of a single pixel, transparent outside the shape.
+    pub fn mask(self, size: usize) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity(size * size * 4);
+        for y in 0..size {
+            for x in 0..size {
+                let center = |i: usize| (i as f32 + 0.5) / size as f32 - 0.5;
+                let alpha = if self.covers(center(x), center(y)) {
+                    255
+                } else {
+                    0
+                };
+                pixels.extend_from_slice(&[255, 255, 255, alpha]);
+            }
+        }
+        pixels
+    }
+}
+
+#[cfg(all(test, any(feature = "sdl", feature = "minifb")))]
+mod tests {
+    use crate::frontend::shape::PixelShape;
+    #[test]
+    fn circle_leaves_corners_out() {
+        assert!(PixelShape::Circle.covers(0.0, 0.0));
+        assert!(PixelShape::Circle.covers(0.4, 0.0));
+        assert!(!PixelShape::Circle.covers(0.4, 0.4));
+        assert!(PixelShape::Square.covers(0.5, 0.5));
+    }
+    #[test]
+    fn rounded_keeps_more_than_circle() {
+        assert!(PixelShape::Rounded.covers(0.3, 0.3));
+        assert!(!PixelShape::Circle.covers(0.3, 0.35));
+        assert!(PixelShape::Rounded.covers(0.3, 0.35));
+        assert!(!PixelShape::Rounded.covers(0.44, 0.44));
+    }
+    #[test]
+    fn mask_is_transparent_outside_the_shape() {
+        let mask = PixelShape::Circle.mask(4);
+        let alpha = |x: usize, y: usize| mask[(y * 4 + x) * 4 + 3];
+        assert_eq!(alpha(0, 0), 0);
+        assert_eq!(alpha(1, 1), 255);
+        assert_eq!(alpha(0, 1), 255);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::chip8::platform::Platform;
+use crate::frontend::runner::TIMER_SPEED;
+use std::fmt;
+
+// Beyond this even the fastest machines fall behind, and below one
+// instruction a second nothing seems to happen at all.
+const MAX_IPS: u32 = 1_000_000;
+
+// How fast to run, as --speed gives it: instructions a second, instructions
+// a frame, as Octo counts them, or a percentage of the platform's own speed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Speed {
+    Ips(u32),
+    Ipf(u32),
+    Percent(u32),
+}
+
+impl Default for Speed {
+    fn default() -> Self {
+        Speed::Percent(100)
+    }
+}
+
+impl fmt::Display for Speed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Speed::Ips(ips) => write!(f, "{}ips", ips),
+            Speed::Ipf(ipf) => write!(f, "{}ipf", ipf),
+            Speed::Percent(percent) => write!(f, "{}%", percent),
+        }
+    }
+}
+
+// Reads 700ips, 11ipf or 150%, or a bare number of instructions a second as
+// --speed always took.
+pub fn parse_speed(text: &str) -> Result<Speed, String> {
+    let lower = text.trim().to_lowercase();
+    let (number, speed): (&str, fn(u32) -> Speed) = if let Some(n) = lower.strip_suffix("ips") {
+        (n, Speed::Ips)
+    } else if let Some(n) = lower.strip_suffix("ipf") {
+        (n, Speed::Ipf)
+    } else if let Some(n) = lower.strip_suffix('%') {
+        (n, Speed::Percent)
+    } else {
+        (&lower, Speed::Ips)
+    };
+    let speed = number
+        .trim()
+        .parse()
+        .ok()
+        .filter(|n| *n > 0)
+        .map(speed)
+        .ok_or_else(|| format!("'{}' is not a speed, such as 700ips, 11ipf or 150%", text))?;
+    if speed.exact(None) > MAX_IPS as u64 {
+        return Err(format!(
+            "{} is faster than {} instructions a second",
+            text, MAX_IPS
+        ));
+    }
+    Ok(speed)
+}
+
+// What each platform ran at, roughly: the COSMAC VIP and the HP-48
+// calculators, and Octo's default for XO-CHIP.
+pub fn platform_ips(platform: Option<Platform>) -> u32 {
+    match platform.unwrap_or(Platform::Chip8) {
+        Platform::Chip8 | Platform::Chip48 => 1000,
+        Platform::Schip => 1800,
+        Platform::XoChip => 6000,
+    }
+}
+
+impl Speed {
+    // Instructions a second, the percentage being of the platform's speed,
+    // CHIP-8's without one.
+    pub fn ips(self, platform: Option<Platform>) -> u32 {
+        self.exact(platform).clamp(1, MAX_IPS as u64) as u32
+    }
+
+    fn exact(self, platform: Option<Platform>) -> u64 {
+        match self {
+            Speed::Ips(ips) => ips as u64,
+            Speed::Ipf(ipf) => ipf as u64 * TIMER_SPEED as u64,
+            Speed::Percent(percent) => platform_ips(platform) as u64 * percent as u64 / 100,
+        }
+    }
+}
+
+// The speed as shown, with the instructions each frame that games are
+// often tuned by.
+pub fn describe(ips: u32) -> String {
+    if ips.is_multiple_of(TIMER_SPEED) {
+        format!("{} IPS ({} IPF)", ips, ips / TIMER_SPEED)
+    } else {
+        format!("{} IPS ({:.1} IPF)", ips, ips as f64 / TIMER_SPEED as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::platform::Platform;
+    use crate::frontend::speed::describe;
+    use crate::frontend::speed::parse_speed;
+    use crate::frontend::speed::Speed;
+    #[test]
+    fn parse_speed_reads_each_unit() {
+        assert_eq!(parse_speed("700"), Ok(Speed::Ips(700)));
+        assert_eq!(parse_speed("700ips"), Ok(Speed::Ips(700)));
+        assert_eq!(parse_speed("11IPF"), Ok(Speed::Ipf(11)));
+        assert_eq!(parse_speed("150%"), Ok(Speed::Percent(150)));
+        assert!(parse_speed("0ipf").is_err());
+        assert!(parse_speed("fast").is_err());
+        assert!(parse_speed("-5%").is_err());
+        assert!(parse_speed("20000ipf").is_err());
+    }
+    #[test]
+    fn ips_counts_frames_and_platforms() {
+        assert_eq!(Speed::Ipf(11).ips(None), 660);
+        assert_eq!(Speed::Percent(150).ips(None), 1500);
+        assert_eq!(Speed::Percent(50).ips(Some(Platform::Schip)), 900);
+        assert_eq!(Speed::Percent(1).ips(None), 10);
+        assert_eq!(Speed::Ips(30).ips(Some(Platform::XoChip)), 30);
+    }
+    #[test]
+    fn describe_shows_instructions_a_frame() {
+        assert_eq!(describe(660), "660 IPS (11 IPF)");
+        assert_eq!(describe(1000), "1000 IPS (16.7 IPF)");
+    }
+}
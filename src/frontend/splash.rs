@@ -0,0 +1,34 @@
+use crate::chip8::octo;
+
+// Written in Octo like the games it stands in for, and assembled when the
+// emulator starts without a ROM, which then shows that the interpreter, the
+// timers and the display all work.
+const SOURCE: &str = include_str!("splash.8o");
+
+pub fn rom() -> Vec<u8> {
+    octo::assemble(SOURCE).expect("the splash screen assembles")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::Chip8;
+    use crate::frontend::splash::rom;
+    #[test]
+    fn rom_draws_the_logo_and_blinks() {
+        let mut chip8 = Chip8::new();
+        chip8.load(&rom()).unwrap();
+        let mut run = |frames| {
+            for _ in 0..frames {
+                for _ in 0..20 {
+                    chip8.fetch_execute();
+                }
+                chip8.dec_timers();
+            }
+            chip8.display.as_buffer().iter().filter(|&&on| on).count()
+        };
+        let shown = run(10);
+        assert!(shown > 100);
+        // Half a second later, the prompt is taken off.
+        assert!(run(35) < shown);
+    }
+}
@@ -0,0 +1,93 @@
+use std::time::Duration;
+use std::time::Instant;
+
+const SAMPLE: Duration = Duration::from_secs(1);
+
+// Measures the frames actually presented and the instructions actually
+// executed per second, so they can be compared with the requested speed.
+pub struct Stats {
+    started: Instant,
+    frames: u32,
+    skipped: u32,
+    cycles: u64,
+    fps: f64,
+    // Frames left undrawn a second to keep up.
+    sps: f64,
+    ips: f64,
+}
+
+impl Stats {
+    pub fn new(now: Instant, cycles: u64) -> Self {
+        Self {
+            started: now,
+            frames: 0,
+            skipped: 0,
+            cycles,
+            fps: 0.0,
+            sps: 0.0,
+            ips: 0.0,
+        }
+    }
+
+    pub fn frame(&mut self) {
+        self.frames += 1;
+    }
+
+    #[cfg(feature = "sdl")]
+    pub fn skip(&mut self) {
+        self.skipped += 1;
+    }
+
+    // Takes a new sample once a second has gone by, returning whether it did.
+    // The cycle count is the interpreter's running total.
+    pub fn update(&mut self, now: Instant, cycles: u64) -> bool {
+        let elapsed = now.saturating_duration_since(self.started);
+        if elapsed < SAMPLE {
+            return false;
+        }
+        let seconds = elapsed.as_secs_f64();
+        self.fps = self.frames as f64 / seconds;
+        self.sps = self.skipped as f64 / seconds;
+        self.ips = cycles.saturating_sub(self.cycles) as f64 / seconds;
+        self.started = now;
+        self.frames = 0;
+        self.skipped = 0;
+        self.cycles = cycles;
+        true
+    }
+
+    pub fn summary(&self, speed: u32) -> String {
+        let summary = format!("{:.0} FPS {:.0}/{} IPS", self.fps, self.ips, speed);
+        if self.sps >= 0.5 {
+            return format!("{} {:.0} SKIP", summary, self.sps);
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frontend::stats::Stats;
+    use std::time::Duration;
+    use std::time::Instant;
+    #[test]
+    fn update_samples_once_a_second() {
+        let start = Instant::now();
+        let mut stats = Stats::new(start, 100);
+        for _ in 0..30 {
+            stats.frame();
+        }
+        assert!(!stats.update(start + Duration::from_millis(500), 600));
+        assert!(stats.update(start + Duration::from_secs(2), 1100));
+        assert_eq!(stats.summary(1000), "15 FPS 500/1000 IPS");
+    }
+    #[cfg(feature = "sdl")]
+    #[test]
+    fn summary_counts_skipped_frames() {
+        let start = Instant::now();
+        let mut stats = Stats::new(start, 100);
+        stats.skip();
+        assert!(stats.update(start + Duration::from_secs(1), 1100));
+        assert_eq!(stats.summary(1000), "0 FPS 1000/1000 IPS 1 SKIP");
+    }
+}
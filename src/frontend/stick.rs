@@ -0,0 +1,155 @@
+use crate::frontend::keymap::Value;
+use serde::Deserialize;
+
+// How far back towards the center the stick has to go to let go of a
+// direction, so that it does not flicker on the edge of the deadzone.
+const HYSTERESIS: f32 = 0.1;
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Spec {
+    up: Value,
+    down: Value,
+    left: Value,
+    right: Value,
+    deadzone: f32,
+}
+
+impl Default for Spec {
+    fn default() -> Self {
+        Self {
+            up: Value::Number(0x5),
+            down: Value::Number(0x8),
+            left: Value::Number(0x7),
+            right: Value::Number(0x9),
+            deadzone: 0.4,
+        }
+    }
+}
+
+// The keypad keys the left stick of a controller presses when pushed in
+// each direction, up, down, left and right, past the deadzone, a fraction
+// of the way to the edge.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "Spec")]
+pub struct StickOptions {
+    keys: [u8; 4],
+    deadzone: f32,
+}
+
+impl Default for StickOptions {
+    fn default() -> Self {
+        Self::try_from(Spec::default()).unwrap()
+    }
+}
+
+impl TryFrom<Spec> for StickOptions {
+    type Error = String;
+
+    fn try_from(spec: Spec) -> Result<Self, String> {
+        let keypad = |value: &Value, direction| {
+            value
+                .keypad()
+                .ok_or_else(|| format!("stick {} must press a keypad value 0 to F", direction))
+        };
+        if !(HYSTERESIS..1.0).contains(&spec.deadzone) {
+            return Err(format!(
+                "stick deadzone must be from {} to under 1",
+                HYSTERESIS
+            ));
+        }
+        Ok(Self {
+            keys: [
+                keypad(&spec.up, "up")?,
+                keypad(&spec.down, "down")?,
+                keypad(&spec.left, "left")?,
+                keypad(&spec.right, "right")?,
+            ],
+            deadzone: spec.deadzone,
+        })
+    }
+}
+
+impl StickOptions {
+    // The directions that press the keypad value.
+    pub fn directions(&self, value: u8) -> Vec<&'static str> {
+        ["up", "down", "left", "right"]
+            .into_iter()
+            .zip(self.keys)
+            .filter(|(_, key)| *key == value)
+            .map(|(direction, _)| direction)
+            .collect()
+    }
+}
+
+// Turns the position of the stick into presses of the direction keys.
+#[cfg(feature = "sdl")]
+pub struct Stick {
+    options: StickOptions,
+    // Up, down, left and right.
+    pressed: [bool; 4],
+}
+
+#[cfg(feature = "sdl")]
+impl Stick {
+    pub fn new(options: &StickOptions) -> Self {
+        Self {
+            options: options.clone(),
+            pressed: [false; 4],
+        }
+    }
+
+    // Takes the position on one axis, from -1 (up or left) to 1 (down or
+    // right), and returns the keypad presses and releases it makes.
+    pub fn axis(&mut self, horizontal: bool, value: f32) -> Vec<(u8, bool)> {
+        let directions = if horizontal { [2, 3] } else { [0, 1] };
+        let mut changes = Vec::new();
+        for (direction, amount) in directions.into_iter().zip([-value, value]) {
+            let pressed = if self.pressed[direction] {
+                amount > self.options.deadzone - HYSTERESIS
+            } else {
+                amount > self.options.deadzone
+            };
+            if pressed != self.pressed[direction] {
+                self.pressed[direction] = pressed;
+                changes.push((self.options.keys[direction], pressed));
+            }
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "sdl")]
+    use crate::frontend::stick::Stick;
+    #[cfg(feature = "sdl")]
+    use crate::frontend::stick::StickOptions;
+    #[cfg(feature = "sdl")]
+    #[test]
+    fn directions_press_past_the_deadzone() {
+        let mut stick = Stick::new(&StickOptions::default());
+        assert_eq!(stick.axis(true, 0.3), vec![]);
+        assert_eq!(stick.axis(true, 0.5), vec![(0x9, true)]);
+        // Inside the deadzone but not by enough to let go.
+        assert_eq!(stick.axis(true, 0.35), vec![]);
+        assert_eq!(stick.axis(true, -0.5), vec![(0x7, true), (0x9, false)]);
+        assert_eq!(stick.axis(false, -0.9), vec![(0x5, true)]);
+        assert_eq!(stick.axis(false, 0.0), vec![(0x5, false)]);
+    }
+    #[cfg(feature = "sdl")]
+    #[test]
+    fn options_are_checked() {
+        let options: StickOptions = toml::from_str("up = \"A\"\ndeadzone = 0.6").unwrap();
+        let mut stick = Stick::new(&options);
+        assert_eq!(stick.axis(false, -0.7), vec![(0xA, true)]);
+        let error = |content| {
+            toml::from_str::<StickOptions>(content)
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(error("left = 16").contains("stick left must press a keypad value"));
+        assert!(error("deadzone = 1.0").contains("stick deadzone must be from 0.1"));
+        assert!(error("diagonal = 1").contains("unknown field"));
+    }
+}
@@ -0,0 +1,513 @@
+use crate::audio::Audio;
+use crate::audio::FREQUENCY_STEP;
+use crate::audio::VOLUME_STEP;
+use crate::chip8::display::RES_HEIGHT;
+use crate::chip8::display::RES_WIDTH;
+use crate::chip8::Chip8;
+use crate::chip8::Reset;
+use crate::config::Config;
+use crate::failure::Failure;
+use crate::frontend::focus::Focus;
+use crate::frontend::framebuffer::levels;
+use crate::frontend::framebuffer::rgb24;
+use crate::frontend::glyphs::cells;
+use crate::frontend::glyphs::Cell;
+use crate::frontend::glyphs::Glyphs;
+use crate::frontend::graphics::kitty;
+use crate::frontend::graphics::kitty_clear;
+use crate::frontend::graphics::sixel;
+use crate::frontend::graphics::upscale_rgb24;
+use crate::frontend::limits::Limits;
+use crate::frontend::macros::Player;
+use crate::frontend::notifications::Notifications;
+use crate::frontend::palette::Palette;
+use crate::frontend::palette::Palettes;
+use crate::frontend::palette::Rgb;
+use crate::frontend::palette::Tone;
+use crate::frontend::phosphor::Phosphor;
+use crate::frontend::pokes::Pokes;
+use crate::frontend::rpc::Rpc;
+use crate::frontend::runner::step_speed;
+use crate::frontend::runner::Hold;
+use crate::frontend::runner::Runner;
+use crate::frontend::runner::TIMER_SPEED;
+use crate::frontend::script::Script;
+use crate::frontend::speed::describe;
+use crate::frontend::stats::Stats;
+use crate::frontend::title::rom_name;
+use crate::Args;
+use clap::ValueEnum;
+use crossterm::cursor;
+use crossterm::event;
+use crossterm::event::Event;
+use crossterm::event::KeyCode;
+use crossterm::event::KeyEventKind;
+use crossterm::event::KeyModifiers;
+use crossterm::event::KeyboardEnhancementFlags;
+use crossterm::event::PopKeyboardEnhancementFlags;
+use crossterm::event::PushKeyboardEnhancementFlags;
+use crossterm::execute;
+use crossterm::queue;
+use crossterm::style::Color;
+use crossterm::style::Print;
+use crossterm::style::ResetColor;
+use crossterm::style::SetBackgroundColor;
+use crossterm::style::SetForegroundColor;
+use crossterm::terminal;
+use crossterm::terminal::ClearType;
+use std::collections::BTreeMap;
+use std::io;
+use std::io::stdout;
+use std::io::Stdout;
+use std::io::Write;
+use std::time::Duration;
+use std::time::Instant;
+
+// Most terminals never report key releases, so a key is considered held for
+// this many frames after its last press (or auto-repeat).
+const KEY_HOLD_FRAMES: u8 = 6;
+
+// Graphics protocols get a pre-scaled bitmap, so that the terminal does not
+// need to (blurrily) scale it up by itself.
+const GRAPHICS_SCALE: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum TerminalMode {
+    /// Two full blocks per pixel
+    Block,
+    /// One cell for two vertically stacked pixels
+    Half,
+    /// One braille character for a 2x4 group of pixels
+    Braille,
+    /// Bitmap drawn with the kitty graphics protocol
+    Kitty,
+    /// Bitmap drawn as sixels
+    Sixel,
+}
+
+impl TerminalMode {
+    fn glyphs(self) -> Option<Glyphs> {
+        match self {
+            TerminalMode::Block => Some(Glyphs::Block),
+            TerminalMode::Half => Some(Glyphs::Half),
+            TerminalMode::Braille => Some(Glyphs::Braille),
+            TerminalMode::Kitty | TerminalMode::Sixel => None,
+        }
+    }
+}
+
+fn key_name(code: KeyCode) -> Option<String> {
+    match code {
+        KeyCode::Char(c) => Some(c.to_string()),
+        KeyCode::Up => Some(String::from("Up")),
+        KeyCode::Down => Some(String::from("Down")),
+        KeyCode::Left => Some(String::from("Left")),
+        KeyCode::Right => Some(String::from("Right")),
+        _ => None,
+    }
+}
+
+fn term_color(rgb: Rgb) -> Color {
+    Color::Rgb {
+        r: rgb.0,
+        g: rgb.1,
+        b: rgb.2,
+    }
+}
+
+// Puts the terminal in raw mode on the alternate screen and restores it when
+// dropped, even when unwinding from a panic.
+struct TerminalGuard {
+    key_releases: bool,
+    mode: TerminalMode,
+}
+
+impl TerminalGuard {
+    fn enter(mode: TerminalMode) -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let key_releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        // Restores whatever was set up so far should the rest fail.
+        let guard = Self { key_releases, mode };
+        let mut out = stdout();
+        execute!(
+            out,
+            terminal::EnterAlternateScreen,
+            terminal::Clear(ClearType::All),
+            cursor::Hide,
+            event::EnableFocusChange
+        )?;
+        if key_releases {
+            execute!(
+                out,
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let mut out = stdout();
+        if self.mode == TerminalMode::Kitty {
+            let _ = execute!(out, Print(kitty_clear()));
+        }
+        if self.key_releases {
+            let _ = execute!(out, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(
+            out,
+            ResetColor,
+            event::DisableFocusChange,
+            cursor::Show,
+            terminal::LeaveAlternateScreen
+        );
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+pub fn run(
+    args: &Args,
+    config: &Config,
+    mut palettes: Palettes,
+    mut chip8: Chip8,
+    audio: &mut dyn Audio,
+) -> Result<(), Failure> {
+    let guard = TerminalGuard::enter(args.terminal_mode)
+        .map_err(|e| Failure::Video(format!("terminal unavailable: {}", e)))?;
+    let mut out = stdout();
+
+    let rom_name = rom_name(args.rom().unwrap_or_default());
+
+    let mut phosphor = Phosphor::from_args(args);
+
+    let mut speed = args.ips;
+    let mut runner = Runner::new(speed);
+    let mut rpc = args
+        .debug_rpc
+        .as_deref()
+        .map(Rpc::bind)
+        .transpose()
+        .map_err(Failure::Input)?;
+    let hold = Hold::from_args(args);
+    let frame = Duration::from_secs(1) / TIMER_SPEED;
+    let mut last_frame = Instant::now();
+    let mut held: [u8; 16] = [0; 16];
+    let mut macros = Player::new(&config.macros);
+    let mut script = Script::from_args(args);
+    let pokes = Pokes::from_args(args);
+    let limits = Limits::from_args(args);
+    // Frames each host key bound to a macro is held for.
+    let mut macro_held: BTreeMap<String, u8> = BTreeMap::new();
+    // Frames Tab is held for, which runs the emulation faster, and the same
+    // for Backspace, which runs it slower.
+    let mut fast_forward: u8 = 0;
+    let mut slow_motion: u8 = 0;
+    let mut pause_emulation = args.pause_on_start;
+    let mut focus = Focus::new(config.window.pause_on_focus_loss);
+    let mut redraw = true;
+    let mut notifications = Notifications::new();
+    let mut stats = Stats::new(Instant::now(), chip8.cycles());
+    let mut show_stats = args.stats;
+    let mut shown_beep = false;
+
+    'running: loop {
+        let deadline = last_frame + frame;
+        while let Some(timeout) = deadline.checked_duration_since(Instant::now()) {
+            if !event::poll(timeout).unwrap() {
+                break;
+            }
+            match event::read().unwrap() {
+                Event::Key(key) => match key.code {
+                    KeyCode::Esc => break 'running,
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        break 'running
+                    }
+                    KeyCode::Char(' ') if key.kind == KeyEventKind::Press => {
+                        pause_emulation = !pause_emulation;
+                        redraw = true;
+                    }
+                    KeyCode::Char('p') if key.kind == KeyEventKind::Press => {
+                        palettes.cycle();
+                        notifications.push(format!("Palette: {}", palettes.current().name));
+                        redraw = true;
+                    }
+                    KeyCode::Char('n') if key.kind == KeyEventKind::Press => {
+                        notifications.push(if palettes.invert() {
+                            "Colors inverted"
+                        } else {
+                            "Colors restored"
+                        });
+                        redraw = true;
+                    }
+                    KeyCode::Char('i') if key.kind == KeyEventKind::Press => {
+                        show_stats = !show_stats;
+                        redraw = true;
+                    }
+                    KeyCode::F(2) if key.kind == KeyEventKind::Press => {
+                        let hard = key.modifiers.contains(KeyModifiers::SHIFT);
+                        if hard {
+                            macros.stop(&mut chip8);
+                            if let Some(script) = script.as_mut() {
+                                script.rewind(&mut chip8);
+                            }
+                        }
+                        chip8.reset(if hard { Reset::Hard } else { Reset::Soft });
+                        if let Some(phosphor) = phosphor.as_mut() {
+                            phosphor.clear();
+                        }
+                        pause_emulation |= args.pause_on_start;
+                        notifications.push(if hard { "Hard reset" } else { "Reset" });
+                        redraw = true;
+                    }
+                    KeyCode::F(n @ 5..=6) if key.kind == KeyEventKind::Press => {
+                        let step = if n == 5 { -Tone::STEP } else { Tone::STEP };
+                        let brightness = palettes.adjust_brightness(step);
+                        notifications.push(format!("Brightness: {:.0}%", brightness * 100.0));
+                        redraw = true;
+                    }
+                    KeyCode::F(n @ 7..=8) if key.kind == KeyEventKind::Press => {
+                        let step = if n == 7 { -Tone::STEP } else { Tone::STEP };
+                        notifications.push(format!("Gamma: {:.1}", palettes.adjust_gamma(step)));
+                        redraw = true;
+                    }
+                    KeyCode::F(n @ 9..=10) if key.kind == KeyEventKind::Press => {
+                        let step = if n == 9 {
+                            -FREQUENCY_STEP
+                        } else {
+                            FREQUENCY_STEP
+                        };
+                        let frequency = audio.adjust_frequency(step);
+                        notifications.push(format!("Beep: {} Hz", frequency));
+                        redraw = true;
+                    }
+                    KeyCode::Char(c @ ('[' | ']')) if key.kind == KeyEventKind::Press => {
+                        let step = if c == '[' { -VOLUME_STEP } else { VOLUME_STEP };
+                        notifications.push(format!("Volume: {}%", audio.adjust_volume(step)));
+                        redraw = true;
+                    }
+                    KeyCode::Char(c @ ('-' | '+' | '=')) if key.kind == KeyEventKind::Press => {
+                        speed = step_speed(speed, c != '-');
+                        runner.set_speed(speed);
+                        notifications.push(format!("Speed: {}", describe(speed)));
+                        redraw = true;
+                    }
+                    KeyCode::Tab | KeyCode::Backspace => {
+                        let frames = if key.kind == KeyEventKind::Release {
+                            0
+                        } else {
+                            KEY_HOLD_FRAMES
+                        };
+                        if key.code == KeyCode::Tab {
+                            fast_forward = frames;
+                        } else {
+                            slow_motion = frames;
+                        }
+                    }
+                    code => {
+                        let Some(name) = key_name(code) else {
+                            continue;
+                        };
+                        if let Some(frames) = macro_held.get_mut(&name) {
+                            if key.kind == KeyEventKind::Release {
+                                macro_held.remove(&name);
+                                macros.key(&name, false);
+                            } else {
+                                *frames = KEY_HOLD_FRAMES;
+                            }
+                            continue;
+                        }
+                        if key.kind != KeyEventKind::Release && macros.key(&name, true) {
+                            macro_held.insert(name, KEY_HOLD_FRAMES);
+                            continue;
+                        }
+                        if let Some(x) = config.keys.key(&name) {
+                            if key.kind == KeyEventKind::Release {
+                                held[x as usize] = 0;
+                                chip8.key_pressed(x, false);
+                            } else {
+                                if held[x as usize] == 0 {
+                                    chip8.key_pressed(x, true);
+                                }
+                                held[x as usize] = KEY_HOLD_FRAMES;
+                            }
+                        }
+                    }
+                },
+                change @ (Event::FocusGained | Event::FocusLost) => {
+                    let focused = matches!(change, Event::FocusGained);
+                    pause_emulation = focus.change(focused, pause_emulation);
+                    redraw = true;
+                }
+                Event::Resize(..) => {
+                    queue!(out, terminal::Clear(ClearType::All)).unwrap();
+                    redraw = true;
+                }
+                _ => {}
+            }
+        }
+
+        let elapsed = last_frame.elapsed();
+        last_frame = Instant::now();
+
+        if !guard.key_releases {
+            fast_forward = fast_forward.saturating_sub(1);
+            slow_motion = slow_motion.saturating_sub(1);
+            for (x, frames) in held.iter_mut().enumerate() {
+                if *frames > 0 {
+                    *frames -= 1;
+                    if *frames == 0 {
+                        chip8.key_pressed(x as u8, false);
+                    }
+                }
+            }
+            macro_held.retain(|name, frames| {
+                *frames -= 1;
+                if *frames == 0 {
+                    macros.key(name, false);
+                }
+                *frames > 0
+            });
+        }
+
+        if let Some(rpc) = rpc.as_mut() {
+            let paused = pause_emulation;
+            rpc.poll(&mut chip8, &mut runner, &mut pause_emulation);
+            redraw |= paused != pause_emulation;
+        }
+        runner.set_scale(hold.scale(fast_forward > 0, slow_motion > 0));
+        if pause_emulation {
+            audio.pause();
+        } else {
+            for _ in 0..runner.advance(&mut chip8, elapsed) {
+                macros.tick(&mut chip8);
+                if let Some(script) = script.as_mut() {
+                    script.tick(&mut chip8);
+                }
+                if let Some(pokes) = &pokes {
+                    pokes.tick(&mut chip8);
+                }
+                if let Some(phosphor) = phosphor.as_mut() {
+                    redraw |= phosphor.update(&chip8.display.as_buffer());
+                }
+            }
+            let timer = chip8.take_sound();
+            if fast_forward > 0 {
+                audio.pause();
+            } else {
+                audio.sound(timer);
+            }
+        }
+        if let Some(end) = limits
+            .as_ref()
+            .and_then(|limits| limits.check(&chip8, Instant::now()))
+        {
+            return end;
+        }
+
+        redraw |= chip8.display.take_dirty();
+        let beeping = args.visual_beep && !pause_emulation && chip8.beep();
+        redraw |= beeping != shown_beep;
+        shown_beep = beeping;
+        redraw |= notifications.expire(Instant::now());
+        redraw |= stats.update(Instant::now(), chip8.cycles()) && show_stats;
+        if redraw {
+            // Notifications go on the status line, the display has no room for
+            // text at terminal resolution.
+            let rate = if show_stats {
+                stats.summary(speed)
+            } else {
+                format!("{} IPS", speed)
+            };
+            let status = format!(
+                "{} | {} | {}{}{}{}",
+                rom_name,
+                rate,
+                "Esc quit, Space pause, P palette, N invert, I stats, F5-F8 tone, F9/F10 beep, [ ] volume, -/+ speed, Tab fast, Backspace slow",
+                if pause_emulation { " | [PAUSED]" } else { "" },
+                if beeping { " | [BEEP]" } else { "" },
+                notifications
+                    .messages()
+                    .map(|message| format!(" | {}", message))
+                    .collect::<String>()
+            );
+            let levels = levels(&chip8.display.as_buffer(), phosphor.as_ref());
+            match args.terminal_mode.glyphs() {
+                Some(glyphs) => {
+                    let rows = cells(&levels, RES_WIDTH, RES_HEIGHT, glyphs, palettes.current());
+                    draw(&mut out, &rows, &status);
+                }
+                None => draw_bitmap(
+                    &mut out,
+                    &levels,
+                    palettes.current(),
+                    args.terminal_mode,
+                    &status,
+                ),
+            }
+            stats.frame();
+            redraw = false;
+        }
+    }
+    Ok(())
+}
+
+fn draw(out: &mut Stdout, rows: &[Vec<Cell>], status: &str) {
+    let mut colors: Option<(Rgb, Rgb)> = None;
+    for (y, row) in rows.iter().enumerate() {
+        queue!(out, cursor::MoveTo(0, y as u16)).unwrap();
+        for cell in row {
+            if colors != Some((cell.fg, cell.bg)) {
+                queue!(
+                    out,
+                    SetForegroundColor(term_color(cell.fg)),
+                    SetBackgroundColor(term_color(cell.bg))
+                )
+                .unwrap();
+                colors = Some((cell.fg, cell.bg));
+            }
+            queue!(out, Print(cell.ch)).unwrap();
+        }
+    }
+    queue!(
+        out,
+        ResetColor,
+        cursor::MoveTo(0, rows.len() as u16),
+        terminal::Clear(ClearType::CurrentLine),
+        Print(status)
+    )
+    .unwrap();
+    out.flush().unwrap();
+}
+
+// Graphics protocols leave the cursor below the image, where its height in
+// lines is not known, so the status line goes above it instead.
+fn draw_bitmap(
+    out: &mut Stdout,
+    levels: &[f32],
+    palette: &Palette,
+    mode: TerminalMode,
+    status: &str,
+) {
+    let data = upscale_rgb24(
+        &rgb24(levels, palette.background, palette.foreground()),
+        RES_WIDTH,
+        RES_HEIGHT,
+        GRAPHICS_SCALE,
+    );
+    let (width, height) = (RES_WIDTH * GRAPHICS_SCALE, RES_HEIGHT * GRAPHICS_SCALE);
+    let image = match mode {
+        TerminalMode::Sixel => sixel(&data, width, height),
+        _ => kitty(&data, width, height, RES_WIDTH * 2, RES_HEIGHT),
+    };
+    queue!(
+        out,
+        cursor::MoveTo(0, 0),
+        terminal::Clear(ClearType::CurrentLine),
+        Print(status),
+        cursor::MoveTo(0, 1),
+        Print(image)
+    )
+    .unwrap();
+    out.flush().unwrap();
+}
@@ -0,0 +1,217 @@
+use crate::frontend::palette::Rgb;
+
+const GLYPH_WIDTH: usize = 3;
+const GLYPH_HEIGHT: usize = 5;
+
+// The overlay has this many pixels for every emulated pixel, which leaves room
+// for a readable amount of text on top of the 64x32 display.
+pub const OVERLAY_SCALE: usize = 4;
+
+const MARGIN: usize = 2;
+pub const LINE_HEIGHT: usize = GLYPH_HEIGHT + 3;
+const BOX_ALPHA: u8 = 160;
+const BORDER_WIDTH: usize = 2;
+
+// Rows of a 3x5 glyph, most significant of the three bits on the left. Letters
+// are upper case only and anything without a glyph is drawn as a question mark.
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    match c.to_ascii_uppercase() {
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        '(' => [0b001, 0b010, 0b010, 0b010, 0b001],
+        ')' => [0b100, 0b010, 0b010, 0b010, 0b100],
+        '[' => [0b011, 0b010, 0b010, 0b010, 0b011],
+        ']' => [0b110, 0b010, 0b010, 0b010, 0b110],
+        '\'' => [0b010, 0b010, 0b000, 0b000, 0b000],
+        '>' => [0b100, 0b010, 0b001, 0b010, 0b100],
+        _ => [0b110, 0b001, 0b010, 0b000, 0b010],
+    }
+}
+
+pub fn text_width(text: &str) -> usize {
+    (text.chars().count() * (GLYPH_WIDTH + 1)).saturating_sub(1)
+}
+
+// A transparent RGBA image the frontends draw on top of the display.
+pub struct Overlay {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+    empty: bool,
+}
+
+impl Overlay {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width * height * 4],
+            empty: true,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.empty
+    }
+
+    pub fn clear(&mut self) {
+        if !self.empty {
+            self.pixels.fill(0);
+            self.empty = true;
+        }
+    }
+
+    fn put(&mut self, x: usize, y: usize, color: Rgb, alpha: u8) {
+        if x < self.width && y < self.height {
+            let offset = (y * self.width + x) * 4;
+            self.pixels[offset..offset + 4].copy_from_slice(&[color.0, color.1, color.2, alpha]);
+            self.empty = false;
+        }
+    }
+
+    pub fn fill(&mut self, x: usize, y: usize, width: usize, height: usize, color: Rgb, alpha: u8) {
+        for dy in 0..height {
+            for dx in 0..width {
+                self.put(x + dx, y + dy, color, alpha);
+            }
+        }
+    }
+
+    // Draws a line of text over a dark box that keeps it readable whatever is
+    // on the display underneath.
+    pub fn draw_text(&mut self, x: usize, y: usize, text: &str, color: Rgb) {
+        let width = text_width(text) + 2;
+        self.fill(x, y, width, GLYPH_HEIGHT + 2, Rgb(0, 0, 0), BOX_ALPHA);
+        for (index, c) in text.chars().enumerate() {
+            let left = x + 1 + index * (GLYPH_WIDTH + 1);
+            for (row, bits) in glyph(c).iter().enumerate() {
+                for column in 0..GLYPH_WIDTH {
+                    if bits & (0b100 >> column) != 0 {
+                        self.put(left + column, y + 1 + row, color, 255);
+                    }
+                }
+            }
+        }
+    }
+
+    // Draws a single line in the top right corner, away from notifications.
+    pub fn draw_status(&mut self, text: &str) {
+        let x = self.width.saturating_sub(text_width(text) + 2 + MARGIN);
+        self.draw_text(x, MARGIN, text, Rgb(255, 255, 255));
+    }
+
+    // Frames the whole display, as a visual cue in place of sound.
+    pub fn draw_border(&mut self, color: Rgb) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let edge = x.min(y).min(self.width - 1 - x).min(self.height - 1 - y);
+                if edge < BORDER_WIDTH {
+                    self.put(x, y, color, 255);
+                }
+            }
+        }
+    }
+
+    // How many lines of text fit one under the other.
+    #[cfg(feature = "sdl")]
+    pub fn rows(&self) -> usize {
+        (self.height - MARGIN) / LINE_HEIGHT
+    }
+
+    // Draws text on the given line, counting from the top left corner.
+    pub fn draw_line(&mut self, line: usize, text: &str, color: Rgb) {
+        self.draw_text(MARGIN, MARGIN + line * LINE_HEIGHT, text, color);
+    }
+
+    // Redraws the overlay with one message per line in the top left corner.
+    pub fn show<'a>(&mut self, messages: impl Iterator<Item = &'a str>) {
+        self.clear();
+        for (line, message) in messages.enumerate() {
+            self.draw_line(line, message, Rgb(255, 255, 255));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frontend::palette::Rgb;
+    use crate::frontend::text::text_width;
+    use crate::frontend::text::Overlay;
+    fn alpha(overlay: &Overlay, x: usize, y: usize) -> u8 {
+        overlay.pixels[(y * overlay.width + x) * 4 + 3]
+    }
+    #[test]
+    fn text_width_leaves_no_trailing_gap() {
+        assert_eq!(text_width(""), 0);
+        assert_eq!(text_width("A"), 3);
+        assert_eq!(text_width("AB"), 7);
+    }
+    #[test]
+    fn draw_text_sets_glyph_pixels_over_a_box() {
+        let mut overlay = Overlay::new(16, 8);
+        assert!(overlay.is_empty());
+        overlay.draw_text(0, 0, "1", Rgb(255, 255, 255));
+        assert!(!overlay.is_empty());
+        // Top row of '1' is 010, shifted by the one pixel border of the box.
+        assert_eq!(alpha(&overlay, 1, 1), 160);
+        assert_eq!(alpha(&overlay, 2, 1), 255);
+        assert_eq!(alpha(&overlay, 5, 1), 0);
+        overlay.clear();
+        assert!(overlay.is_empty());
+        assert_eq!(alpha(&overlay, 2, 1), 0);
+    }
+    #[test]
+    fn draw_border_leaves_the_middle_clear() {
+        let mut overlay = Overlay::new(16, 8);
+        overlay.draw_border(Rgb(255, 255, 255));
+        assert_eq!(alpha(&overlay, 0, 0), 255);
+        assert_eq!(alpha(&overlay, 15, 6), 255);
+        assert_eq!(alpha(&overlay, 8, 1), 255);
+        assert_eq!(alpha(&overlay, 8, 2), 0);
+    }
+}
@@ -0,0 +1,47 @@
+use std::path::Path;
+
+// The ROM file name without the directories leading to it.
+pub fn rom_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+// Window title telling instances apart by ROM and showing the speed and
+// whether emulation is paused.
+#[cfg(any(feature = "sdl", feature = "minifb", feature = "egui"))]
+pub fn title(rom: &str, speed: u32, paused: bool) -> String {
+    if rom.is_empty() {
+        return String::from("Chip8");
+    }
+    format!(
+        "{} - {} IPS - Chip8{}",
+        rom,
+        speed,
+        if paused { " [PAUSED]" } else { "" }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frontend::title::rom_name;
+    #[cfg(any(feature = "sdl", feature = "minifb", feature = "egui"))]
+    use crate::frontend::title::title;
+    #[test]
+    fn rom_name_drops_directories() {
+        assert_eq!(rom_name("roms/games/pong.ch8"), "pong.ch8");
+        assert_eq!(rom_name(""), "");
+    }
+    #[cfg(any(feature = "sdl", feature = "minifb", feature = "egui"))]
+    #[test]
+    fn title_shows_speed_and_pause() {
+        assert_eq!(title("pong.ch8", 700, false), "pong.ch8 - 700 IPS - Chip8");
+        assert_eq!(
+            title("pong.ch8", 700, true),
+            "pong.ch8 - 700 IPS - Chip8 [PAUSED]"
+        );
+        // Nothing to tell about before a ROM is picked.
+        assert_eq!(title("", 700, false), "Chip8");
+    }
+}
@@ -0,0 +1,88 @@
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+
+// How often the file is looked at, which is still well before anyone gets to
+// switch back to the window after saving it.
+pub const INTERVAL: Duration = Duration::from_millis(250);
+
+// Tells when the ROM's file was written to, so that it can run again as soon
+// as it is rebuilt or its source saved. The modification time is polled,
+// which works the same everywhere without a crate to be told by the system.
+pub struct Watch {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    checked: Instant,
+}
+
+impl Watch {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            modified: modified(path),
+            checked: Instant::now(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Whether the file changed since it was last looked at.
+    pub fn changed(&mut self, now: Instant) -> bool {
+        if now.duration_since(self.checked) < INTERVAL {
+            return false;
+        }
+        self.checked = now;
+        self.update(modified(&self.path))
+    }
+
+    // A file gone for a moment, as some editors save by replacing it, is
+    // only seen again once it is back.
+    fn update(&mut self, modified: Option<SystemTime>) -> bool {
+        match modified {
+            Some(time) if self.modified != Some(time) => {
+                self.modified = Some(time);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frontend::watch::Watch;
+    use std::path::Path;
+    use std::time::Duration;
+    use std::time::SystemTime;
+    #[test]
+    fn update_reports_each_new_time_once() {
+        let mut watch = Watch::new(Path::new("missing.8o"));
+        let saved = SystemTime::UNIX_EPOCH + Duration::from_secs(60);
+        assert!(watch.update(Some(saved)));
+        assert!(!watch.update(Some(saved)));
+        assert!(!watch.update(None));
+        assert!(!watch.update(Some(saved)));
+        assert!(watch.update(Some(saved + Duration::from_secs(1))));
+    }
+    #[test]
+    fn changed_waits_between_looks() {
+        let mut watch = Watch::new(Path::new("missing.8o"));
+        watch.modified = Some(SystemTime::UNIX_EPOCH);
+        let start = watch.checked;
+        assert!(!watch.changed(start + Duration::from_millis(100)));
+        assert_eq!(watch.checked, start);
+        assert!(!watch.changed(start + Duration::from_millis(300)));
+        assert_eq!(watch.checked, start + Duration::from_millis(300));
+    }
+}
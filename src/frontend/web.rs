@@ -0,0 +1,534 @@
+use crate::chip8::display::DisplayBuffer;
+use crate::chip8::display::RES_HEIGHT;
+use crate::chip8::display::RES_WIDTH;
+use crate::chip8::Chip8;
+use crate::config::Config;
+use crate::failure::Failure;
+use crate::frontend::control::apply;
+use crate::frontend::control::route;
+use crate::frontend::control::Command;
+use crate::frontend::control::Response;
+use crate::frontend::limits::Limits;
+use crate::frontend::macros::Player;
+use crate::frontend::metrics::Metrics;
+use crate::frontend::palette::Palettes;
+use crate::frontend::palette::Rgb;
+use crate::frontend::pokes::Pokes;
+use crate::frontend::rpc::Rpc;
+use crate::frontend::runner::Runner;
+use crate::frontend::runner::TIMER_SPEED;
+use crate::frontend::script::Script;
+use crate::frontend::websocket::accept_key;
+use crate::frontend::websocket::read_message;
+use crate::frontend::websocket::write_close;
+use crate::frontend::websocket::write_pong;
+use crate::frontend::websocket::write_text;
+use crate::frontend::websocket::Message;
+use crate::Args;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Read;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+use tracing::debug;
+use tracing::info;
+use tracing::warn;
+
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:8080";
+
+// The page that mirrors the display and sends the keys back.
+const PAGE: &str = include_str!("web.html");
+
+// A viewer whose connection cannot take a frame in this long is dropped, so
+// that it does not hold the emulation up.
+const WRITE_TIMEOUT: Duration = Duration::from_millis(100);
+
+// The largest request line and headers taken together.
+const MAX_HEAD: u64 = 8 * 1024;
+
+// The largest request body taken, far more than any ROM.
+const MAX_BODY: usize = 64 * 1024;
+
+// A client that takes longer than this to send its request is dropped, so
+// that it does not hold a thread forever.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+// How long a request to the API waits for the emulation to answer it.
+const ANSWER_TIMEOUT: Duration = Duration::from_secs(5);
+
+// A viewer's connection, which the server writes the display to while its
+// own thread reads the keys from it.
+type Viewer = Arc<Mutex<TcpStream>>;
+
+// A request as far as the server cares.
+struct Request {
+    method: String,
+    path: String,
+    // With the names in lowercase.
+    headers: BTreeMap<String, String>,
+    body: Vec<u8>,
+}
+
+// Reads a line of the request line and headers, failing once they go over
+// MAX_HEAD.
+fn read_line(head: &mut io::Take<impl BufRead>, line: &mut String) -> io::Result<usize> {
+    let read = head.read_line(line)?;
+    if head.limit() == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "request headers too large",
+        ));
+    }
+    Ok(read)
+}
+
+fn read_request(input: &mut impl BufRead) -> io::Result<Request> {
+    let mut head = input.by_ref().take(MAX_HEAD);
+    let mut line = String::new();
+    read_line(&mut head, &mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not an HTTP request",
+        ));
+    };
+    let mut request = Request {
+        method: String::from(method),
+        path: String::from(path),
+        headers: BTreeMap::new(),
+        body: Vec::new(),
+    };
+    loop {
+        line.clear();
+        if read_line(&mut head, &mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            request
+                .headers
+                .insert(name.trim().to_ascii_lowercase(), String::from(value.trim()));
+        }
+    }
+    let length = match request.headers.get("content-length") {
+        Some(length) => length.parse().unwrap_or(usize::MAX),
+        None => 0,
+    };
+    if length > MAX_BODY {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "request too large",
+        ));
+    }
+    request.body = vec![0; length];
+    input.read_exact(&mut request.body)?;
+    Ok(request)
+}
+
+fn respond(out: &mut impl Write, status: &str, content_type: &str, body: &[u8]) -> io::Result<()> {
+    write!(
+        out,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    out.write_all(body)
+}
+
+// What the page sends.
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Incoming {
+    // A key of the computer running the page, named as in the keymap.
+    Key { name: String, down: bool },
+}
+
+// What the page is sent: the whole display when it connects, then the
+// pixels that changed, and whether the buzzer sounds.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Outgoing {
+    Display {
+        width: usize,
+        height: usize,
+        // A 0 or 1 a pixel, row by row.
+        pixels: String,
+        foreground: String,
+        background: String,
+    },
+    Diff {
+        flip: Vec<usize>,
+    },
+    Beep {
+        on: bool,
+    },
+}
+
+fn hex(rgb: Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", rgb.0, rgb.1, rgb.2)
+}
+
+// The pixels that differ between two displays.
+fn flips(shown: &DisplayBuffer, display: &DisplayBuffer) -> Vec<usize> {
+    (0..display.len())
+        .filter(|&index| shown[index] != display[index])
+        .collect()
+}
+
+enum Event {
+    Joined(Viewer),
+    Key { name: String, down: bool },
+    Command(Command, Sender<Response>),
+    Rejected(&'static str),
+    Metrics(Sender<String>),
+}
+
+// Passes the keys from the browser on until it goes.
+fn relay(
+    input: &mut impl Read,
+    viewer: &Viewer,
+    events: &Sender<Event>,
+    held: &mut BTreeSet<String>,
+) -> io::Result<()> {
+    loop {
+        match read_message(input)? {
+            Message::Text(text) => match serde_json::from_str(&text) {
+                Ok(Incoming::Key { name, down }) => {
+                    if down {
+                        held.insert(name.clone());
+                    } else {
+                        held.remove(&name);
+                    }
+                    let _ = events.send(Event::Key { name, down });
+                }
+                Err(e) => debug!("unknown message from the page: {}", e),
+            },
+            Message::Ping(payload) => write_pong(&mut *viewer.lock().unwrap(), &payload)?,
+            Message::Close => return write_close(&mut *viewer.lock().unwrap()),
+        }
+    }
+}
+
+fn serve(stream: TcpStream, events: Sender<Event>) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut input = BufReader::new(stream.try_clone()?);
+    let mut out = stream;
+    let request = read_request(&mut input)?;
+    if let Some(command) = route(&request.method, &request.path, &request.body) {
+        let response = command.and_then(|command| {
+            let (sender, answer) = mpsc::channel();
+            let _ = events.send(Event::Command(command, sender));
+            answer
+                .recv_timeout(ANSWER_TIMEOUT)
+                .map_err(|_| Response::error("503 Service Unavailable", "no answer"))
+        });
+        let response = response.unwrap_or_else(|error| error);
+        if !response.status.starts_with('2') {
+            let _ = events.send(Event::Rejected(response.status));
+        }
+        return respond(
+            &mut out,
+            response.status,
+            response.content_type,
+            &response.body,
+        );
+    }
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/") => respond(
+            &mut out,
+            "200 OK",
+            "text/html; charset=utf-8",
+            PAGE.as_bytes(),
+        ),
+        ("GET", "/metrics") => {
+            let (sender, answer) = mpsc::channel();
+            let _ = events.send(Event::Metrics(sender));
+            match answer.recv_timeout(ANSWER_TIMEOUT) {
+                Ok(text) => respond(
+                    &mut out,
+                    "200 OK",
+                    "text/plain; version=0.0.4",
+                    text.as_bytes(),
+                ),
+                Err(_) => respond(
+                    &mut out,
+                    "503 Service Unavailable",
+                    "text/plain",
+                    b"no answer\n",
+                ),
+            }
+        }
+        ("GET", "/ws") => {
+            let Some(key) = request.headers.get("sec-websocket-key") else {
+                return respond(
+                    &mut out,
+                    "400 Bad Request",
+                    "text/plain",
+                    b"not a WebSocket\n",
+                );
+            };
+            write!(
+                out,
+                "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+                accept_key(key)
+            )?;
+            out.set_write_timeout(Some(WRITE_TIMEOUT))?;
+            // The page only sends when a key changes.
+            out.set_read_timeout(None)?;
+            let viewer = Arc::new(Mutex::new(out));
+            let _ = events.send(Event::Joined(viewer.clone()));
+            // The keys still held when the browser goes are let go of.
+            let mut held = BTreeSet::new();
+            let result = relay(&mut input, &viewer, &events, &mut held);
+            for name in held {
+                let _ = events.send(Event::Key { name, down: false });
+            }
+            result
+        }
+        _ => respond(&mut out, "404 Not Found", "text/plain", b"not found\n"),
+    }
+}
+
+// Serves the page and the display to any number of browsers, each on its own
+// thread, which pass what the browsers send on to the emulation.
+struct Server {
+    address: SocketAddr,
+    events: Receiver<Event>,
+    viewers: Vec<Viewer>,
+    shown: DisplayBuffer,
+    beeping: bool,
+    metrics: Metrics,
+}
+
+impl Server {
+    fn bind(address: &str) -> Result<Self, String> {
+        let listener = TcpListener::bind(address)
+            .map_err(|e| format!("unable to serve on {}: {}", address, e))?;
+        let address = listener.local_addr().map_err(|e| e.to_string())?;
+        let (sender, events) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let sender = sender.clone();
+                thread::spawn(move || {
+                    if let Err(e) = serve(stream, sender) {
+                        debug!("connection closed: {}", e);
+                    }
+                });
+            }
+        });
+        Ok(Self {
+            address,
+            events,
+            viewers: Vec::new(),
+            shown: [false; RES_WIDTH * RES_HEIGHT],
+            beeping: false,
+            metrics: Metrics::default(),
+        })
+    }
+
+    fn send(&mut self, message: &Outgoing) {
+        let text = serde_json::to_string(message).unwrap();
+        let before = self.viewers.len();
+        self.viewers
+            .retain(|viewer| write_text(&mut *viewer.lock().unwrap(), &text).is_ok());
+        self.metrics.viewers_dropped(before - self.viewers.len());
+    }
+
+    // Shows the new viewer the display as the others have it.
+    fn welcome(&mut self, viewer: Viewer, palettes: &Palettes) {
+        let palette = palettes.current();
+        let message = Outgoing::Display {
+            width: RES_WIDTH,
+            height: RES_HEIGHT,
+            pixels: self
+                .shown
+                .iter()
+                .map(|&on| if on { '1' } else { '0' })
+                .collect(),
+            foreground: hex(palette.foreground()),
+            background: hex(palette.background),
+        };
+        let text = serde_json::to_string(&message).unwrap();
+        if write_text(&mut *viewer.lock().unwrap(), &text).is_ok() {
+            info!("{} viewer(s) connected", self.viewers.len() + 1);
+            self.viewers.push(viewer);
+        }
+    }
+
+    fn publish(&mut self, display: &DisplayBuffer, beeping: bool) {
+        let flip = flips(&self.shown, display);
+        if !flip.is_empty() {
+            self.send(&Outgoing::Diff { flip });
+            self.shown = *display;
+            self.metrics.display_update();
+        }
+        if beeping != self.beeping {
+            self.send(&Outgoing::Beep { on: beeping });
+            self.beeping = beeping;
+        }
+    }
+}
+
+// Runs the ROM without a window, for browsers to watch and play it from
+// the page served at --serve, and for other programs to drive it through the
+// control API there.
+pub fn run(
+    args: &Args,
+    config: &Config,
+    palettes: Palettes,
+    mut chip8: Chip8,
+) -> Result<(), Failure> {
+    let mut server =
+        Server::bind(args.serve.as_deref().unwrap_or(DEFAULT_ADDRESS)).map_err(Failure::Video)?;
+    info!("serving the display on http://{}", server.address);
+
+    let mut runner = Runner::new(args.ips);
+    let mut rpc = args
+        .debug_rpc
+        .as_deref()
+        .map(Rpc::bind)
+        .transpose()
+        .map_err(Failure::Input)?;
+    let frame = Duration::from_secs(1) / TIMER_SPEED;
+    let mut last_frame = Instant::now();
+    let mut macros = Player::new(&config.macros);
+    let mut script = Script::from_args(args);
+    let pokes = Pokes::from_args(args);
+    let limits = Limits::from_args(args);
+    let mut paused = args.pause_on_start;
+
+    loop {
+        let deadline = last_frame + frame;
+        while let Some(timeout) = deadline.checked_duration_since(Instant::now()) {
+            let event = match server.events.recv_timeout(timeout) {
+                Ok(event) => event,
+                Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => break,
+            };
+            match event {
+                Event::Joined(viewer) => server.welcome(viewer, &palettes),
+                Event::Key { name, down } => {
+                    if macros.key(&name, down) {
+                        continue;
+                    }
+                    if let Some(x) = config.keys.key(&name) {
+                        chip8.key_pressed(x, down);
+                    }
+                }
+                Event::Command(command, answer) => {
+                    let _ = answer.send(apply(command, &mut chip8, &mut paused));
+                }
+                Event::Rejected(status) => server.metrics.rejected(status),
+                Event::Metrics(answer) => {
+                    let viewers = server.viewers.len();
+                    let _ = answer.send(server.metrics.render(&chip8, viewers, paused));
+                }
+            }
+        }
+
+        if let Some(rpc) = rpc.as_mut() {
+            rpc.poll(&mut chip8, &mut runner, &mut paused);
+        }
+        let elapsed = last_frame.elapsed();
+        last_frame = Instant::now();
+        let frames = if paused {
+            0
+        } else {
+            // An instruction the interpreter fails on pauses the emulation,
+            // as it does when stepping, so that the server stays up for the
+            // next ROM loaded.
+            match panic::catch_unwind(AssertUnwindSafe(|| runner.advance(&mut chip8, elapsed))) {
+                Ok(frames) => frames,
+                Err(cause) => {
+                    warn!("{}", Failure::crashed(cause));
+                    server.metrics.rejected("500 Internal Server Error");
+                    paused = true;
+                    0
+                }
+            }
+        };
+        for _ in 0..frames {
+            server.metrics.frame();
+            macros.tick(&mut chip8);
+            if let Some(script) = script.as_mut() {
+                script.tick(&mut chip8);
+            }
+            if let Some(pokes) = &pokes {
+                pokes.tick(&mut chip8);
+            }
+        }
+        if let Some(end) = limits
+            .as_ref()
+            .and_then(|limits| limits.check(&chip8, Instant::now()))
+        {
+            return end;
+        }
+
+        let beeping = chip8.beep();
+        if chip8.display.take_dirty() || beeping != server.beeping {
+            server.publish(&chip8.display.as_buffer(), beeping);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frontend::web::flips;
+    use crate::frontend::web::read_request;
+    use crate::frontend::web::Incoming;
+    #[test]
+    fn read_request_lowercases_header_names() {
+        let mut input =
+            &b"GET /ws HTTP/1.1\r\nHost: localhost\r\nSec-WebSocket-Key: abc==\r\n\r\n"[..];
+        let request = read_request(&mut input).unwrap();
+        assert_eq!(
+            (request.method.as_str(), request.path.as_str()),
+            ("GET", "/ws")
+        );
+        assert_eq!(request.headers["sec-websocket-key"], "abc==");
+    }
+    #[test]
+    fn read_request_refuses_endless_headers() {
+        let mut request = b"GET / HTTP/1.1\r\nX: ".to_vec();
+        request.resize(16 * 1024, b'x');
+        let error = read_request(&mut &request[..]).err().unwrap();
+        assert_eq!(error.to_string(), "request headers too large");
+    }
+    #[test]
+    fn flips_lists_the_changed_pixels() {
+        let mut shown = [false; 2048];
+        shown[3] = true;
+        let mut display = shown;
+        display[3] = false;
+        display[70] = true;
+        assert_eq!(flips(&shown, &display), vec![3, 70]);
+    }
+    #[test]
+    fn key_messages_name_the_key() {
+        assert_eq!(
+            serde_json::from_str::<Incoming>(r#"{"type":"key","name":"Up","down":true}"#).unwrap(),
+            Incoming::Key {
+                name: String::from("Up"),
+                down: true
+            }
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use crate::frontend::graphics::base64;
+use sha1::Digest;
+use sha1::Sha1;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+
+// Appended to the key of the browser to prove that the server speaks
+// WebSocket, as RFC 6455 has it.
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+// Messages from the browser are a few keys at most, anything much longer is
+// not from the page.
+const MAX_MESSAGE: u64 = 64 * 1024;
+
+const TEXT: u8 = 0x1;
+const CLOSE: u8 = 0x8;
+const PING: u8 = 0x9;
+const PONG: u8 = 0xA;
+
+// The answer to the Sec-WebSocket-Key of the handshake.
+pub fn accept_key(key: &str) -> String {
+    let digest = Sha1::digest(format!("{}{}", key.trim(), GUID));
+    base64(&digest)
+}
+
+fn frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x80 | opcode];
+    match payload.len() {
+        len @ 0..=125 => out.push(len as u8),
+        len @ 126..=0xFFFF => {
+            out.push(126);
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            out.push(127);
+            out.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    out.extend_from_slice(payload);
+    out
+}
+
+pub fn write_text(out: &mut impl Write, text: &str) -> io::Result<()> {
+    out.write_all(&frame(TEXT, text.as_bytes()))
+}
+
+pub fn write_pong(out: &mut impl Write, payload: &[u8]) -> io::Result<()> {
+    out.write_all(&frame(PONG, payload))
+}
+
+pub fn write_close(out: &mut impl Write) -> io::Result<()> {
+    out.write_all(&frame(CLOSE, &[]))
+}
+
+// What the browser sends that matters to the server.
+#[derive(Debug, PartialEq)]
+pub enum Message {
+    Text(String),
+    Ping(Vec<u8>),
+    Close,
+}
+
+// Reads up to the next message. Messages split over several frames, which
+// the page never sends, are skipped, as are pongs.
+pub fn read_message(input: &mut impl Read) -> io::Result<Message> {
+    loop {
+        let mut head = [0; 2];
+        input.read_exact(&mut head)?;
+        let (fin, opcode) = (head[0] & 0x80 != 0, head[0] & 0x0F);
+        let len = match head[1] & 0x7F {
+            126 => {
+                let mut len = [0; 2];
+                input.read_exact(&mut len)?;
+                u16::from_be_bytes(len) as u64
+            }
+            127 => {
+                let mut len = [0; 8];
+                input.read_exact(&mut len)?;
+                u64::from_be_bytes(len)
+            }
+            len => len as u64,
+        };
+        if len > MAX_MESSAGE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "message too long",
+            ));
+        }
+        let mut mask = [0; 4];
+        if head[1] & 0x80 != 0 {
+            input.read_exact(&mut mask)?;
+        }
+        let mut payload = vec![0; len as usize];
+        input.read_exact(&mut payload)?;
+        for (index, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[index % 4];
+        }
+        match opcode {
+            CLOSE => return Ok(Message::Close),
+            PING => return Ok(Message::Ping(payload)),
+            TEXT if fin => {
+                return String::from_utf8(payload)
+                    .map(Message::Text)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e));
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frontend::websocket::accept_key;
+    use crate::frontend::websocket::read_message;
+    use crate::frontend::websocket::write_text;
+    use crate::frontend::websocket::Message;
+    #[test]
+    fn accept_key_answers_the_example_of_the_rfc() {
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+    #[test]
+    fn read_message_unmasks_what_the_browser_sends() {
+        let input = [
+            0x8A, 0x80, 1, 2, 3, 4, // a pong, skipped
+            0x81, 0x85, 0x37, 0xFA, 0x21, 0x3D, 0x7F, 0x9F, 0x4D, 0x51, 0x58, // "Hello"
+            0x89, 0x81, 1, 2, 3, 4, 0x2B, // a ping
+            0x88, 0x80, 1, 2, 3, 4, // close
+        ];
+        let mut input = &input[..];
+        assert_eq!(
+            read_message(&mut input).unwrap(),
+            Message::Text(String::from("Hello"))
+        );
+        assert_eq!(read_message(&mut input).unwrap(), Message::Ping(vec![0x2A]));
+        assert_eq!(read_message(&mut input).unwrap(), Message::Close);
+    }
+    #[test]
+    fn write_text_sizes_long_messages() {
+        let mut out = Vec::new();
+        write_text(&mut out, &"x".repeat(300)).unwrap();
+        assert_eq!(out[..4], [0x81, 126, 0x01, 0x2C]);
+        assert_eq!(out.len(), 304);
+    }
+}
@@ -0,0 +1,449 @@
+use crate::chip8::display::RES_HEIGHT;
+use crate::chip8::display::RES_WIDTH;
+use crate::frontend::backdrop::Backdrop;
+use crate::frontend::bezel::arrange;
+use crate::frontend::bezel::Bezel;
+use crate::frontend::crt::CrtOptions;
+use crate::frontend::framebuffer::rgb24;
+use crate::frontend::framebuffer::rgba32;
+use crate::frontend::layout::Filter;
+use crate::frontend::layout::PixelAspect;
+use crate::frontend::layout::Scaling;
+use crate::frontend::layout::Viewport;
+use crate::frontend::palette::Palette;
+use crate::frontend::renderer::Renderer;
+use crate::frontend::shape::PixelShape;
+use crate::frontend::text::Overlay;
+use crate::frontend::text::OVERLAY_SCALE;
+use sdl2::video::Window;
+use std::num::NonZeroU32;
+
+// Size of the Params uniform in wgpu.wgsl: five vec4<f32>.
+const PARAMS_SIZE: u64 = 16 * 5;
+
+// Uploads the framebuffer as a texture and leaves letterboxing and the CRT
+// effects to a fragment shader. The surface is declared before the window so
+// that it is dropped first.
+pub struct WgpuRenderer {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    config: wgpu::SurfaceConfiguration,
+    texture: wgpu::Texture,
+    osd: wgpu::Texture,
+    params: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+    scaling: Scaling,
+    aspect: PixelAspect,
+    bezel: Option<Bezel>,
+    // Whether the framebuffer holds intensities as alpha, to be composited
+    // over a backdrop.
+    translucent: bool,
+    window: Window,
+}
+
+impl WgpuRenderer {
+    pub fn new(
+        window: Window,
+        vsync: bool,
+        scaling: Scaling,
+        filter: Filter,
+        bezel: Option<Bezel>,
+        backdrop: Option<Backdrop>,
+        aspect: PixelAspect,
+    ) -> Self {
+        let instance = wgpu::Instance::new(wgpu::Backends::all());
+        // Safety: the window is stored alongside the surface and outlives it.
+        let surface = unsafe { instance.create_surface(&window) };
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            compatible_surface: Some(&surface),
+            force_fallback_adapter: false,
+        }))
+        .expect("No suitable graphics adapter found!");
+        let (device, queue) = pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                features: wgpu::Features::empty(),
+                limits:
+                    wgpu::Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits()),
+            },
+            None,
+        ))
+        .unwrap();
+
+        let formats = surface.get_supported_formats(&adapter);
+        let format = formats
+            .iter()
+            .copied()
+            .find(|format| format.describe().srgb)
+            .unwrap_or(formats[0]);
+        let (width, height) = window.drawable_size();
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format,
+            width,
+            height,
+            present_mode: if vsync {
+                wgpu::PresentMode::AutoVsync
+            } else {
+                wgpu::PresentMode::AutoNoVsync
+            },
+        };
+        surface.configure(&device, &config);
+
+        let texture = create_texture(&device, "framebuffer", texture_size(1));
+        let osd = create_texture(&device, "osd", texture_size(OVERLAY_SCALE as u32));
+        // Without a bezel the shader still needs a texture to bind, so it gets
+        // a single transparent pixel that is never drawn.
+        let (bezel_size, bezel_pixels) = match &bezel {
+            Some(bezel) => (extent(bezel.width, bezel.height), bezel.pixels.as_slice()),
+            None => (extent(1, 1), [0; 4].as_slice()),
+        };
+        let bezel_texture = create_texture(&device, "bezel", bezel_size);
+        write_texture(&queue, &bezel_texture, bezel_pixels, bezel_size);
+        let (backdrop_size, backdrop_pixels) = match &backdrop {
+            Some(backdrop) => (
+                extent(backdrop.width, backdrop.height),
+                backdrop.pixels.as_slice(),
+            ),
+            None => (extent(1, 1), [0; 4].as_slice()),
+        };
+        let backdrop_texture = create_texture(&device, "backdrop", backdrop_size);
+        write_texture(&queue, &backdrop_texture, backdrop_pixels, backdrop_size);
+        let filter_mode = match filter {
+            Filter::Nearest => wgpu::FilterMode::Nearest,
+            Filter::Linear => wgpu::FilterMode::Linear,
+        };
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: filter_mode,
+            min_filter: filter_mode,
+            ..Default::default()
+        });
+        let params = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("params"),
+            size: PARAMS_SIZE,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let osd_view = osd.create_view(&wgpu::TextureViewDescriptor::default());
+        let bezel_view = bezel_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let backdrop_view = backdrop_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&osd_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(&bezel_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::TextureView(&backdrop_view),
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(include_str!("wgpu.wgsl").into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        WgpuRenderer {
+            surface,
+            device,
+            queue,
+            config,
+            texture,
+            osd,
+            params,
+            bind_group,
+            pipeline,
+            scaling,
+            aspect,
+            bezel,
+            translucent: backdrop.is_some(),
+            window,
+        }
+    }
+
+    // Keeps the surface in step with the window, which may have been resized
+    // or switched to fullscreen since the last frame.
+    fn resize(&mut self) {
+        let (width, height) = self.window.drawable_size();
+        if (width, height) != (self.config.width, self.config.height) && width > 0 && height > 0 {
+            self.config.width = width;
+            self.config.height = height;
+            self.surface.configure(&self.device, &self.config);
+        }
+    }
+}
+
+fn extent(width: u32, height: u32) -> wgpu::Extent3d {
+    wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    }
+}
+
+// Display textures are the size of the display times the given scale.
+fn texture_size(scale: u32) -> wgpu::Extent3d {
+    extent(RES_WIDTH as u32 * scale, RES_HEIGHT as u32 * scale)
+}
+
+fn create_texture(device: &wgpu::Device, label: &str, size: wgpu::Extent3d) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+    })
+}
+
+fn write_texture(queue: &wgpu::Queue, texture: &wgpu::Texture, rgba: &[u8], size: wgpu::Extent3d) {
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        rgba,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: NonZeroU32::new(size.width * 4),
+            rows_per_image: NonZeroU32::new(size.height),
+        },
+        size,
+    );
+}
+
+impl Renderer for WgpuRenderer {
+    fn window_mut(&mut self) -> &mut Window {
+        &mut self.window
+    }
+
+    fn viewport(&self) -> Viewport {
+        let (_, viewport) = arrange(
+            self.bezel.as_ref(),
+            (self.config.width, self.config.height),
+            self.scaling,
+            self.aspect,
+        );
+        viewport
+    }
+
+    fn render(&mut self, levels: &[f32], palette: &Palette, crt: &CrtOptions, overlay: &Overlay) {
+        self.resize();
+
+        let rgba: Vec<u8> = if self.translucent {
+            rgba32(levels, palette.foreground())
+        } else {
+            rgb24(levels, palette.background, palette.foreground())
+                .chunks(3)
+                .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+                .collect()
+        };
+        write_texture(&self.queue, &self.texture, &rgba, texture_size(1));
+        write_texture(
+            &self.queue,
+            &self.osd,
+            &overlay.pixels,
+            texture_size(OVERLAY_SCALE as u32),
+        );
+
+        let (area, viewport) = arrange(
+            self.bezel.as_ref(),
+            (self.config.width, self.config.height),
+            self.scaling,
+            self.aspect,
+        );
+        let area = area.unwrap_or(Viewport {
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+        });
+        // The texture is sampled as sRGB, so the shader sees linear colours
+        // and the background has to be converted to match.
+        let linear = |value: u8| (value as f32 / 255.0).powf(2.2);
+        let background = palette.background;
+        let shape = match crt.shape {
+            PixelShape::Square => 0.0,
+            PixelShape::Circle => 1.0,
+            PixelShape::Rounded => 2.0,
+        };
+        let params: [f32; 20] = [
+            viewport.x as f32,
+            viewport.y as f32,
+            viewport.width as f32,
+            viewport.height as f32,
+            RES_WIDTH as f32,
+            RES_HEIGHT as f32,
+            shape,
+            0.0,
+            linear(background.0),
+            linear(background.1),
+            linear(background.2),
+            1.0,
+            crt.scanlines,
+            crt.curvature,
+            crt.bloom,
+            crt.grid,
+            area.x as f32,
+            area.y as f32,
+            area.width as f32,
+            area.height as f32,
+        ];
+        let bytes: Vec<u8> = params
+            .iter()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect();
+        self.queue.write_buffer(&self.params, 0, &bytes);
+
+        let frame = match self.surface.get_current_texture() {
+            Ok(frame) => frame,
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                self.surface.configure(&self.device, &self.config);
+                return;
+            }
+            Err(error) => panic!("Unable to acquire frame: {error}"),
+        };
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &self.bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        }
+        self.queue.submit(Some(encoder.finish()));
+        frame.present();
+    }
+}
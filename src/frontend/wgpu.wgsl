@@ -0,0 +1,125 @@
+struct Params {
+    // Letterboxed area of the window the display is drawn into, in pixels.
+    viewport: vec4<f32>,
+    // Emulated resolution in xy, pixel shape in z (0 square, 1 circle,
+    // 2 rounded), w unused.
+    display: vec4<f32>,
+    background: vec4<f32>,
+    // Scanlines, curvature, bloom and pixel grid.
+    crt: vec4<f32>,
+    // Area of the window covered by the bezel image, empty without one.
+    bezel: vec4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var frame: texture_2d<f32>;
+@group(0) @binding(2) var frame_sampler: sampler;
+// On-screen text, laid flat over the display regardless of curvature.
+@group(0) @binding(3) var osd: texture_2d<f32>;
+@group(0) @binding(4) var bezel: texture_2d<f32>;
+// Shown through unlit pixels, fully transparent without a backdrop.
+@group(0) @binding(5) var backdrop: texture_2d<f32>;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+};
+
+// A single triangle large enough to cover the whole window.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let x = f32((index << 1u) & 2u);
+    let y = f32(index & 2u);
+    var out: VertexOutput;
+    out.position = vec4<f32>(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    return out;
+}
+
+// The framebuffer is either opaque or, with a backdrop, the foreground with
+// the intensity of every pixel as alpha.
+fn texel_at(uv: vec2<f32>) -> vec3<f32> {
+    let texel = textureSampleLevel(frame, frame_sampler, uv, 0.0);
+    return mix(params.background.rgb, texel.rgb, texel.a);
+}
+
+fn base_at(uv: vec2<f32>) -> vec3<f32> {
+    let image = textureSampleLevel(backdrop, frame_sampler, uv, 0.0);
+    return mix(params.background.rgb, image.rgb, image.a);
+}
+
+// The emulated display with the CRT effects applied, black outside of it.
+fn display_at(position: vec2<f32>) -> vec3<f32> {
+    let scanlines = params.crt.x;
+    let curvature = params.crt.y;
+    let bloom = params.crt.z;
+    let grid = params.crt.w;
+
+    // Work in -1..1 across the viewport and bend the lookup outwards towards
+    // the corners, the inverse of the warp the canvas renderer applies.
+    let local = (position - params.viewport.xy) / params.viewport.zw;
+    var p = local * 2.0 - 1.0;
+    p = p / (1.0 - curvature * 0.25 * dot(p, p));
+    let uv = (p + 1.0) * 0.5;
+    if any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) {
+        return vec3<f32>(0.0);
+    }
+
+    let base = base_at(uv);
+    let texel = textureSampleLevel(frame, frame_sampler, uv, 0.0);
+    var color = mix(base, texel.rgb, texel.a);
+
+    // Same extent and corner radius as PixelShape::covers.
+    let cell = fract(uv * params.display.xy) - 0.5;
+    let shape = u32(params.display.z);
+    var covered = true;
+    if shape == 1u {
+        covered = length(cell) <= 0.45;
+    } else if shape == 2u {
+        let corner = max(abs(cell) - vec2<f32>(0.25), vec2<f32>(0.0));
+        covered = all(abs(cell) <= vec2<f32>(0.45)) && length(corner) <= 0.2;
+    }
+    if !covered {
+        color = base;
+    }
+
+    if bloom > 0.0 {
+        let texel = 1.0 / params.display.xy;
+        var glow = vec3<f32>(0.0);
+        for (var dy = -1; dy <= 1; dy++) {
+            for (var dx = -1; dx <= 1; dx++) {
+                let neighbour = texel_at(uv + vec2<f32>(f32(dx), f32(dy)) * texel);
+                glow += max(neighbour - params.background.rgb, vec3<f32>(0.0));
+            }
+        }
+        color += glow / 9.0 * bloom;
+    }
+
+    // The first row and column of output pixels in every cell, except along
+    // the outer edges, become the gap between emulated pixels.
+    let index = uv * params.display.xy;
+    let inside = fract(index) * params.viewport.zw / params.display.xy;
+    let gap = (inside.x < 1.0 && index.x >= 1.0) || (inside.y < 1.0 && index.y >= 1.0);
+    if grid > 0.0 && gap {
+        color = mix(color, params.background.rgb, grid);
+    }
+
+    if scanlines > 0.0 && (u32(position.y - params.viewport.y) & 1u) == 1u {
+        color *= 1.0 - scanlines;
+    }
+
+    return color;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    var color = display_at(in.position.xy);
+
+    let framed = (in.position.xy - params.bezel.xy) / params.bezel.zw;
+    if params.bezel.z > 0.0 && all(framed >= vec2<f32>(0.0)) && all(framed <= vec2<f32>(1.0)) {
+        let border = textureSampleLevel(bezel, frame_sampler, framed, 0.0);
+        color = mix(color, border.rgb, border.a);
+    }
+
+    let local = (in.position.xy - params.viewport.xy) / params.viewport.zw;
+    let text = textureSampleLevel(osd, frame_sampler, local, 0.0);
+    return vec4<f32>(mix(color, text.rgb, text.a), 1.0);
+}
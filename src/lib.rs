@@ -0,0 +1,6 @@
+// The emulator core, for other programs to run ROMs with, and the egui
+// widget that shows one in their own window. The emulator itself is the
+// binary built from main.rs.
+pub mod chip8;
+#[cfg(feature = "egui")]
+pub mod widget;
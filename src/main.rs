@@ -1,176 +1,1085 @@
-mod chip8;
+mod audio;
+mod config;
+mod download;
+mod failure;
+mod frontend;
+mod logging;
+mod tools;
 
+#[cfg(feature = "sdl")]
 extern crate sdl2;
 
-use chip8::display::DisplayBuffer;
-use chip8::display::RES_HEIGHT;
-use chip8::display::RES_WIDTH;
+#[cfg(feature = "cpal")]
+use audio::cpal::CpalAudio;
+#[cfg(feature = "rodio")]
+use audio::rodio::open_sink;
+#[cfg(feature = "rodio")]
+use audio::rodio::Beeper;
+use audio::Audio;
+use audio::NullAudio;
+use chip8::hash::RomHash;
+use chip8::platform::Platform;
+use chip8::profile::Profile;
+use chip8::quirks::parse_override;
+use chip8::quirks::Quirk;
+use chip8::quirks::Quirks;
 use chip8::Chip8;
+use chip8_emu_rust::chip8;
+use clap::builder::ValueHint;
+use clap::parser::ValueSource;
+use clap::ArgGroup;
+use clap::ArgMatches;
+use clap::CommandFactory;
+use clap::FromArgMatches;
 use clap::Parser;
-use rodio::OutputStream;
-use rodio::Sink;
-use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
-use sdl2::pixels::Color;
-use sdl2::rect::Rect;
-use sdl2::render::WindowCanvas;
-use std::fs::File;
-use std::io::Read;
+use clap::Subcommand;
+use clap::ValueEnum;
+use clap_complete::Shell;
+use config::portable;
+use config::sidecar::Sidecar;
+use config::Config;
+use failure::Failure;
+use frontend::cartridge::Cartridge;
+use frontend::cartridge::Options;
+#[cfg(feature = "sdl")]
+use frontend::compare::Comparison;
+use frontend::dump::Format;
+use frontend::keymap::Keymap;
+use frontend::layout::Filter;
+use frontend::layout::PixelAspect;
+use frontend::layout::Scaling;
+#[cfg(feature = "sdl")]
+use frontend::netplay::Netplay;
+use frontend::palette::builtin_palettes;
+use frontend::palette::load_themes;
+use frontend::palette::parse_color;
+use frontend::palette::Palettes;
+use frontend::palette::Rgb;
+use frontend::palette::Tone;
+use frontend::playlist;
+use frontend::pokes::parse_poke;
+use frontend::pokes::Pokes;
+use frontend::recent::Recent;
+use frontend::shape::PixelShape;
+#[cfg(feature = "sdl")]
+use frontend::speed::describe;
+use frontend::speed::parse_speed;
+use frontend::speed::Speed;
+use frontend::splash;
+use frontend::terminal::TerminalMode;
+use logging::LogLevel;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process;
+use std::sync::Arc;
+use std::sync::Mutex;
 use std::time::Duration;
+use tracing::info;
+use tracing::warn;
 
-const TIMER_SPEED: u32 = 60;
-
-fn map_keycode(key: Keycode) -> Option<u8> {
-    match key {
-        Keycode::Num1 => Some(0x1),
-        Keycode::Num2 => Some(0x2),
-        Keycode::Num3 => Some(0x3),
-        Keycode::Num4 => Some(0xC),
-        Keycode::Q => Some(0x4),
-        Keycode::W => Some(0x5),
-        Keycode::E => Some(0x6),
-        Keycode::R => Some(0xD),
-        Keycode::A => Some(0x7),
-        Keycode::S => Some(0x8),
-        Keycode::D => Some(0x9),
-        Keycode::F => Some(0xE),
-        Keycode::Z => Some(0xA),
-        Keycode::X => Some(0x0),
-        Keycode::C => Some(0xB),
-        Keycode::V => Some(0xF),
-        _ => None,
+// How many subroutines and addresses --hotspots prints, the most run first.
+const HOTSPOTS: usize = 20;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum FrontendKind {
+    #[cfg(feature = "sdl")]
+    Sdl,
+    #[cfg(feature = "minifb")]
+    Minifb,
+    #[cfg(feature = "egui")]
+    Egui,
+    Terminal,
+    Web,
+}
+
+impl Default for FrontendKind {
+    #[allow(unreachable_code)]
+    fn default() -> Self {
+        #[cfg(feature = "sdl")]
+        return FrontendKind::Sdl;
+        #[cfg(feature = "minifb")]
+        return FrontendKind::Minifb;
+        FrontendKind::Terminal
     }
 }
 
+impl FrontendKind {
+    // Whether the frontend can start without a ROM and let one be picked.
+    fn browses(self) -> bool {
+        match self {
+            #[cfg(feature = "sdl")]
+            FrontendKind::Sdl => true,
+            _ => false,
+        }
+    }
+
+    // Whether the frontend can switch between the ROMs of a playlist.
+    fn switches(self) -> bool {
+        match self {
+            #[cfg(feature = "sdl")]
+            FrontendKind::Sdl => true,
+            _ => false,
+        }
+    }
+
+    // Whether the frontend sounds the buzzer itself, which the web frontend
+    // leaves to the browsers.
+    fn sounds(self) -> bool {
+        !matches!(self, FrontendKind::Web)
+    }
+
+    // Whether the frontend can be driven by a debugger over the network.
+    fn debugs_remotely(self) -> bool {
+        match self {
+            #[cfg(feature = "minifb")]
+            FrontendKind::Minifb => false,
+            #[cfg(feature = "egui")]
+            FrontendKind::Egui => false,
+            _ => true,
+        }
+    }
+
+    // Whether the frontend takes keys from elsewhere than its own window: from
+    // another instance over the network, from a chat or from a MIDI
+    // controller.
+    fn external_input(self) -> bool {
+        match self {
+            #[cfg(feature = "sdl")]
+            FrontendKind::Sdl => true,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(feature = "sdl")]
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum RendererKind {
+    Canvas,
+    #[cfg(feature = "wgpu")]
+    Wgpu,
+}
+
+#[cfg(any(feature = "rodio", feature = "cpal"))]
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+enum AudioBackend {
+    #[cfg(feature = "rodio")]
+    #[default]
+    Rodio,
+    #[cfg(feature = "cpal")]
+    #[cfg_attr(not(feature = "rodio"), default)]
+    Cpal,
+}
+
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(
+    author,
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    // Running a ROM is what the emulator is for, so it needs no command.
+    #[command(flatten)]
+    run: Args,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run a ROM, the same as giving it without a command
+    Run(Box<Args>),
+
+    /// Print the instructions of a ROM in a listing that assembles back into it
+    Disasm { rom: PathBuf },
+
+    /// Assemble a program written as the disassembler writes it into a ROM
+    Asm {
+        source: PathBuf,
+
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+
+    /// Run a ROM without a window and print the display at the end, or compare it with a file
+    Test {
+        rom: PathBuf,
+
+        #[arg(long, default_value_t = 600)]
+        frames: u64,
+
+        #[arg(long, default_value_t = 1000)]
+        speed: u32,
+
+        #[arg(long, value_enum)]
+        platform: Option<Platform>,
+
+        #[arg(long, value_name = "FILE")]
+        input_script: Option<PathBuf>,
+
+        /// Seed for the random numbers of the ROM, so that the display comes out the same
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// Write the display to the file instead of the standard output
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Format to write the display in, instead of the one of the file's extension or text
+        #[arg(long, value_enum)]
+        format: Option<Format>,
+
+        /// File with the expected display, text with # for lit pixels and . for unlit ones, or an
+        /// image or raw bits as --format writes them
+        #[arg(long, value_name = "FILE")]
+        expect: Option<PathBuf>,
+    },
+
+    /// Run a ROM without a window as fast as possible and report the speed reached
+    Bench {
+        rom: PathBuf,
+
+        #[arg(long, default_value_t = 5.0)]
+        seconds: f64,
+
+        #[arg(long, default_value_t = 1000)]
+        speed: u32,
+
+        #[arg(long, value_enum)]
+        platform: Option<Platform>,
+    },
+
+    /// Check that a ROM fits in memory and only runs instructions the interpreter knows
+    Verify { rom: PathBuf },
+
+    /// Print the size, platform and hashes of a ROM, and where its own settings are read from
+    Info { rom: PathBuf },
+
+    /// List the ROMs played last, with the speed and palette they were played with
+    Recent,
+
+    /// Write a commented configuration file with the default bindings, or print the bindings in
+    /// effect
+    #[command(group(ArgGroup::new("action").required(true)))]
+    Keymap {
+        /// Write the template to the standard configuration file, unless there is one already
+        #[arg(long, group = "action")]
+        generate: bool,
+
+        /// Print what presses each keypad key after --config and --keymap are applied
+        #[arg(long, group = "action")]
+        dump: bool,
+
+        #[arg(long)]
+        config: Option<PathBuf>,
+
+        /// Profile of the configuration file to apply, as --profile does when running
+        #[arg(long, value_name = "NAME")]
+        profile: Option<String>,
+
+        #[arg(long, value_name = "FILE")]
+        keymap: Option<PathBuf>,
+    },
+
+    /// Print a script that completes the commands and options in the shell
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+
+    /// Print a manual page for man, as in chip8-emu-rust man > chip8-emu-rust.1
+    Man,
+}
+
+#[derive(clap::Args, Debug)]
 struct Args {
-    #[arg(short, long)]
-    rom: String,
+    /// ROM file to run, an Octo cartridge (.gif) or source file (.8o), or an http(s) URL to
+    /// download one from. More ROMs, or .m3u lists of them, make a playlist to switch between
+    #[arg(value_name = "ROM", value_hint = ValueHint::FilePath)]
+    files: Vec<String>,
+
+    /// Same as giving the ROM file as an argument
+    #[arg(
+        short,
+        long,
+        value_name = "ROM",
+        value_hint = ValueHint::FilePath,
+        conflicts_with = "files"
+    )]
+    rom: Option<String>,
+
+    /// How fast to run: instructions a second (700ips, or just 700), instructions a frame (11ipf)
+    /// or a percentage of the platform's speed (150%)
+    #[arg(long, default_value_t, value_parser = parse_speed)]
+    speed: Speed,
+
+    // Instructions a second, worked out from --speed or the ROM's settings
+    // and the platform, for the frontends to run at.
+    #[arg(skip)]
+    ips: u32,
+
+    #[arg(long, value_name = "FACTOR", default_value_t = 4.0, value_parser = parse_factor)]
+    fast_forward: f64,
 
-    #[arg(long, default_value_t = 1000)]
-    speed: u16,
+    #[arg(long, value_name = "FACTOR", default_value_t = 0.25, value_parser = parse_factor)]
+    slow_motion: f64,
+
+    #[arg(long, value_enum, default_value_t = FrontendKind::default())]
+    frontend: FrontendKind,
+
+    #[cfg(feature = "sdl")]
+    #[arg(long, value_enum, default_value_t = RendererKind::Canvas)]
+    renderer: RendererKind,
+
+    #[arg(long, value_enum, default_value_t = TerminalMode::Block)]
+    terminal_mode: TerminalMode,
 
     #[arg(long, default_value_t = 16)]
     scale: u8,
-}
 
-pub fn main() {
-    let sdl_context = sdl2::init().unwrap();
-    let video_subsystem = sdl_context.video().unwrap();
+    #[arg(long, value_name = "FACTOR")]
+    dpi_scale: Option<f32>,
 
-    let (_stream, stream_handle) = OutputStream::try_default().unwrap();
-    let sink = Sink::try_new(&stream_handle).unwrap();
-    let source = rodio::source::SineWave::new(700.0);
-    sink.pause();
-    sink.append(source);
+    #[arg(long, value_enum, default_value_t = Scaling::Fit)]
+    scaling: Scaling,
 
-    let mut pause_emulation = false;
+    #[arg(long, value_enum, default_value_t = Filter::Nearest)]
+    filter: Filter,
 
-    let args = Args::parse();
+    #[arg(long, value_name = "W:H", default_value = "1:1")]
+    pixel_aspect: PixelAspect,
 
-    let window = video_subsystem
-        .window(
-            "Chip8",
-            RES_WIDTH as u32 * args.scale as u32,
-            RES_HEIGHT as u32 * args.scale as u32,
-        )
-        .position_centered()
-        .build()
-        .unwrap();
+    /// Palette to start with: classic, high-contrast, high-contrast-light, deuteranopia,
+    /// protanopia or one from --palette-file
+    #[arg(long, default_value = "classic")]
+    palette: String,
 
-    let mut canvas = window.into_canvas().build().unwrap();
+    #[arg(long)]
+    palette_file: Option<PathBuf>,
 
-    canvas.present();
+    #[arg(long)]
+    invert: bool,
 
-    let canvas_color = Color::RGB(0, 0, 0);
-    let pixel_color = Color::RGB(255, 255, 255);
+    #[arg(long, value_name = "FACTOR")]
+    brightness: Option<f32>,
 
-    let cpu_timer_speed_ratio: u32 = args.speed as u32 / TIMER_SPEED;
+    #[arg(long, value_name = "GAMMA")]
+    gamma: Option<f32>,
 
-    let mut chip8 = Chip8::new();
+    #[arg(long, value_name = "FRAMES")]
+    phosphor: Option<u8>,
+
+    #[arg(
+        long,
+        value_name = "WEIGHT",
+        num_args = 0..=1,
+        default_missing_value = "0.5",
+        conflicts_with = "phosphor"
+    )]
+    frame_blend: Option<f32>,
+
+    #[arg(long, value_name = "INTENSITY")]
+    scanlines: Option<f32>,
+
+    #[arg(long, value_name = "AMOUNT")]
+    curvature: Option<f32>,
+
+    #[arg(long, value_name = "INTENSITY")]
+    bloom: Option<f32>,
+
+    #[arg(long, value_name = "INTENSITY")]
+    grid: Option<f32>,
+
+    #[arg(long, value_enum)]
+    pixel_shape: Option<PixelShape>,
+
+    #[arg(long, value_name = "PNG")]
+    bezel: Option<PathBuf>,
+
+    #[arg(long, value_name = "PNG")]
+    background: Option<PathBuf>,
+
+    #[arg(
+        long,
+        num_args = 2,
+        value_names = ["TOP", "BOTTOM"],
+        value_parser = parse_color,
+        conflicts_with = "background"
+    )]
+    background_gradient: Vec<Rgb>,
+
+    #[arg(long)]
+    stats: bool,
+
+    #[arg(long)]
+    no_audio: bool,
+
+    #[cfg(any(feature = "rodio", feature = "cpal"))]
+    #[arg(long, value_enum, default_value_t, conflicts_with = "no_audio")]
+    audio_backend: AudioBackend,
+
+    #[arg(long, value_name = "HZ")]
+    beep_freq: Option<f32>,
+
+    #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u8).range(0..=100))]
+    volume: Option<u8>,
+
+    #[arg(long, value_name = "WAV")]
+    beep_sample: Option<PathBuf>,
+
+    #[arg(long)]
+    visual_beep: bool,
+
+    #[cfg(feature = "sdl")]
+    #[arg(long)]
+    debug_window: bool,
+
+    #[cfg(feature = "sdl")]
+    #[arg(long)]
+    rumble: bool,
+
+    #[cfg(feature = "sdl")]
+    #[arg(long)]
+    pause_on_disconnect: bool,
+
+    /// Directory of ROMs to pick from when started without one, the current directory if missing
+    #[cfg(feature = "sdl")]
+    #[arg(long, value_name = "DIR")]
+    rom_dir: Option<PathBuf>,
+
+    #[cfg(any(feature = "sdl", feature = "egui"))]
+    #[arg(long)]
+    keypad: bool,
+
+    #[arg(long)]
+    fullscreen: bool,
+
+    #[arg(long, value_name = "N")]
+    monitor: Option<u32>,
+
+    #[arg(long)]
+    borderless: bool,
+
+    #[arg(long)]
+    vsync: bool,
+
+    /// Most frames in a row left undrawn when the host cannot keep up, so that the ROM keeps its
+    /// speed, 0 to draw every frame however slow
+    #[cfg(feature = "sdl")]
+    #[arg(long, value_name = "N", default_value_t = 4)]
+    max_frame_skip: u32,
+
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Profile of the configuration file to apply, a [profiles.NAME] table whose settings take
+    /// the place of those of the rest of the file
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Keep the configuration, per-ROM settings and recent ROMs in chip8-emu-rust-data beside
+    /// the executable, as a portable.txt file next to it does
+    #[arg(long)]
+    portable: bool,
+
+    #[arg(long, value_name = "FILE")]
+    keymap: Option<PathBuf>,
+
+    #[arg(long, value_name = "FILE")]
+    input_script: Option<PathBuf>,
+
+    #[arg(long, value_name = "FRAMES", default_value_t = 0)]
+    key_latch: u8,
+
+    /// Write a byte into memory once the ROM is loaded, both in hexadecimal, as in 0x2A4=0x00
+    #[arg(long = "poke", value_name = "ADDR=VAL", value_parser = parse_poke)]
+    pokes: Vec<(u16, u8)>,
+
+    /// Write the bytes of --poke again every frame, so that the program cannot change them
+    #[arg(long, requires = "pokes")]
+    freeze: bool,
+
+    /// Seed for the random numbers of the ROM, to run it again the same way, as logged at startup
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Start paused, and pause again on reset, before the first instruction runs
+    #[arg(long)]
+    pause_on_start: bool,
 
-    let mut file = File::open(args.rom).expect("Unable to open ROM file!");
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer).unwrap();
-    chip8.load(&buffer);
-
-    let mut cycle_n: u64 = 0;
-
-    let mut event_pump = sdl_context.event_pump().unwrap();
-    'running: loop {
-        canvas.set_draw_color(canvas_color);
-        canvas.clear();
-
-        for event in event_pump.poll_iter() {
-            match event {
-                Event::Quit { .. }
-                | Event::KeyDown {
-                    keycode: Some(Keycode::Escape),
-                    ..
-                } => break 'running,
-                Event::KeyDown {
-                    keycode: Some(Keycode::Space),
-                    ..
-                } => {
-                    pause_emulation = !pause_emulation;
-                }
-                Event::KeyDown {
-                    keycode: Some(key), ..
-                } => {
-                    if let Some(x) = map_keycode(key) {
-                        chip8.key_pressed(x, true);
-                    }
-                }
-                Event::KeyUp {
-                    keycode: Some(key), ..
-                } => {
-                    if let Some(x) = map_keycode(key) {
-                        chip8.key_pressed(x, false);
-                    }
-                }
-                _ => {}
+    /// Stop after this many instructions, or as soon as the ROM halts in a jump to itself,
+    /// exiting with 0 or 5 to tell which
+    #[arg(long, value_name = "N")]
+    max_cycles: Option<u64>,
+
+    /// Stop after this many seconds, or as soon as the ROM halts in a jump to itself, exiting
+    /// with 0 or 5 to tell which
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    timeout: Option<Duration>,
+
+    /// Wait on the port for another instance to join with --join, and play the ROM together
+    #[arg(long, value_name = "PORT", conflicts_with = "join")]
+    host: Option<u16>,
+
+    /// Join the instance started with --host at the address, as in 192.168.1.20:7000, taking its
+    /// seed and speed
+    #[arg(long, value_name = "ADDRESS")]
+    join: Option<String>,
+
+    /// Let the viewers of the Twitch channel play the ROM, voting for keys as they are named in
+    /// the keymap in its chat
+    #[arg(long, value_name = "CHANNEL")]
+    chat: Option<String>,
+
+    /// IRC server of the chat
+    #[cfg(feature = "sdl")]
+    #[arg(long, value_name = "ADDRESS", default_value = frontend::chat::TWITCH, requires = "chat")]
+    chat_server: String,
+
+    /// Time the viewers have to vote, after which the key most voted for is held until the next
+    /// vote
+    #[cfg(feature = "sdl")]
+    #[arg(long, value_name = "SECONDS", default_value = "1", value_parser = parse_seconds, requires = "chat")]
+    chat_window: Duration,
+
+    /// Play the keypad from the pads of the MIDI device, as in /dev/snd/midiC1D0, laid out as in
+    /// the configuration file
+    #[arg(long, value_name = "DEVICE", value_hint = ValueHint::FilePath)]
+    midi: Option<PathBuf>,
+
+    /// Listen at the address, as in 127.0.0.1:6464, for debuggers speaking JSON-RPC
+    #[arg(long, value_name = "ADDRESS")]
+    debug_rpc: Option<String>,
+
+    /// Count the instructions executed by kind, subroutine and address, and print the most run
+    /// when the emulator quits
+    #[arg(long)]
+    hotspots: bool,
+
+    /// Run without a window, serving the display to browsers at the address, as in 0.0.0.0:8080,
+    /// which play the ROM with their keys
+    #[arg(long, value_name = "ADDRESS")]
+    serve: Option<String>,
+
+    /// Run the ROM again from the start whenever its file changes, keeping the window open
+    #[cfg(any(feature = "sdl", feature = "egui"))]
+    #[arg(long)]
+    watch: bool,
+
+    /// Least severe events to log, from warnings about the setup to the timing of every frame
+    #[arg(long, value_enum, default_value_t = LogLevel::Info)]
+    log_level: LogLevel,
+
+    /// Write the log to the file instead of the terminal
+    #[arg(long, value_name = "FILE")]
+    log_file: Option<PathBuf>,
+
+    /// Interpreter to behave like, by its quirks only: the instructions and high resolution
+    /// SCHIP and XO-CHIP add are not emulated
+    #[arg(long, value_enum)]
+    platform: Option<Platform>,
+
+    /// Turn a quirk on or off over those of the platform: vf-reset, memory, display-wait,
+    /// clipping, shifting or jumping
+    #[arg(long = "quirk", value_name = "NAME=on|off", value_parser = parse_override)]
+    quirks: Vec<(Quirk, bool)>,
+
+    // Those the configuration file turns on or off, below those of a ROM's
+    // sidecar file and of --quirk.
+    #[arg(skip)]
+    config_quirks: Vec<(Quirk, bool)>,
+
+    /// Seconds of play run again under the quirk Ctrl+1 to Ctrl+6 turn on or off
+    #[cfg(feature = "sdl")]
+    #[arg(long, value_name = "SECONDS", default_value = "5", value_parser = parse_seconds)]
+    quirk_replay: Duration,
+
+    /// Run the ROM twice side by side with the same keys, the one on the right with the quirks of
+    /// --compare-platform and --compare-quirk, pausing at the first frame where they differ
+    #[cfg(feature = "sdl")]
+    #[arg(
+        long,
+        value_name = "ROM",
+        value_hint = ValueHint::FilePath,
+        conflicts_with_all = ["files", "rom", "host", "join", "chat", "midi", "serve"]
+    )]
+    compare: Option<String>,
+
+    /// Interpreter the machine on the right of --compare behaves like, instead of the one on the
+    /// left
+    #[cfg(feature = "sdl")]
+    #[arg(long, value_enum, value_name = "PLATFORM", requires = "compare")]
+    compare_platform: Option<Platform>,
+
+    /// Turn a quirk on or off for the machine on the right of --compare
+    #[cfg(feature = "sdl")]
+    #[arg(long, value_name = "NAME=on|off", value_parser = parse_override, requires = "compare")]
+    compare_quirk: Vec<(Quirk, bool)>,
+}
+
+impl Args {
+    fn rom(&self) -> Option<&str> {
+        self.files
+            .first()
+            .map(String::as_str)
+            .or(self.rom.as_deref())
+    }
+
+    // The quirks of the platform, given here or else in the ROM's sidecar
+    // file, or else of the cartridge, with those the configuration file
+    // turns on or off over them, then those of the sidecar file and last
+    // those of the command line.
+    fn quirks(
+        &self,
+        platform: Option<Platform>,
+        sidecar: &BTreeMap<Quirk, bool>,
+        cartridge: Option<&Options>,
+    ) -> Quirks {
+        let overrides: Vec<(Quirk, bool)> = self
+            .config_quirks
+            .iter()
+            .copied()
+            .chain(sidecar.iter().map(|(quirk, on)| (*quirk, *on)))
+            .chain(self.quirks.iter().copied())
+            .collect();
+        match (self.platform.or(platform), cartridge) {
+            (None, Some(options)) => options.quirks().overridden(&overrides),
+            (platform, _) => Quirks::resolve(platform, &overrides),
+        }
+    }
+}
+
+pub fn main() {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+    // The tools only go portable by the file next to the executable.
+    let portable = match &cli.command {
+        None => cli.run.portable,
+        Some(Command::Run(args)) => args.portable,
+        Some(_) => false,
+    };
+    let result = portable::init(portable)
+        .map_err(Failure::Input)
+        .and_then(|()| match cli.command {
+            None => guarded(|| run(cli.run, &matches)),
+            Some(Command::Run(args)) => {
+                guarded(|| run(*args, matches.subcommand_matches("run").unwrap()))
+            }
+            Some(command @ (Command::Test { .. } | Command::Bench { .. })) => {
+                guarded(|| tool(command).map_err(Failure::Tool))
             }
+            Some(command) => tool(command).map_err(Failure::Tool),
+        });
+    if let Err(failure) = result {
+        eprintln!("{}", failure);
+        process::exit(failure.code());
+    }
+}
+
+fn tool(command: Command) -> Result<(), String> {
+    match command {
+        Command::Run(_) => unreachable!("running a ROM is not a tool"),
+        Command::Disasm { rom } => tools::disasm(&rom),
+        Command::Asm { source, output } => tools::asm(&source, &output),
+        Command::Test {
+            rom,
+            frames,
+            speed,
+            platform,
+            input_script,
+            seed,
+            output,
+            format,
+            expect,
+        } => tools::play(&rom, frames, speed, platform, input_script.as_deref(), seed).and_then(
+            |buffer| {
+                tools::test(
+                    &rom,
+                    frames,
+                    &buffer,
+                    format,
+                    output.as_deref(),
+                    expect.as_deref(),
+                )
+            },
+        ),
+        Command::Bench {
+            rom,
+            seconds,
+            speed,
+            platform,
+        } => tools::bench(&rom, seconds, speed, platform),
+        Command::Verify { rom } => tools::verify(&rom),
+        Command::Info { rom } => tools::info(&rom),
+        Command::Recent => tools::recent(),
+        Command::Keymap {
+            generate,
+            config,
+            profile,
+            keymap,
+            ..
+        } => tools::keymap(
+            generate,
+            config.as_deref(),
+            profile.as_deref(),
+            keymap.as_deref(),
+        ),
+        Command::Completions { shell } => {
+            tools::completions(Cli::command(), shell, &mut io::stdout());
+            Ok(())
         }
+        Command::Man => tools::man(Cli::command(), &mut io::stdout()),
+    }
+}
 
-        draw_canvas(
-            &mut canvas,
-            chip8.display.as_buffer(),
-            pixel_color,
-            args.scale as u32,
-        );
+// A ROM that makes the interpreter fail, as with an instruction it does not
+// know, ends the run, or the test or bench, with a status of its own, once
+// the panic has said where.
+fn guarded(run: impl FnOnce() -> Result<(), Failure>) -> Result<(), Failure> {
+    panic::catch_unwind(AssertUnwindSafe(run)).unwrap_or_else(|cause| Err(Failure::crashed(cause)))
+}
+
+fn run(mut args: Args, matches: &ArgMatches) -> Result<(), Failure> {
+    logging::init(args.log_level, args.log_file.as_deref()).map_err(Failure::Input)?;
+    if let Some(dir) = portable::dir() {
+        info!("portable, keeping settings in {}", dir.display());
+    }
+    // --serve alone is enough to run without a window.
+    if args.serve.is_some() {
+        match matches.value_source("frontend") {
+            Some(ValueSource::CommandLine) if !matches!(args.frontend, FrontendKind::Web) => {
+                return Err(Failure::Input(String::from(
+                    "only the web frontend serves the display",
+                )));
+            }
+            _ => args.frontend = FrontendKind::Web,
+        }
+    }
+    // The ROM compared runs as if given alone.
+    #[cfg(feature = "sdl")]
+    if let Some(rom) = &args.compare {
+        args.files = vec![rom.clone()];
+    }
+    if !args.files.is_empty() {
+        args.files = playlist::expand(&args.files).map_err(Failure::Input)?;
+        if args.files.is_empty() {
+            return Err(Failure::Input(String::from("the playlist has no ROMs")));
+        }
+    }
+    // The ROMs of a playlist are opened again while playing.
+    if args.files.len() > 1 && args.files.iter().any(|rom| download::is_url(rom)) {
+        return Err(Failure::Input(String::from(
+            "a playlist can only hold files, not downloads",
+        )));
+    }
+    if args.files.len() > 1 && !args.frontend.switches() {
+        warn!("only the SDL frontend switches between ROMs, playing the first one");
+    }
+    let mut config =
+        Config::find(args.config.as_deref(), args.profile.as_deref()).map_err(Failure::Input)?;
+    if let Some(name) = &args.profile {
+        info!("using profile {}", name);
+    }
+    if let Some(path) = &args.keymap {
+        config.keys = Keymap::load(path).map_err(Failure::Input)?;
+    }
 
-        canvas.present();
-        ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / args.speed as u32));
+    let mut rom = Vec::new();
+    let mut cartridge = None;
+    // The ROM's own settings take precedence over those of its cartridge,
+    // which take precedence over the configuration, but neither over the
+    // command line.
+    let sidecar = match args.rom() {
+        Some(path) => {
+            let data = if download::is_url(path) {
+                download::download(path).map_err(Failure::Input)?
+            } else {
+                fs::read(path).map_err(|e| Failure::file("ROM", Path::new(path), e))?
+            };
+            let hash = RomHash::of(&data);
+            info!("{}: SHA-1 {}, CRC32 {}", path, hash.sha1(), hash.crc32());
+            (rom, cartridge) = Cartridge::open(Path::new(path), data)
+                .map_err(|e| Failure::Input(format!("{}: {}", path, e)))?;
+            Sidecar::find(Path::new(path), &hash).map_err(Failure::Input)?
+        }
+        // Without a ROM to pick one from a directory, the splash screen
+        // runs instead.
+        None if !args.frontend.browses() => {
+            info!("no ROM given, running the splash screen");
+            rom = splash::rom();
+            Sidecar::default()
+        }
+        None => Sidecar::default(),
+    };
+    #[cfg(any(feature = "sdl", feature = "egui"))]
+    if args.watch && args.rom().is_some_and(download::is_url) {
+        warn!("only files can be watched, not downloads");
+        args.watch = false;
+    }
+    let given = |id| matches.value_source(id) == Some(ValueSource::CommandLine);
+    if let Some(speed) = config.emulation.speed.filter(|_| !given("speed")) {
+        args.speed = Speed::Ips(speed as u32);
+    }
+    if let Some(palette) = config
+        .emulation
+        .palette
+        .clone()
+        .filter(|_| !given("palette"))
+    {
+        args.palette = palette;
+    }
+    let mut themes = builtin_palettes();
+    if let Some(options) = &cartridge {
+        if let Some(speed) = options.speed().filter(|_| !given("speed")) {
+            args.speed = Speed::Ips(speed as u32);
+        }
+        themes.push(options.palette().map_err(Failure::Input)?);
+        if !given("palette") {
+            args.palette = String::from("cartridge");
+        }
+    }
+    if let Some(speed) = sidecar.speed.filter(|_| !given("speed")) {
+        args.speed = Speed::Ips(speed as u32);
+    }
+    // The platform and quirks of the configuration give way to the quirks a
+    // cartridge comes with, as its speed does.
+    let mut platform = sidecar.platform;
+    if cartridge.is_none() {
+        platform = platform.or(config.emulation.platform);
+        args.config_quirks = config
+            .emulation
+            .quirks
+            .iter()
+            .map(|(quirk, on)| (*quirk, *on))
+            .collect();
+    }
+    args.ips = args.speed.ips(args.platform.or(platform));
+    if let Some(palette) = sidecar.palette.filter(|_| !given("palette")) {
+        args.palette = palette;
+    }
+    if let Some(keys) = sidecar.keys.filter(|_| args.keymap.is_none()) {
+        config.keys = keys;
+    }
 
-        if pause_emulation {
-            sink.pause();
-            continue;
+    if let Some(path) = &args.palette_file {
+        themes.extend(load_themes(path).map_err(Failure::Input)?);
+    }
+    let mut palettes = Palettes::new(themes);
+    palettes.select(&args.palette).map_err(Failure::Input)?;
+    if args.invert {
+        palettes.invert();
+    }
+    // A downloaded ROM is gone once the emulator quits.
+    if let Some(path) = args.rom().filter(|path| !download::is_url(path)) {
+        Recent::record(Path::new(path), args.ips, &args.palette);
+    }
+    palettes.set_tone(Tone {
+        brightness: args.brightness.unwrap_or(config.color.brightness),
+        gamma: args.gamma.unwrap_or(config.color.gamma),
+    });
+
+    let frequency = args.beep_freq.unwrap_or(config.audio.beep_freq);
+    let volume = args.volume.unwrap_or(config.audio.volume);
+    // Without an audio device, as on headless machines, the emulator keeps
+    // running silently and frames the display while the buzzer would sound.
+    let mut audio: Box<dyn Audio> = if args.no_audio || !args.frontend.sounds() {
+        Box::new(NullAudio::new(frequency, volume))
+    } else {
+        match open_audio(&args, &config, frequency, volume) {
+            Ok(audio) => audio,
+            Err(error) => {
+                warn!("audio disabled, {}", error);
+                args.visual_beep = true;
+                Box::new(NullAudio::new(frequency, volume))
+            }
         }
+    };
+
+    let mut chip8 = Chip8::new();
+    if let Some(seed) = args.seed {
+        chip8.set_seed(seed);
+    }
+    info!("random seed {}", chip8.seed());
+    chip8.set_latch(args.key_latch);
+    chip8.set_quirks(args.quirks(platform, &sidecar.quirks, cartridge.as_ref()));
+    if let Some(platform @ (Platform::Schip | Platform::XoChip)) = args.platform.or(platform) {
+        warn!(
+            "only the quirks of {} are emulated, not its instructions",
+            platform
+        );
+    }
 
-        chip8.fetch_execute();
-        if chip8.beep() {
-            sink.play();
-        } else {
-            sink.pause();
+    if !rom.is_empty() {
+        chip8.load(&rom).map_err(Failure::Input)?;
+    }
+    if let Some(pokes) = Pokes::from_args(&args) {
+        pokes.apply(&mut chip8);
+    }
+    if args.debug_rpc.is_some() && !args.frontend.debugs_remotely() {
+        return Err(Failure::Input(String::from(
+            "only the SDL, terminal and web frontends take a debugger",
+        )));
+    }
+    if args.chat.is_some() && !args.frontend.external_input() {
+        return Err(Failure::Input(String::from(
+            "only the SDL frontend takes keys from a chat",
+        )));
+    }
+    if args.midi.is_some() && !args.frontend.external_input() {
+        return Err(Failure::Input(String::from(
+            "only the SDL frontend takes keys from a MIDI controller",
+        )));
+    }
+    if args.host.is_some() || args.join.is_some() {
+        if !args.frontend.external_input() {
+            return Err(Failure::Input(String::from(
+                "only the SDL frontend plays over the network",
+            )));
+        }
+        if rom.is_empty() || args.files.len() > 1 {
+            return Err(Failure::Input(String::from(
+                "netplay needs a single ROM on both sides",
+            )));
         }
+    }
+
+    // The debugger can ask for the profile, even when it is not printed.
+    let profile = (args.hotspots || args.debug_rpc.is_some()).then(|| {
+        let profile = Arc::new(Mutex::new(Profile::new()));
+        chip8.set_profile(profile.clone());
+        profile
+    });
 
-        if cycle_n % cpu_timer_speed_ratio as u64 == 0 {
-            chip8.dec_timers();
+    let result = match args.frontend {
+        #[cfg(feature = "sdl")]
+        FrontendKind::Sdl if args.compare.is_some() => {
+            if rom.is_empty() || args.files.len() > 1 {
+                return Err(Failure::Input(String::from("--compare needs a single ROM")));
+            }
+            if args.hotspots {
+                return Err(Failure::Input(String::from(
+                    "--hotspots counts a single machine, not those compared",
+                )));
+            }
+            let comparison = compared(&args, chip8);
+            frontend::compare::run(&args, &config, palettes, comparison, audio.as_mut())
+        }
+        #[cfg(feature = "sdl")]
+        FrontendKind::Sdl => {
+            let netplay = connect(&mut args, &mut chip8, &rom).map_err(Failure::Input)?;
+            frontend::sdl::run(&args, &config, palettes, chip8, audio.as_mut(), netplay)
+        }
+        #[cfg(feature = "minifb")]
+        FrontendKind::Minifb => {
+            frontend::minifb::run(&args, &config, palettes, chip8, audio.as_mut())
         }
+        #[cfg(feature = "egui")]
+        FrontendKind::Egui => frontend::egui::run(&args, &config, palettes, chip8, audio),
+        FrontendKind::Terminal => {
+            frontend::terminal::run(&args, &config, palettes, chip8, audio.as_mut())
+        }
+        FrontendKind::Web => frontend::web::run(&args, &config, palettes, chip8),
+    };
+    if let Some(profile) = profile.filter(|_| args.hotspots) {
+        print!("{}", profile.lock().unwrap().summary(HOTSPOTS));
+    }
+    result
+}
 
-        cycle_n += 1;
+// The machine as it is on the left, and on the right a copy of it with the
+// quirks to compare with.
+#[cfg(feature = "sdl")]
+fn compared(args: &Args, chip8: Chip8) -> Comparison {
+    let left = chip8.quirks();
+    let quirks = args
+        .compare_platform
+        .map_or(left, Quirks::of)
+        .overridden(&args.compare_quirk);
+    if quirks == left {
+        warn!("both machines compared have the same quirks");
     }
+    let mut right = chip8.clone();
+    right.set_quirks(quirks);
+    Comparison::new(chip8, right, args.ips)
 }
 
-fn draw_canvas(canvas: &mut WindowCanvas, buffer: DisplayBuffer, color: Color, scale: u32) {
-    for (index, item) in buffer.iter().enumerate() {
-        if item == &true {
-            let x: i32 = i32::try_from(index % RES_WIDTH).unwrap() * scale as i32;
-            let y: i32 = i32::try_from(index / RES_WIDTH).unwrap() * scale as i32;
-            let rectangle = Rect::new(x, y, scale, scale);
-            canvas.set_draw_color(color);
-            canvas.fill_rect(rectangle).unwrap();
+// Waits for the other player or joins them, taking the host's seed and
+// speed when joining.
+#[cfg(feature = "sdl")]
+fn connect(args: &mut Args, chip8: &mut Chip8, rom: &[u8]) -> Result<Option<Netplay>, String> {
+    let hash = RomHash::of(rom).sha1();
+    match (args.host, args.join.as_deref()) {
+        (Some(port), _) => Netplay::host(port, chip8, &hash, args.ips).map(Some),
+        (None, Some(address)) => {
+            let (netplay, ips) = Netplay::join(address, chip8, &hash)?;
+            info!(
+                "random seed {} and {} from the host",
+                chip8.seed(),
+                describe(ips)
+            );
+            args.ips = ips;
+            Ok(Some(netplay))
         }
+        (None, None) => Ok(None),
+    }
+}
+
+#[cfg(any(feature = "rodio", feature = "cpal"))]
+fn open_audio(
+    args: &Args,
+    config: &Config,
+    frequency: f32,
+    volume: u8,
+) -> Result<Box<dyn Audio>, String> {
+    let beep_sample = args
+        .beep_sample
+        .as_ref()
+        .or(config.audio.beep_sample.as_ref());
+    match args.audio_backend {
+        #[cfg(feature = "rodio")]
+        AudioBackend::Rodio => open_sink().and_then(|(stream, sink)| {
+            let beeper = match beep_sample {
+                Some(path) => Beeper::from_file(sink, path, frequency, volume)?,
+                None => Beeper::new(sink, frequency, volume),
+            };
+            Ok(Box::new(beeper.playing_on(stream)) as Box<dyn Audio>)
+        }),
+        #[cfg(feature = "cpal")]
+        AudioBackend::Cpal => {
+            if beep_sample.is_some() {
+                warn!("the cpal backend only plays the generated tone");
+            }
+            CpalAudio::open(frequency, volume).map(|audio| Box::new(audio) as Box<dyn Audio>)
+        }
+    }
+}
+
+// Built without any audio backend the buzzer is silent, as with --no-audio.
+#[cfg(not(any(feature = "rodio", feature = "cpal")))]
+fn open_audio(
+    _args: &Args,
+    _config: &Config,
+    frequency: f32,
+    volume: u8,
+) -> Result<Box<dyn Audio>, String> {
+    Ok(Box::new(NullAudio::new(frequency, volume)))
+}
+
+fn parse_seconds(value: &str) -> Result<Duration, String> {
+    parse_factor(value).map(Duration::from_secs_f64)
+}
+
+// A speed multiplier, which has to make emulation run at all.
+fn parse_factor(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(factor) if factor > 0.0 && factor.is_finite() => Ok(factor),
+        _ => Err(format!("{} is not a positive number", value)),
     }
 }
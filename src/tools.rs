@@ -0,0 +1,532 @@
+use crate::chip8::asm::assemble;
+use crate::chip8::disasm::disassemble;
+use crate::chip8::display::DisplayBuffer;
+use crate::chip8::hash::RomHash;
+use crate::chip8::platform::detect;
+use crate::chip8::platform::Platform;
+use crate::chip8::quirks::Quirks;
+use crate::chip8::Chip8;
+use crate::chip8::MAX_ROM_SIZE;
+use crate::config::sidecar::Sidecar;
+use crate::config::Config;
+use crate::frontend::dump::dump;
+use crate::frontend::dump::text;
+use crate::frontend::dump::Format;
+use crate::frontend::keymap::Keymap;
+use crate::frontend::recent::ago;
+use crate::frontend::recent::now;
+use crate::frontend::recent::Recent;
+use crate::frontend::runner::frame_instructions;
+use crate::frontend::runner::TIMER_SPEED;
+use crate::frontend::script::Script;
+use clap_complete::generate;
+use clap_complete::Shell;
+use clap_mangen::Man;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+use std::time::Instant;
+
+const START: usize = 0x200;
+
+fn read(path: &Path) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|e| format!("unable to read {}: {}", path.display(), e))
+}
+
+// One instruction a line, with its address and opcode in a comment, so that
+// the listing assembles back into the same ROM.
+fn listing(rom: &[u8]) -> String {
+    let mut lines = Vec::new();
+    for (index, chunk) in rom.chunks(2).enumerate() {
+        let address = START + 2 * index;
+        let line = match chunk {
+            [high, low] => {
+                let opcode = u16::from_be_bytes([*high, *low]);
+                format!(
+                    "{:<16}; {:03X}  {:04X}",
+                    disassemble(opcode),
+                    address,
+                    opcode
+                )
+            }
+            [byte] => format!(
+                "{:<16}; {:03X}  {:02X}",
+                format!("DB {:02X}", byte),
+                address,
+                byte
+            ),
+            _ => unreachable!(),
+        };
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+pub fn disasm(rom: &Path) -> Result<(), String> {
+    println!("{}", listing(&read(rom)?));
+    Ok(())
+}
+
+pub fn asm(source: &Path, output: &Path) -> Result<(), String> {
+    let content = fs::read_to_string(source)
+        .map_err(|e| format!("unable to read {}: {}", source.display(), e))?;
+    let rom = assemble(&content).map_err(|e| format!("{}: {}", source.display(), e))?;
+    fs::write(output, &rom).map_err(|e| format!("unable to write {}: {}", output.display(), e))?;
+    println!("Wrote {} bytes to {}", rom.len(), output.display());
+    Ok(())
+}
+
+// Follows the code from the entry point, through jumps, calls and both ways
+// of every skip, and reports what would stop the interpreter: instructions
+// it does not know and running off the end of the ROM into empty memory.
+// Returns the number of instructions reached along with the problems, by
+// address.
+fn trace(rom: &[u8]) -> (usize, BTreeMap<usize, String>) {
+    let end = START + rom.len();
+    let mut problems = BTreeMap::new();
+    let mut visited = [false; 0x1000];
+    let mut pending = vec![START];
+    let mut reached = 0;
+    while let Some(address) = pending.pop() {
+        if address < START || address + 2 > end {
+            problems.insert(address, String::from("runs outside the ROM"));
+            continue;
+        }
+        if std::mem::replace(&mut visited[address], true) {
+            continue;
+        }
+        reached += 1;
+        let opcode = u16::from_be_bytes([rom[address - START], rom[address + 1 - START]]);
+        let target = (opcode & 0x0FFF) as usize;
+        let next = address + 2;
+        let text = disassemble(opcode);
+        match text.split(' ').next().unwrap() {
+            "DW" => {
+                problems.insert(address, format!("{:04X} is not an instruction", opcode));
+            }
+            "SYS" => {
+                problems.insert(address, format!("{} calls machine code", text));
+            }
+            "RET" => {}
+            // Where JP V0 goes depends on V0.
+            "JP" if opcode >> 12 == 0xB => {}
+            "JP" => pending.push(target),
+            "CALL" => pending.extend([next, target]),
+            "SE" | "SNE" | "SKP" | "SKNP" => pending.extend([next, next + 2]),
+            _ => pending.push(next),
+        }
+    }
+    (reached, problems)
+}
+
+pub fn verify(path: &Path) -> Result<(), String> {
+    let rom = read(path)?;
+    if rom.is_empty() {
+        return Err(format!("{} is empty", path.display()));
+    }
+    if rom.len() > MAX_ROM_SIZE {
+        return Err(format!(
+            "{} is {} bytes, more than the {} that fit in memory",
+            path.display(),
+            rom.len(),
+            MAX_ROM_SIZE
+        ));
+    }
+    let (reached, problems) = trace(&rom);
+    if !problems.is_empty() {
+        let problems: Vec<String> = problems
+            .iter()
+            .map(|(address, problem)| format!("{:03X}: {}", address, problem))
+            .collect();
+        return Err(format!("{}:\n{}", path.display(), problems.join("\n")));
+    }
+    println!(
+        "{}: {} bytes, {} instructions reachable, ok",
+        path.display(),
+        rom.len(),
+        reached
+    );
+    Ok(())
+}
+
+pub fn info(path: &Path) -> Result<(), String> {
+    let rom = read(path)?;
+    let hash = RomHash::of(&rom);
+    let settings = match Sidecar::locate(path, &hash) {
+        Some(sidecar) => sidecar.display().to_string(),
+        None => String::from("none"),
+    };
+    println!("File      {}", path.display());
+    println!("Size      {} bytes", rom.len());
+    println!("Platform  {}", detect(&rom));
+    println!("SHA-1     {}", hash.sha1());
+    println!("CRC32     {}", hash.crc32());
+    println!("Settings  {}", settings);
+    Ok(())
+}
+
+// One ROM a line, the latest first, with when it was played and the speed
+// and palette it started with.
+fn played(recent: &Recent, now: u64) -> String {
+    if recent.roms.is_empty() {
+        return String::from("No ROMs played yet");
+    }
+    recent
+        .roms
+        .iter()
+        .map(|played| {
+            format!(
+                "{:<15} {:>5} IPS  {:<20} {}",
+                ago(played.time, now),
+                played.speed,
+                played.palette,
+                played.path.display()
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+pub fn recent() -> Result<(), String> {
+    let path =
+        Recent::default_path().ok_or_else(|| String::from("unable to find the data directory"))?;
+    println!("{}", played(&Recent::load(&path)?, now()));
+    Ok(())
+}
+
+// Writes a script that completes the commands and options in the shell.
+pub fn completions(mut command: clap::Command, shell: Shell, out: &mut dyn Write) {
+    let name = command.get_name().to_string();
+    generate(shell, &mut command, name, out);
+}
+
+// Writes a manual page for man.
+pub fn man(command: clap::Command, out: &mut dyn Write) -> Result<(), String> {
+    Man::new(command).render(out).map_err(|e| e.to_string())
+}
+
+fn load(path: &Path, platform: Option<Platform>) -> Result<Chip8, String> {
+    let rom = read(path)?;
+    let mut chip8 = Chip8::new();
+    chip8.set_quirks(Quirks::resolve(platform, &[]));
+    chip8
+        .load(&rom)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(chip8)
+}
+
+// Runs one frame's worth of instructions at the speed, spreading the
+// remainder over the frames, then ticks the timers.
+fn frame(chip8: &mut Chip8, speed: u32, frame: u64) {
+    frame_with(chip8, speed, frame, Chip8::fetch_execute);
+}
+
+fn frame_with(chip8: &mut Chip8, speed: u32, frame: u64, mut execute: impl FnMut(&mut Chip8)) {
+    for _ in 0..frame_instructions(frame, speed) {
+        execute(chip8);
+    }
+    chip8.dec_timers();
+}
+
+// Opcodes by their first digit.
+const FAMILIES: [&str; 16] = [
+    "0nnn", "1nnn", "2nnn", "3xnn", "4xnn", "5xy0", "6xnn", "7xnn", "8xyn", "9xy0", "Annn", "Bnnn",
+    "Cxnn", "Dxyn", "Exnn", "Fxnn",
+];
+
+// How many instructions of each family ran and the time they took.
+#[derive(Default)]
+struct Profile {
+    counts: [u64; 16],
+    times: [Duration; 16],
+}
+
+impl Profile {
+    // Runs the next instruction, timing it unless the machine is waiting.
+    fn execute(&mut self, chip8: &mut Chip8) {
+        let family = (chip8.ram[chip8.pc as usize & 0xFFF] >> 4) as usize;
+        let cycles = chip8.cycles();
+        let start = Instant::now();
+        chip8.fetch_execute();
+        let time = start.elapsed();
+        if chip8.cycles() > cycles {
+            self.counts[family] += 1;
+            self.times[family] += time;
+        }
+    }
+
+    fn draws(&self) -> u64 {
+        self.counts[0xD]
+    }
+
+    // A line for each family that ran, the one that took longest first.
+    fn table(&self) -> String {
+        let total: Duration = self.times.iter().sum();
+        let mut families: Vec<usize> = (0..16).filter(|&family| self.counts[family] > 0).collect();
+        families.sort_by_key(|&family| std::cmp::Reverse(self.times[family]));
+        families
+            .into_iter()
+            .map(|family| {
+                let time = self.times[family];
+                format!(
+                    "{}  {:>12} run  {:>9.3}s  {:>5.1}%  {:>6.1} ns each",
+                    FAMILIES[family],
+                    self.counts[family],
+                    time.as_secs_f64(),
+                    100.0 * time.as_secs_f64() / total.as_secs_f64().max(f64::MIN_POSITIVE),
+                    time.as_nanos() as f64 / self.counts[family] as f64
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+// Runs the ROM without a window for the frames, pressing the keys of the
+// input script, and gives the display it ends up with.
+pub fn play(
+    rom: &Path,
+    frames: u64,
+    speed: u32,
+    platform: Option<Platform>,
+    input_script: Option<&Path>,
+    seed: Option<u64>,
+) -> Result<DisplayBuffer, String> {
+    let mut chip8 = load(rom, platform)?;
+    if let Some(seed) = seed {
+        chip8.set_seed(seed);
+    }
+    let mut script = input_script.map(Script::load).transpose()?;
+    for index in 0..frames {
+        if let Some(script) = &mut script {
+            script.tick(&mut chip8);
+        }
+        frame(&mut chip8, speed, index);
+    }
+    Ok(chip8.display.as_buffer())
+}
+
+// Writes the display out, to the file or else the standard output, in the
+// format given or else the one of the file's extension, text without
+// either. Compared with an expected display instead, the file is in the
+// format of its own extension.
+pub fn test(
+    rom: &Path,
+    frames: u64,
+    buffer: &DisplayBuffer,
+    format: Option<Format>,
+    output: Option<&Path>,
+    expect: Option<&Path>,
+) -> Result<(), String> {
+    let format_of = |path: Option<&Path>| {
+        format
+            .or_else(|| path.and_then(Format::from_path))
+            .unwrap_or(Format::Text)
+    };
+    if let Some(path) = output {
+        let data = dump(buffer, format_of(output));
+        fs::write(path, &data).map_err(|e| format!("unable to write {}: {}", path.display(), e))?;
+        println!("Wrote {} bytes to {}", data.len(), path.display());
+    }
+    let Some(path) = expect else {
+        if output.is_none() {
+            io::stdout()
+                .write_all(&dump(buffer, format_of(None)))
+                .map_err(|e| format!("unable to write the display: {}", e))?;
+        }
+        return Ok(());
+    };
+    let expected = read(path)?;
+    let same = match format_of(expect) {
+        Format::Text => String::from_utf8_lossy(&expected).trim_end() == text(buffer),
+        format => expected == dump(buffer, format),
+    };
+    if !same {
+        return Err(format!(
+            "the display after {} frames differs from {}:\n{}",
+            frames,
+            path.display(),
+            text(buffer)
+        ));
+    }
+    println!("{}: ok after {} frames", rom.display(), frames);
+    Ok(())
+}
+
+pub fn bench(
+    rom: &Path,
+    seconds: f64,
+    speed: u32,
+    platform: Option<Platform>,
+) -> Result<(), String> {
+    let mut chip8 = load(rom, platform)?;
+    let duration = Duration::from_secs_f64(seconds);
+    let start = Instant::now();
+    let mut frames = 0;
+    let mut profile = Profile::default();
+    while start.elapsed() < duration {
+        frame_with(&mut chip8, speed, frames, |chip8| profile.execute(chip8));
+        frames += 1;
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+    let emulated = frames as f64 / TIMER_SPEED as f64;
+    println!(
+        "{} instructions in {:.2}s, {:.0} a second",
+        chip8.cycles(),
+        elapsed,
+        chip8.cycles() as f64 / elapsed
+    );
+    println!(
+        "{:.1}s emulated at {} IPS, {:.0}x real time",
+        emulated,
+        speed,
+        emulated / elapsed
+    );
+    println!(
+        "{} draws, {:.0} a second",
+        profile.draws(),
+        profile.draws() as f64 / elapsed
+    );
+    println!("{}", profile.table());
+    Ok(())
+}
+
+// Writes the commented configuration template, or prints the bindings the
+// emulator would use with the given files.
+pub fn keymap(
+    generate: bool,
+    config: Option<&Path>,
+    profile: Option<&str>,
+    keymap: Option<&Path>,
+) -> Result<(), String> {
+    if generate {
+        let path = Config::default_path()
+            .ok_or_else(|| String::from("unable to find the configuration directory"))?;
+        Config::generate(&path)?;
+        println!("Wrote {}", path.display());
+        return Ok(());
+    }
+    let mut config = Config::find(config, profile)?;
+    if let Some(path) = keymap {
+        config.keys = Keymap::load(path)?;
+    }
+    println!("{}", config.bindings());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::asm::assemble;
+    use crate::chip8::Chip8;
+    use crate::frontend::dump::text;
+    use crate::frontend::recent::Recent;
+    use crate::tools::completions;
+    use crate::tools::frame;
+    use crate::tools::listing;
+    use crate::tools::man;
+    use crate::tools::played;
+    use crate::tools::trace;
+    use crate::tools::Profile;
+    use crate::Cli;
+    use clap::CommandFactory;
+    use clap_complete::Shell;
+    #[test]
+    fn listing_assembles_back() {
+        let rom = [0x00, 0xE0, 0x6A, 0x0F, 0xD0, 0x15, 0xFF, 0xFF, 0x80];
+        let listing = listing(&rom);
+        assert!(listing.starts_with("CLS             ; 200  00E0\n"));
+        assert_eq!(assemble(&listing).unwrap(), rom);
+    }
+    #[test]
+    fn played_lists_the_latest_first() {
+        let recent: Recent = toml::from_str(
+            "[[rom]]\npath = \"/roms/PONG\"\ntime = 7200\nspeed = 500\npalette = \"classic\"\n\
+             [[rom]]\npath = \"/roms/TETRIS\"\ntime = 0\nspeed = 1000\npalette = \"protanopia\"",
+        )
+        .unwrap();
+        assert_eq!(
+            played(&recent, 7230),
+            "just now          500 IPS  classic              /roms/PONG\n\
+             2 hours ago      1000 IPS  protanopia           /roms/TETRIS"
+        );
+        assert_eq!(played(&Recent::default(), 0), "No ROMs played yet");
+    }
+    #[test]
+    fn trace_follows_every_path() {
+        let rom = assemble("SE V0, 1\nJP 208\nCALL 208\nJP 206\nRET").unwrap();
+        let (reached, problems) = trace(&rom);
+        assert_eq!(reached, 5);
+        assert!(problems.is_empty());
+        let rom = assemble("SNE V0, 1\nDW FFFF\nSKP V0\nSYS 123").unwrap();
+        let (_, problems) = trace(&rom);
+        let problems: Vec<_> = problems.into_iter().collect();
+        assert_eq!(
+            problems,
+            vec![
+                (0x202, String::from("FFFF is not an instruction")),
+                (0x206, String::from("SYS 123 calls machine code")),
+                (0x208, String::from("runs outside the ROM")),
+            ]
+        );
+    }
+    #[test]
+    fn frames_spread_the_speed() {
+        let mut chip8 = Chip8::new();
+        chip8.load(&assemble("loop: JP loop").unwrap()).unwrap();
+        for index in 0..60 {
+            frame(&mut chip8, 100, index);
+        }
+        assert_eq!(chip8.cycles(), 100);
+    }
+    #[test]
+    fn profile_counts_the_instructions_that_ran() {
+        let mut chip8 = Chip8::new();
+        chip8
+            .load(&assemble("LD I, 0\nloop: DRW V0, V0, 1\nADD V0, 1\nJP loop").unwrap())
+            .unwrap();
+        let mut profile = Profile::default();
+        for _ in 0..7 {
+            profile.execute(&mut chip8);
+        }
+        assert_eq!(profile.counts[0xA], 1);
+        assert_eq!(profile.draws(), 2);
+        assert_eq!(profile.counts[0x7], 2);
+        assert_eq!(profile.counts[0x1], 2);
+        let table = profile.table();
+        assert_eq!(table.lines().count(), 4);
+        assert!(table.lines().all(|line| line.ends_with(" ns each")));
+    }
+    #[test]
+    fn screen_shows_lit_pixels() {
+        let mut chip8 = Chip8::new();
+        chip8
+            .load(&assemble("LD I, 0\nDRW V0, V0, 1").unwrap())
+            .unwrap();
+        chip8.fetch_execute();
+        chip8.fetch_execute();
+        let screen = text(&chip8.display.as_buffer());
+        assert_eq!(screen.lines().count(), 32);
+        assert!(screen.starts_with("####....."));
+        assert!(
+            screen.ends_with("\n................................................................")
+        );
+    }
+    #[test]
+    fn completions_and_man_page_cover_the_commands() {
+        let mut bash = Vec::new();
+        completions(Cli::command(), Shell::Bash, &mut bash);
+        let bash = String::from_utf8(bash).unwrap();
+        assert!(
+            bash.contains("complete -F _chip8__emu__rust -o bashdefault -o default chip8-emu-rust")
+        );
+        assert!(bash.contains("chip8__emu__rust__subcmd__disasm"));
+        let mut page = Vec::new();
+        man(Cli::command(), &mut page).unwrap();
+        let page = String::from_utf8(page).unwrap();
+        assert!(page.contains(".TH chip8-emu-rust 1"));
+        assert!(page.contains("chip8\\-emu\\-rust\\-completions(1)"));
+    }
+}
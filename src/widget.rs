@@ -0,0 +1,194 @@
+use crate::chip8::disasm::disassemble;
+use crate::chip8::display::RES_HEIGHT;
+use crate::chip8::display::RES_WIDTH;
+use crate::chip8::Chip8;
+use eframe::egui;
+
+// The keypad as laid out on the COSMAC VIP.
+const KEYPAD: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+// How many instructions the code view shows from the program counter on.
+const CODE_LINES: u16 = 8;
+
+// A machine shown in the window of another egui application, which owns
+// the Chip8 and runs it as it sees fit, calling fetch_execute and
+// dec_timers. The widget draws the display, and beside it a keypad to click,
+// the registers and the code at the program counter, each when asked for.
+//
+//     ui.add(Chip8Widget::new(&mut chip8).keypad(true).registers(true));
+pub struct Chip8Widget<'a> {
+    chip8: &'a mut Chip8,
+    scale: f32,
+    keypad: bool,
+    registers: bool,
+    code: bool,
+    foreground: egui::Color32,
+    background: egui::Color32,
+}
+
+impl<'a> Chip8Widget<'a> {
+    pub fn new(chip8: &'a mut Chip8) -> Self {
+        Self {
+            chip8,
+            scale: 8.0,
+            keypad: false,
+            registers: false,
+            code: false,
+            foreground: egui::Color32::WHITE,
+            background: egui::Color32::BLACK,
+        }
+    }
+
+    // Points each pixel of the display takes on either side, 8 by default.
+    pub fn scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn keypad(mut self, keypad: bool) -> Self {
+        self.keypad = keypad;
+        self
+    }
+
+    pub fn registers(mut self, registers: bool) -> Self {
+        self.registers = registers;
+        self
+    }
+
+    pub fn code(mut self, code: bool) -> Self {
+        self.code = code;
+        self
+    }
+
+    pub fn colors(mut self, foreground: egui::Color32, background: egui::Color32) -> Self {
+        self.foreground = foreground;
+        self.background = background;
+        self
+    }
+
+    fn display(&mut self, ui: &mut egui::Ui) -> egui::Response {
+        let size = egui::vec2(RES_WIDTH as f32, RES_HEIGHT as f32) * self.scale;
+        let (rect, response) = ui.allocate_exact_size(size, egui::Sense::click());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, self.background);
+        let pixel = egui::vec2(self.scale, self.scale);
+        for (index, on) in self.chip8.display.as_buffer().iter().enumerate() {
+            if *on {
+                let (x, y) = ((index % RES_WIDTH) as f32, (index / RES_WIDTH) as f32);
+                let min = rect.min + egui::vec2(x, y) * self.scale;
+                painter.rect_filled(egui::Rect::from_min_size(min, pixel), 0.0, self.foreground);
+            }
+        }
+        response
+    }
+
+    // Clickable keys, held down for as long as the pointer is. The key held
+    // is kept in egui's memory, as the widget lasts a single frame.
+    fn keypad_view(&mut self, ui: &mut egui::Ui) {
+        let mut down = None;
+        egui::Grid::new("keypad").show(ui, |ui| {
+            for keys in KEYPAD {
+                for key in keys {
+                    let label = egui::RichText::new(format!("{:X}", key)).monospace();
+                    let button = egui::Button::new(label).min_size(egui::vec2(32.0, 32.0));
+                    if ui.add(button).is_pointer_button_down_on() {
+                        down = Some(key);
+                    }
+                }
+                ui.end_row();
+            }
+        });
+        let id = ui.make_persistent_id("chip8 keypad");
+        let held = ui.ctx().data_mut(|data| data.get_temp::<u8>(id));
+        if down != held {
+            if let Some(released) = held {
+                self.chip8.key_pressed(released, false);
+            }
+            if let Some(pressed) = down {
+                self.chip8.key_pressed(pressed, true);
+            }
+            ui.ctx().data_mut(|data| match down {
+                Some(key) => data.insert_temp(id, key),
+                None => data.remove::<u8>(id),
+            });
+        }
+    }
+
+    fn register_view(&self, ui: &mut egui::Ui) {
+        let chip8 = &self.chip8;
+        egui::Grid::new("registers").striped(true).show(ui, |ui| {
+            for (index, value) in chip8.reg_v().iter().enumerate() {
+                ui.monospace(format!("V{:X}", index));
+                ui.monospace(format!("{:02X}", value));
+                if index % 4 == 3 {
+                    ui.end_row();
+                }
+            }
+            ui.monospace("PC");
+            ui.monospace(format!("{:03X}", chip8.pc));
+            ui.monospace("I");
+            ui.monospace(format!("{:03X}", chip8.reg_i()));
+            ui.end_row();
+            ui.monospace("DT");
+            ui.monospace(format!("{:02X}", chip8.delay_timer()));
+            ui.monospace("ST");
+            ui.monospace(format!("{:02X}", chip8.sound_timer()));
+            ui.end_row();
+        });
+        ui.monospace(format!("Stack ({})", chip8.stack().len()));
+        for address in chip8.stack().iter().rev() {
+            ui.monospace(format!("{:03X}", address));
+        }
+    }
+
+    // The instructions from the program counter on, as they are in memory.
+    fn code_view(&self, ui: &mut egui::Ui) {
+        let ram = &self.chip8.ram;
+        for line in 0..CODE_LINES {
+            let address = self.chip8.pc as usize + line as usize * 2;
+            if address + 1 >= ram.len() {
+                break;
+            }
+            let opcode = u16::from_be_bytes([ram[address], ram[address + 1]]);
+            ui.monospace(format!(
+                "{} {:03X}  {:04X}  {}",
+                if line == 0 { ">" } else { " " },
+                address,
+                opcode,
+                disassemble(opcode)
+            ));
+        }
+    }
+}
+
+// Answers with the display's response, so that the application can tell
+// when it is clicked or hovered, as to take keys from the keyboard.
+impl egui::Widget for Chip8Widget<'_> {
+    fn ui(mut self, ui: &mut egui::Ui) -> egui::Response {
+        ui.horizontal_top(|ui| {
+            let display = self.display(ui);
+            if self.keypad || self.registers || self.code {
+                ui.vertical(|ui| {
+                    if self.keypad {
+                        self.keypad_view(ui);
+                        ui.separator();
+                    }
+                    if self.registers {
+                        self.register_view(ui);
+                        ui.separator();
+                    }
+                    if self.code {
+                        self.code_view(ui);
+                    }
+                });
+            }
+            display
+        })
+        .inner
+    }
+}